@@ -0,0 +1,71 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request never got a response, or the response wasn't what was expected.
+    Http(reqwest::Error),
+    InvalidBaseUrl(String),
+    /// The endpoint needs a signed in session.
+    Unauthorized,
+    BadRequest(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    TooManyRequests(String),
+    Status(u16, String),
+}
+
+impl ApiError {
+    // the server explains most failures in a plain text body
+    pub(crate) fn from_status(status: u16, message: String) -> ApiError {
+        match status {
+            400 => ApiError::BadRequest(message),
+            401 => ApiError::Unauthorized,
+            403 => ApiError::Forbidden(message),
+            404 => ApiError::NotFound(message),
+            409 => ApiError::Conflict(message),
+            429 => ApiError::TooManyRequests(message),
+            status => ApiError::Status(status, message),
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::Http(e) => e.status().map(|status| status.as_u16()),
+            ApiError::InvalidBaseUrl(_) => None,
+            ApiError::Unauthorized => Some(401),
+            ApiError::BadRequest(_) => Some(400),
+            ApiError::Forbidden(_) => Some(403),
+            ApiError::NotFound(_) => Some(404),
+            ApiError::Conflict(_) => Some(409),
+            ApiError::TooManyRequests(_) => Some(429),
+            ApiError::Status(status, _) => Some(*status),
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::Http(e) => write!(f, "http error: {}", e),
+            ApiError::InvalidBaseUrl(url) => write!(f, "invalid base url: {}", url),
+            ApiError::Unauthorized => write!(f, "not signed in"),
+            ApiError::BadRequest(message) | ApiError::Forbidden(message) | ApiError::NotFound(message) | ApiError::Conflict(message) | ApiError::TooManyRequests(message) => {
+                write!(f, "{}", message)
+            }
+            ApiError::Status(status, message) => write!(f, "unexpected status {}: {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> ApiError {
+        ApiError::Http(e)
+    }
+}
@@ -0,0 +1,400 @@
+//! A typed client for the `/v1/` API, for native Rust and for the browser.
+//!
+//! Signing in sets the server's session cookie; natively the client keeps it in its own cookie
+//! store, in the browser `fetch` sends it along like any other same origin cookie.  Every method
+//! takes and returns the `shared` types, and failed requests come back as an [`ApiError`] carrying
+//! the server's explanation.
+//!
+//! Live updates (`/v1/updates/stream`) are server-sent events and aren't wrapped here.
+
+use reqwest::{Method, RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+mod error;
+
+pub use error::ApiError;
+
+pub type Result<T> = std::result::Result<T, ApiError>;
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: Url,
+}
+
+impl Client {
+    /// `base_url` is wherever the server is listening, e.g. `http://127.0.0.1:8080`.
+    pub fn new(base_url: &str) -> Result<Client> {
+        let base_url = Url::parse(base_url).map_err(|_| ApiError::InvalidBaseUrl(base_url.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(ApiError::InvalidBaseUrl(base_url.to_string()));
+        }
+        Ok(Client {
+            http: build_http_client()?,
+            base_url,
+        })
+    }
+
+    fn url(&self, path: &str) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut().expect("checked in Client::new").pop_if_empty().push("v1").extend(path.split('/'));
+        url
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        self.http.request(method, url)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response.json().await?)
+        } else {
+            let message = response.text().await.unwrap_or_default();
+            Err(ApiError::from_status(status.as_u16(), message))
+        }
+    }
+
+    async fn send_bytes(&self, request: RequestBuilder) -> Result<Vec<u8>> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response.bytes().await?.to_vec())
+        } else {
+            let message = response.text().await.unwrap_or_default();
+            Err(ApiError::from_status(status.as_u16(), message))
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.request(Method::GET, self.url(path))).await
+    }
+
+    async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.request(Method::DELETE, self.url(path))).await
+    }
+
+    async fn send_empty<T: DeserializeOwned>(&self, method: Method, path: &str) -> Result<T> {
+        self.send(self.request(method, self.url(path))).await
+    }
+
+    async fn send_json<B: Serialize, T: DeserializeOwned>(&self, method: Method, path: &str, body: &B) -> Result<T> {
+        self.send(self.request(method, self.url(path)).json(body)).await
+    }
+
+    // a few listings answer 404 rather than an empty list when there's nothing in them
+    async fn get_list<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        match self.get(path).await {
+            Err(e) if e.is_not_found() => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    // users
+
+    /// Registers a user and signs them in.
+    pub async fn register(&self, body: &shared::RegisterRequestBody) -> Result<shared::LoggedUser> {
+        self.send_json(Method::POST, "users/add", body).await
+    }
+
+    /// `None` when the email or password is wrong.
+    pub async fn login(&self, body: &shared::LoginRequestBody) -> Result<Option<shared::LoggedUser>> {
+        self.send_json(Method::POST, "users/login", body).await
+    }
+
+    pub async fn logout(&self) -> Result<bool> {
+        self.send_empty(Method::POST, "users/logout").await
+    }
+
+    /// `false` when the address is already verified.
+    pub async fn request_email_verification(&self) -> Result<bool> {
+        self.send_empty(Method::POST, "users/verify_email/request").await
+    }
+
+    pub async fn verify_email(&self, body: &shared::VerifyEmailRequestBody) -> Result<bool> {
+        self.send_json(Method::POST, "users/verify_email/redeem", body).await
+    }
+
+    pub async fn request_password_reset(&self, body: &shared::ForgotPasswordRequestBody) -> Result<bool> {
+        self.send_json(Method::POST, "users/reset_password/request", body).await
+    }
+
+    pub async fn reset_password(&self, body: &shared::ResetPasswordRequestBody) -> Result<bool> {
+        self.send_json(Method::POST, "users/reset_password/redeem", body).await
+    }
+
+    pub async fn change_password(&self, body: &shared::ChangePasswordRequestBody) -> Result<bool> {
+        self.send_json(Method::POST, "users/change_password", body).await
+    }
+
+    pub async fn delete_account(&self, body: &shared::DeleteAccountRequestBody) -> Result<bool> {
+        self.send_json(Method::POST, "users/delete", body).await
+    }
+
+    pub async fn update_profile(&self, body: &shared::UpdateProfileRequest) -> Result<shared::LoggedUser> {
+        self.send_json(Method::PATCH, "users/update", body).await
+    }
+
+    pub async fn find_all_users(&self) -> Result<Vec<shared::MinimalUserInfo>> {
+        self.get_list("users/find_all").await
+    }
+
+    pub async fn find_user_by_id(&self, user_id: i32) -> Result<shared::MinimalUserInfo> {
+        self.get(&format!("users/find_by_id/{}", user_id)).await
+    }
+
+    pub async fn find_user_by_email(&self, email: &str) -> Result<shared::MinimalUserInfo> {
+        let mut url = self.url("users/find_by_email");
+        url.path_segments_mut().expect("checked in Client::new").push(email);
+        self.send(self.request(Method::GET, url)).await
+    }
+
+    // gift ideas
+
+    pub async fn add_gift_idea(&self, body: &shared::GiftIdeaRequestBody) -> Result<shared::GiftIdeaResponseBody> {
+        self.send_json(Method::POST, "gifts/add", body).await
+    }
+
+    pub async fn update_gift_idea(&self, gift_id: i32, body: &shared::UpdateGiftIdeaRequest) -> Result<shared::GiftIdeaResponseBody> {
+        self.send_json(Method::PATCH, &format!("gifts/update/{}", gift_id), body).await
+    }
+
+    /// Reads a pasted wishlist into rows, each with any problems that would stop it importing.
+    pub async fn preview_wishlist(&self, body: &shared::WishlistPreviewRequest) -> Result<Vec<shared::WishlistRow>> {
+        self.send_json(Method::POST, "gifts/import_wishlist/preview", body).await
+    }
+
+    /// Adds the rows that pass validation; the rest come back with their problems.
+    pub async fn import_wishlist(&self, body: &shared::WishlistImportRequest) -> Result<shared::WishlistImportResponseBody> {
+        self.send_json(Method::POST, "gifts/import_wishlist", body).await
+    }
+
+    pub async fn find_link_metadata(&self, body: &shared::LinkMetadataRequestBody) -> Result<shared::LinkMetadataResponseBody> {
+        self.send_json(Method::POST, "gifts/link_metadata", body).await
+    }
+
+    pub async fn find_all_gift_ideas(&self) -> Result<Vec<shared::GiftIdeaResponseBody>> {
+        self.get_list("gifts/find_all").await
+    }
+
+    pub async fn find_gift_ideas_by_event(&self, event_id: i32) -> Result<Vec<shared::GiftIdeaResponseBody>> {
+        self.get(&format!("gifts/find_by_event/{}", event_id)).await
+    }
+
+    pub async fn find_gift_idea_by_id(&self, gift_id: i32) -> Result<shared::GiftIdeaResponseBody> {
+        self.get(&format!("gifts/find_by_id/{}", gift_id)).await
+    }
+
+    pub async fn reserve_gift_idea(&self, gift_id: i32) -> Result<shared::GiftIdeaResponseBody> {
+        self.send_empty(Method::PATCH, &format!("gifts/reserve/{}", gift_id)).await
+    }
+
+    pub async fn unreserve_gift_idea(&self, gift_id: i32) -> Result<shared::GiftIdeaResponseBody> {
+        self.send_empty(Method::PATCH, &format!("gifts/unreserve/{}", gift_id)).await
+    }
+
+    pub async fn mark_purchased(&self, gift_id: i32) -> Result<shared::GiftIdeaResponseBody> {
+        self.send_empty(Method::PATCH, &format!("gifts/purchase/{}", gift_id)).await
+    }
+
+    pub async fn find_gift_idea_history(&self, gift_id: i32) -> Result<Vec<shared::AuditEntryResponseBody>> {
+        self.get(&format!("gifts/history/{}", gift_id)).await
+    }
+
+    /// Moves the idea to the trash; `false` when there was nothing to delete.
+    pub async fn delete_gift_idea(&self, gift_id: i32) -> Result<bool> {
+        self.delete(&format!("gifts/delete/{}", gift_id)).await
+    }
+
+    pub async fn find_trash(&self) -> Result<Vec<shared::GiftIdeaResponseBody>> {
+        self.get("gifts/trash").await
+    }
+
+    pub async fn restore_gift_idea(&self, gift_id: i32) -> Result<shared::GiftIdeaResponseBody> {
+        self.send_empty(Method::PATCH, &format!("gifts/restore/{}", gift_id)).await
+    }
+
+    // comments
+
+    pub async fn find_comments_by_gift_idea(&self, gift_id: i32) -> Result<Vec<shared::CommentResponseBody>> {
+        self.get(&format!("comments/find_by_gift_idea/{}", gift_id)).await
+    }
+
+    pub async fn add_comment(&self, gift_id: i32, body: &shared::CommentRequestBody) -> Result<shared::CommentResponseBody> {
+        self.send_json(Method::POST, &format!("comments/add/{}", gift_id), body).await
+    }
+
+    pub async fn update_comment(&self, comment_id: i32, body: &shared::CommentRequestBody) -> Result<shared::CommentResponseBody> {
+        self.send_json(Method::PATCH, &format!("comments/update/{}", comment_id), body).await
+    }
+
+    pub async fn delete_comment(&self, comment_id: i32) -> Result<bool> {
+        self.delete(&format!("comments/delete/{}", comment_id)).await
+    }
+
+    // events
+
+    pub async fn find_all_events(&self) -> Result<Vec<shared::EventResponseBody>> {
+        self.get("events/find_all").await
+    }
+
+    pub async fn find_event_by_id(&self, event_id: i32) -> Result<shared::EventResponseBody> {
+        self.get(&format!("events/find_by_id/{}", event_id)).await
+    }
+
+    pub async fn add_event(&self, body: &shared::EventRequestBody) -> Result<shared::EventResponseBody> {
+        self.send_json(Method::POST, "events/add", body).await
+    }
+
+    pub async fn delete_event(&self, event_id: i32) -> Result<bool> {
+        self.delete(&format!("events/delete/{}", event_id)).await
+    }
+
+    pub async fn archive_event(&self, event_id: i32) -> Result<shared::EventResponseBody> {
+        self.send_empty(Method::PATCH, &format!("events/archive/{}", event_id)).await
+    }
+
+    pub async fn carry_over_event(&self, event_id: i32, body: &shared::CarryOverRequestBody) -> Result<shared::CarryOverResponseBody> {
+        self.send_json(Method::POST, &format!("events/carry_over/{}", event_id), body).await
+    }
+
+    // notifications
+
+    pub async fn find_notification_preferences(&self) -> Result<shared::NotificationPreferencesBody> {
+        self.get("notifications/preferences").await
+    }
+
+    pub async fn update_notification_preferences(&self, body: &shared::NotificationPreferencesBody) -> Result<shared::NotificationPreferencesBody> {
+        self.send_json(Method::PUT, "notifications/preferences", body).await
+    }
+
+    // profile dates
+
+    pub async fn find_user_dates(&self, user_id: i32) -> Result<Vec<shared::UserDateBody>> {
+        self.get(&format!("users/dates/find_by_user/{}", user_id)).await
+    }
+
+    pub async fn add_user_date(&self, body: &shared::UserDateRequest) -> Result<shared::UserDateBody> {
+        self.send_json(Method::POST, "users/dates/add", body).await
+    }
+
+    pub async fn update_user_date(&self, date_id: i32, body: &shared::UserDateRequest) -> Result<shared::UserDateBody> {
+        self.send_json(Method::PUT, &format!("users/dates/update/{}", date_id), body).await
+    }
+
+    pub async fn delete_user_date(&self, date_id: i32) -> Result<bool> {
+        self.delete(&format!("users/dates/delete/{}", date_id)).await
+    }
+
+    // calendar
+
+    pub async fn find_calendar_feed(&self) -> Result<shared::CalendarFeedBody> {
+        self.get("calendar/feed").await
+    }
+
+    /// Creates the feed, or moves it to a new address; the response is the only place the
+    /// address is given.
+    pub async fn create_calendar_feed(&self, body: &shared::CalendarFeedRequest) -> Result<shared::CalendarFeedBody> {
+        self.send_json(Method::POST, "calendar/feed", body).await
+    }
+
+    pub async fn update_calendar_feed(&self, body: &shared::CalendarFeedRequest) -> Result<shared::CalendarFeedBody> {
+        self.send_json(Method::PUT, "calendar/feed", body).await
+    }
+
+    pub async fn delete_calendar_feed(&self) -> Result<bool> {
+        self.delete("calendar/feed").await
+    }
+
+    // attachments
+
+    /// Uploads an image for the gift idea.  In the browser, post the `File` as `FormData` instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn add_attachment(&self, gift_id: i32, file_name: &str, content_type: &str, data: Vec<u8>) -> Result<Vec<shared::AttachmentResponseBody>> {
+        let part = reqwest::multipart::Part::bytes(data).file_name(file_name.to_string()).mime_str(content_type)?;
+        let form = reqwest::multipart::Form::new().part("image", part);
+        self.send(self.request(Method::POST, self.url(&format!("attachments/add/{}", gift_id))).multipart(form))
+            .await
+    }
+
+    pub async fn find_all_attachments(&self) -> Result<Vec<shared::AttachmentResponseBody>> {
+        self.get("attachments/find_all").await
+    }
+
+    pub async fn find_attachments_by_gift_idea(&self, gift_id: i32) -> Result<Vec<shared::AttachmentResponseBody>> {
+        self.get(&format!("attachments/find_by_gift_idea/{}", gift_id)).await
+    }
+
+    pub async fn download_attachment(&self, attachment_id: i32) -> Result<Vec<u8>> {
+        self.send_bytes(self.request(Method::GET, self.url(&format!("attachments/download/{}", attachment_id))))
+            .await
+    }
+
+    /// Always a PNG.
+    pub async fn download_attachment_thumbnail(&self, attachment_id: i32) -> Result<Vec<u8>> {
+        self.send_bytes(self.request(Method::GET, self.url(&format!("attachments/thumbnail/{}", attachment_id))))
+            .await
+    }
+
+    pub async fn delete_attachment(&self, attachment_id: i32) -> Result<bool> {
+        self.delete(&format!("attachments/delete/{}", attachment_id)).await
+    }
+
+    // export and import
+
+    pub async fn export_json(&self) -> Result<shared::ExportDocument> {
+        self.get("export/json").await
+    }
+
+    pub async fn export_csv(&self) -> Result<Vec<u8>> {
+        self.send_bytes(self.request(Method::GET, self.url("export/csv"))).await
+    }
+
+    pub async fn find_my_groups(&self) -> Result<Vec<shared::GroupBody>> {
+        self.get("groups/mine").await
+    }
+
+    /// Every member's ideas for a group the signed in user is in; a 404 for any other group.
+    pub async fn export_group_json(&self, group_id: i32) -> Result<shared::ExportDocument> {
+        self.get(&format!("export/group/{}/json", group_id)).await
+    }
+
+    pub async fn export_group_csv(&self, group_id: i32) -> Result<Vec<u8>> {
+        self.send_bytes(self.request(Method::GET, self.url(&format!("export/group/{}/csv", group_id)))).await
+    }
+
+    /// With `dry_run` nothing is changed; the report says what would have been.
+    pub async fn import(&self, document: &shared::ExportDocument, dry_run: bool) -> Result<shared::ImportReport> {
+        let path = if dry_run { "import/dry_run" } else { "import" };
+        self.send_json(Method::POST, path, document).await
+    }
+
+    // admin
+
+    /// Only for the addresses in the server's `ADMIN_EMAILS`; anyone else gets a 403.
+    pub async fn find_jobs(&self) -> Result<Vec<shared::JobBody>> {
+        self.get("admin/jobs").await
+    }
+
+    pub async fn run_job(&self, name: &str) -> Result<shared::JobBody> {
+        self.send_empty(Method::POST, &format!("admin/jobs/run/{}", name)).await
+    }
+
+    /// The OpenAPI document describing all of the above.
+    pub async fn openapi_document(&self) -> Result<serde_json::Value> {
+        self.get("openapi.json").await
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn build_http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder().cookie_store(true).build()?)
+}
+
+// the browser looks after cookies itself
+#[cfg(target_arch = "wasm32")]
+fn build_http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::new())
+}
@@ -12,9 +12,18 @@ pub use session::Session;
 mod page;
 mod route;
 mod session;
+mod updates;
 
 const STORAGE_KEY: &str = "gift_app";
 
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// The typed API client, pointed at whichever host served the page.
+pub fn api_client() -> api::Client {
+    let origin = seed::window().location().origin().expect("window has no origin");
+    api::Client::new(&origin).expect("page origin is not a valid base url")
+}
+
 lazy_static! {
     pub static ref VALID_EMAIL_REGEX: Regex = Regex::new("^([a-zA-Z0-9_\\-\\.]+)@([a-zA-Z0-9_\\-\\.]+)\\.([a-zA-Z]{2,5})$").unwrap();
 }
@@ -29,6 +38,14 @@ enum Model {
     Profile(page::profile::Model),
     GiftIdeas(page::gift_ideas::Model),
     AddGiftIdea(page::add_gift_idea::Model),
+    ForgotPassword(page::forgot_password::Model),
+    ResetPassword(page::reset_password::Model),
+    VerifyEmail(page::verify_email::Model),
+    GiftIdea(page::gift_idea::Model),
+    Trash(page::trash::Model),
+    ImportWishlist(page::import_wishlist::Model),
+    Events(page::events::Model),
+    Event(page::event::Model),
 }
 
 impl Default for Model {
@@ -47,6 +64,14 @@ impl From<Model> for Session {
             Model::Profile(model) => model.into(),
             Model::GiftIdeas(model) => model.into(),
             Model::AddGiftIdea(model) => model.into(),
+            Model::ForgotPassword(model) => model.into(),
+            Model::ResetPassword(model) => model.into(),
+            Model::VerifyEmail(model) => model.into(),
+            Model::GiftIdea(model) => model.into(),
+            Model::Trash(model) => model.into(),
+            Model::ImportWishlist(model) => model.into(),
+            Model::Events(model) => model.into(),
+            Model::Event(model) => model.into(),
         }
     }
 }
@@ -73,6 +98,14 @@ fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
         Model::Profile(model) => page::profile::sink(g_msg, model, &mut orders.proxy(Msg::ProfileMsg)),
         Model::GiftIdeas(model) => page::gift_ideas::sink(g_msg, model, &mut orders.proxy(Msg::GiftIdeasMsg)),
         Model::AddGiftIdea(model) => page::add_gift_idea::sink(g_msg, model, &mut orders.proxy(Msg::AddGiftIdeaMsg)),
+        Model::ForgotPassword(model) => page::forgot_password::sink(g_msg, model),
+        Model::ResetPassword(model) => page::reset_password::sink(g_msg, model),
+        Model::VerifyEmail(model) => page::verify_email::sink(g_msg, model),
+        Model::GiftIdea(model) => page::gift_idea::sink(g_msg, model, &mut orders.proxy(Msg::GiftIdeaMsg)),
+        Model::Trash(model) => page::trash::sink(g_msg, model, &mut orders.proxy(Msg::TrashMsg)),
+        Model::ImportWishlist(model) => page::import_wishlist::sink(g_msg, model, &mut orders.proxy(Msg::ImportWishlistMsg)),
+        Model::Events(model) => page::events::sink(g_msg, model, &mut orders.proxy(Msg::EventsMsg)),
+        Model::Event(model) => page::event::sink(g_msg, model, &mut orders.proxy(Msg::EventMsg)),
     }
 }
 
@@ -86,6 +119,14 @@ enum Msg {
     ProfileMsg(page::profile::Msg),
     GiftIdeasMsg(page::gift_ideas::Msg),
     AddGiftIdeaMsg(page::add_gift_idea::Msg),
+    ForgotPasswordMsg(page::forgot_password::Msg),
+    ResetPasswordMsg(page::reset_password::Msg),
+    VerifyEmailMsg(page::verify_email::Msg),
+    GiftIdeaMsg(page::gift_idea::Msg),
+    TrashMsg(page::trash::Msg),
+    ImportWishlistMsg(page::import_wishlist::Msg),
+    EventsMsg(page::events::Msg),
+    EventMsg(page::event::Msg),
 }
 
 fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -123,6 +164,46 @@ fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
                 page::add_gift_idea::update(module_msg, module_model, &mut orders.proxy(Msg::AddGiftIdeaMsg));
             }
         }
+        Msg::ForgotPasswordMsg(module_msg) => {
+            if let Model::ForgotPassword(module_model) = model {
+                page::forgot_password::update(module_msg, module_model, &mut orders.proxy(Msg::ForgotPasswordMsg));
+            }
+        }
+        Msg::ResetPasswordMsg(module_msg) => {
+            if let Model::ResetPassword(module_model) = model {
+                page::reset_password::update(module_msg, module_model, &mut orders.proxy(Msg::ResetPasswordMsg));
+            }
+        }
+        Msg::VerifyEmailMsg(module_msg) => {
+            if let Model::VerifyEmail(module_model) = model {
+                page::verify_email::update(module_msg, module_model, &mut orders.proxy(Msg::VerifyEmailMsg));
+            }
+        }
+        Msg::GiftIdeaMsg(module_msg) => {
+            if let Model::GiftIdea(module_model) = model {
+                page::gift_idea::update(module_msg, module_model, &mut orders.proxy(Msg::GiftIdeaMsg));
+            }
+        }
+        Msg::TrashMsg(module_msg) => {
+            if let Model::Trash(module_model) = model {
+                page::trash::update(module_msg, module_model, &mut orders.proxy(Msg::TrashMsg));
+            }
+        }
+        Msg::ImportWishlistMsg(module_msg) => {
+            if let Model::ImportWishlist(module_model) = model {
+                page::import_wishlist::update(module_msg, module_model, &mut orders.proxy(Msg::ImportWishlistMsg));
+            }
+        }
+        Msg::EventsMsg(module_msg) => {
+            if let Model::Events(module_model) = model {
+                page::events::update(module_msg, module_model, &mut orders.proxy(Msg::EventsMsg));
+            }
+        }
+        Msg::EventMsg(module_msg) => {
+            if let Model::Event(module_model) = model {
+                page::event::update(module_msg, module_model, &mut orders.proxy(Msg::EventMsg));
+            }
+        }
     }
 }
 
@@ -132,6 +213,12 @@ fn change_model_by_route(route: Option<Route>, model: &mut Model, orders: &mut i
         None => *model = Model::NotFound(session()),
         Some(route) => match route {
             Route::Logout => {
+                let client = api_client();
+                orders.perform_cmd(async move {
+                    if let Err(error) = client.logout().await {
+                        seed::log!("error logging out", error);
+                    }
+                });
                 LocalStorage::remove(STORAGE_KEY).expect("remove saved user");
                 orders.send_g_msg(GMsg::SessionChanged(Session::Guest));
                 route::go_to(Route::Home, orders)
@@ -148,11 +235,45 @@ fn change_model_by_route(route: Option<Route>, model: &mut Model, orders: &mut i
             Route::Profile => {
                 *model = Model::Profile(page::profile::init(session(), &mut orders.proxy(Msg::ProfileMsg)));
             }
-            Route::GiftIdeas => {
-                *model = Model::GiftIdeas(page::gift_ideas::init(session(), &mut orders.proxy(Msg::GiftIdeasMsg)));
+            Route::GiftIdeas(filter) => {
+                *model = Model::GiftIdeas(page::gift_ideas::init(session(), filter, &mut orders.proxy(Msg::GiftIdeasMsg)));
+            }
+            Route::GiftIdea(gift_idea_id) => {
+                *model = Model::GiftIdea(page::gift_idea::init(session(), gift_idea_id, &mut orders.proxy(Msg::GiftIdeaMsg)));
+            }
+            Route::Trash => {
+                *model = Model::Trash(page::trash::init(session(), &mut orders.proxy(Msg::TrashMsg)));
+            }
+            Route::ImportWishlist => {
+                *model = Model::ImportWishlist(page::import_wishlist::init(session(), &mut orders.proxy(Msg::ImportWishlistMsg)));
+            }
+            Route::Wishlist(user_id) => {
+                let filter = route::GiftIdeasFilter {
+                    recipient: Some(user_id),
+                    ..route::GiftIdeasFilter::default()
+                };
+                *model = Model::GiftIdeas(page::gift_ideas::init(session(), filter, &mut orders.proxy(Msg::GiftIdeasMsg)));
+            }
+            Route::Events => {
+                *model = Model::Events(page::events::init(session(), &mut orders.proxy(Msg::EventsMsg)));
+            }
+            Route::Event(event_id) => {
+                *model = Model::Event(page::event::init(session(), event_id, &mut orders.proxy(Msg::EventMsg)));
             }
             Route::AddGiftIdea => {
-                *model = Model::AddGiftIdea(page::add_gift_idea::init(session(), &mut orders.proxy(Msg::AddGiftIdeaMsg)));
+                *model = Model::AddGiftIdea(page::add_gift_idea::init(session(), None, &mut orders.proxy(Msg::AddGiftIdeaMsg)));
+            }
+            Route::EditGiftIdea(gift_idea_id) => {
+                *model = Model::AddGiftIdea(page::add_gift_idea::init(session(), Some(gift_idea_id), &mut orders.proxy(Msg::AddGiftIdeaMsg)));
+            }
+            Route::ForgotPassword => {
+                *model = Model::ForgotPassword(page::forgot_password::init(session()));
+            }
+            Route::ResetPassword(token) => {
+                *model = Model::ResetPassword(page::reset_password::init(session(), token));
+            }
+            Route::VerifyEmail(token) => {
+                *model = Model::VerifyEmail(page::verify_email::init(session(), token, &mut orders.proxy(Msg::VerifyEmailMsg)));
             }
         },
     };
@@ -170,6 +291,18 @@ fn view(model: &Model) -> impl IntoNodes<Msg> {
         Model::AddGiftIdea(model) => Page::AddGiftIdea
             .view(page::add_gift_idea::view(model), model.session().viewer())
             .map_msg(Msg::AddGiftIdeaMsg),
+        Model::ForgotPassword(model) => Page::Other
+            .view(page::forgot_password::view(model), model.session().viewer())
+            .map_msg(Msg::ForgotPasswordMsg),
+        Model::ResetPassword(model) => Page::Other.view(page::reset_password::view(model), model.session().viewer()).map_msg(Msg::ResetPasswordMsg),
+        Model::VerifyEmail(model) => Page::Other.view(page::verify_email::view(model), model.session().viewer()).map_msg(Msg::VerifyEmailMsg),
+        Model::GiftIdea(model) => Page::GiftIdeas.view(page::gift_idea::view(model), model.session().viewer()).map_msg(Msg::GiftIdeaMsg),
+        Model::Trash(model) => Page::GiftIdeas.view(page::trash::view(model), model.session().viewer()).map_msg(Msg::TrashMsg),
+        Model::ImportWishlist(model) => Page::GiftIdeas
+            .view(page::import_wishlist::view(model), model.session().viewer())
+            .map_msg(Msg::ImportWishlistMsg),
+        Model::Events(model) => Page::Events.view(page::events::view(model), model.session().viewer()).map_msg(Msg::EventsMsg),
+        Model::Event(model) => Page::Events.view(page::event::view(model), model.session().viewer()).map_msg(Msg::EventMsg),
     }
 }
 
@@ -4,12 +4,20 @@ use seed::*;
 
 pub mod add_gift_idea;
 pub mod blank;
+pub mod event;
+pub mod events;
+pub mod forgot_password;
+pub mod gift_idea;
 pub mod gift_ideas;
 pub mod home;
+pub mod import_wishlist;
 pub mod login;
 pub mod not_found;
 pub mod profile;
 pub mod register;
+pub mod reset_password;
+pub mod trash;
+pub mod verify_email;
 
 // pub fn scroll_to_top() {
 //     seed::window().scroll_to_with_scroll_to_options(web_sys::ScrollToOptions::new().top(0.).left(0.).behavior(web_sys::ScrollBehavior::Smooth))
@@ -67,6 +75,7 @@ pub enum Page {
     Profile,
     GiftIdeas,
     AddGiftIdea,
+    Events,
 }
 
 #[allow(clippy::unused_self)]
@@ -82,10 +91,11 @@ impl Page {
             None => empty![],
             Some(viewer) => nav![
                 class!["navbar", "navbar-expand-lg", "navbar-dark", "bg-dark"],
-                a![class!["navbar-brand"], attrs! {At::Href => Route::GiftIdeas.to_string()}, "Gift Ideas"],
+                a![class!["navbar-brand"], attrs! {At::Href => Route::GiftIdeas(Default::default()).to_string()}, "Gift Ideas"],
                 ul![
                     class!["navbar-nav"],
                     self.view_navbar_link(&Route::AddGiftIdea, "Add Gift Idea"),
+                    self.view_navbar_link(&Route::Events, "Events"),
                     self.view_navbar_link(&Route::Profile, "Profile"),
                     self.view_navbar_link(&Route::Logout, "Logout"),
                 ],
@@ -110,8 +120,9 @@ impl Page {
             | (Page::Login, Route::Login)
             | (Page::Register, Route::Register)
             | (Page::Profile, Route::Profile)
-            | (Page::GiftIdeas, Route::GiftIdeas)
-            | (Page::AddGiftIdea, Route::AddGiftIdea) => true,
+            | (Page::GiftIdeas, Route::GiftIdeas(_))
+            | (Page::AddGiftIdea, Route::AddGiftIdea)
+            | (Page::Events, Route::Events) => true,
             _ => false,
         }
     }
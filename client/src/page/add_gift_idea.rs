@@ -14,6 +14,8 @@ pub struct Form {
     url: String,
     owner_id: i32,
     recipient_user_id: i32,
+    // 0 when the idea isn't for any particular event
+    event_id: i32,
 }
 
 impl From<Form> for shared::GiftIdeaRequestBody {
@@ -25,16 +27,62 @@ impl From<Form> for shared::GiftIdeaRequestBody {
             url: Some(form.url),
             owner_id: form.owner_id,
             recipient_user_id: form.recipient_user_id,
+            event_id: Some(form.event_id).filter(|e| *e > 0),
         }
     }
 }
 
+impl From<Form> for shared::UpdateGiftIdeaRequest {
+    fn from(form: Form) -> shared::UpdateGiftIdeaRequest {
+        shared::UpdateGiftIdeaRequest {
+            title: Some(form.title),
+            description: Some(form.description),
+            price: Some(form.price),
+            url: Some(form.url),
+            recipient_user_id: Some(form.recipient_user_id),
+            event_id: Some(form.event_id),
+        }
+    }
+}
+
+impl From<shared::GiftIdeaResponseBody> for Form {
+    fn from(gift_idea: shared::GiftIdeaResponseBody) -> Form {
+        Form {
+            title: gift_idea.title,
+            description: gift_idea.description.unwrap_or_default(),
+            price: gift_idea.price.unwrap_or_default(),
+            url: gift_idea.url.unwrap_or_default(),
+            owner_id: gift_idea.owner_id,
+            recipient_user_id: gift_idea.recipient_user_id,
+            event_id: gift_idea.event_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl Form {
+    fn validate(&self) -> collections::HashMap<String, String> {
+        let mut problems = collections::HashMap::new();
+        if self.title.trim().is_empty() {
+            problems.insert("title".to_string(), "Title is required".to_string());
+        }
+        if self.recipient_user_id == 0 {
+            problems.insert("recipient".to_string(), "Choose who the gift is for".to_string());
+        }
+        problems
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct Model {
     session: Session,
+    // set when editing an existing idea rather than adding one
+    gift_idea_id: Option<i32>,
     recipients: Vec<shared::MinimalUserInfo>,
+    events: Vec<shared::EventResponseBody>,
     problems: collections::HashMap<String, String>,
     form: Form,
+    image: Option<web_sys::File>,
+    link_metadata: Option<shared::LinkMetadataResponseBody>,
 }
 
 impl Model {
@@ -49,19 +97,27 @@ impl From<Model> for Session {
     }
 }
 
-pub fn init(session: Session, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+pub fn init(session: Session, gift_idea_id: Option<i32>, orders: &mut impl Orders<Msg, GMsg>) -> Model {
     let user = LocalStorage::get(crate::STORAGE_KEY).ok();
     let session = Session::new(user.clone());
     if session.viewer().is_none() {
         route::go_to(route::Route::Login, orders);
     }
 
-    let request = Request::new("/v1/users/find_all").method(Method::Get);
-    orders.perform_cmd(async { Msg::RecipientNamesLoaded(async { request.fetch().await?.check_status()?.json().await }.await) });
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::RecipientNamesLoaded(client.find_all_users().await) });
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::EventsLoaded(client.find_all_events().await) });
+
+    if let Some(gift_idea_id) = gift_idea_id {
+        let client = crate::api_client();
+        orders.perform_cmd(async move { Msg::GiftIdeaLoaded(client.find_gift_idea_by_id(gift_idea_id).await) });
+    }
 
     let mut model = Model::default();
     model.form.owner_id = session.viewer().unwrap().id.clone();
-    Model { session, ..model }
+    Model { session, gift_idea_id, ..model }
 }
 
 pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -83,10 +139,17 @@ pub enum Msg {
     PriceChanged(String),
     URLChanged(String),
     RecipientChanged(String),
+    EventChanged(String),
+    ImageChanged(Option<web_sys::File>),
+    LinkMetadataRequested,
+    LinkMetadataFetched(api::Result<shared::LinkMetadataResponseBody>),
     AddGiftIdeaSubmitted,
-    AddGiftIdeaFetched(fetch::Result<shared::GiftIdeaResponseBody>),
+    AddGiftIdeaFetched(api::Result<shared::GiftIdeaResponseBody>),
     AddGiftIdeaCancelled,
-    RecipientNamesLoaded(fetch::Result<Vec<shared::MinimalUserInfo>>),
+    RecipientNamesLoaded(api::Result<Vec<shared::MinimalUserInfo>>),
+    EventsLoaded(api::Result<Vec<shared::EventResponseBody>>),
+    GiftIdeaLoaded(api::Result<shared::GiftIdeaResponseBody>),
+    ImageUploaded(fetch::Result<Vec<shared::AttachmentResponseBody>>),
 }
 
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -98,34 +161,98 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
         Msg::RecipientChanged(recipient_user_id) => {
             model.form.recipient_user_id = recipient_user_id.parse::<i32>().unwrap();
         }
-        Msg::AddGiftIdeaCancelled => route::go_to(route::Route::GiftIdeas, orders),
+        Msg::EventChanged(event_id) => model.form.event_id = event_id.parse::<i32>().unwrap(),
+        Msg::AddGiftIdeaCancelled => route::go_to(route::Route::GiftIdeas(Default::default()), orders),
         Msg::AddGiftIdeaSubmitted => {
-            if !model.form.title.is_empty() && model.form.recipient_user_id != 0 {
-                let request = Request::new("/v1/gifts/add")
-                    .method(Method::Post)
-                    .json::<shared::GiftIdeaRequestBody>(&model.form.clone().into());
-                orders.perform_cmd(async { Msg::AddGiftIdeaFetched(async { request?.fetch().await?.check_status()?.json().await }.await) });
+            model.problems = model.form.validate();
+            if model.problems.is_empty() {
+                let client = crate::api_client();
+                match model.gift_idea_id {
+                    Some(gift_idea_id) => {
+                        let body: shared::UpdateGiftIdeaRequest = model.form.clone().into();
+                        orders.perform_cmd(async move { Msg::AddGiftIdeaFetched(client.update_gift_idea(gift_idea_id, &body).await) });
+                    }
+                    None => {
+                        let body: shared::GiftIdeaRequestBody = model.form.clone().into();
+                        orders.perform_cmd(async move { Msg::AddGiftIdeaFetched(client.add_gift_idea(&body).await) });
+                    }
+                }
+            }
+        }
+        Msg::GiftIdeaLoaded(Ok(gift_idea)) => {
+            if model.session.viewer().map_or(false, |viewer| viewer.id == gift_idea.owner_id) {
+                model.form = gift_idea.into();
+            } else {
+                route::go_to(route::Route::GiftIdeas(Default::default()), orders);
+            }
+        }
+        Msg::GiftIdeaLoaded(Err(error)) => {
+            log!(error);
+            route::go_to(route::Route::GiftIdeas(Default::default()), orders);
+        }
+        Msg::ImageChanged(image) => model.image = image,
+        Msg::LinkMetadataRequested => {
+            if !model.form.url.is_empty() {
+                let client = crate::api_client();
+                let body = shared::LinkMetadataRequestBody { url: model.form.url.clone() };
+                orders.perform_cmd(async move { Msg::LinkMetadataFetched(client.find_link_metadata(&body).await) });
             }
         }
-        Msg::AddGiftIdeaFetched(Ok(gift)) => {
-            route::go_to(route::Route::GiftIdeas, orders);
+        Msg::LinkMetadataFetched(Ok(metadata)) => {
+            if model.form.title.is_empty() {
+                model.form.title = metadata.title.clone().unwrap_or_default();
+            }
+            if model.form.description.is_empty() {
+                model.form.description = metadata.description.clone().unwrap_or_default();
+            }
+            if model.form.price.is_empty() {
+                model.form.price = match (&metadata.price, &metadata.currency) {
+                    (Some(price), Some(currency)) => format!("{} {}", price, currency),
+                    (Some(price), None) => price.clone(),
+                    _ => String::new(),
+                };
+            }
+            model.link_metadata = Some(metadata);
         }
+        Msg::LinkMetadataFetched(Err(error)) => log!(error),
+        Msg::AddGiftIdeaFetched(Ok(gift)) => match model.image.take() {
+            Some(image) => {
+                let form_data = web_sys::FormData::new().unwrap();
+                form_data.append_with_blob_and_filename("image", &image, &image.name()).unwrap();
+                // the api client can't post a browser `File`, so this one goes through seed's fetch
+                let request = Request::new(format!("/v1/attachments/add/{}", gift.id)).method(Method::Post).body(form_data.into());
+                orders.perform_cmd(async { Msg::ImageUploaded(async { request.fetch().await?.check_status()?.json().await }.await) });
+            }
+            None => route::go_to(route::Route::GiftIdeas(Default::default()), orders),
+        },
         Msg::AddGiftIdeaFetched(Err(error)) => log!(error),
         Msg::RecipientNamesLoaded(Ok(recipients)) => {
             model.recipients = recipients;
         }
         Msg::RecipientNamesLoaded(Err(error)) => log!(error),
+        Msg::EventsLoaded(Ok(events)) => model.events = events,
+        Msg::EventsLoaded(Err(error)) => log!(error),
+        Msg::ImageUploaded(Ok(_)) => route::go_to(route::Route::GiftIdeas(Default::default()), orders),
+        Msg::ImageUploaded(Err(error)) => log!(error),
+    }
+}
+
+fn view_problem(model: &Model, field: &str) -> Node<Msg> {
+    match model.problems.get(field) {
+        Some(problem) => div![class!["invalid-feedback", "d-block"], problem],
+        None => empty![],
     }
 }
 
 pub fn view(model: &Model) -> ViewPage<Msg> {
+    let title = if model.gift_idea_id.is_some() { "Edit Gift Idea" } else { "Add Gift Idea" };
     ViewPage::new(
-        "Add Gift Idea",
+        title,
         div![
             class!["col-md-4", "offset-md-4" "mt-4"],
             div![
                 attrs![At::Class => "card"],
-                h4![attrs! { At::Class => "card-header" }, "Add Gift Idea",],
+                h4![attrs! { At::Class => "card-header" }, title,],
                 div![
                     attrs! { At::Class => "card-body" },
                     form![
@@ -139,12 +266,32 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                     .recipients
                                     .iter()
                                     .map(|e| option![
-                                        attrs! [ At::Value => e.id.to_string() ],
+                                        attrs! [ At::Value => e.id.to_string(), At::Selected => (e.id == model.form.recipient_user_id).as_at_value() ],
                                         format!("{} {}", e.first_name.as_ref().unwrap_or(&"".to_string()), e.last_name.as_ref().unwrap_or(&"".to_string()))
                                     ])
                                     .collect::<Vec<seed::virtual_dom::node::Node<Msg>>>(),
                                 input_ev(Ev::Input, Msg::RecipientChanged)
                             ],
+                            view_problem(model, "recipient"),
+                        ],
+                        div![
+                            attrs! { At::Class => "form-group" },
+                            label![class!["control-label"], "Event"],
+                            select![
+                                class!["form-control"],
+                                option![attrs![ At::Value => "0" ], "None"],
+                                model
+                                    .events
+                                    .iter()
+                                    // an archived event can't take new ideas, but keep showing the one this idea is already under
+                                    .filter(|e| e.date_archived.is_none() || e.id == model.form.event_id)
+                                    .map(|e| option![
+                                        attrs! [ At::Value => e.id.to_string(), At::Selected => (e.id == model.form.event_id).as_at_value() ],
+                                        format!("{} ({})", e.name, e.event_date.format("%Y-%m-%d"))
+                                    ])
+                                    .collect::<Vec<seed::virtual_dom::node::Node<Msg>>>(),
+                                input_ev(Ev::Input, Msg::EventChanged)
+                            ],
                         ],
                         div![
                             attrs! { At::Class => "form-group" },
@@ -153,6 +300,7 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                 attrs! { At::Type => "text", At::Class => "form-control", At::Value => model.form.title, },
                                 input_ev(Ev::Input, Msg::TitleChanged)
                             ],
+                            view_problem(model, "title"),
                         ],
                         div![
                             attrs! { At::Class => "form-group" },
@@ -175,10 +323,40 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                         div![
                             attrs! { At::Class => "form-group" },
                             label![class!["control-label"], "URL"],
+                            div![
+                                class!["input-group"],
+                                input![
+                                    class!["form-control"],
+                                    attrs! { At::Type => "text", At::Value => model.form.url },
+                                    input_ev(Ev::Input, Msg::URLChanged)
+                                ],
+                                div![
+                                    class!["input-group-append"],
+                                    button![
+                                        class!["btn", "btn-outline-secondary"],
+                                        "Fetch Details",
+                                        ev(Ev::Click, |event| {
+                                            event.prevent_default();
+                                            Msg::LinkMetadataRequested
+                                        })
+                                    ],
+                                ],
+                            ],
+                            match model.link_metadata.as_ref().and_then(|metadata| metadata.image.as_ref()) {
+                                Some(image) => img![class!["img-thumbnail", "mt-2"], attrs! { At::Src => image, At::Width => "160" }],
+                                None => empty![],
+                            },
+                        ],
+                        div![
+                            attrs! { At::Class => "form-group" },
+                            label![class!["control-label"], "Image"],
                             input![
-                                class!["form-control"],
-                                attrs! { At::Type => "text", At::Value => model.form.url },
-                                input_ev(Ev::Input, Msg::URLChanged)
+                                class!["form-control-file"],
+                                attrs! { At::Type => "file", At::Accept => "image/png,image/jpeg,image/gif,image/webp" },
+                                ev(Ev::Change, |event| {
+                                    let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                                    Msg::ImageChanged(input.files().and_then(|files| files.get(0)))
+                                })
                             ],
                         ],
                         div![
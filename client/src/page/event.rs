@@ -0,0 +1,246 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use chrono::NaiveDate;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    event: Option<shared::EventResponseBody>,
+    gift_ideas: Vec<shared::GiftIdeaResponseBody>,
+    problem: Option<String>,
+    // the event the un-given ideas get carried over into
+    carry_over_name: String,
+    carry_over_date: String,
+    carry_over_problem: Option<String>,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, event_id: i32, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+    let user = LocalStorage::get(crate::STORAGE_KEY).ok();
+    let session = Session::new(user.clone());
+    if session.viewer().is_none() {
+        route::go_to(route::Route::Login, orders);
+    }
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::EventLoaded(client.find_event_by_id(event_id).await) });
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::GiftIdeasLoaded(client.find_gift_ideas_by_event(event_id).await) });
+
+    Model { session, ..Model::default() }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match g_msg {
+        GMsg::SessionChanged(session) => {
+            model.session = session;
+            route::go_to(route::Route::Login, orders);
+        }
+        _ => (),
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    EventLoaded(api::Result<shared::EventResponseBody>),
+    GiftIdeasLoaded(api::Result<Vec<shared::GiftIdeaResponseBody>>),
+    ArchiveEvent,
+    EventArchived(api::Result<shared::EventResponseBody>),
+    CarryOverNameChanged(String),
+    CarryOverDateChanged(String),
+    CarryOverSubmitted,
+    CarriedOver(api::Result<shared::CarryOverResponseBody>),
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::EventLoaded(Ok(event)) => {
+            if model.carry_over_name.is_empty() {
+                model.carry_over_name = event.name.clone();
+            }
+            model.event = Some(event);
+        }
+        Msg::EventLoaded(Err(error)) => {
+            log!("error loading event", error);
+            model.problem = Some("That event doesn't exist".to_string());
+        }
+        Msg::GiftIdeasLoaded(Ok(mut gift_ideas)) => {
+            let user_id = model.session.viewer().map_or(0, |viewer| viewer.id);
+            gift_ideas.retain(|e| e.recipient_user_id != user_id);
+            model.gift_ideas = gift_ideas;
+        }
+        Msg::GiftIdeasLoaded(Err(error)) => log!("error loading gift ideas", error),
+        Msg::ArchiveEvent => {
+            if let Some(event) = &model.event {
+                let client = crate::api_client();
+                let event_id = event.id;
+                orders.perform_cmd(async move { Msg::EventArchived(client.archive_event(event_id).await) });
+            }
+        }
+        Msg::EventArchived(Ok(event)) => {
+            let client = crate::api_client();
+            let event_id = event.id;
+            orders.perform_cmd(async move { Msg::GiftIdeasLoaded(client.find_gift_ideas_by_event(event_id).await) });
+            model.event = Some(event);
+        }
+        Msg::EventArchived(Err(error)) => log!("error archiving event", error),
+        Msg::CarryOverNameChanged(name) => model.carry_over_name = name,
+        Msg::CarryOverDateChanged(event_date) => model.carry_over_date = event_date,
+        Msg::CarryOverSubmitted => {
+            let name = model.carry_over_name.trim().to_string();
+            let event_date = NaiveDate::parse_from_str(&model.carry_over_date, "%Y-%m-%d");
+            match (&model.event, name.is_empty(), event_date) {
+                (None, _, _) => (),
+                (_, true, _) => model.carry_over_problem = Some("Name is required".to_string()),
+                (_, _, Err(_)) => model.carry_over_problem = Some("Date is required".to_string()),
+                (Some(event), false, Ok(event_date)) => {
+                    model.carry_over_problem = None;
+                    let client = crate::api_client();
+                    let event_id = event.id;
+                    let body = shared::CarryOverRequestBody { name, event_date };
+                    orders.perform_cmd(async move { Msg::CarriedOver(client.carry_over_event(event_id, &body).await) });
+                }
+            }
+        }
+        Msg::CarriedOver(Ok(carried_over)) => route::go_to(route::Route::Event(carried_over.event.id), orders),
+        Msg::CarriedOver(Err(error)) => {
+            log!("error carrying over event", error);
+            model.carry_over_problem = Some("Couldn't carry the ideas over".to_string());
+        }
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    let title = model.event.as_ref().map_or_else(|| "Event".to_string(), |e| e.name.clone());
+    ViewPage::new(
+        &title,
+        div![
+            class!["col-md-6", "offset-md-3" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, &title],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    match (&model.event, &model.problem) {
+                        (_, Some(problem)) => p![problem],
+                        (None, None) => p!["Loading..."],
+                        (Some(event), None) => build_event(event, model),
+                    },
+                    a![attrs! { At::Href => route::Route::Events.to_string() }, "Back to events"],
+                ],
+            ],
+        ],
+    )
+}
+
+fn build_event(event: &shared::EventResponseBody, model: &Model) -> Node<Msg> {
+    let is_owner = model.session.viewer().map_or(false, |viewer| viewer.id == event.owner_id);
+    let days_remaining = (event.event_date - chrono::Local::today().naive_local()).num_days();
+    div![
+        p![strong!["Date: "], event.event_date.format("%A, %B %-d, %Y").to_string()],
+        p![match days_remaining {
+            0 => "Today".to_string(),
+            1 => "Tomorrow".to_string(),
+            days if days < 0 => format!("{} days ago", -days),
+            days => format!("{} days away", days),
+        }],
+        match event.date_archived {
+            Some(date_archived) => p![span![class!["badge", "badge-secondary"], format!("Archived {}", date_archived.format("%Y-%m-%d"))]],
+            None => p![a![
+                attrs! { At::Href => route::Route::GiftIdeas(route::GiftIdeasFilter { status: Some(route::GiftIdeaStatus::Open), ..route::GiftIdeasFilter::default() }).to_string() },
+                "Browse open gift ideas"
+            ]],
+        },
+        build_gift_ideas(&model.gift_ideas),
+        match (is_owner, event.date_archived) {
+            (false, _) => empty![],
+            (true, None) => p![
+                class!["small", "text-muted"],
+                "Once it's over, archiving the event takes its ideas out of the gift idea list. ",
+                button![
+                    class!["btn", "btn-secondary", "btn-sm"],
+                    "Archive",
+                    ev(Ev::Click, |event| {
+                        event.prevent_default();
+                        Msg::ArchiveEvent
+                    })
+                ],
+            ],
+            (true, Some(_)) => build_carry_over_form(model),
+        },
+    ]
+}
+
+fn build_gift_ideas(gift_ideas: &[shared::GiftIdeaResponseBody]) -> Node<Msg> {
+    if gift_ideas.is_empty() {
+        return p![class!["text-muted"], "No gift ideas are filed under this event."];
+    }
+    ul![
+        class!["list-group", "mb-3"],
+        gift_ideas
+            .iter()
+            .map(|gift_idea| li![
+                class!["list-group-item"],
+                a![attrs! { At::Href => route::Route::GiftIdea(gift_idea.id).to_string() }, &gift_idea.title],
+                if gift_idea.date_purchased.is_some() {
+                    span![class!["badge", "badge-success", "ml-2"], "Purchased"]
+                } else if gift_idea.reserved_by_user_id.is_some() {
+                    span![class!["badge", "badge-info", "ml-2"], "Reserved"]
+                } else {
+                    empty![]
+                },
+            ])
+            .collect::<Vec<_>>(),
+    ]
+}
+
+fn build_carry_over_form(model: &Model) -> Node<Msg> {
+    div![
+        h5!["Carry over"],
+        p![
+            class!["small", "text-muted"],
+            "Copies every idea that was never purchased into a new event. Reservations, comments and images aren't copied."
+        ],
+        form![
+            class!["form-inline"],
+            match &model.carry_over_problem {
+                Some(problem) => div![class!["alert", "alert-danger", "w-100"], problem],
+                None => empty![],
+            },
+            input![
+                class!["form-control", "mr-2"],
+                attrs! { At::Type => "text", At::Placeholder => "Name", At::Value => model.carry_over_name },
+                input_ev(Ev::Input, Msg::CarryOverNameChanged)
+            ],
+            input![
+                class!["form-control", "mr-2"],
+                attrs! { At::Type => "date", At::Value => model.carry_over_date },
+                input_ev(Ev::Input, Msg::CarryOverDateChanged)
+            ],
+            button![
+                class!["btn", "btn-primary"],
+                "Carry Over",
+                ev(Ev::Click, |event| {
+                    event.prevent_default();
+                    Msg::CarryOverSubmitted
+                })
+            ],
+        ],
+    ]
+}
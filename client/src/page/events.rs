@@ -0,0 +1,189 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use chrono::NaiveDate;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    events: Vec<shared::EventResponseBody>,
+    name: String,
+    event_date: String,
+    problem: Option<String>,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+    let user = LocalStorage::get(crate::STORAGE_KEY).ok();
+    let session = Session::new(user.clone());
+    if session.viewer().is_none() {
+        route::go_to(route::Route::Login, orders);
+    }
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::EventsLoaded(client.find_all_events().await) });
+
+    Model { session, ..Model::default() }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match g_msg {
+        GMsg::SessionChanged(session) => {
+            model.session = session;
+            route::go_to(route::Route::Login, orders);
+        }
+        _ => (),
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    EventsLoaded(api::Result<Vec<shared::EventResponseBody>>),
+    NameChanged(String),
+    EventDateChanged(String),
+    AddEventSubmitted,
+    EventAdded(api::Result<shared::EventResponseBody>),
+    DeleteEvent(i32),
+    EventDeleted(i32, api::Result<bool>),
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::EventsLoaded(Ok(events)) => model.events = events,
+        Msg::EventsLoaded(Err(error)) => log!("error loading events", error),
+        Msg::NameChanged(name) => model.name = name,
+        Msg::EventDateChanged(event_date) => model.event_date = event_date,
+        Msg::AddEventSubmitted => {
+            let name = model.name.trim().to_string();
+            let event_date = NaiveDate::parse_from_str(&model.event_date, "%Y-%m-%d");
+            match (name.is_empty(), event_date) {
+                (true, _) => model.problem = Some("Name is required".to_string()),
+                (_, Err(_)) => model.problem = Some("Date is required".to_string()),
+                (false, Ok(event_date)) => {
+                    model.problem = None;
+                    let client = crate::api_client();
+                    let body = shared::EventRequestBody { name, event_date };
+                    orders.perform_cmd(async move { Msg::EventAdded(client.add_event(&body).await) });
+                }
+            }
+        }
+        Msg::EventAdded(Ok(event)) => {
+            model.name.clear();
+            model.event_date.clear();
+            model.events.push(event);
+            model.events.sort_by_key(|e| e.event_date);
+        }
+        Msg::EventAdded(Err(error)) => {
+            log!("error adding event", error);
+            model.problem = Some("Couldn't add the event".to_string());
+        }
+        Msg::DeleteEvent(event_id) => {
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::EventDeleted(event_id, client.delete_event(event_id).await) });
+        }
+        Msg::EventDeleted(event_id, Ok(true)) => model.events.retain(|e| e.id != event_id),
+        Msg::EventDeleted(event_id, Ok(false)) => log!("event was not deleted: ", event_id),
+        Msg::EventDeleted(_, Err(error)) => log!("error deleting event", error),
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    let user_id = model.session.viewer().map_or(0, |viewer| viewer.id);
+    ViewPage::new(
+        "Events",
+        div![
+            class!["col-md-8", "offset-md-2" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, "Events",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    build_table(model.events.iter().filter(|e| e.date_archived.is_none()), user_id, "No Data"),
+                    form![
+                        class!["form-inline"],
+                        match &model.problem {
+                            Some(problem) => div![class!["alert", "alert-danger", "w-100"], problem],
+                            None => empty![],
+                        },
+                        input![
+                            class!["form-control", "mr-2"],
+                            attrs! { At::Type => "text", At::Placeholder => "Name", At::Value => model.name },
+                            input_ev(Ev::Input, Msg::NameChanged)
+                        ],
+                        input![
+                            class!["form-control", "mr-2"],
+                            attrs! { At::Type => "date", At::Value => model.event_date },
+                            input_ev(Ev::Input, Msg::EventDateChanged)
+                        ],
+                        button![
+                            class!["btn", "btn-primary"],
+                            "Add Event",
+                            ev(Ev::Click, |event| {
+                                event.prevent_default();
+                                Msg::AddEventSubmitted
+                            })
+                        ],
+                    ],
+                    h5![class!["mt-4"], "Archived"],
+                    build_table(model.events.iter().filter(|e| e.date_archived.is_some()), user_id, "Nothing archived yet"),
+                ],
+            ],
+        ],
+    )
+}
+
+fn build_table<'a>(events: impl Iterator<Item = &'a shared::EventResponseBody>, user_id: i32, empty_text: &str) -> Node<Msg> {
+    let rows = events.map(|e| build_row(e, user_id)).collect::<Vec<_>>();
+    table![
+        class!["table", "table-striped"],
+        thead![
+            class!["thead-dark"],
+            tr![
+                th![attrs![ At::Scope => "col", At::Width => "50%"], "Name"],
+                th![attrs![ At::Scope => "col", At::Width => "30%"], "Date"],
+                th![attrs![ At::Scope => "col", At::Width => "20%"], "Actions"],
+            ],
+        ],
+        tbody![if rows.is_empty() {
+            vec![tr![td![attrs![ At::ColSpan => "3"], empty_text]]]
+        } else {
+            rows
+        }],
+    ]
+}
+
+fn build_row(event: &shared::EventResponseBody, user_id: i32) -> Node<Msg> {
+    let event_id = event.id;
+    tr![
+        th![
+            attrs![ At::Scope => "row"],
+            a![attrs! { At::Href => route::Route::Event(event_id).to_string() }, &event.name],
+        ],
+        td![event.event_date.format("%Y-%m-%d").to_string()],
+        td![match event.owner_id == user_id {
+            true => a![
+                class!["btn", "btn-primary", "btn-sm", "text-white"],
+                "Delete",
+                ev(Ev::Click, move |event| {
+                    event.prevent_default();
+                    Msg::DeleteEvent(event_id)
+                })
+            ],
+            _ => empty![],
+        }],
+    ]
+}
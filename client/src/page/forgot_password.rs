@@ -0,0 +1,110 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    email: String,
+    submitted: bool,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session) -> Model {
+    Model { session, ..Model::default() }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model) {
+    if let GMsg::SessionChanged(session) = g_msg {
+        model.session = session;
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    EmailChanged(String),
+    Submitted,
+    Cancelled,
+    Requested(api::Result<bool>),
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::EmailChanged(email) => model.email = email,
+        Msg::Submitted => {
+            if crate::VALID_EMAIL_REGEX.is_match(model.email.trim()) {
+                let client = crate::api_client();
+                let body = shared::ForgotPasswordRequestBody {
+                    email: model.email.trim().to_string(),
+                };
+                orders.perform_cmd(async move { Msg::Requested(client.request_password_reset(&body).await) });
+            }
+        }
+        Msg::Cancelled => route::go_to(route::Route::Login, orders),
+        Msg::Requested(Ok(_)) => model.submitted = true,
+        Msg::Requested(Err(error)) => log!(error),
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    ViewPage::new(
+        "Forgot Password",
+        div![
+            class!["col-md-4", "offset-md-4" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, "Forgot Password",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    if model.submitted {
+                        p!["If an account exists for that address, we've sent it a link to reset your password."]
+                    } else {
+                        form![
+                            div![
+                                attrs! { At::Class => "form-group" },
+                                label!["Email"],
+                                input![
+                                    attrs! { At::Type => "text", At::Class => "form-control", At::Value => model.email, },
+                                    input_ev(Ev::Input, Msg::EmailChanged)
+                                ],
+                            ],
+                            div![
+                                attrs! { At::Class => "form-group" },
+                                button![
+                                    class!["btn", "btn-primary"],
+                                    "Send Reset Link",
+                                    ev(Ev::Click, |event| {
+                                        event.prevent_default();
+                                        Msg::Submitted
+                                    })
+                                ],
+                                a![
+                                    class!["btn", "btn-primary", "text-white", "ml-1"],
+                                    "Cancel",
+                                    ev(Ev::Click, |event| {
+                                        event.prevent_default();
+                                        Msg::Cancelled
+                                    })
+                                ],
+                            ],
+                        ]
+                    },
+                ],
+            ],
+        ],
+    )
+}
@@ -0,0 +1,383 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    gift_idea: Option<shared::GiftIdeaResponseBody>,
+    problem: Option<String>,
+    users: Vec<shared::MinimalUserInfo>,
+    attachments: Vec<shared::AttachmentResponseBody>,
+    link_metadata: Option<shared::LinkMetadataResponseBody>,
+    comments: Vec<shared::CommentResponseBody>,
+    new_comment: String,
+    history: Vec<shared::AuditEntryResponseBody>,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+
+    fn user_name(&self, user_id: i32) -> String {
+        match self.users.iter().find(|e| e.id == user_id) {
+            Some(user) => format!(
+                "{} {}",
+                user.first_name.as_ref().unwrap_or(&"".to_string()),
+                user.last_name.as_ref().unwrap_or(&"".to_string())
+            ),
+            None => "Someone".to_string(),
+        }
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, gift_idea_id: i32, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+    let user = LocalStorage::get(crate::STORAGE_KEY).ok();
+    let session = Session::new(user.clone());
+    if session.viewer().is_none() {
+        route::go_to(route::Route::Login, orders);
+    }
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::GiftIdeaLoaded(client.find_gift_idea_by_id(gift_idea_id).await) });
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::AttachmentsLoaded(client.find_attachments_by_gift_idea(gift_idea_id).await) });
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::UsersLoaded(client.find_all_users().await) });
+
+    load_history(gift_idea_id, orders);
+
+    Model { session, ..Model::default() }
+}
+
+fn load_history(gift_idea_id: i32, orders: &mut impl Orders<Msg, GMsg>) {
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::HistoryLoaded(client.find_gift_idea_history(gift_idea_id).await) });
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match g_msg {
+        GMsg::SessionChanged(session) => {
+            model.session = session;
+            route::go_to(route::Route::Login, orders);
+        }
+        _ => (),
+    }
+}
+
+#[allow(clippy::pub_enum_variant_names)]
+#[derive(Debug)]
+pub enum Msg {
+    GiftIdeaLoaded(api::Result<shared::GiftIdeaResponseBody>),
+    AttachmentsLoaded(api::Result<Vec<shared::AttachmentResponseBody>>),
+    UsersLoaded(api::Result<Vec<shared::MinimalUserInfo>>),
+    LinkMetadataFetched(api::Result<shared::LinkMetadataResponseBody>),
+    CommentsLoaded(api::Result<Vec<shared::CommentResponseBody>>),
+    HistoryLoaded(api::Result<Vec<shared::AuditEntryResponseBody>>),
+    ReserveClicked,
+    UnreserveClicked,
+    MarkPurchasedClicked,
+    EditClicked,
+    GiftIdeaChanged(api::Result<shared::GiftIdeaResponseBody>),
+    NewCommentChanged(String),
+    AddComment,
+    CommentAdded(api::Result<shared::CommentResponseBody>),
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::GiftIdeaLoaded(Ok(gift_idea)) => {
+            if let Some(url) = gift_idea.url.clone().filter(|url| !url.is_empty()) {
+                let client = crate::api_client();
+                let body = shared::LinkMetadataRequestBody { url };
+                orders.perform_cmd(async move { Msg::LinkMetadataFetched(client.find_link_metadata(&body).await) });
+            }
+            // comments are for the people buying, not the recipient
+            if !is_recipient(model, &gift_idea) {
+                let client = crate::api_client();
+                let gift_idea_id = gift_idea.id;
+                orders.perform_cmd(async move { Msg::CommentsLoaded(client.find_comments_by_gift_idea(gift_idea_id).await) });
+            }
+            model.gift_idea = Some(gift_idea);
+        }
+        Msg::GiftIdeaLoaded(Err(error)) => {
+            log!("error loading gift idea", error);
+            model.problem = Some("That gift idea doesn't exist".to_string());
+        }
+        Msg::AttachmentsLoaded(Ok(attachments)) => model.attachments = attachments,
+        Msg::AttachmentsLoaded(Err(error)) => log!("error loading attachments", error),
+        Msg::UsersLoaded(Ok(users)) => model.users = users,
+        Msg::UsersLoaded(Err(error)) => log!("error loading users", error),
+        Msg::LinkMetadataFetched(Ok(metadata)) => model.link_metadata = Some(metadata),
+        Msg::LinkMetadataFetched(Err(error)) => log!("error fetching link metadata", error),
+        Msg::CommentsLoaded(Ok(comments)) => model.comments = comments,
+        Msg::CommentsLoaded(Err(error)) => log!("error loading comments", error),
+        Msg::HistoryLoaded(Ok(history)) => model.history = history,
+        Msg::HistoryLoaded(Err(error)) => log!("error loading history", error),
+        Msg::ReserveClicked => {
+            if let Some(gift_idea) = &model.gift_idea {
+                let client = crate::api_client();
+                let gift_idea_id = gift_idea.id;
+                orders.perform_cmd(async move { Msg::GiftIdeaChanged(client.reserve_gift_idea(gift_idea_id).await) });
+            }
+        }
+        Msg::UnreserveClicked => {
+            if let Some(gift_idea) = &model.gift_idea {
+                let client = crate::api_client();
+                let gift_idea_id = gift_idea.id;
+                orders.perform_cmd(async move { Msg::GiftIdeaChanged(client.unreserve_gift_idea(gift_idea_id).await) });
+            }
+        }
+        Msg::MarkPurchasedClicked => {
+            if let Some(gift_idea) = &model.gift_idea {
+                let client = crate::api_client();
+                let gift_idea_id = gift_idea.id;
+                orders.perform_cmd(async move { Msg::GiftIdeaChanged(client.mark_purchased(gift_idea_id).await) });
+            }
+        }
+        Msg::EditClicked => {
+            if let Some(gift_idea) = &model.gift_idea {
+                route::go_to(route::Route::EditGiftIdea(gift_idea.id), orders);
+            }
+        }
+        Msg::GiftIdeaChanged(Ok(gift_idea)) => {
+            load_history(gift_idea.id, orders);
+            model.gift_idea = Some(gift_idea);
+        }
+        Msg::GiftIdeaChanged(Err(error)) => log!("error updating gift idea", error),
+        Msg::NewCommentChanged(body) => model.new_comment = body,
+        Msg::AddComment => {
+            let body = model.new_comment.trim().to_string();
+            if let (Some(gift_idea), false) = (&model.gift_idea, body.is_empty()) {
+                let client = crate::api_client();
+                let gift_idea_id = gift_idea.id;
+                let body = shared::CommentRequestBody { body };
+                orders.perform_cmd(async move { Msg::CommentAdded(client.add_comment(gift_idea_id, &body).await) });
+            }
+        }
+        Msg::CommentAdded(Ok(comment)) => {
+            model.new_comment.clear();
+            model.comments.push(comment);
+        }
+        Msg::CommentAdded(Err(error)) => log!("error adding comment", error),
+    }
+}
+
+fn is_recipient(model: &Model, gift_idea: &shared::GiftIdeaResponseBody) -> bool {
+    model.session.viewer().map_or(false, |viewer| viewer.id == gift_idea.recipient_user_id)
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    let title = model.gift_idea.as_ref().map_or_else(|| "Gift Idea".to_string(), |e| e.title.clone());
+    ViewPage::new(
+        &title,
+        div![
+            class!["col-md-8", "offset-md-2" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, &title],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    match (&model.gift_idea, &model.problem) {
+                        (_, Some(problem)) => vec![p![problem]],
+                        (None, None) => vec![p!["Loading..."]],
+                        (Some(gift_idea), None) => build_gift_idea(model, gift_idea),
+                    },
+                    a![attrs! { At::Href => route::Route::GiftIdeas(Default::default()).to_string() }, "Back to gift ideas"],
+                ],
+            ],
+        ],
+    )
+}
+
+fn build_gift_idea(model: &Model, gift_idea: &shared::GiftIdeaResponseBody) -> Vec<Node<Msg>> {
+    let is_recipient = is_recipient(model, gift_idea);
+    vec![
+        p![
+            strong!["For: "],
+            model.user_name(gift_idea.recipient_user_id),
+            span![class!["text-muted", "ml-2"], format!("(added by {})", model.user_name(gift_idea.owner_id))],
+        ],
+        match &gift_idea.price {
+            Some(price) if !price.is_empty() => p![strong!["Price: "], price],
+            _ => empty![],
+        },
+        match gift_idea.event_id {
+            Some(event_id) => p![
+                a![attrs! { At::Href => route::Route::Event(event_id).to_string() }, "View event"],
+                match gift_idea.date_archived {
+                    Some(_) => span![class!["badge", "badge-secondary", "ml-2"], "Archived"],
+                    None => empty![],
+                },
+            ],
+            None => empty![],
+        },
+        match gift_idea.carried_over_from_id {
+            Some(original_id) => p![
+                class!["small", "text-muted"],
+                "Carried over from ",
+                a![attrs! { At::Href => route::Route::GiftIdea(original_id).to_string() }, "an earlier event"],
+            ],
+            None => empty![],
+        },
+        p![gift_idea.description.clone().unwrap_or_default()],
+        build_link_preview(gift_idea, model.link_metadata.as_ref()),
+        div![model.attachments.iter().map(|attachment| a![
+            attrs! { At::Href => format!("/v1/attachments/download/{}", attachment.id), At::Target => "_blank" },
+            img![
+                class!["img-thumbnail", "mr-1", "mb-2"],
+                attrs! { At::Src => format!("/v1/attachments/thumbnail/{}", attachment.id), At::Alt => attachment.file_name },
+            ],
+        ])],
+        h5![class!["mt-3"], "History"],
+        ul![build_history(model)],
+        build_actions(model, gift_idea, is_recipient),
+        match is_recipient {
+            true => empty![],
+            false => build_comments(model),
+        },
+    ]
+}
+
+fn build_link_preview(gift_idea: &shared::GiftIdeaResponseBody, metadata: Option<&shared::LinkMetadataResponseBody>) -> Node<Msg> {
+    let url = match &gift_idea.url {
+        Some(url) if !url.is_empty() => url,
+        _ => return empty![],
+    };
+    div![
+        class!["media", "border", "rounded", "p-2", "mb-3"],
+        match metadata.and_then(|metadata| metadata.image.as_ref()) {
+            Some(image) => img![class!["mr-3"], attrs! { At::Src => image, At::Width => "96" }],
+            None => empty![],
+        },
+        div![
+            class!["media-body"],
+            a![
+                attrs! { At::Href => url, At::Target => "_blank" },
+                metadata.and_then(|metadata| metadata.title.clone()).unwrap_or_else(|| url.to_string())
+            ],
+            match metadata.and_then(|metadata| metadata.description.as_ref()) {
+                Some(description) => p![class!["small", "text-muted", "mb-0"], description],
+                None => empty![],
+            },
+        ],
+    ]
+}
+
+fn build_history(model: &Model) -> Vec<Node<Msg>> {
+    if model.history.is_empty() {
+        return vec![li![class!["text-muted"], "No changes recorded yet"]];
+    }
+    // the server has already taken out anything the recipient shouldn't see
+    model
+        .history
+        .iter()
+        .map(|entry| {
+            let actor = entry.actor_name.clone().unwrap_or_else(|| "Someone".to_string());
+            li![
+                span![class!["text-muted", "small", "mr-2"], entry.date_added.format("%Y-%m-%d %H:%M").to_string()],
+                format!("{} {}", actor, describe(entry)),
+            ]
+        })
+        .collect()
+}
+
+fn describe(entry: &shared::AuditEntryResponseBody) -> String {
+    match entry.action.as_str() {
+        "created" => "added it".to_string(),
+        "updated" => format!(
+            "changed the {}",
+            entry.changes.iter().map(|change| change.field.replace('_', " ")).collect::<Vec<_>>().join(", ")
+        ),
+        "reserved" => "reserved it".to_string(),
+        "unreserved" => "released the reservation".to_string(),
+        "purchased" => "marked it purchased".to_string(),
+        "deleted" => "deleted it".to_string(),
+        action => action.replace('_', " "),
+    }
+}
+
+fn build_actions(model: &Model, gift_idea: &shared::GiftIdeaResponseBody, is_recipient: bool) -> Node<Msg> {
+    let user_id = model.session.viewer().map_or(0, |viewer| viewer.id);
+    div![
+        class!["mb-3"],
+        match (is_recipient, gift_idea.reserved_by_user_id, gift_idea.date_purchased) {
+            (true, _, _) => vec![],
+            (false, None, _) => vec![build_button("Reserve", || Msg::ReserveClicked)],
+            (false, Some(reserved_by_user_id), None) if reserved_by_user_id == user_id => vec![
+                build_button("Unreserve", || Msg::UnreserveClicked),
+                build_button("Mark Purchased", || Msg::MarkPurchasedClicked),
+            ],
+            (false, Some(_), Some(_)) => vec![a![class!["btn", "btn-success", "btn-sm", "mr-1", "disabled"], "Purchased"]],
+            (false, Some(_), None) => vec![a![class!["btn", "btn-primary", "btn-sm", "mr-1", "disabled"], "Reserved"]],
+        },
+        match user_id == gift_idea.owner_id {
+            true => build_button("Edit", || Msg::EditClicked),
+            false => empty![],
+        },
+    ]
+}
+
+fn build_button(text: &str, to_msg: fn() -> Msg) -> Node<Msg> {
+    button![
+        class!["btn", "btn-primary", "btn-sm", "mr-1"],
+        text,
+        ev(Ev::Click, move |event| {
+            event.prevent_default();
+            to_msg()
+        })
+    ]
+}
+
+fn build_comments(model: &Model) -> Node<Msg> {
+    div![
+        h5!["Comments"],
+        if model.comments.is_empty() {
+            vec![p![class!["text-muted", "small"], "No comments yet"]]
+        } else {
+            model
+                .comments
+                .iter()
+                .map(|comment| {
+                    div![
+                        class!["mb-2"],
+                        strong![&comment.author_name],
+                        span![class!["text-muted", "small", "ml-2"], comment.date_added.format("%Y-%m-%d %H:%M").to_string()],
+                        p![class!["mb-0"], &comment.body],
+                    ]
+                })
+                .collect()
+        },
+        div![
+            class!["input-group", "input-group-sm", "mb-3"],
+            input![
+                class!["form-control"],
+                attrs! { At::Type => "text", At::Placeholder => "Add a comment", At::Value => model.new_comment },
+                input_ev(Ev::Input, Msg::NewCommentChanged)
+            ],
+            div![
+                class!["input-group-append"],
+                button![
+                    class!["btn", "btn-primary"],
+                    "Post",
+                    ev(Ev::Click, |event| {
+                        event.prevent_default();
+                        Msg::AddComment
+                    })
+                ],
+            ],
+        ],
+    ]
+}
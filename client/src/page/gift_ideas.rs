@@ -1,6 +1,7 @@
 use crate::page::ViewPage;
 use crate::route;
 use crate::session::Session;
+use crate::updates;
 use crate::GMsg;
 use seed::prelude::*;
 use seed::*;
@@ -9,8 +10,16 @@ use std::collections;
 #[derive(Default, Debug)]
 pub struct Model {
     session: Session,
+    filter: route::GiftIdeasFilter,
+    users: Vec<shared::MinimalUserInfo>,
     problems: collections::HashMap<String, String>,
     gift_ideas: Vec<shared::GiftIdeaResponseBody>,
+    attachments: collections::HashMap<i32, Vec<shared::AttachmentResponseBody>>,
+    open_comment_threads: collections::HashSet<i32>,
+    comments: collections::HashMap<i32, Vec<shared::CommentResponseBody>>,
+    new_comments: collections::HashMap<i32, String>,
+    editing_comment: Option<(i32, String)>,
+    updates: Option<updates::UpdateStream>,
 }
 
 impl Model {
@@ -25,17 +34,30 @@ impl From<Model> for Session {
     }
 }
 
-pub fn init(session: Session, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+pub fn init(session: Session, filter: route::GiftIdeasFilter, orders: &mut impl Orders<Msg, GMsg>) -> Model {
     let user = LocalStorage::get(crate::STORAGE_KEY).ok();
     let session = Session::new(user.clone());
     if session.viewer().is_none() {
         route::go_to(route::Route::Login, orders);
     }
 
-    let find_all_gifts_request = Request::new("/v1/gifts/find_all").method(Method::Get);
-    orders.perform_cmd(async { Msg::GiftIdeasLoaded(async { find_all_gifts_request.fetch().await?.check_status()?.json().await }.await) });
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::GiftIdeasLoaded(client.find_all_gift_ideas().await) });
 
-    Model { session, ..Model::default() }
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::AttachmentsLoaded(client.find_all_attachments().await) });
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::UsersLoaded(client.find_all_users().await) });
+
+    let updates = updates::connect(orders, Msg::UpdateReceived);
+
+    Model {
+        session,
+        filter,
+        updates,
+        ..Model::default()
+    }
 }
 
 pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -54,12 +76,29 @@ pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
 pub enum Msg {
     ReserveGiftIdea(i32),
     UnreserveGiftIdea(i32),
-    GiftIdeaReserved(fetch::Result<shared::GiftIdeaResponseBody>),
-    GiftIdeaUnreserved(fetch::Result<shared::GiftIdeaResponseBody>),
+    GiftIdeaReserved(api::Result<shared::GiftIdeaResponseBody>),
+    GiftIdeaUnreserved(api::Result<shared::GiftIdeaResponseBody>),
     EditGiftIdea(i32),
     DeleteGiftIdea(i32),
-    GiftIdeaDeleted(fetch::Result<bool>),
-    GiftIdeasLoaded(fetch::Result<Vec<shared::GiftIdeaResponseBody>>),
+    GiftIdeaDeleted(i32, api::Result<bool>),
+    GiftIdeasLoaded(api::Result<Vec<shared::GiftIdeaResponseBody>>),
+    AttachmentsLoaded(api::Result<Vec<shared::AttachmentResponseBody>>),
+    UsersLoaded(api::Result<Vec<shared::MinimalUserInfo>>),
+    RecipientFilterChanged(String),
+    StatusFilterChanged(String),
+    ToggleComments(i32),
+    CommentsLoaded(i32, api::Result<Vec<shared::CommentResponseBody>>),
+    NewCommentChanged(i32, String),
+    AddComment(i32),
+    CommentAdded(api::Result<shared::CommentResponseBody>),
+    EditComment(i32, String),
+    EditedCommentChanged(String),
+    SaveEditedComment,
+    CancelEditedComment,
+    CommentUpdated(api::Result<shared::CommentResponseBody>),
+    DeleteComment(i32, i32),
+    CommentDeleted(i32, i32, api::Result<bool>),
+    UpdateReceived(shared::GiftIdeaEvent),
 }
 
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -67,28 +106,19 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
     match msg {
         Msg::ReserveGiftIdea(gift_id) => {
             log!("ReserveGiftIdea.id: ", gift_id);
-            let reserve_gift_request = Request::new(format!("/v1/gifts/reserve/{}/{}", gift_id, model.session.viewer().unwrap().id)).method(Method::Patch);
-            orders.perform_cmd(async { Msg::GiftIdeaReserved(async { reserve_gift_request.fetch().await?.check_status()?.json().await }.await) });
-            // let mut gift = model.gift_ideas.iter().filter(|e| e.id == gift_id).take(1).nth(0).unwrap();
-            // gift.reserved_by_user_id = Some(model.session.viewer().unwrap().id.clone());
-            // let find_all_gifts = Request::new("/v1/gifts/find_all").method(Method::Get);
-            // orders
-            //     .perform_cmd(async { Msg::GiftIdeaReserved(async { reserve_gift_request.fetch().await?.check_status()?.json().await }.await) })
-            //     .perform_cmd(async { Msg::GiftIdeasLoaded(async { find_all_gifts.fetch().await?.check_status()?.json().await }.await) });
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::GiftIdeaReserved(client.reserve_gift_idea(gift_id).await) });
         }
         Msg::UnreserveGiftIdea(gift_id) => {
             log!("UnreserveGiftIdea.id: ", gift_id);
-            let unreserve_gift_request = Request::new(format!("/v1/gifts/unreserve/{}", gift_id)).method(Method::Patch);
-            orders.perform_cmd(async { Msg::GiftIdeaUnreserved(async { unreserve_gift_request.fetch().await?.check_status()?.json().await }.await) });
-        }
-        Msg::EditGiftIdea(gift_id) => {
-            log!("EditGiftIdea.id: ", gift_id);
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::GiftIdeaUnreserved(client.unreserve_gift_idea(gift_id).await) });
         }
+        Msg::EditGiftIdea(gift_id) => route::go_to(route::Route::EditGiftIdea(gift_id), orders),
         Msg::DeleteGiftIdea(gift_id) => {
             log!("DeleteGiftIdea.id: ", gift_id);
-            let delete_gift_request = Request::new(format!("/v1/gifts/delete/{}", gift_id)).method(Method::Delete);
-            orders.perform_cmd(async { Msg::GiftIdeaDeleted(async { delete_gift_request.fetch().await?.check_status()?.json().await }.await) });
-            model.gift_ideas.retain(|e| e.id != gift_id);
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::GiftIdeaDeleted(gift_id, client.delete_gift_idea(gift_id).await) });
         }
         Msg::GiftIdeasLoaded(Ok(mut gift_ideas)) => {
             gift_ideas.retain(|e| e.recipient_user_id != model.session.viewer().unwrap().id);
@@ -98,38 +128,165 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
             log!("error loading gift ideas", fetch_error);
             orders.skip();
         }
-        Msg::GiftIdeaDeleted(Ok(confirmation)) => {
-            log!("GiftIdea was deleted: ", confirmation);
-            route::go_to(route::Route::GiftIdeas, orders);
+        Msg::AttachmentsLoaded(Ok(attachments)) => {
+            model.attachments.clear();
+            for attachment in attachments {
+                model.attachments.entry(attachment.gift_idea_id).or_insert_with(Vec::new).push(attachment);
+            }
         }
-        Msg::GiftIdeaDeleted(Err(confirmation)) => {
-            log!("GiftIdea was not deleted: ", confirmation);
+        Msg::AttachmentsLoaded(Err(fetch_error)) => {
+            log!("error loading attachments", fetch_error);
+            orders.skip();
         }
-        Msg::GiftIdeaReserved(Ok(gift_idea)) => {
-            let mut gift = model.gift_ideas.iter_mut().filter(|e| e.id == gift_idea.id).take(1).nth(0).unwrap();
-            gift.reserved_by_user_id = gift_idea.reserved_by_user_id;
-            // route::go_to(route::Route::GiftIdeas, orders);
+        Msg::UsersLoaded(Ok(users)) => model.users = users,
+        Msg::UsersLoaded(Err(fetch_error)) => log!("error loading users", fetch_error),
+        // the list is already loaded, so only the address bar needs to follow the filter
+        Msg::RecipientFilterChanged(recipient) => {
+            model.filter.recipient = recipient.parse::<i32>().ok();
+            seed::push_route(route::Route::GiftIdeas(model.filter.clone()));
         }
-        Msg::GiftIdeaReserved(Err(error)) => log!(error),
-        Msg::GiftIdeaUnreserved(Ok(gift_idea)) => {
-            route::go_to(route::Route::GiftIdeas, orders);
+        Msg::StatusFilterChanged(status) => {
+            model.filter.status = route::GiftIdeaStatus::parse(&status);
+            seed::push_route(route::Route::GiftIdeas(model.filter.clone()));
+        }
+        Msg::ToggleComments(gift_id) => {
+            if !model.open_comment_threads.remove(&gift_id) {
+                model.open_comment_threads.insert(gift_id);
+                let client = crate::api_client();
+                orders.perform_cmd(async move { Msg::CommentsLoaded(gift_id, client.find_comments_by_gift_idea(gift_id).await) });
+            }
+        }
+        Msg::CommentsLoaded(gift_id, Ok(comments)) => {
+            model.comments.insert(gift_id, comments);
+        }
+        Msg::CommentsLoaded(_, Err(error)) => log!("error loading comments", error),
+        Msg::NewCommentChanged(gift_id, body) => {
+            model.new_comments.insert(gift_id, body);
+        }
+        Msg::AddComment(gift_id) => {
+            let body = model.new_comments.get(&gift_id).map(|body| body.trim().to_string()).unwrap_or_default();
+            if !body.is_empty() {
+                let client = crate::api_client();
+                let body = shared::CommentRequestBody { body };
+                orders.perform_cmd(async move { Msg::CommentAdded(client.add_comment(gift_id, &body).await) });
+            }
+        }
+        Msg::CommentAdded(Ok(comment)) => {
+            model.new_comments.remove(&comment.gift_idea_id);
+            model.comments.entry(comment.gift_idea_id).or_insert_with(Vec::new).push(comment);
+        }
+        Msg::CommentAdded(Err(error)) => log!("error adding comment", error),
+        Msg::EditComment(comment_id, body) => model.editing_comment = Some((comment_id, body)),
+        Msg::EditedCommentChanged(body) => {
+            if let Some((_, editing_body)) = model.editing_comment.as_mut() {
+                *editing_body = body;
+            }
+        }
+        Msg::SaveEditedComment => {
+            if let Some((comment_id, body)) = model.editing_comment.take() {
+                let client = crate::api_client();
+                let body = shared::CommentRequestBody { body };
+                orders.perform_cmd(async move { Msg::CommentUpdated(client.update_comment(comment_id, &body).await) });
+            }
+        }
+        Msg::CancelEditedComment => model.editing_comment = None,
+        Msg::CommentUpdated(Ok(comment)) => {
+            if let Some(existing) = model
+                .comments
+                .get_mut(&comment.gift_idea_id)
+                .and_then(|comments| comments.iter_mut().find(|e| e.id == comment.id))
+            {
+                *existing = comment;
+            }
+        }
+        Msg::CommentUpdated(Err(error)) => log!("error updating comment", error),
+        Msg::DeleteComment(gift_id, comment_id) => {
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::CommentDeleted(gift_id, comment_id, client.delete_comment(comment_id).await) });
         }
+        Msg::CommentDeleted(gift_id, comment_id, Ok(true)) => {
+            if let Some(comments) = model.comments.get_mut(&gift_id) {
+                comments.retain(|e| e.id != comment_id);
+            }
+        }
+        Msg::CommentDeleted(_, comment_id, Ok(false)) => log!("comment was not deleted: ", comment_id),
+        Msg::CommentDeleted(_, _, Err(error)) => log!("error deleting comment", error),
+        // only drop the row once the server has it in the trash
+        Msg::GiftIdeaDeleted(gift_id, Ok(true)) => {
+            model.gift_ideas.retain(|e| e.id != gift_id);
+            model.problems.remove("delete");
+        }
+        Msg::GiftIdeaDeleted(gift_id, Ok(false)) => {
+            log!("GiftIdea was not deleted: ", gift_id);
+            model.problems.insert("delete".to_string(), "That gift idea couldn't be deleted".to_string());
+        }
+        Msg::GiftIdeaDeleted(_, Err(error)) => {
+            log!("GiftIdea was not deleted: ", error);
+            model.problems.insert("delete".to_string(), "That gift idea couldn't be deleted".to_string());
+        }
+        Msg::GiftIdeaReserved(Ok(gift_idea)) | Msg::GiftIdeaUnreserved(Ok(gift_idea)) => apply_gift_idea(model, gift_idea),
+        Msg::GiftIdeaReserved(Err(error)) => log!(error),
         Msg::GiftIdeaUnreserved(Err(error)) => log!(error),
+        Msg::UpdateReceived(event) => match event {
+            shared::GiftIdeaEvent::Created(gift_idea)
+            | shared::GiftIdeaEvent::Updated(gift_idea)
+            | shared::GiftIdeaEvent::Reserved(gift_idea)
+            | shared::GiftIdeaEvent::Unreserved(gift_idea)
+            | shared::GiftIdeaEvent::Purchased(gift_idea) => apply_gift_idea(model, gift_idea),
+            shared::GiftIdeaEvent::Deleted(gift_id) => {
+                model.gift_ideas.retain(|e| e.id != gift_id);
+                model.attachments.remove(&gift_id);
+                model.comments.remove(&gift_id);
+                model.open_comment_threads.remove(&gift_id);
+            }
+        },
+    }
+}
+
+/// Replaces the row for `gift_idea` in place, or appends it if this is the first we've seen of it.
+fn apply_gift_idea(model: &mut Model, gift_idea: shared::GiftIdeaResponseBody) {
+    // an edit can turn it into a gift for the viewer, who mustn't see it any more
+    if model.session.viewer().map_or(false, |viewer| viewer.id == gift_idea.recipient_user_id) {
+        model.gift_ideas.retain(|e| e.id != gift_idea.id);
+        return;
+    }
+    match model.gift_ideas.iter_mut().find(|e| e.id == gift_idea.id) {
+        Some(existing) => *existing = gift_idea,
+        None => model.gift_ideas.push(gift_idea),
     }
 }
 
 pub fn view(model: &Model) -> ViewPage<Msg> {
     log!("gift_ideas.view(): model.session.viewer(): ", model.session.viewer());
     let user_id = model.session.viewer().unwrap().id;
+    let gift_ideas = model.gift_ideas.iter().filter(|e| model.filter.matches(e)).collect::<Vec<_>>();
     ViewPage::new(
         "Gift Ideas",
         div![
             class!["col-md-8", "offset-md-2" "mt-4"],
             div![
                 attrs! { At::Class => "card" },
-                h4![attrs! { At::Class => "card-header" }, "Gift Ideas",],
+                h4![
+                    attrs! { At::Class => "card-header" },
+                    "Gift Ideas",
+                    a![
+                        class!["btn", "btn-outline-secondary", "btn-sm", "float-right"],
+                        attrs! { At::Href => route::Route::Trash.to_string() },
+                        "Trash"
+                    ],
+                    a![
+                        class!["btn", "btn-outline-secondary", "btn-sm", "float-right", "mr-1"],
+                        attrs! { At::Href => route::Route::ImportWishlist.to_string() },
+                        "Import Wishlist"
+                    ],
+                ],
                 div![
                     attrs! { At::Class => "card-body" },
+                    match model.problems.get("delete") {
+                        Some(problem) => div![class!["alert", "alert-danger"], problem],
+                        None => empty![],
+                    },
+                    build_filter(model, user_id),
                     form![table![
                         class!["table", "table-striped"],
                         attrs![ At::Width => "100%"],
@@ -143,10 +300,18 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                 th![attrs![ At::Scope => "col",At::Width => "20%"], "Actions"],
                             ],
                         ],
-                        tbody![if model.gift_ideas.is_empty() {
+                        tbody![if gift_ideas.is_empty() {
                             vec![tr![td![attrs![ At::ColSpan => "5"], "No Data"]]]
                         } else {
-                            model.gift_ideas.iter().map(|e| build_row(e.clone(), user_id)).collect()
+                            gift_ideas
+                                .into_iter()
+                                .flat_map(|e| {
+                                    vec![
+                                        build_row(e.clone(), model.attachments.get(&e.id).map_or(&[][..], |a| a.as_slice()), user_id),
+                                        build_comment_thread(model, e.id, user_id),
+                                    ]
+                                })
+                                .collect()
                         }],
                     ],],
                 ],
@@ -155,10 +320,60 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
     )
 }
 
-pub fn build_row(gift_idea: shared::GiftIdeaResponseBody, user_id: i32) -> seed::virtual_dom::node::Node<Msg> {
+fn build_filter(model: &Model, user_id: i32) -> Node<Msg> {
+    let recipient = model.filter.recipient;
+    let status = model.filter.status;
+    div![
+        class!["form-row", "mb-3"],
+        div![
+            class!["col"],
+            select![
+                class!["form-control"],
+                attrs! { At::Name => "recipient" },
+                option![attrs! { At::Value => "" }, "All recipients"],
+                model.users.iter().filter(|e| e.id != user_id).map(|e| option![
+                    attrs! { At::Value => e.id.to_string(), At::Selected => (recipient == Some(e.id)).as_at_value() },
+                    format!("{} {}", e.first_name.as_ref().unwrap_or(&"".to_string()), e.last_name.as_ref().unwrap_or(&"".to_string()))
+                ]),
+                input_ev(Ev::Change, Msg::RecipientFilterChanged)
+            ],
+        ],
+        div![
+            class!["col"],
+            select![
+                class!["form-control"],
+                attrs! { At::Name => "status" },
+                option![attrs! { At::Value => "" }, "Any status"],
+                [route::GiftIdeaStatus::Open, route::GiftIdeaStatus::Reserved, route::GiftIdeaStatus::Purchased]
+                    .iter()
+                    .map(|e| option![
+                        attrs! { At::Value => e.as_str(), At::Selected => (status == Some(*e)).as_at_value() },
+                        match e {
+                            route::GiftIdeaStatus::Open => "Open",
+                            route::GiftIdeaStatus::Reserved => "Reserved",
+                            route::GiftIdeaStatus::Purchased => "Purchased",
+                        }
+                    ]),
+                input_ev(Ev::Change, Msg::StatusFilterChanged)
+            ],
+        ],
+    ]
+}
+
+pub fn build_row(gift_idea: shared::GiftIdeaResponseBody, attachments: &[shared::AttachmentResponseBody], user_id: i32) -> seed::virtual_dom::node::Node<Msg> {
     let gift_idea_id = gift_idea.id.clone();
     tr![
-        th![attrs![ At::Scope => "row"], gift_idea.title],
+        th![
+            attrs![ At::Scope => "row"],
+            a![attrs! { At::Href => route::Route::GiftIdea(gift_idea_id).to_string() }, gift_idea.title],
+            div![attachments.iter().map(|attachment| a![
+                attrs! { At::Href => format!("/v1/attachments/download/{}", attachment.id), At::Target => "_blank" },
+                img![
+                    class!["img-thumbnail", "mr-1", "mt-1"],
+                    attrs! { At::Src => format!("/v1/attachments/thumbnail/{}", attachment.id), At::Alt => attachment.file_name },
+                ],
+            ])],
+        ],
         td![gift_idea.description],
         td![gift_idea.price],
         td![gift_idea.url],
@@ -201,13 +416,24 @@ pub fn build_row(gift_idea: shared::GiftIdeaResponseBody, user_id: i32) -> seed:
                 },
                 _ => empty![],
             },
+            match user_id != gift_idea.recipient_user_id {
+                true => a![
+                    class!["btn", "btn-secondary", "btn-sm", "text-white", "ml-1"],
+                    "Comments",
+                    ev(Ev::Click, move |event| {
+                        event.prevent_default();
+                        Msg::ToggleComments(gift_idea_id)
+                    })
+                ],
+                _ => empty![],
+            },
             match user_id == gift_idea.owner_id {
                 true => {
                     vec![
                         button![
                             class!["btn", "btn-primary", "btn-sm", "ml-1"],
                             "Edit",
-                            ev(Ev::Submit, move |event| {
+                            ev(Ev::Click, move |event| {
                                 event.prevent_default();
                                 Msg::EditGiftIdea(gift_idea_id)
                             })
@@ -229,3 +455,107 @@ pub fn build_row(gift_idea: shared::GiftIdeaResponseBody, user_id: i32) -> seed:
         ],
     ]
 }
+
+fn build_comment_thread(model: &Model, gift_idea_id: i32, user_id: i32) -> Node<Msg> {
+    if !model.open_comment_threads.contains(&gift_idea_id) {
+        return empty![];
+    }
+    let comments = model.comments.get(&gift_idea_id).map_or(&[][..], |c| c.as_slice());
+    let new_comment = model.new_comments.get(&gift_idea_id).cloned().unwrap_or_default();
+    tr![td![
+        attrs![ At::ColSpan => "5"],
+        if comments.is_empty() {
+            vec![p![class!["text-muted", "small"], "No comments yet"]]
+        } else {
+            comments.iter().map(|comment| build_comment(comment, model.editing_comment.as_ref(), user_id)).collect()
+        },
+        div![
+            class!["input-group", "input-group-sm"],
+            input![
+                class!["form-control"],
+                attrs! { At::Type => "text", At::Placeholder => "Add a comment", At::Value => new_comment },
+                input_ev(Ev::Input, move |body| Msg::NewCommentChanged(gift_idea_id, body))
+            ],
+            div![
+                class!["input-group-append"],
+                button![
+                    class!["btn", "btn-primary"],
+                    "Post",
+                    ev(Ev::Click, move |event| {
+                        event.prevent_default();
+                        Msg::AddComment(gift_idea_id)
+                    })
+                ],
+            ],
+        ],
+    ]]
+}
+
+fn build_comment(comment: &shared::CommentResponseBody, editing_comment: Option<&(i32, String)>, user_id: i32) -> Node<Msg> {
+    let comment_id = comment.id;
+    let gift_idea_id = comment.gift_idea_id;
+    match editing_comment {
+        Some((editing_id, body)) if *editing_id == comment_id => div![
+            class!["input-group", "input-group-sm", "mb-2"],
+            input![
+                class!["form-control"],
+                attrs! { At::Type => "text", At::Value => body },
+                input_ev(Ev::Input, Msg::EditedCommentChanged)
+            ],
+            div![
+                class!["input-group-append"],
+                button![
+                    class!["btn", "btn-primary"],
+                    "Save",
+                    ev(Ev::Click, |event| {
+                        event.prevent_default();
+                        Msg::SaveEditedComment
+                    })
+                ],
+                button![
+                    class!["btn", "btn-secondary"],
+                    "Cancel",
+                    ev(Ev::Click, |event| {
+                        event.prevent_default();
+                        Msg::CancelEditedComment
+                    })
+                ],
+            ],
+        ],
+        _ => {
+            let body = comment.body.clone();
+            div![
+                class!["mb-2"],
+                strong![&comment.author_name],
+                span![class!["text-muted", "small", "ml-2"], comment.date_added.format("%Y-%m-%d %H:%M").to_string()],
+                if comment.date_last_modified != comment.date_added {
+                    span![class!["text-muted", "small", "ml-1"], "(edited)"]
+                } else {
+                    empty![]
+                },
+                match comment.author_user_id == user_id {
+                    true => vec![
+                        a![
+                            class!["small", "ml-2"],
+                            "Edit",
+                            ev(Ev::Click, move |event| {
+                                event.prevent_default();
+                                Msg::EditComment(comment_id, body)
+                            })
+                        ],
+                        a![
+                            class!["small", "ml-2"],
+                            "Delete",
+                            ev(Ev::Click, move |event| {
+                                event.prevent_default();
+                                Msg::DeleteComment(gift_idea_id, comment_id)
+                            })
+                        ],
+                    ],
+                    _ => vec![empty![]],
+                },
+                p![class!["mb-0"], &comment.body],
+            ]
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    recipients: Vec<shared::MinimalUserInfo>,
+    recipient_user_id: i32,
+    format: shared::WishlistFormat,
+    text: String,
+    rows: Vec<shared::WishlistRow>,
+    problem: Option<String>,
+    message: Option<String>,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+    let user = LocalStorage::get(crate::STORAGE_KEY).ok();
+    let session = Session::new(user.clone());
+    if session.viewer().is_none() {
+        route::go_to(route::Route::Login, orders);
+    }
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::RecipientNamesLoaded(client.find_all_users().await) });
+
+    Model { session, ..Model::default() }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match g_msg {
+        GMsg::SessionChanged(session) => {
+            model.session = session;
+            route::go_to(route::Route::Login, orders);
+        }
+        _ => (),
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    RecipientNamesLoaded(api::Result<Vec<shared::MinimalUserInfo>>),
+    RecipientChanged(String),
+    FormatChanged(String),
+    TextChanged(String),
+    FileChanged(Option<web_sys::File>),
+    FileRead(Option<String>),
+    PreviewRequested,
+    PreviewFetched(api::Result<Vec<shared::WishlistRow>>),
+    RowTitleChanged(usize, String),
+    RowPriceChanged(usize, String),
+    RowURLChanged(usize, String),
+    RowRemoved(usize),
+    ImportRequested,
+    ImportFetched(api::Result<shared::WishlistImportResponseBody>),
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::RecipientNamesLoaded(Ok(recipients)) => model.recipients = recipients,
+        Msg::RecipientNamesLoaded(Err(error)) => log!(error),
+        Msg::RecipientChanged(recipient_user_id) => {
+            model.recipient_user_id = recipient_user_id.parse::<i32>().unwrap_or_default();
+            // what's a duplicate depends on whose list it is
+            model.rows.clear();
+        }
+        Msg::FormatChanged(format) => {
+            model.format = match format.as_str() {
+                "csv" => shared::WishlistFormat::Csv,
+                "bookmarks" => shared::WishlistFormat::Bookmarks,
+                _ => shared::WishlistFormat::Urls,
+            }
+        }
+        Msg::TextChanged(text) => model.text = text,
+        Msg::FileChanged(Some(file)) => {
+            if file.name().to_lowercase().ends_with(".csv") {
+                model.format = shared::WishlistFormat::Csv;
+            } else if file.name().to_lowercase().ends_with(".html") || file.name().to_lowercase().ends_with(".htm") {
+                model.format = shared::WishlistFormat::Bookmarks;
+            }
+            orders.perform_cmd(async move { Msg::FileRead(JsFuture::from(file.text()).await.ok().and_then(|text| text.as_string())) });
+        }
+        Msg::FileChanged(None) => (),
+        Msg::FileRead(Some(text)) => model.text = text,
+        Msg::FileRead(None) => model.problem = Some("That file couldn't be read".to_string()),
+        Msg::PreviewRequested => {
+            model.message = None;
+            if model.recipient_user_id == 0 {
+                model.problem = Some("Choose who the list is for".to_string());
+            } else if model.text.trim().is_empty() {
+                model.problem = Some("Paste a list or choose a file first".to_string());
+            } else {
+                model.problem = None;
+                let client = crate::api_client();
+                let body = shared::WishlistPreviewRequest {
+                    recipient_user_id: model.recipient_user_id,
+                    format: model.format,
+                    text: model.text.clone(),
+                };
+                orders.perform_cmd(async move { Msg::PreviewFetched(client.preview_wishlist(&body).await) });
+            }
+        }
+        Msg::PreviewFetched(Ok(rows)) => {
+            if rows.is_empty() {
+                model.problem = Some("No gift ideas were found in that list".to_string());
+            }
+            model.rows = rows;
+        }
+        Msg::PreviewFetched(Err(error)) => {
+            log!(error);
+            model.problem = Some(error.to_string());
+        }
+        Msg::RowTitleChanged(index, title) => edit_row(model, index, |row| row.title = title),
+        Msg::RowPriceChanged(index, price) => edit_row(model, index, |row| row.price = Some(price).filter(|e| !e.is_empty())),
+        Msg::RowURLChanged(index, url) => edit_row(model, index, |row| row.url = Some(url).filter(|e| !e.is_empty())),
+        Msg::RowRemoved(index) => {
+            if index < model.rows.len() {
+                model.rows.remove(index);
+            }
+        }
+        Msg::ImportRequested => {
+            if !model.rows.is_empty() {
+                let client = crate::api_client();
+                let body = shared::WishlistImportRequest {
+                    recipient_user_id: model.recipient_user_id,
+                    rows: model.rows.clone(),
+                };
+                orders.perform_cmd(async move { Msg::ImportFetched(client.import_wishlist(&body).await) });
+            }
+        }
+        Msg::ImportFetched(Ok(response)) => {
+            model.problem = None;
+            model.message = Some(match (response.created.len(), response.rejected.len()) {
+                (created, 0) => format!("Added {} gift ideas", created),
+                (created, rejected) => format!("Added {} gift ideas; fix or remove the {} below and import again", created, rejected),
+            });
+            // what's left is what still needs attention
+            model.rows = response.rejected;
+            if model.rows.is_empty() {
+                model.text.clear();
+            }
+        }
+        Msg::ImportFetched(Err(error)) => {
+            log!(error);
+            model.problem = Some(error.to_string());
+        }
+    }
+}
+
+// a changed row gets checked again when it's imported, so its old problems no longer apply
+fn edit_row(model: &mut Model, index: usize, change: impl FnOnce(&mut shared::WishlistRow)) {
+    if let Some(row) = model.rows.get_mut(index) {
+        change(row);
+        row.problems.clear();
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    ViewPage::new(
+        "Import Wishlist",
+        div![
+            class!["col-md-10", "offset-md-1" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, "Import Wishlist",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    p![
+                        class!["small", "text-muted"],
+                        "Paste one link per line, a spreadsheet saved as CSV (title, price, url), or a bookmarks file exported from your browser. \
+                         Check the ideas found before they're added."
+                    ],
+                    match &model.problem {
+                        Some(problem) => div![class!["alert", "alert-danger"], problem],
+                        None => empty![],
+                    },
+                    match &model.message {
+                        Some(message) => div![class!["alert", "alert-success"], message],
+                        None => empty![],
+                    },
+                    view_form(model),
+                    if model.rows.is_empty() { empty![] } else { view_rows(model) },
+                    a![attrs! { At::Href => route::Route::GiftIdeas(Default::default()).to_string() }, "Back to gift ideas"],
+                ],
+            ],
+        ],
+    )
+}
+
+fn view_form(model: &Model) -> Node<Msg> {
+    let format = match model.format {
+        shared::WishlistFormat::Urls => "urls",
+        shared::WishlistFormat::Csv => "csv",
+        shared::WishlistFormat::Bookmarks => "bookmarks",
+    };
+    form![
+        div![
+            attrs! { At::Class => "form-group" },
+            label![class!["control-label"], "Recipient"],
+            select![
+                class!["form-control"],
+                option![attrs![ At::Value => "0" ], "Select One"],
+                model
+                    .recipients
+                    .iter()
+                    .map(|e| option![
+                        attrs! [ At::Value => e.id.to_string(), At::Selected => (e.id == model.recipient_user_id).as_at_value() ],
+                        format!("{} {}", e.first_name.as_ref().unwrap_or(&"".to_string()), e.last_name.as_ref().unwrap_or(&"".to_string()))
+                    ])
+                    .collect::<Vec<Node<Msg>>>(),
+                input_ev(Ev::Input, Msg::RecipientChanged)
+            ],
+        ],
+        div![
+            attrs! { At::Class => "form-group" },
+            label![class!["control-label"], "Format"],
+            select![
+                class!["form-control"],
+                vec![("urls", "One link per line"), ("csv", "CSV"), ("bookmarks", "Bookmarks file")]
+                    .into_iter()
+                    .map(|(value, name)| option![attrs! [ At::Value => value, At::Selected => (value == format).as_at_value() ], name])
+                    .collect::<Vec<Node<Msg>>>(),
+                input_ev(Ev::Input, Msg::FormatChanged)
+            ],
+        ],
+        div![
+            attrs! { At::Class => "form-group" },
+            label![class!["control-label"], "List"],
+            textarea![
+                class!["form-control"],
+                attrs! { At::Rows => "8", At::Value => model.text, },
+                input_ev(Ev::Input, Msg::TextChanged)
+            ],
+        ],
+        div![
+            attrs! { At::Class => "form-group" },
+            label![class!["control-label"], "Or read it from a file"],
+            input![
+                class!["form-control-file"],
+                attrs! { At::Type => "file", At::Accept => ".txt,.csv,.html,.htm,text/plain,text/csv,text/html" },
+                ev(Ev::Change, |event| {
+                    let input = event.target().unwrap().dyn_into::<web_sys::HtmlInputElement>().unwrap();
+                    Msg::FileChanged(input.files().and_then(|files| files.get(0)))
+                })
+            ],
+        ],
+        div![
+            attrs! { At::Class => "form-group" },
+            button![
+                class!["btn", "btn-primary"],
+                "Preview",
+                ev(Ev::Click, |event| {
+                    event.prevent_default();
+                    Msg::PreviewRequested
+                })
+            ],
+        ],
+    ]
+}
+
+fn view_rows(model: &Model) -> Node<Msg> {
+    let problems = model.rows.iter().filter(|row| !row.problems.is_empty()).count();
+    div![
+        table![
+            class!["table", "table-sm"],
+            thead![
+                class!["thead-dark"],
+                tr![
+                    th![attrs![ At::Scope => "col", At::Width => "5%"], "Row"],
+                    th![attrs![ At::Scope => "col", At::Width => "35%"], "Title"],
+                    th![attrs![ At::Scope => "col", At::Width => "15%"], "Price"],
+                    th![attrs![ At::Scope => "col", At::Width => "35%"], "URL"],
+                    th![attrs![ At::Scope => "col", At::Width => "10%"], ""],
+                ],
+            ],
+            tbody![model.rows.iter().enumerate().flat_map(|(index, row)| build_row(index, row)).collect::<Vec<Node<Msg>>>()],
+        ],
+        div![
+            class!["form-group"],
+            button![
+                class!["btn", "btn-primary"],
+                format!("Add {} gift ideas", model.rows.len()),
+                ev(Ev::Click, |event| {
+                    event.prevent_default();
+                    Msg::ImportRequested
+                })
+            ],
+            if problems > 0 {
+                span![class!["small", "text-muted", "ml-2"], format!("{} with problems will be left out", problems)]
+            } else {
+                empty![]
+            },
+        ],
+    ]
+}
+
+// each row is followed by its problems, if it has any
+fn build_row(index: usize, row: &shared::WishlistRow) -> Vec<Node<Msg>> {
+    let cell = |value: &str, msg: fn(usize, String) -> Msg| {
+        td![input![
+            class!["form-control", "form-control-sm"],
+            attrs! { At::Type => "text", At::Value => value },
+            input_ev(Ev::Input, move |value| msg(index, value))
+        ]]
+    };
+    vec![
+        tr![
+            class!["table-warning" => !row.problems.is_empty()],
+            td![row.row.to_string()],
+            cell(&row.title, Msg::RowTitleChanged),
+            cell(row.price.as_deref().unwrap_or_default(), Msg::RowPriceChanged),
+            cell(row.url.as_deref().unwrap_or_default(), Msg::RowURLChanged),
+            td![a![
+                class!["btn", "btn-outline-secondary", "btn-sm"],
+                "Remove",
+                ev(Ev::Click, move |event| {
+                    event.prevent_default();
+                    Msg::RowRemoved(index)
+                })
+            ]],
+        ],
+        if row.problems.is_empty() {
+            empty![]
+        } else {
+            tr![
+                class!["table-warning"],
+                td![],
+                td![attrs![ At::ColSpan => "4"], class!["small", "text-danger"], row.problems.join("; ")],
+            ]
+        },
+    ]
+}
@@ -48,7 +48,7 @@ pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
         GMsg::SessionChanged(session) => {
             // log!("login.sink.session.viewer(): ", session.viewer());
             model.session = session;
-            route::go_to(route::Route::GiftIdeas, orders);
+            route::go_to(route::Route::GiftIdeas(Default::default()), orders);
         }
         _ => (),
     }
@@ -61,8 +61,9 @@ pub enum Msg {
     PasswordChanged(String),
     LoginSubmitted,
     LoginCancelled,
-    LoginFetched(fetch::Result<shared::LoggedUser>),
+    LoginFetched(api::Result<Option<shared::LoggedUser>>),
     RegisterClicked,
+    ForgotPasswordClicked,
 }
 
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -71,20 +72,21 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
         Msg::EmailChanged(email) => model.form.email = email,
         Msg::PasswordChanged(password) => model.form.password = password,
         Msg::LoginSubmitted => {
-            let request = Request::new("/v1/users/login")
-                .method(Method::Post)
-                .json::<shared::LoginRequestBody>(&model.form.clone().into());
-            orders.perform_cmd(async { Msg::LoginFetched(async { request?.fetch().await?.check_status()?.json().await }.await) });
+            let client = crate::api_client();
+            let body: shared::LoginRequestBody = model.form.clone().into();
+            orders.perform_cmd(async move { Msg::LoginFetched(client.login(&body).await) });
         }
         Msg::LoginCancelled => route::go_to(route::Route::Home, orders),
-        Msg::LoginFetched(Ok(logged_user)) => {
+        Msg::LoginFetched(Ok(Some(logged_user))) => {
             log!("LoginFetched: ", logged_user);
             LocalStorage::insert(crate::STORAGE_KEY, &logged_user).expect("save user");
             orders.send_g_msg(GMsg::SessionChanged(Session::LoggedIn(logged_user)));
-            // route::go_to(route::Route::GiftIdeas, orders);
+            // route::go_to(route::Route::GiftIdeas(Default::default()), orders);
         }
+        Msg::LoginFetched(Ok(None)) => log!("incorrect email or password"),
         Msg::LoginFetched(Err(error)) => log!(error),
         Msg::RegisterClicked => route::go_to(route::Route::Register, orders),
+        Msg::ForgotPasswordClicked => route::go_to(route::Route::ForgotPassword, orders),
     }
 }
 
@@ -115,6 +117,15 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                 attrs! { At::Id => "password_error", At::Type => "password", At::Value => model.form.password, },
                                 input_ev(Ev::Input, Msg::PasswordChanged)
                             ],
+                            a![
+                                class!["small"],
+                                attrs! { At::Href => route::Route::ForgotPassword.to_string() },
+                                "Forgot password?",
+                                ev(Ev::Click, |event| {
+                                    event.prevent_default();
+                                    Msg::ForgotPasswordClicked
+                                })
+                            ],
                         ],
                         div![
                             attrs! { At::Class => "form-group" },
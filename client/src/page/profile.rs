@@ -5,6 +5,54 @@ use crate::GMsg;
 use seed::prelude::*;
 use seed::*;
 
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Default, Debug)]
+struct DateForm {
+    date_id: Option<i32>,
+    kind: shared::UserDateKind,
+    label: String,
+    month: u32,
+    day: String,
+    year: String,
+    year_visible: bool,
+    problem: Option<String>,
+}
+
+impl DateForm {
+    fn to_request(&self) -> Result<shared::UserDateRequest, String> {
+        if self.month == 0 {
+            return Err("Choose a month".to_string());
+        }
+        let day = self.day.trim().parse::<u32>().map_err(|_| "Enter the day of the month".to_string())?;
+        let year = match self.year.trim() {
+            "" => None,
+            year => Some(year.parse::<i32>().map_err(|_| "Enter the year as a number, or leave it out".to_string())?),
+        };
+        Ok(shared::UserDateRequest {
+            kind: self.kind,
+            label: Some(self.label.trim().to_string()).filter(|e| !e.is_empty()),
+            month: self.month,
+            day,
+            year,
+            year_visible: self.year_visible,
+        })
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct Model {
     session: Session,
@@ -12,6 +60,18 @@ pub struct Model {
     first_name: String,
     last_name: String,
     phone: String,
+    profile_message: Option<String>,
+    notification_preferences: shared::NotificationPreferencesBody,
+    calendar_feed: shared::CalendarFeedBody,
+    dates: Vec<shared::UserDateBody>,
+    date_form: DateForm,
+    verification_message: Option<String>,
+    current_password: String,
+    new_password: String,
+    confirm_new_password: String,
+    password_message: Option<String>,
+    delete_password: String,
+    delete_problem: Option<String>,
 }
 
 impl Model {
@@ -33,17 +93,33 @@ pub fn init(session: Session, orders: &mut impl Orders<Msg, GMsg>) -> Model {
         route::go_to(route::Route::Login, orders);
     }
 
-    let user = user.unwrap();
     let mut model = Model::default();
     model.session = session;
-    model.first_name = user.first_name;
-    model.last_name = user.last_name;
-    model.email = user.email;
-    // model.phone = user.phone.unwrap_or("");
+    reset_form(&mut model);
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::NotificationPreferencesLoaded(client.find_notification_preferences().await) });
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::CalendarFeedLoaded(client.find_calendar_feed().await) });
+
+    if let Some(user_id) = model.session.viewer().map(|viewer| viewer.id) {
+        let client = crate::api_client();
+        orders.perform_cmd(async move { Msg::DatesLoaded(client.find_user_dates(user_id).await) });
+    }
 
     model
 }
 
+fn reset_form(model: &mut Model) {
+    if let Some(user) = model.session.viewer() {
+        model.first_name = user.first_name.to_string();
+        model.last_name = user.last_name.to_string();
+        model.email = user.email.to_string();
+        model.phone = user.phone.clone().unwrap_or_default();
+    }
+}
+
 pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
     match g_msg {
         GMsg::SessionChanged(session) => {
@@ -61,8 +137,41 @@ pub enum Msg {
     FirstNameChanged(String),
     LastNameChanged(String),
     PhoneChanged(String),
-    RegisterSubmitted,
-    RegisterCancelled,
+    ProfileSubmitted,
+    ProfileCancelled,
+    ProfileSaved(api::Result<shared::LoggedUser>),
+    NewGiftIdeaNotificationToggled,
+    ReservationReleasedNotificationToggled,
+    EventReminderNotificationToggled,
+    NotificationPreferencesLoaded(api::Result<shared::NotificationPreferencesBody>),
+    DatesLoaded(api::Result<Vec<shared::UserDateBody>>),
+    DateKindChanged(String),
+    DateLabelChanged(String),
+    DateMonthChanged(String),
+    DateDayChanged(String),
+    DateYearChanged(String),
+    DateYearVisibleToggled,
+    DateSubmitted,
+    DateSaved(api::Result<shared::UserDateBody>),
+    DateEditClicked(i32),
+    DateEditCancelled,
+    DateRemoveClicked(i32),
+    DateRemoved(i32, api::Result<bool>),
+    CalendarReservationsToggled,
+    CalendarFeedRequested,
+    CalendarFeedLoaded(api::Result<shared::CalendarFeedBody>),
+    CalendarFeedTurnedOff,
+    CalendarFeedDeleted(api::Result<bool>),
+    ResendVerificationClicked,
+    VerificationRequested(api::Result<bool>),
+    CurrentPasswordChanged(String),
+    NewPasswordChanged(String),
+    ConfirmNewPasswordChanged(String),
+    ChangePasswordSubmitted,
+    PasswordChanged(api::Result<bool>),
+    DeletePasswordChanged(String),
+    DeleteAccountSubmitted,
+    AccountDeleted(api::Result<bool>),
 }
 
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -71,33 +180,206 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
         Msg::FirstNameChanged(first_name) => model.first_name = first_name,
         Msg::LastNameChanged(last_name) => model.last_name = last_name,
         Msg::PhoneChanged(phone) => model.phone = phone,
-        Msg::RegisterSubmitted => {
-            // orders.notify(subs::UrlRequested::new(Urls::new(&model.base_url).register()));
-        }
-        Msg::RegisterCancelled => {
-            // orders.notify(subs::UrlRequested::new(Urls::new(&model.base_url).login()));
-        } // Msg::FormSubmitted => match model.form.trim_fields().validate() {
-          //     Ok(valid_form) => {
-          //         model.problems.clear();
-          //         orders.perform_cmd(request::register::register(
-          //             &valid_form,
-          //             Msg::RegisterCompleted,
-          //         ));
-          //     }
-          //     Err(problems) => {
-          //         model.problems = problems;
-          //     }
-          // },
-          // Msg::FieldChanged(field) => {
-          //     model.form.upsert_field(field);
-          // }
-          // Msg::RegisterCompleted(Ok(viewer)) => {
-          //     viewer.store();
-          //     orders.send_g_msg(GMsg::SessionChanged(Session::LoggedIn(viewer)));
-          // }
-          // Msg::RegisterCompleted(Err(problems)) => {
-          //     model.problems = problems;
-          // }
+        Msg::NewGiftIdeaNotificationToggled | Msg::ReservationReleasedNotificationToggled | Msg::EventReminderNotificationToggled => {
+            let preferences = &mut model.notification_preferences;
+            match msg {
+                Msg::NewGiftIdeaNotificationToggled => preferences.new_gift_idea = !preferences.new_gift_idea,
+                Msg::ReservationReleasedNotificationToggled => preferences.reservation_released = !preferences.reservation_released,
+                _ => preferences.event_reminder = !preferences.event_reminder,
+            }
+            let client = crate::api_client();
+            let body = preferences.clone();
+            orders.perform_cmd(async move { Msg::NotificationPreferencesLoaded(client.update_notification_preferences(&body).await) });
+        }
+        Msg::NotificationPreferencesLoaded(Ok(preferences)) => model.notification_preferences = preferences,
+        Msg::NotificationPreferencesLoaded(Err(error)) => log!("error loading notification preferences", error),
+        Msg::DatesLoaded(Ok(dates)) => model.dates = dates,
+        Msg::DatesLoaded(Err(error)) => log!("error loading dates", error),
+        Msg::DateKindChanged(kind) => model.date_form.kind = shared::UserDateKind::parse(&kind).unwrap_or_default(),
+        Msg::DateLabelChanged(label) => model.date_form.label = label,
+        Msg::DateMonthChanged(month) => model.date_form.month = month.parse().unwrap_or_default(),
+        Msg::DateDayChanged(day) => model.date_form.day = day,
+        Msg::DateYearChanged(year) => model.date_form.year = year,
+        Msg::DateYearVisibleToggled => model.date_form.year_visible = !model.date_form.year_visible,
+        Msg::DateSubmitted => match model.date_form.to_request() {
+            Ok(body) => {
+                let client = crate::api_client();
+                match model.date_form.date_id {
+                    Some(date_id) => orders.perform_cmd(async move { Msg::DateSaved(client.update_user_date(date_id, &body).await) }),
+                    None => orders.perform_cmd(async move { Msg::DateSaved(client.add_user_date(&body).await) }),
+                };
+            }
+            Err(problem) => model.date_form.problem = Some(problem),
+        },
+        Msg::DateSaved(Ok(date)) => {
+            model.dates.retain(|e| e.id != date.id);
+            model.dates.push(date);
+            model.dates.sort_by_key(|e| (e.month, e.day));
+            model.date_form = DateForm::default();
+        }
+        Msg::DateSaved(Err(api::ApiError::BadRequest(problem))) => model.date_form.problem = Some(problem),
+        Msg::DateSaved(Err(error)) => {
+            log!(error);
+            model.date_form.problem = Some("Couldn't save that date, try again later.".to_string());
+        }
+        Msg::DateEditClicked(date_id) => {
+            if let Some(date) = model.dates.iter().find(|e| e.id == date_id) {
+                model.date_form = DateForm {
+                    date_id: Some(date.id),
+                    kind: date.kind,
+                    label: date.label.clone().unwrap_or_default(),
+                    month: date.month,
+                    day: date.day.to_string(),
+                    year: date.year.map(|year| year.to_string()).unwrap_or_default(),
+                    year_visible: date.year_visible,
+                    problem: None,
+                };
+            }
+        }
+        Msg::DateEditCancelled => model.date_form = DateForm::default(),
+        Msg::DateRemoveClicked(date_id) => {
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::DateRemoved(date_id, client.delete_user_date(date_id).await) });
+        }
+        Msg::DateRemoved(date_id, Ok(_)) => {
+            model.dates.retain(|e| e.id != date_id);
+            if model.date_form.date_id == Some(date_id) {
+                model.date_form = DateForm::default();
+            }
+        }
+        Msg::DateRemoved(_, Err(error)) => log!("error removing date", error),
+        Msg::CalendarReservationsToggled => {
+            let feed = &mut model.calendar_feed;
+            feed.include_reservations = !feed.include_reservations;
+            // before there's a feed this only picks what the new one will have
+            if feed.enabled {
+                let client = crate::api_client();
+                let body = shared::CalendarFeedRequest {
+                    include_reservations: feed.include_reservations,
+                };
+                orders.perform_cmd(async move { Msg::CalendarFeedLoaded(client.update_calendar_feed(&body).await) });
+            }
+        }
+        Msg::CalendarFeedRequested => {
+            let confirmed = !model.calendar_feed.enabled
+                || window()
+                    .confirm_with_message("Calendars subscribed to the old address will stop updating.  Continue?")
+                    .unwrap_or(false);
+            if confirmed {
+                let client = crate::api_client();
+                let body = shared::CalendarFeedRequest {
+                    include_reservations: model.calendar_feed.include_reservations,
+                };
+                orders.perform_cmd(async move { Msg::CalendarFeedLoaded(client.create_calendar_feed(&body).await) });
+            }
+        }
+        Msg::CalendarFeedLoaded(Ok(feed)) => {
+            // the address only comes back when it's made, so hang on to it while the page is open
+            let url = feed.url.clone().or_else(|| model.calendar_feed.url.take());
+            model.calendar_feed = shared::CalendarFeedBody { url, ..feed };
+        }
+        Msg::CalendarFeedLoaded(Err(error)) => log!("error loading calendar feed", error),
+        Msg::CalendarFeedTurnedOff => {
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::CalendarFeedDeleted(client.delete_calendar_feed().await) });
+        }
+        Msg::CalendarFeedDeleted(Ok(_)) => model.calendar_feed = shared::CalendarFeedBody::default(),
+        Msg::CalendarFeedDeleted(Err(error)) => log!("error turning off calendar feed", error),
+        Msg::ResendVerificationClicked => {
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::VerificationRequested(client.request_email_verification().await) });
+        }
+        Msg::VerificationRequested(Ok(true)) => model.verification_message = Some("Verification email sent, check your inbox.".to_string()),
+        Msg::VerificationRequested(Ok(false)) => model.verification_message = Some("Your email address is already verified.".to_string()),
+        Msg::VerificationRequested(Err(error)) => {
+            log!(error);
+            model.verification_message = Some("Couldn't send a verification email right now, try again later.".to_string());
+        }
+        Msg::CurrentPasswordChanged(password) => model.current_password = password,
+        Msg::NewPasswordChanged(password) => model.new_password = password,
+        Msg::ConfirmNewPasswordChanged(password) => model.confirm_new_password = password,
+        Msg::ChangePasswordSubmitted => {
+            if model.new_password.len() < crate::MIN_PASSWORD_LENGTH {
+                model.password_message = Some(format!("Password must be at least {} characters", crate::MIN_PASSWORD_LENGTH));
+            } else if model.new_password != model.confirm_new_password {
+                model.password_message = Some("Passwords do not match".to_string());
+            } else {
+                let client = crate::api_client();
+                let body = shared::ChangePasswordRequestBody {
+                    current_password: model.current_password.to_string(),
+                    new_password: model.new_password.to_string(),
+                };
+                orders.perform_cmd(async move { Msg::PasswordChanged(client.change_password(&body).await) });
+            }
+        }
+        Msg::PasswordChanged(Ok(_)) => {
+            model.current_password.clear();
+            model.new_password.clear();
+            model.confirm_new_password.clear();
+            model.password_message = Some("Password changed.".to_string());
+        }
+        Msg::PasswordChanged(Err(api::ApiError::BadRequest(message))) => model.password_message = Some(message),
+        Msg::PasswordChanged(Err(error)) => {
+            log!(error);
+            model.password_message = Some("Current password is incorrect.".to_string());
+        }
+        Msg::DeletePasswordChanged(password) => model.delete_password = password,
+        Msg::DeleteAccountSubmitted => {
+            let confirmed = window()
+                .confirm_with_message("This permanently deletes your account, your gift ideas and your reservations.  Continue?")
+                .unwrap_or(false);
+            if confirmed {
+                let client = crate::api_client();
+                let body = shared::DeleteAccountRequestBody {
+                    password: model.delete_password.to_string(),
+                };
+                orders.perform_cmd(async move { Msg::AccountDeleted(client.delete_account(&body).await) });
+            }
+        }
+        Msg::AccountDeleted(Ok(_)) => {
+            LocalStorage::remove(crate::STORAGE_KEY).expect("remove saved user");
+            orders.send_g_msg(GMsg::SessionChanged(Session::Guest));
+        }
+        Msg::AccountDeleted(Err(error)) => {
+            log!(error);
+            model.delete_problem = Some("Password is incorrect.".to_string());
+        }
+        Msg::ProfileSubmitted => {
+            if !crate::VALID_EMAIL_REGEX.is_match(model.email.trim()) {
+                model.profile_message = Some("Please enter a valid email address.".to_string());
+            } else {
+                let client = crate::api_client();
+                let body = shared::UpdateProfileRequest {
+                    email: Some(model.email.trim().to_string()),
+                    first_name: Some(model.first_name.to_string()),
+                    last_name: Some(model.last_name.to_string()),
+                    phone: Some(model.phone.to_string()),
+                };
+                orders.perform_cmd(async move { Msg::ProfileSaved(client.update_profile(&body).await) });
+            }
+        }
+        Msg::ProfileCancelled => {
+            reset_form(model);
+            model.profile_message = None;
+        }
+        Msg::ProfileSaved(Ok(user)) => {
+            model.profile_message = Some(if user.email_verified {
+                "Profile saved.".to_string()
+            } else {
+                "Profile saved.  Check your inbox to verify your email address.".to_string()
+            });
+            LocalStorage::insert(crate::STORAGE_KEY, &user).expect("save user");
+            // not a GMsg::SessionChanged, that is for logging in and out and would send us home
+            model.session = Session::LoggedIn(user);
+            reset_form(model);
+        }
+        Msg::ProfileSaved(Err(error @ api::ApiError::BadRequest(_))) | Msg::ProfileSaved(Err(error @ api::ApiError::Conflict(_))) => {
+            model.profile_message = Some(error.to_string());
+        }
+        Msg::ProfileSaved(Err(error)) => {
+            log!(error);
+            model.profile_message = Some("Couldn't save your profile, that email address may already be in use.".to_string());
+        }
     }
 }
 
@@ -112,6 +394,10 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                 div![
                     attrs! { At::Class => "card-body" },
                     form![
+                        match &model.profile_message {
+                            Some(message) => div![class!["alert", "alert-info"], message],
+                            None => empty![],
+                        },
                         div![
                             attrs! { At::Class => "form-group" },
                             label!["First Name"],
@@ -135,6 +421,18 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                 attrs! { At::Type => "text", At::Class => "form-control", At::Value => model.email, },
                                 input_ev(Ev::Input, Msg::EmailChanged)
                             ],
+                            match &model.verification_message {
+                                Some(message) => small![class!["form-text", "text-muted"], message],
+                                None => a![
+                                    class!["small"],
+                                    attrs! { At::Href => "#" },
+                                    "Resend verification email",
+                                    ev(Ev::Click, |event| {
+                                        event.prevent_default();
+                                        Msg::ResendVerificationClicked
+                                    })
+                                ],
+                            },
                         ],
                         div![
                             attrs! { At::Class => "form-group" },
@@ -151,7 +449,7 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                 "Submit",
                                 ev(Ev::Click, |event| {
                                     event.prevent_default();
-                                    Msg::RegisterSubmitted
+                                    Msg::ProfileSubmitted
                                 })
                             ],
                             button![
@@ -159,13 +457,305 @@ pub fn view(model: &Model) -> ViewPage<Msg> {
                                 "Cancel",
                                 ev(Ev::Click, |event| {
                                     event.prevent_default();
-                                    Msg::RegisterCancelled
+                                    Msg::ProfileCancelled
                                 })
                             ],
                         ],
                     ],
                 ],
             ],
+            div![
+                attrs! { At::Class => "card mt-4" },
+                h4![attrs! { At::Class => "card-header" }, "Email Notifications",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    build_checkbox(
+                        "A new gift idea is added for someone I'm shopping for",
+                        model.notification_preferences.new_gift_idea,
+                        || Msg::NewGiftIdeaNotificationToggled
+                    ),
+                    build_checkbox("A gift idea I reserved is released or removed", model.notification_preferences.reservation_released, || {
+                        Msg::ReservationReleasedNotificationToggled
+                    }),
+                    build_checkbox("An event is a week away", model.notification_preferences.event_reminder, || {
+                        Msg::EventReminderNotificationToggled
+                    }),
+                ],
+            ],
+            build_dates(&model.dates, &model.date_form),
+            build_calendar_feed(&model.calendar_feed),
+            div![
+                attrs! { At::Class => "card mt-4" },
+                h4![attrs! { At::Class => "card-header" }, "Change Password",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    form![
+                        match &model.password_message {
+                            Some(message) => div![class!["alert", "alert-info"], message],
+                            None => empty![],
+                        },
+                        build_password_input("Current Password", &model.current_password, Msg::CurrentPasswordChanged),
+                        build_password_input("New Password", &model.new_password, Msg::NewPasswordChanged),
+                        build_password_input("Confirm New Password", &model.confirm_new_password, Msg::ConfirmNewPasswordChanged),
+                        button![
+                            class!["btn", "btn-primary"],
+                            "Change Password",
+                            ev(Ev::Click, |event| {
+                                event.prevent_default();
+                                Msg::ChangePasswordSubmitted
+                            })
+                        ],
+                    ],
+                ],
+            ],
+            div![
+                attrs! { At::Class => "card mt-4 mb-4 border-danger" },
+                h4![attrs! { At::Class => "card-header" }, "Delete Account",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    p![
+                        class!["small"],
+                        "Deleting your account removes the gift ideas you added and the ones added for you, releases anything you reserved, and deletes your comments and events.  This can't be undone."
+                    ],
+                    form![
+                        match &model.delete_problem {
+                            Some(problem) => div![class!["alert", "alert-danger"], problem],
+                            None => empty![],
+                        },
+                        build_password_input("Password", &model.delete_password, Msg::DeletePasswordChanged),
+                        button![
+                            class!["btn", "btn-danger"],
+                            "Delete Account",
+                            ev(Ev::Click, |event| {
+                                event.prevent_default();
+                                Msg::DeleteAccountSubmitted
+                            })
+                        ],
+                    ],
+                ],
+            ],
         ],
     )
 }
+
+fn describe_date(date: &shared::UserDateBody) -> String {
+    let kind = match (date.kind, &date.label) {
+        (_, Some(label)) => label.to_string(),
+        (shared::UserDateKind::Birthday, None) => "Birthday".to_string(),
+        (shared::UserDateKind::Anniversary, None) => "Anniversary".to_string(),
+        (shared::UserDateKind::Other, None) => String::new(),
+    };
+    let month = MONTHS.get(date.month as usize - 1).copied().unwrap_or_default();
+    match date.year {
+        Some(year) => format!("{}: {} {}, {}", kind, month, date.day, year),
+        None => format!("{}: {} {}", kind, month, date.day),
+    }
+}
+
+fn build_dates(dates: &[shared::UserDateBody], form: &DateForm) -> Node<Msg> {
+    let kind = form.kind.as_str();
+    div![
+        attrs! { At::Class => "card mt-4" },
+        h4![attrs! { At::Class => "card-header" }, "Birthdays and Anniversaries",],
+        div![
+            attrs! { At::Class => "card-body" },
+            p![
+                class!["small"],
+                "An event is made for each of these ahead of time every year.  Leave the year out, or keep it to yourself, if you'd rather nobody worked out your age."
+            ],
+            ul![
+                class!["list-unstyled"],
+                dates
+                    .iter()
+                    .map(|date| {
+                        let date_id = date.id;
+                        li![
+                            describe_date(date),
+                            a![
+                                class!["small", "ml-2"],
+                                attrs! { At::Href => "#" },
+                                "Edit",
+                                ev(Ev::Click, move |event| {
+                                    event.prevent_default();
+                                    Msg::DateEditClicked(date_id)
+                                })
+                            ],
+                            a![
+                                class!["small", "ml-2"],
+                                attrs! { At::Href => "#" },
+                                "Remove",
+                                ev(Ev::Click, move |event| {
+                                    event.prevent_default();
+                                    Msg::DateRemoveClicked(date_id)
+                                })
+                            ],
+                        ]
+                    })
+                    .collect::<Vec<Node<Msg>>>(),
+            ],
+            form![
+                match &form.problem {
+                    Some(problem) => div![class!["alert", "alert-danger"], problem],
+                    None => empty![],
+                },
+                div![
+                    class!["form-row"],
+                    div![
+                        class!["form-group", "col-md-4"],
+                        select![
+                            class!["form-control"],
+                            vec![("birthday", "Birthday"), ("anniversary", "Anniversary"), ("other", "Other")]
+                                .into_iter()
+                                .map(|(value, name)| option![attrs! [ At::Value => value, At::Selected => (value == kind).as_at_value() ], name])
+                                .collect::<Vec<Node<Msg>>>(),
+                            input_ev(Ev::Input, Msg::DateKindChanged)
+                        ],
+                    ],
+                    div![
+                        class!["form-group", "col-md-8"],
+                        input![
+                            attrs! { At::Type => "text", At::Class => "form-control", At::Placeholder => "Name (needed for Other)", At::Value => form.label },
+                            input_ev(Ev::Input, Msg::DateLabelChanged)
+                        ],
+                    ],
+                ],
+                div![
+                    class!["form-row"],
+                    div![
+                        class!["form-group", "col-md-5"],
+                        select![
+                            class!["form-control"],
+                            option![attrs![ At::Value => "0" ], "Month"],
+                            MONTHS
+                                .iter()
+                                .enumerate()
+                                .map(|(i, name)| option![
+                                    attrs! [ At::Value => (i + 1).to_string(), At::Selected => (i as u32 + 1 == form.month).as_at_value() ],
+                                    name
+                                ])
+                                .collect::<Vec<Node<Msg>>>(),
+                            input_ev(Ev::Input, Msg::DateMonthChanged)
+                        ],
+                    ],
+                    div![
+                        class!["form-group", "col-md-3"],
+                        input![
+                            attrs! { At::Type => "number", At::Class => "form-control", At::Placeholder => "Day", At::Value => form.day },
+                            input_ev(Ev::Input, Msg::DateDayChanged)
+                        ],
+                    ],
+                    div![
+                        class!["form-group", "col-md-4"],
+                        input![
+                            attrs! { At::Type => "number", At::Class => "form-control", At::Placeholder => "Year (optional)", At::Value => form.year },
+                            input_ev(Ev::Input, Msg::DateYearChanged)
+                        ],
+                    ],
+                ],
+                build_checkbox("Let others see the year", form.year_visible, || Msg::DateYearVisibleToggled),
+                div![
+                    class!["mt-3"],
+                    button![
+                        class!["btn", "btn-primary"],
+                        if form.date_id.is_some() { "Save Date" } else { "Add Date" },
+                        ev(Ev::Click, |event| {
+                            event.prevent_default();
+                            Msg::DateSubmitted
+                        })
+                    ],
+                    if form.date_id.is_some() {
+                        button![
+                            class!["btn", "btn-outline-secondary", "ml-2"],
+                            "Cancel",
+                            ev(Ev::Click, |event| {
+                                event.prevent_default();
+                                Msg::DateEditCancelled
+                            })
+                        ]
+                    } else {
+                        empty![]
+                    },
+                ],
+            ],
+        ],
+    ]
+}
+
+fn build_calendar_feed(feed: &shared::CalendarFeedBody) -> Node<Msg> {
+    div![
+        attrs! { At::Class => "card mt-4" },
+        h4![attrs! { At::Class => "card-header" }, "Calendar",],
+        div![
+            attrs! { At::Class => "card-body" },
+            p![
+                class!["small"],
+                "Subscribe to a private address in any calendar app to see upcoming events there.  Anyone with the address can read it, so keep it to yourself."
+            ],
+            build_checkbox("Include gifts I've reserved but not bought yet, as reminders", feed.include_reservations, || {
+                Msg::CalendarReservationsToggled
+            }),
+            match (&feed.url, feed.enabled) {
+                (Some(url), _) => div![
+                    class!["form-group", "mt-3"],
+                    label!["Your calendar address"],
+                    input![attrs! { At::Type => "text", At::Class => "form-control", At::Value => url, At::ReadOnly => true.as_at_value() }],
+                    small![class!["form-text", "text-muted"], "Copy it now; it won't be shown again."],
+                ],
+                (None, true) => p![
+                    class!["small", "text-muted", "mt-3"],
+                    "Your calendar feed is on.  Get a new address if you've lost it or shared it by mistake."
+                ],
+                (None, false) => empty![],
+            },
+            div![
+                class!["mt-3"],
+                button![
+                    class!["btn", "btn-primary"],
+                    if feed.enabled { "Get a New Address" } else { "Turn On" },
+                    ev(Ev::Click, |event| {
+                        event.prevent_default();
+                        Msg::CalendarFeedRequested
+                    })
+                ],
+                if feed.enabled {
+                    button![
+                        class!["btn", "btn-outline-secondary", "ml-2"],
+                        "Turn Off",
+                        ev(Ev::Click, |event| {
+                            event.prevent_default();
+                            Msg::CalendarFeedTurnedOff
+                        })
+                    ]
+                } else {
+                    empty![]
+                },
+            ],
+        ],
+    ]
+}
+
+fn build_password_input(text: &str, value: &str, to_msg: fn(String) -> Msg) -> Node<Msg> {
+    div![
+        attrs! { At::Class => "form-group" },
+        label![text],
+        input![
+            attrs! { At::Type => "password", At::Class => "form-control", At::Value => value, },
+            input_ev(Ev::Input, to_msg)
+        ],
+    ]
+}
+
+fn build_checkbox(text: &str, checked: bool, to_msg: fn() -> Msg) -> Node<Msg> {
+    div![
+        class!["form-check"],
+        label![
+            class!["form-check-label"],
+            input![
+                class!["form-check-input"],
+                attrs! { At::Type => "checkbox", At::Checked => checked.as_at_value() },
+                ev(Ev::Change, move |_| to_msg())
+            ],
+            text,
+        ],
+    ]
+}
@@ -70,7 +70,7 @@ pub enum Msg {
     ConfirmPasswordChanged(String),
     RegisterSubmitted,
     RegisterCancelled,
-    RegisterFetched(fetch::Result<shared::LoggedUser>),
+    RegisterFetched(api::Result<shared::LoggedUser>),
 }
 
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
@@ -94,16 +94,15 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>)
             }
 
             if is_valid {
-                let request = Request::new("/v1/users/add")
-                    .method(Method::Post)
-                    .json::<shared::RegisterRequestBody>(&model.form.clone().into());
-                orders.perform_cmd(async { Msg::RegisterFetched(async { request?.fetch().await?.check_status()?.json().await }.await) });
+                let client = crate::api_client();
+                let body: shared::RegisterRequestBody = model.form.clone().into();
+                orders.perform_cmd(async move { Msg::RegisterFetched(client.register(&body).await) });
             }
         }
         Msg::RegisterFetched(Ok(logged_user)) => {
             LocalStorage::insert(crate::STORAGE_KEY, &logged_user).expect("save user");
             //orders.send_g_msg(GMsg::SessionChanged(Session::LoggedIn(logged_user)));
-            route::go_to(route::Route::GiftIdeas, orders);
+            route::go_to(route::Route::GiftIdeas(Default::default()), orders);
         }
         Msg::RegisterFetched(Err(error)) => log!(error),
     }
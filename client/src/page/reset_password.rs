@@ -0,0 +1,143 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    token: String,
+    password: String,
+    confirm_password: String,
+    problem: Option<String>,
+    reset: bool,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, token: String) -> Model {
+    Model {
+        session,
+        token,
+        ..Model::default()
+    }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model) {
+    if let GMsg::SessionChanged(session) = g_msg {
+        model.session = session;
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    PasswordChanged(String),
+    ConfirmPasswordChanged(String),
+    Submitted,
+    Reset(api::Result<bool>),
+    LoginClicked,
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::PasswordChanged(password) => model.password = password,
+        Msg::ConfirmPasswordChanged(confirm_password) => model.confirm_password = confirm_password,
+        Msg::Submitted => {
+            if model.password.len() < crate::MIN_PASSWORD_LENGTH {
+                model.problem = Some(format!("Password must be at least {} characters", crate::MIN_PASSWORD_LENGTH));
+            } else if model.password != model.confirm_password {
+                model.problem = Some("Passwords do not match".to_string());
+            } else {
+                model.problem = None;
+                let client = crate::api_client();
+                let body = shared::ResetPasswordRequestBody {
+                    token: model.token.to_string(),
+                    password: model.password.to_string(),
+                };
+                orders.perform_cmd(async move { Msg::Reset(client.reset_password(&body).await) });
+            }
+        }
+        Msg::Reset(Ok(reset)) => model.reset = reset,
+        Msg::Reset(Err(api::ApiError::BadRequest(message))) => model.problem = Some(message),
+        Msg::Reset(Err(error)) => {
+            log!(error);
+            model.problem = Some("This reset link is invalid or has expired.".to_string());
+        }
+        Msg::LoginClicked => route::go_to(route::Route::Login, orders),
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    ViewPage::new(
+        "Reset Password",
+        div![
+            class!["col-md-4", "offset-md-4" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, "Reset Password",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    if model.reset {
+                        div![
+                            p!["Your password has been changed."],
+                            a![
+                                class!["btn", "btn-primary", "text-white"],
+                                "Login",
+                                ev(Ev::Click, |event| {
+                                    event.prevent_default();
+                                    Msg::LoginClicked
+                                })
+                            ],
+                        ]
+                    } else {
+                        form![
+                            match &model.problem {
+                                Some(problem) => div![class!["alert", "alert-danger"], problem],
+                                None => empty![],
+                            },
+                            div![
+                                attrs! { At::Class => "form-group" },
+                                label!["New Password"],
+                                input![
+                                    attrs! { At::Type => "password", At::Class => "form-control", At::Value => model.password, },
+                                    input_ev(Ev::Input, Msg::PasswordChanged)
+                                ],
+                            ],
+                            div![
+                                attrs! { At::Class => "form-group" },
+                                label!["Confirm Password"],
+                                input![
+                                    attrs! { At::Type => "password", At::Class => "form-control", At::Value => model.confirm_password, },
+                                    input_ev(Ev::Input, Msg::ConfirmPasswordChanged)
+                                ],
+                            ],
+                            div![
+                                attrs! { At::Class => "form-group" },
+                                button![
+                                    class!["btn", "btn-primary"],
+                                    "Change Password",
+                                    ev(Ev::Click, |event| {
+                                        event.prevent_default();
+                                        Msg::Submitted
+                                    })
+                                ],
+                            ],
+                        ]
+                    },
+                ],
+            ],
+        ],
+    )
+}
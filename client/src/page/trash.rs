@@ -0,0 +1,131 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    gift_ideas: Vec<shared::GiftIdeaResponseBody>,
+    problem: Option<String>,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+    let user = LocalStorage::get(crate::STORAGE_KEY).ok();
+    let session = Session::new(user.clone());
+    if session.viewer().is_none() {
+        route::go_to(route::Route::Login, orders);
+    }
+
+    let client = crate::api_client();
+    orders.perform_cmd(async move { Msg::TrashLoaded(client.find_trash().await) });
+
+    Model { session, ..Model::default() }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match g_msg {
+        GMsg::SessionChanged(session) => {
+            model.session = session;
+            route::go_to(route::Route::Login, orders);
+        }
+        _ => (),
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    TrashLoaded(api::Result<Vec<shared::GiftIdeaResponseBody>>),
+    RestoreGiftIdea(i32),
+    GiftIdeaRestored(i32, api::Result<shared::GiftIdeaResponseBody>),
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::TrashLoaded(Ok(gift_ideas)) => model.gift_ideas = gift_ideas,
+        Msg::TrashLoaded(Err(error)) => log!("error loading trash", error),
+        Msg::RestoreGiftIdea(gift_id) => {
+            let client = crate::api_client();
+            orders.perform_cmd(async move { Msg::GiftIdeaRestored(gift_id, client.restore_gift_idea(gift_id).await) });
+        }
+        Msg::GiftIdeaRestored(gift_id, Ok(_)) => {
+            model.problem = None;
+            model.gift_ideas.retain(|e| e.id != gift_id);
+        }
+        Msg::GiftIdeaRestored(_, Err(error)) => {
+            log!("error restoring gift idea", error);
+            model.problem = Some("That gift idea can no longer be restored".to_string());
+        }
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    ViewPage::new(
+        "Trash",
+        div![
+            class!["col-md-8", "offset-md-2" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, "Trash",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    p![
+                        class!["small", "text-muted"],
+                        "Gift ideas you've deleted can be restored for a while before they're removed for good."
+                    ],
+                    match &model.problem {
+                        Some(problem) => div![class!["alert", "alert-danger"], problem],
+                        None => empty![],
+                    },
+                    table![
+                        class!["table", "table-striped"],
+                        thead![
+                            class!["thead-dark"],
+                            tr![
+                                th![attrs![ At::Scope => "col", At::Width => "50%"], "Title"],
+                                th![attrs![ At::Scope => "col", At::Width => "30%"], "Deleted"],
+                                th![attrs![ At::Scope => "col", At::Width => "20%"], "Actions"],
+                            ],
+                        ],
+                        tbody![if model.gift_ideas.is_empty() {
+                            vec![tr![td![attrs![ At::ColSpan => "3"], "The trash is empty"]]]
+                        } else {
+                            model.gift_ideas.iter().map(build_row).collect()
+                        }],
+                    ],
+                    a![attrs! { At::Href => route::Route::GiftIdeas(Default::default()).to_string() }, "Back to gift ideas"],
+                ],
+            ],
+        ],
+    )
+}
+
+fn build_row(gift_idea: &shared::GiftIdeaResponseBody) -> Node<Msg> {
+    let gift_idea_id = gift_idea.id;
+    tr![
+        th![attrs![ At::Scope => "row"], &gift_idea.title],
+        td![gift_idea.date_deleted.map(|date| date.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default()],
+        td![a![
+            class!["btn", "btn-primary", "btn-sm", "text-white"],
+            "Restore",
+            ev(Ev::Click, move |event| {
+                event.prevent_default();
+                Msg::RestoreGiftIdea(gift_idea_id)
+            })
+        ]],
+    ]
+}
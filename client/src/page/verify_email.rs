@@ -0,0 +1,100 @@
+use crate::page::ViewPage;
+use crate::route;
+use crate::session::Session;
+use crate::GMsg;
+use seed::prelude::*;
+use seed::*;
+
+#[derive(Debug)]
+enum Status {
+    Verifying,
+    Verified,
+    Failed,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Status::Verifying
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Model {
+    session: Session,
+    status: Status,
+}
+
+impl Model {
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl From<Model> for Session {
+    fn from(model: Model) -> Self {
+        model.session
+    }
+}
+
+pub fn init(session: Session, token: String, orders: &mut impl Orders<Msg, GMsg>) -> Model {
+    let client = crate::api_client();
+    let body = shared::VerifyEmailRequestBody { token };
+    orders.perform_cmd(async move { Msg::Verified(client.verify_email(&body).await) });
+    Model { session, ..Model::default() }
+}
+
+pub fn sink(g_msg: GMsg, model: &mut Model) {
+    if let GMsg::SessionChanged(session) = g_msg {
+        model.session = session;
+    }
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    Verified(api::Result<bool>),
+    ContinueClicked,
+}
+
+pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg, GMsg>) {
+    match msg {
+        Msg::Verified(Ok(true)) => model.status = Status::Verified,
+        Msg::Verified(Ok(false)) => model.status = Status::Failed,
+        Msg::Verified(Err(error)) => {
+            log!(error);
+            model.status = Status::Failed;
+        }
+        Msg::ContinueClicked => match model.session.viewer() {
+            Some(_) => route::go_to(route::Route::GiftIdeas(Default::default()), orders),
+            None => route::go_to(route::Route::Login, orders),
+        },
+    }
+}
+
+pub fn view(model: &Model) -> ViewPage<Msg> {
+    ViewPage::new(
+        "Verify Email",
+        div![
+            class!["col-md-4", "offset-md-4" "mt-4"],
+            div![
+                attrs! { At::Class => "card" },
+                h4![attrs! { At::Class => "card-header" }, "Verify Email",],
+                div![
+                    attrs! { At::Class => "card-body" },
+                    match model.status {
+                        Status::Verifying => p!["Verifying your email address..."],
+                        Status::Verified => p!["Thanks, your email address is verified."],
+                        Status::Failed => p!["This verification link is invalid or has expired.  You can ask for a new one from your profile page."],
+                    },
+                    a![
+                        class!["btn", "btn-primary", "text-white"],
+                        "Continue",
+                        ev(Ev::Click, |event| {
+                            event.prevent_default();
+                            Msg::ContinueClicked
+                        })
+                    ],
+                ],
+            ],
+        ],
+    )
+}
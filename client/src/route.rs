@@ -4,41 +4,126 @@ use std::fmt;
 
 use seed::prelude::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GiftIdeaStatus {
+    Open,
+    Reserved,
+    Purchased,
+}
+
+impl GiftIdeaStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GiftIdeaStatus::Open => "open",
+            GiftIdeaStatus::Reserved => "reserved",
+            GiftIdeaStatus::Purchased => "purchased",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<GiftIdeaStatus> {
+        match value {
+            "open" => Some(GiftIdeaStatus::Open),
+            "reserved" => Some(GiftIdeaStatus::Reserved),
+            "purchased" => Some(GiftIdeaStatus::Purchased),
+            _ => None,
+        }
+    }
+
+    pub fn matches(self, gift_idea: &shared::GiftIdeaResponseBody) -> bool {
+        match self {
+            GiftIdeaStatus::Open => gift_idea.reserved_by_user_id.is_none(),
+            GiftIdeaStatus::Reserved => gift_idea.reserved_by_user_id.is_some() && gift_idea.date_purchased.is_none(),
+            GiftIdeaStatus::Purchased => gift_idea.date_purchased.is_some(),
+        }
+    }
+}
+
+/// The filters on the gift ideas list, carried in the query string so a filtered list can be
+/// bookmarked, e.g. `/gift_ideas?recipient=3&status=open`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GiftIdeasFilter {
+    pub recipient: Option<i32>,
+    pub status: Option<GiftIdeaStatus>,
+}
+
+impl GiftIdeasFilter {
+    pub fn matches(&self, gift_idea: &shared::GiftIdeaResponseBody) -> bool {
+        self.recipient.map_or(true, |recipient| recipient == gift_idea.recipient_user_id) && self.status.map_or(true, |status| status.matches(gift_idea))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Route {
     Home,
     Login,
     Logout,
     Register,
     Profile,
-    GiftIdeas,
+    GiftIdeas(GiftIdeasFilter),
+    GiftIdea(i32),
+    Trash,
+    ImportWishlist,
     AddGiftIdea,
+    EditGiftIdea(i32),
+    Events,
+    Event(i32),
+    Wishlist(i32),
+    ForgotPassword,
+    ResetPassword(String),
+    VerifyEmail(String),
 }
 
 impl Route {
-    pub fn path(&self) -> Vec<&str> {
+    pub fn path(&self) -> Vec<String> {
+        match self {
+            Route::Home => vec![],
+            Route::Login => vec!["login".into()],
+            Route::Logout => vec!["logout".into()],
+            Route::Register => vec!["register".into()],
+            Route::Profile => vec!["profile".into()],
+            Route::GiftIdeas(_) => vec!["gift_ideas".into()],
+            Route::GiftIdea(gift_idea_id) => vec!["gift_ideas".into(), gift_idea_id.to_string()],
+            Route::Trash => vec!["trash".into()],
+            Route::ImportWishlist => vec!["import_wishlist".into()],
+            Route::AddGiftIdea => vec!["add_gift_idea".into()],
+            Route::EditGiftIdea(gift_idea_id) => vec!["edit_gift_idea".into(), gift_idea_id.to_string()],
+            Route::Events => vec!["events".into()],
+            Route::Event(event_id) => vec!["events".into(), event_id.to_string()],
+            Route::Wishlist(user_id) => vec!["users".into(), user_id.to_string(), "wishlist".into()],
+            Route::ForgotPassword => vec!["forgot_password".into()],
+            Route::ResetPassword(_) => vec!["reset_password".into()],
+            Route::VerifyEmail(_) => vec!["verify_email".into()],
+        }
+    }
+
+    pub fn search(&self) -> Vec<(&'static str, String)> {
         match self {
-            super::Route::Home => vec![],
-            super::Route::Login => vec!["login"],
-            super::Route::Logout => vec!["logout"],
-            super::Route::Register => vec!["register"],
-            super::Route::Profile => vec!["profile"],
-            super::Route::GiftIdeas => vec!["gift_ideas"],
-            super::Route::AddGiftIdea => vec!["add_gift_idea"],
+            Route::GiftIdeas(filter) => {
+                let mut search = Vec::new();
+                if let Some(recipient) = filter.recipient {
+                    search.push(("recipient", recipient.to_string()));
+                }
+                if let Some(status) = filter.status {
+                    search.push(("status", status.as_str().to_string()));
+                }
+                search
+            }
+            Route::ResetPassword(token) | Route::VerifyEmail(token) => vec![("token", token.to_string())],
+            _ => vec![],
         }
     }
 }
 
 impl fmt::Display for Route {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "/{}", self.path().join("/"))
+        write!(f, "{}", seed::Url::from(self.clone()))
     }
 }
 
 impl From<Route> for seed::Url {
     fn from(route: Route) -> Self {
-        seed::Url::new().set_path(route.path())
-        //route.path().into()
+        let search = route.search().into_iter().map(|(key, value)| (key, vec![value]));
+        seed::Url::new().set_path(route.path()).set_search(UrlSearch::new(search))
     }
 }
 
@@ -46,17 +131,32 @@ impl convert::TryFrom<seed::Url> for Route {
     type Error = ();
 
     fn try_from(url: seed::Url) -> Result<Self, Self::Error> {
-        let mut path = url.path().into_iter();
-
-        match path.next().as_ref().map(|e| e.as_str()) {
-            None | Some("") => Some(Route::Home),
-            Some("login") => Some(Route::Login),
-            Some("logout") => Some(Route::Logout),
-            Some("register") => Some(Route::Register),
-            Some("home") => Some(Route::Home),
-            Some("profile") => Some(Route::Profile),
-            Some("gift_ideas") => Some(Route::GiftIdeas),
-            Some("add_gift_idea") => Some(Route::AddGiftIdea),
+        let param = |key: &str| url.search().get(key).and_then(|e| e.first()).cloned();
+        let id = |value: &str| value.parse::<i32>().ok();
+        // tolerate trailing and doubled slashes
+        let path = url.path().iter().map(String::as_str).filter(|e| !e.is_empty()).collect::<Vec<_>>();
+
+        match path.as_slice() {
+            [] | ["home"] => Some(Route::Home),
+            ["login"] => Some(Route::Login),
+            ["logout"] => Some(Route::Logout),
+            ["register"] => Some(Route::Register),
+            ["profile"] => Some(Route::Profile),
+            ["gift_ideas"] => Some(Route::GiftIdeas(GiftIdeasFilter {
+                recipient: param("recipient").as_deref().and_then(id),
+                status: param("status").as_deref().and_then(GiftIdeaStatus::parse),
+            })),
+            ["gift_ideas", gift_idea_id] => id(*gift_idea_id).map(Route::GiftIdea),
+            ["trash"] => Some(Route::Trash),
+            ["import_wishlist"] => Some(Route::ImportWishlist),
+            ["add_gift_idea"] => Some(Route::AddGiftIdea),
+            ["edit_gift_idea", gift_idea_id] => id(*gift_idea_id).map(Route::EditGiftIdea),
+            ["events"] => Some(Route::Events),
+            ["events", event_id] => id(*event_id).map(Route::Event),
+            ["users", user_id, "wishlist"] => id(*user_id).map(Route::Wishlist),
+            ["forgot_password"] => Some(Route::ForgotPassword),
+            ["reset_password"] => Some(Route::ResetPassword(param("token").unwrap_or_default())),
+            ["verify_email"] => Some(Route::VerifyEmail(param("token").unwrap_or_default())),
             _ => None,
         }
         .ok_or(())
@@ -67,3 +167,98 @@ pub fn go_to<Msg: 'static>(route: Route, orders: &mut impl Orders<Msg, GMsg>) {
     seed::push_route(route.clone());
     orders.send_g_msg(GMsg::RoutePushed(route));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn url(path: &[&str], search: Vec<(&str, Vec<&str>)>) -> seed::Url {
+        seed::Url::new().set_path(path).set_search(UrlSearch::new(search))
+    }
+
+    fn round_trip(route: Route) -> Result<Route, ()> {
+        Route::try_from(seed::Url::from(route))
+    }
+
+    #[test]
+    fn statuses_round_trip() {
+        for status in &[GiftIdeaStatus::Open, GiftIdeaStatus::Reserved, GiftIdeaStatus::Purchased] {
+            assert_eq!(GiftIdeaStatus::parse(status.as_str()), Some(*status));
+        }
+        assert_eq!(GiftIdeaStatus::parse(""), None);
+        assert_eq!(GiftIdeaStatus::parse("given"), None);
+    }
+
+    #[test]
+    fn routes_round_trip() {
+        let routes = vec![
+            Route::Home,
+            Route::Login,
+            Route::Logout,
+            Route::Register,
+            Route::Profile,
+            Route::GiftIdeas(GiftIdeasFilter::default()),
+            Route::GiftIdeas(GiftIdeasFilter { recipient: Some(3), status: None }),
+            Route::GiftIdeas(GiftIdeasFilter {
+                recipient: None,
+                status: Some(GiftIdeaStatus::Purchased),
+            }),
+            Route::GiftIdeas(GiftIdeasFilter {
+                recipient: Some(3),
+                status: Some(GiftIdeaStatus::Open),
+            }),
+            Route::GiftIdea(7),
+            Route::Trash,
+            Route::ImportWishlist,
+            Route::AddGiftIdea,
+            Route::EditGiftIdea(7),
+            Route::Events,
+            Route::Event(12),
+            Route::Wishlist(5),
+            Route::ForgotPassword,
+            Route::ResetPassword("abc123".to_string()),
+            Route::VerifyEmail("abc123".to_string()),
+        ];
+        for route in routes {
+            assert_eq!(round_trip(route.clone()), Ok(route));
+        }
+    }
+
+    #[test]
+    fn empty_filters_leave_the_query_string_out() {
+        assert!(Route::GiftIdeas(GiftIdeasFilter::default()).search().is_empty());
+        // `?recipient=&status=` is the same as no filter at all
+        let route = Route::try_from(url(&["gift_ideas"], vec![("recipient", vec![""]), ("status", vec![""])]));
+        assert_eq!(route, Ok(Route::GiftIdeas(GiftIdeasFilter::default())));
+        let route = Route::try_from(url(&["reset_password"], vec![]));
+        assert_eq!(route, Ok(Route::ResetPassword(String::new())));
+    }
+
+    #[test]
+    fn values_needing_encoding_round_trip() {
+        for token in &["a+b/c=d", "x&y z", "100%", "ünïcödé"] {
+            assert_eq!(round_trip(Route::ResetPassword(token.to_string())), Ok(Route::ResetPassword(token.to_string())));
+            assert_eq!(round_trip(Route::VerifyEmail(token.to_string())), Ok(Route::VerifyEmail(token.to_string())));
+        }
+    }
+
+    #[test]
+    fn unknown_params_are_ignored() {
+        let route = Route::try_from(url(&["gift_ideas"], vec![("recipient", vec!["3"]), ("utm_source", vec!["email"])]));
+        assert_eq!(route, Ok(Route::GiftIdeas(GiftIdeasFilter { recipient: Some(3), status: None })));
+        let route = Route::try_from(url(&["gift_ideas"], vec![("recipient", vec!["three"]), ("status", vec!["given"])]));
+        assert_eq!(route, Ok(Route::GiftIdeas(GiftIdeasFilter::default())));
+        let route = Route::try_from(url(&["events", "12"], vec![("status", vec!["open"])]));
+        assert_eq!(route, Ok(Route::Event(12)));
+    }
+
+    #[test]
+    fn unknown_paths_are_rejected() {
+        assert_eq!(Route::try_from(url(&["nowhere"], vec![])), Err(()));
+        assert_eq!(Route::try_from(url(&["events", "twelve"], vec![])), Err(()));
+        assert_eq!(Route::try_from(url(&["users", "5"], vec![])), Err(()));
+        // trailing and doubled slashes are tolerated
+        assert_eq!(Route::try_from(url(&["", "gift_ideas", "", "7", ""], vec![])), Ok(Route::GiftIdea(7)));
+    }
+}
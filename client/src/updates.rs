@@ -0,0 +1,50 @@
+use futures::channel::mpsc;
+use futures::StreamExt;
+use seed::prelude::*;
+
+/// Live gift idea events pushed by the server over `/v1/updates/stream`.  The browser's
+/// `EventSource` reconnects on its own; dropping this closes the connection.
+pub struct UpdateStream {
+    event_source: web_sys::EventSource,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _handle: StreamHandle,
+}
+
+impl std::fmt::Debug for UpdateStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateStream").field("url", &self.event_source.url()).finish()
+    }
+}
+
+impl Drop for UpdateStream {
+    fn drop(&mut self) {
+        self.event_source.close();
+    }
+}
+
+pub fn connect<Ms: 'static, GMs: 'static>(orders: &mut impl Orders<Ms, GMs>, to_msg: fn(shared::GiftIdeaEvent) -> Ms) -> Option<UpdateStream> {
+    let event_source = match web_sys::EventSource::new("/v1/updates/stream") {
+        Ok(event_source) => event_source,
+        Err(error) => {
+            seed::log!("error opening update stream", error);
+            return None;
+        }
+    };
+    let (sender, receiver) = mpsc::unbounded();
+    let on_message = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+        let data = event.data().as_string().unwrap_or_default();
+        match serde_json::from_str::<shared::GiftIdeaEvent>(&data) {
+            Ok(gift_idea_event) => {
+                let _ = sender.unbounded_send(gift_idea_event);
+            }
+            Err(error) => seed::log!("error parsing update", error.to_string()),
+        }
+    }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+    event_source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    let handle = orders.stream_with_handle(receiver.map(to_msg));
+    Some(UpdateStream {
+        event_source,
+        _on_message: on_message,
+        _handle: handle,
+    })
+}
@@ -0,0 +1 @@
+DROP TABLE gift_idea_attachments;
@@ -0,0 +1,11 @@
+CREATE TABLE gift_idea_attachments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  gift_idea_id INTEGER NOT NULL,
+  file_name VARCHAR(255) NOT NULL,
+  content_type VARCHAR(255) NOT NULL,
+  size INTEGER NOT NULL,
+  storage_key VARCHAR(255) NOT NULL UNIQUE,
+  thumbnail_storage_key VARCHAR(255) NOT NULL UNIQUE,
+  date_added DATETIME NOT NULL,
+  FOREIGN KEY (gift_idea_id) REFERENCES gift_ideas (id)
+)
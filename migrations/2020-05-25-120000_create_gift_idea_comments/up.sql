@@ -0,0 +1,10 @@
+CREATE TABLE gift_idea_comments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  gift_idea_id INTEGER NOT NULL,
+  author_user_id INTEGER NOT NULL,
+  body TEXT NOT NULL,
+  date_added DATETIME NOT NULL,
+  date_last_modified DATETIME NOT NULL,
+  FOREIGN KEY (gift_idea_id) REFERENCES gift_ideas (id),
+  FOREIGN KEY (author_user_id) REFERENCES users (id)
+)
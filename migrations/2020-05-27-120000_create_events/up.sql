@@ -0,0 +1,9 @@
+CREATE TABLE events (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  event_date DATE NOT NULL,
+  owner_id INTEGER NOT NULL,
+  date_added DATETIME NOT NULL,
+  date_reminder_sent DATETIME,
+  FOREIGN KEY (owner_id) REFERENCES users (id)
+)
@@ -0,0 +1,7 @@
+CREATE TABLE notification_preferences (
+  user_id INTEGER PRIMARY KEY NOT NULL,
+  new_gift_idea BOOLEAN NOT NULL DEFAULT 1,
+  reservation_released BOOLEAN NOT NULL DEFAULT 1,
+  event_reminder BOOLEAN NOT NULL DEFAULT 1,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+)
@@ -0,0 +1,16 @@
+CREATE TABLE notifications (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  kind VARCHAR(64) NOT NULL,
+  subject VARCHAR(255) NOT NULL,
+  body TEXT NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
+  date_added DATETIME NOT NULL,
+  date_next_attempt DATETIME NOT NULL,
+  date_sent DATETIME,
+  date_failed DATETIME,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX notifications_pending ON notifications (date_sent, date_failed, date_next_attempt)
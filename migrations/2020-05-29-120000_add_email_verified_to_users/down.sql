@@ -0,0 +1,11 @@
+CREATE TABLE users_backup (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  email VARCHAR (255) NOT NULL UNIQUE,
+  password VARCHAR (255) NOT NULL,
+  first_name VARCHAR(255),
+  last_name VARCHAR(255),
+  phone VARCHAR(15)
+);
+INSERT INTO users_backup SELECT id, email, password, first_name, last_name, phone FROM users;
+DROP TABLE users;
+ALTER TABLE users_backup RENAME TO users;
@@ -0,0 +1,11 @@
+CREATE TABLE user_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  purpose VARCHAR(32) NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  email VARCHAR(255) NOT NULL,
+  date_added DATETIME NOT NULL,
+  date_expires DATETIME NOT NULL,
+  date_used DATETIME,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+)
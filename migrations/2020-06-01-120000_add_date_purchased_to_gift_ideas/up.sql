@@ -0,0 +1 @@
+ALTER TABLE gift_ideas ADD COLUMN date_purchased DATETIME
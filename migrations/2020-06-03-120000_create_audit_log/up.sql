@@ -0,0 +1,22 @@
+CREATE TABLE audit_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  actor_user_id INTEGER,
+  entity_type VARCHAR(32) NOT NULL,
+  entity_id INTEGER NOT NULL,
+  action VARCHAR(32) NOT NULL,
+  changes TEXT NOT NULL,
+  date_added DATETIME NOT NULL
+);
+
+CREATE INDEX audit_log_entity ON audit_log (entity_type, entity_id);
+
+-- history is only ever appended to, even when the gift idea or user it describes is deleted
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -0,0 +1,21 @@
+DROP INDEX gift_ideas_date_deleted;
+CREATE TABLE gift_ideas_backup (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  description TEXT,
+  price VARCHAR(255),
+  url VARCHAR(255),
+  date_added DATETIME NOT NULL,
+  date_last_modified DATETIME NOT NULL,
+  date_reserved DATETIME,
+  owner_id INTEGER NOT NULL,
+  recipient_user_id INTEGER NOT NULL,
+  reserved_by_user_id INTEGER,
+  date_purchased DATETIME,
+  FOREIGN KEY (owner_id) REFERENCES users (id),
+  FOREIGN KEY (recipient_user_id) REFERENCES users (id),
+  FOREIGN KEY (reserved_by_user_id) REFERENCES users (id)
+);
+INSERT INTO gift_ideas_backup SELECT id, title, description, price, url, date_added, date_last_modified, date_reserved, owner_id, recipient_user_id, reserved_by_user_id, date_purchased FROM gift_ideas;
+DROP TABLE gift_ideas;
+ALTER TABLE gift_ideas_backup RENAME TO gift_ideas;
@@ -0,0 +1,4 @@
+ALTER TABLE gift_ideas ADD COLUMN date_deleted DATETIME;
+ALTER TABLE gift_ideas ADD COLUMN deleted_by_user_id INTEGER REFERENCES users (id);
+
+CREATE INDEX gift_ideas_date_deleted ON gift_ideas (date_deleted)
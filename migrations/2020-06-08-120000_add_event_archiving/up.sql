@@ -0,0 +1,7 @@
+ALTER TABLE events ADD COLUMN date_archived DATETIME;
+
+ALTER TABLE gift_ideas ADD COLUMN event_id INTEGER REFERENCES events (id);
+ALTER TABLE gift_ideas ADD COLUMN date_archived DATETIME;
+ALTER TABLE gift_ideas ADD COLUMN carried_over_from_id INTEGER REFERENCES gift_ideas (id);
+
+CREATE INDEX gift_ideas_event_id ON gift_ideas (event_id)
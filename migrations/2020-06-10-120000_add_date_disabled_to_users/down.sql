@@ -0,0 +1,12 @@
+CREATE TABLE users_backup (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  email VARCHAR (255) NOT NULL UNIQUE,
+  password VARCHAR (255) NOT NULL,
+  first_name VARCHAR(255),
+  last_name VARCHAR(255),
+  phone VARCHAR(15),
+  email_verified BOOLEAN NOT NULL DEFAULT 0
+);
+INSERT INTO users_backup SELECT id, email, password, first_name, last_name, phone, email_verified FROM users;
+DROP TABLE users;
+ALTER TABLE users_backup RENAME TO users;
\ No newline at end of file
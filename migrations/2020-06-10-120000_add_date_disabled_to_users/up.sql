@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN date_disabled DATETIME
@@ -0,0 +1,7 @@
+CREATE TABLE calendar_feeds (
+  user_id INTEGER PRIMARY KEY NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  include_reservations BOOLEAN NOT NULL DEFAULT 1,
+  date_added DATETIME NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+)
@@ -0,0 +1,13 @@
+CREATE TABLE user_dates (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL,
+  kind VARCHAR(32) NOT NULL,
+  label VARCHAR(100),
+  month INTEGER NOT NULL,
+  day INTEGER NOT NULL,
+  year INTEGER,
+  year_visible BOOLEAN NOT NULL DEFAULT 0,
+  date_added DATETIME NOT NULL,
+  date_last_event DATE,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+)
@@ -0,0 +1,12 @@
+CREATE TABLE jobs (
+  name VARCHAR(64) PRIMARY KEY NOT NULL,
+  schedule VARCHAR(64) NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
+  date_next_run DATETIME NOT NULL,
+  date_last_started DATETIME,
+  date_last_succeeded DATETIME,
+  date_last_failed DATETIME,
+  lease_owner VARCHAR(64),
+  date_lease_expires DATETIME
+)
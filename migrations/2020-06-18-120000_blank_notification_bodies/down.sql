@@ -0,0 +1,2 @@
+-- the bodies are gone for good
+SELECT 1;
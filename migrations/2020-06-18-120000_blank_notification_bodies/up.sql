@@ -0,0 +1,3 @@
+-- sent emails aren't kept, and account emails are now rendered as they're sent, so the tokens
+-- queued before that go
+UPDATE notifications SET body = '' WHERE date_sent IS NOT NULL OR kind IN ('verify_email', 'reset_password');
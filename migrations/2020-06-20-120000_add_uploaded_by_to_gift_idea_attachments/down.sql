@@ -0,0 +1,14 @@
+CREATE TABLE gift_idea_attachments_backup (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  gift_idea_id INTEGER NOT NULL,
+  file_name VARCHAR(255) NOT NULL,
+  content_type VARCHAR(255) NOT NULL,
+  size INTEGER NOT NULL,
+  storage_key VARCHAR(255) NOT NULL UNIQUE,
+  thumbnail_storage_key VARCHAR(255) NOT NULL UNIQUE,
+  date_added DATETIME NOT NULL,
+  FOREIGN KEY (gift_idea_id) REFERENCES gift_ideas (id)
+);
+INSERT INTO gift_idea_attachments_backup SELECT id, gift_idea_id, file_name, content_type, size, storage_key, thumbnail_storage_key, date_added FROM gift_idea_attachments;
+DROP TABLE gift_idea_attachments;
+ALTER TABLE gift_idea_attachments_backup RENAME TO gift_idea_attachments;
@@ -0,0 +1 @@
+ALTER TABLE gift_idea_attachments ADD COLUMN uploaded_by_user_id INTEGER REFERENCES users (id)
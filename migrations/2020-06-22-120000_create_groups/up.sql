@@ -0,0 +1,16 @@
+CREATE TABLE groups (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(100) NOT NULL UNIQUE,
+  date_added DATETIME NOT NULL
+);
+CREATE TABLE group_members (
+  group_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  date_added DATETIME NOT NULL,
+  PRIMARY KEY (group_id, user_id),
+  FOREIGN KEY (group_id) REFERENCES groups (id),
+  FOREIGN KEY (user_id) REFERENCES users (id)
+);
+-- until now everyone shared one list, so existing users start out in one group together
+INSERT INTO groups (name, date_added) SELECT 'Everyone', CURRENT_TIMESTAMP WHERE EXISTS (SELECT 1 FROM users);
+INSERT INTO group_members (group_id, user_id, date_added) SELECT groups.id, users.id, CURRENT_TIMESTAMP FROM groups, users
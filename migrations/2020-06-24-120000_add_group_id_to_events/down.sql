@@ -0,0 +1,14 @@
+DROP INDEX events_group_id;
+CREATE TABLE events_backup (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  event_date DATE NOT NULL,
+  owner_id INTEGER NOT NULL,
+  date_added DATETIME NOT NULL,
+  date_reminder_sent DATETIME,
+  date_archived DATETIME,
+  FOREIGN KEY (owner_id) REFERENCES users (id)
+);
+INSERT INTO events_backup SELECT id, name, event_date, owner_id, date_added, date_reminder_sent, date_archived FROM events;
+DROP TABLE events;
+ALTER TABLE events_backup RENAME TO events
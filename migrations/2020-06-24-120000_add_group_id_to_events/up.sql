@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN group_id INTEGER REFERENCES groups (id);
+CREATE INDEX events_group_id ON events (group_id)
@@ -0,0 +1,21 @@
+// Writes out the versions of the migrations `embed_migrations!` builds into the server, so
+// `has_pending_migrations` compares the database against those rather than against whatever
+// `migrations` directory happens to be near the working directory.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR")).join("../migrations");
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let mut versions = fs::read_dir(&dir)
+        .expect("migrations directory")
+        .map(|entry| entry.expect("migration").file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        // as diesel has it: the name up to its first underscore, without the dashes
+        .map(|name| name.split('_').next().unwrap_or_default().replace('-', ""))
+        .collect::<Vec<_>>();
+    versions.sort();
+    let out = Path::new(&env::var("OUT_DIR").expect("OUT_DIR")).join("migration_versions.rs");
+    fs::write(out, format!("const MIGRATION_VERSIONS: &[&str] = &{:?};\n", versions)).expect("migration_versions.rs written");
+}
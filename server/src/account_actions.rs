@@ -0,0 +1,79 @@
+use diesel::prelude::*;
+
+use crate::attachment_actions;
+use crate::calendar_actions;
+use crate::comment_actions;
+use crate::event_actions;
+use crate::gift_idea_actions;
+use crate::group_actions;
+use crate::models;
+use crate::notification_actions;
+use crate::notifications;
+use crate::schema::user_destinations;
+use crate::storage::Storage;
+use crate::token_actions;
+use crate::user_actions;
+use crate::user_date_actions;
+
+/// What changed for everyone else when an account was deleted, so connected clients can be told.
+pub struct DeletedAccount {
+    pub deleted_gift_ideas: Vec<models::GiftIdea>,
+    pub released_gift_ideas: Vec<shared::GiftIdeaResponseBody>,
+}
+
+pub fn check_password(uid: i32, password: &str, conn: &SqliteConnection) -> Result<Option<models::User>, diesel::result::Error> {
+    Ok(user_actions::find_by_id(uid, conn)?.filter(|user| user.password == password))
+}
+
+pub fn change_password(uid: i32, current_password: &str, new_password: String, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    match check_password(uid, current_password, conn)? {
+        Some(user) => {
+            user_actions::set_password(user.id, new_password, Some(uid), conn)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Removes a user and everything that only makes sense with them around:
+///
+/// - ideas they are the recipient of, and ideas they added for someone else, are deleted along
+///   with their images and comments; anyone who had reserved one of those is emailed
+/// - their reservations on other people's ideas are released so someone else can pick them up
+/// - their comments, events, profile dates, tokens, calendar feed, notification preferences,
+///   queued emails and group memberships are deleted
+pub fn delete(uid: i32, storage: &dyn Storage, conn: &SqliteConnection) -> Result<DeletedAccount, attachment_actions::AttachmentError> {
+    conn.transaction::<_, attachment_actions::AttachmentError, _>(|| {
+        // ones already in the trash had their reservations emailed about when they went there
+        let (deleted_gift_ideas, trashed): (Vec<_>, Vec<_>) = gift_idea_actions::find_by_owner_or_recipient_including_deleted(uid, conn)?
+            .into_iter()
+            .partition(|gift| gift.date_deleted.is_none());
+        for gift in deleted_gift_ideas.iter() {
+            notifications::queue_reservation_released(gift, Some(uid), conn)?;
+        }
+        for gift in deleted_gift_ideas.iter().chain(trashed.iter()) {
+            attachment_actions::delete_by_gift_idea(gift.id, storage, conn)?;
+            comment_actions::delete_by_gift_idea(gift.id, conn)?;
+            gift_idea_actions::purge(gift.id, Some(uid), conn)?;
+        }
+        let mut released_gift_ideas = Vec::new();
+        for gift in gift_idea_actions::find_reserved_by(uid, conn)? {
+            released_gift_ideas.extend(gift_idea_actions::unreserve(gift.id, Some(uid), conn)?);
+        }
+        gift_idea_actions::clear_reserver(uid, conn)?;
+        attachment_actions::clear_uploader(uid, conn)?;
+        comment_actions::delete_by_author(uid, conn)?;
+        event_actions::delete_by_owner(uid, conn)?;
+        user_date_actions::delete_by_user(uid, conn)?;
+        token_actions::delete_by_user(uid, conn)?;
+        calendar_actions::delete(uid, conn)?;
+        group_actions::remove_from_all(uid, Some(uid), conn)?;
+        notification_actions::delete_by_user(uid, conn)?;
+        diesel::delete(user_destinations::table.filter(user_destinations::dsl::user_id.eq(uid))).execute(conn)?;
+        user_actions::delete(uid, Some(uid), conn)?;
+        Ok(DeletedAccount {
+            deleted_gift_ideas,
+            released_gift_ideas,
+        })
+    })
+}
@@ -0,0 +1,256 @@
+use diesel::prelude::*;
+use std::fmt;
+use std::io;
+
+use crate::gift_idea_actions;
+use crate::models;
+use crate::schema::gift_idea_attachments;
+use crate::storage::Storage;
+
+pub const MAX_ATTACHMENT_SIZE: usize = 5 * 1024 * 1024;
+
+pub const ALLOWED_CONTENT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+const THUMBNAIL_SIZE: u32 = 160;
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    Database(diesel::result::Error),
+    Storage(io::Error),
+    Image(image::ImageError),
+    ContentTypeMismatch(String),
+    TooLarge(usize),
+}
+
+impl fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttachmentError::Database(e) => write!(f, "database error: {}", e),
+            AttachmentError::Storage(e) => write!(f, "storage error: {}", e),
+            AttachmentError::Image(e) => write!(f, "image error: {}", e),
+            AttachmentError::ContentTypeMismatch(content_type) => write!(f, "file contents do not match content type: {}", content_type),
+            AttachmentError::TooLarge(size) => write!(f, "attachments are limited to {} bytes, this one is {}", MAX_ATTACHMENT_SIZE, size),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for AttachmentError {
+    fn from(e: diesel::result::Error) -> AttachmentError {
+        AttachmentError::Database(e)
+    }
+}
+
+impl From<io::Error> for AttachmentError {
+    fn from(e: io::Error) -> AttachmentError {
+        AttachmentError::Storage(e)
+    }
+}
+
+impl From<image::ImageError> for AttachmentError {
+    fn from(e: image::ImageError) -> AttachmentError {
+        AttachmentError::Image(e)
+    }
+}
+
+pub fn is_allowed_content_type(content_type: &str) -> bool {
+    ALLOWED_CONTENT_TYPES.contains(&content_type)
+}
+
+fn make_thumbnail(content_type: &str, data: &[u8]) -> Result<Vec<u8>, AttachmentError> {
+    let detected = match image::guess_format(data)? {
+        image::ImageFormat::Png => "image/png",
+        image::ImageFormat::Jpeg => "image/jpeg",
+        image::ImageFormat::Gif => "image/gif",
+        image::ImageFormat::WebP => "image/webp",
+        _ => "",
+    };
+    if detected != content_type {
+        return Err(AttachmentError::ContentTypeMismatch(content_type.to_string()));
+    }
+    let thumbnail = image::load_from_memory(data)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut buffer = Vec::new();
+    thumbnail.write_to(&mut buffer, image::ImageOutputFormat::Png)?;
+    Ok(buffer)
+}
+
+// an attachment is seen by whoever can see its gift idea, see `gift_idea_actions::visible_to`
+fn is_visible_to(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    match gift_idea_actions::find_by_id(gid, conn)? {
+        Some(gift) => Ok(!gift_idea_actions::visible_to(vec![gift], uid, conn)?.is_empty()),
+        None => Ok(false),
+    }
+}
+
+/// The attachments on the gift ideas `uid` can see.
+pub fn find_all(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::GiftIdeaAttachment>, diesel::result::Error> {
+    let gift_ids: Vec<i32> = gift_idea_actions::visible_to(gift_idea_actions::find_all(conn)?, uid, conn)?
+        .into_iter()
+        .map(|gift| gift.id)
+        .collect();
+    let results = gift_idea_attachments::table
+        .filter(gift_idea_attachments::dsl::gift_idea_id.eq_any(gift_ids))
+        .load::<models::GiftIdeaAttachment>(conn)?;
+    Ok(results)
+}
+
+pub fn find_by_id(aid: i32, conn: &SqliteConnection) -> Result<Option<models::GiftIdeaAttachment>, diesel::result::Error> {
+    let attachment = gift_idea_attachments::table
+        .filter(gift_idea_attachments::dsl::id.eq(aid))
+        .first::<models::GiftIdeaAttachment>(conn)
+        .optional()?;
+    Ok(attachment)
+}
+
+/// `None` if there's no such attachment or `uid` can't see its gift idea.
+pub fn find_visible(aid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<models::GiftIdeaAttachment>, diesel::result::Error> {
+    match find_by_id(aid, conn)? {
+        Some(attachment) if is_visible_to(attachment.gift_idea_id, uid, conn)? => Ok(Some(attachment)),
+        _ => Ok(None),
+    }
+}
+
+/// `None` if there's no such gift idea or `uid` can't see it.
+pub fn find_visible_by_gift_idea(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<Vec<models::GiftIdeaAttachment>>, diesel::result::Error> {
+    if !is_visible_to(gid, uid, conn)? {
+        return Ok(None);
+    }
+    Ok(Some(find_by_gift_idea(gid, conn)?))
+}
+
+pub fn find_by_gift_idea(gid: i32, conn: &SqliteConnection) -> Result<Vec<models::GiftIdeaAttachment>, diesel::result::Error> {
+    let results = gift_idea_attachments::table
+        .filter(gift_idea_attachments::dsl::gift_idea_id.eq(gid))
+        .load::<models::GiftIdeaAttachment>(conn)?;
+    Ok(results)
+}
+
+/// Stores `data` and its thumbnail and records them against the gift idea.  Nothing is left in
+/// storage when any step fails.
+pub fn add(
+    gid: i32,
+    uploaded_by_user_id: i32,
+    file_name: String,
+    content_type: String,
+    data: Vec<u8>,
+    storage: &dyn Storage,
+    conn: &SqliteConnection,
+) -> Result<models::GiftIdeaAttachment, AttachmentError> {
+    if data.len() > MAX_ATTACHMENT_SIZE {
+        return Err(AttachmentError::TooLarge(data.len()));
+    }
+    let thumbnail = make_thumbnail(&content_type, &data)?;
+    let new_attachment = models::NewGiftIdeaAttachment::new(gid, file_name, content_type, data.len() as i32, uploaded_by_user_id);
+    let stored = storage
+        .put(&new_attachment.storage_key, &data)
+        .and_then(|_| storage.put(&new_attachment.thumbnail_storage_key, &thumbnail))
+        .map_err(AttachmentError::from)
+        .and_then(|_| {
+            diesel::insert_into(gift_idea_attachments::table).values(&new_attachment).execute(conn)?;
+            let attachment = gift_idea_attachments::table
+                .filter(gift_idea_attachments::dsl::storage_key.eq(new_attachment.storage_key.to_string()))
+                .first::<models::GiftIdeaAttachment>(conn)?;
+            Ok(attachment)
+        });
+    if stored.is_err() {
+        // best effort, the original error is the one worth reporting
+        storage.delete(&new_attachment.storage_key).ok();
+        storage.delete(&new_attachment.thumbnail_storage_key).ok();
+    }
+    stored
+}
+
+pub fn read(attachment: &models::GiftIdeaAttachment, thumbnail: bool, storage: &dyn Storage) -> Result<Vec<u8>, AttachmentError> {
+    let key = if thumbnail { &attachment.thumbnail_storage_key } else { &attachment.storage_key };
+    Ok(storage.get(key)?)
+}
+
+pub fn delete(aid: i32, storage: &dyn Storage, conn: &SqliteConnection) -> Result<bool, AttachmentError> {
+    match find_by_id(aid, conn)? {
+        Some(attachment) => {
+            let num_deleted = diesel::delete(gift_idea_attachments::table.find(aid)).execute(conn)?;
+            storage.delete(&attachment.storage_key)?;
+            storage.delete(&attachment.thumbnail_storage_key)?;
+            Ok(num_deleted == 1)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Forgets who uploaded what, for when their account goes; the images stay with the ideas.
+pub fn clear_uploader(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::update(gift_idea_attachments::table.filter(gift_idea_attachments::dsl::uploaded_by_user_id.eq(uid)))
+        .set(gift_idea_attachments::dsl::uploaded_by_user_id.eq(None::<i32>))
+        .execute(conn)
+}
+
+pub fn delete_by_gift_idea(gid: i32, storage: &dyn Storage, conn: &SqliteConnection) -> Result<usize, AttachmentError> {
+    let attachments = find_by_gift_idea(gid, conn)?;
+    for attachment in attachments.iter() {
+        delete(attachment.id, storage, conn)?;
+    }
+    Ok(attachments.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use image::GenericImageView;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(width, height))
+            .write_to(&mut buffer, image::ImageOutputFormat::Png)
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn only_images_are_allowed() {
+        for content_type in &["image/png", "image/jpeg", "image/gif", "image/webp"] {
+            assert!(is_allowed_content_type(content_type), "{} should be allowed", content_type);
+        }
+        for content_type in &["image/svg+xml", "text/html", "application/pdf", "IMAGE/PNG", ""] {
+            assert!(!is_allowed_content_type(content_type), "{} should not be allowed", content_type);
+        }
+    }
+
+    #[test]
+    fn thumbnails_are_small_pngs() {
+        let thumbnail = make_thumbnail("image/png", &png(800, 400)).unwrap();
+        assert_eq!(image::guess_format(&thumbnail).unwrap(), image::ImageFormat::Png);
+        let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+    }
+
+    #[test]
+    fn contents_must_match_the_content_type() {
+        match make_thumbnail("image/jpeg", &png(10, 10)) {
+            Err(AttachmentError::ContentTypeMismatch(content_type)) => assert_eq!(content_type, "image/jpeg"),
+            other => panic!("expected a content type mismatch, got {:?}", other.map(|_| ())),
+        }
+        assert!(make_thumbnail("image/png", b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").is_err());
+    }
+
+    #[test]
+    fn oversized_attachments_are_refused_before_anything_is_stored() {
+        let storage = MemoryStorage::default();
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        let data = vec![0; MAX_ATTACHMENT_SIZE + 1];
+        match add(1, 1, "big.png".to_string(), "image/png".to_string(), data, &storage, &conn) {
+            Err(AttachmentError::TooLarge(size)) => assert_eq!(size, MAX_ATTACHMENT_SIZE + 1),
+            other => panic!("expected too large, got {:?}", other.map(|_| ())),
+        }
+        assert!(storage.files.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn files_are_removed_when_the_insert_fails() {
+        let storage = MemoryStorage::default();
+        // no migrations, so there's no table to insert into
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        let result = add(1, 1, "small.png".to_string(), "image/png".to_string(), png(10, 10), &storage, &conn);
+        assert!(matches!(result, Err(AttachmentError::Database(_))));
+        assert!(storage.files.lock().unwrap().is_empty());
+    }
+}
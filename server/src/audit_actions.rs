@@ -0,0 +1,140 @@
+use diesel::prelude::*;
+use serde::Serialize;
+
+use crate::models;
+use crate::schema::{audit_log, users};
+
+pub const GIFT_IDEA: &str = "gift_idea";
+pub const USER: &str = "user";
+pub const GROUP: &str = "group";
+
+pub const CREATED: &str = "created";
+pub const UPDATED: &str = "updated";
+pub const DELETED: &str = "deleted";
+pub const RESTORED: &str = "restored";
+pub const PURGED: &str = "purged";
+pub const RESERVED: &str = "reserved";
+pub const UNRESERVED: &str = "unreserved";
+pub const PURCHASED: &str = "purchased";
+pub const ARCHIVED: &str = "archived";
+pub const PASSWORD_CHANGED: &str = "password_changed";
+pub const EMAIL_VERIFIED: &str = "email_verified";
+pub const DISABLED: &str = "disabled";
+pub const ENABLED: &str = "enabled";
+pub const MEMBER_ADDED: &str = "member_added";
+pub const MEMBER_REMOVED: &str = "member_removed";
+
+const REDACTED: &str = "[redacted]";
+
+// only the fact these changed is kept, never the values
+const REDACTED_FIELDS: &[&str] = &["password"];
+
+// the entry's own id and timestamp already cover these
+const IGNORED_FIELDS: &[&str] = &["id", "date_last_modified"];
+
+// what a recipient mustn't learn about the ideas for them
+const HIDDEN_FROM_RECIPIENT_FIELDS: &[&str] = &["reserved_by_user_id", "date_reserved", "date_purchased"];
+const HIDDEN_FROM_RECIPIENT_ACTIONS: &[&str] = &[RESERVED, UNRESERVED, PURCHASED];
+
+/// Field by field differences between two versions of a row; pass `None` for the side that
+/// doesn't exist when something is created or deleted.
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Vec<shared::FieldChange> {
+    let to_fields = |value: Option<&T>| match value.map(serde_json::to_value) {
+        Some(Ok(serde_json::Value::Object(fields))) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let before = to_fields(before);
+    let after = to_fields(after);
+    let mut fields = before.keys().chain(after.keys()).cloned().collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(&field).and_then(render);
+            let new = after.get(&field).and_then(render);
+            if old == new {
+                return None;
+            }
+            if REDACTED_FIELDS.contains(&field.as_str()) {
+                let redact = |value: Option<String>| value.map(|_| REDACTED.to_string());
+                return Some(shared::FieldChange {
+                    field,
+                    before: redact(old),
+                    after: redact(new),
+                });
+            }
+            Some(shared::FieldChange { field, before: old, after: new })
+        })
+        .collect()
+}
+
+fn render(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(value) => Some(value.to_string()),
+        value => Some(value.to_string()),
+    }
+}
+
+pub fn record(
+    actor_user_id: Option<i32>,
+    entity_type: &str,
+    entity_id: i32,
+    action: &str,
+    changes: &[shared::FieldChange],
+    conn: &SqliteConnection,
+) -> Result<(), diesel::result::Error> {
+    let entry = models::NewAuditEntry::new(actor_user_id, entity_type, entity_id, action, changes);
+    diesel::insert_into(audit_log::table).values(&entry).execute(conn)?;
+    Ok(())
+}
+
+pub fn find_by_entity(entity_type: &str, entity_id: i32, conn: &SqliteConnection) -> Result<Vec<shared::AuditEntryResponseBody>, diesel::result::Error> {
+    let results = audit_log::table
+        .left_join(users::table)
+        .filter(audit_log::dsl::entity_type.eq(entity_type))
+        .filter(audit_log::dsl::entity_id.eq(entity_id))
+        .order(audit_log::dsl::id.asc())
+        .load::<(models::AuditEntry, Option<models::User>)>(conn)?;
+    Ok(results.into_iter().map(|(entry, actor)| entry.into_response(actor.as_ref())).collect())
+}
+
+/// The last recipient a gift idea's history mentions, for ideas that have since been purged.
+pub fn last_recipient(entries: &[shared::AuditEntryResponseBody]) -> Option<i32> {
+    last_user_id(entries, "recipient_user_id")
+}
+
+/// The last owner a gift idea's history mentions, for ideas that have since been purged.
+pub fn last_owner(entries: &[shared::AuditEntryResponseBody]) -> Option<i32> {
+    last_user_id(entries, "owner_id")
+}
+
+fn last_user_id(entries: &[shared::AuditEntryResponseBody], field: &str) -> Option<i32> {
+    entries
+        .iter()
+        .flat_map(|entry| entry.changes.iter())
+        .filter(|change| change.field == field)
+        .filter_map(|change| change.after.as_ref().or(change.before.as_ref()))
+        .next_back()
+        .and_then(|user_id| user_id.parse().ok())
+}
+
+/// Strips anything about reservations and purchases from a gift idea's history, so it can be
+/// shown to the person the idea is for.
+pub fn redact_for_recipient(entries: Vec<shared::AuditEntryResponseBody>) -> Vec<shared::AuditEntryResponseBody> {
+    entries
+        .into_iter()
+        .filter(|entry| !HIDDEN_FROM_RECIPIENT_ACTIONS.contains(&entry.action.as_str()))
+        .filter_map(|mut entry| {
+            entry.changes.retain(|change| !HIDDEN_FROM_RECIPIENT_FIELDS.contains(&change.field.as_str()));
+            // an update that only touched hidden fields would still give the game away
+            if entry.action == UPDATED && entry.changes.is_empty() {
+                None
+            } else {
+                Some(entry)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,71 @@
+use actix_session::Session;
+use actix_web::{error, Error};
+
+use crate::models;
+use crate::notifications;
+use crate::telemetry;
+use crate::user_actions;
+use crate::DbPool;
+
+const USER_ID_KEY: &str = "user_id";
+
+pub fn session_key() -> Vec<u8> {
+    match std::env::var("SESSION_KEY") {
+        Ok(key) if key.len() >= 32 => key.into_bytes(),
+        _ => {
+            warn!("SESSION_KEY is missing or shorter than 32 bytes, sessions will not survive a restart");
+            let mut key = uuid::Uuid::new_v4().as_bytes().to_vec();
+            key.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+            key
+        }
+    }
+}
+
+/// Whether the session cookie is only sent over HTTPS: `SESSION_COOKIE_SECURE` (`true` or `false`)
+/// if it's set, otherwise whether `APP_URL` is an https address.
+pub fn secure_cookies() -> bool {
+    match std::env::var("SESSION_COOKIE_SECURE") {
+        Ok(secure) => secure.trim().parse().unwrap_or_else(|_| {
+            warn!("SESSION_COOKIE_SECURE should be true or false, not {}", secure);
+            true
+        }),
+        Err(_) => notifications::app_url().starts_with("https://"),
+    }
+}
+
+pub fn login(session: &Session, uid: i32) -> Result<(), Error> {
+    session.renew();
+    session.set(USER_ID_KEY, uid)
+}
+
+pub fn logout(session: &Session) {
+    session.purge();
+}
+
+/// The signed in user.  A session whose user has since been disabled or deleted is purged and
+/// treated as signed out, so disabling someone ends the sessions they already have.
+pub async fn current_user_id(session: &Session, pool: &DbPool) -> Result<i32, Error> {
+    let uid = session.get::<i32>(USER_ID_KEY)?.ok_or_else(|| error::ErrorUnauthorized("Not logged in"))?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let active = telemetry::block(move || user_actions::is_active(uid, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        error::ErrorInternalServerError("")
+    })?;
+    if !active {
+        session.purge();
+        return Err(error::ErrorUnauthorized("Not logged in"));
+    }
+    Ok(uid)
+}
+
+/// Whether `user` has verified an email that is one of the comma separated addresses in
+/// `ADMIN_EMAILS`.  Anyone can sign up with, or change to, an address nobody has claimed yet, so
+/// the address alone isn't enough.  Nobody is an admin when it isn't set.
+pub fn is_admin(user: &models::User) -> bool {
+    user.email_verified
+        && std::env::var("ADMIN_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .any(|admin| !admin.is_empty() && admin.eq_ignore_ascii_case(user.email.trim()))
+}
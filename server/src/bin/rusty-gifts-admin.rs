@@ -0,0 +1,664 @@
+//! Operator tooling for a gifts database, run against the same `DATABASE_URL` (and
+//! `ATTACHMENT_DIR`) as the server.  Everything goes through the server's action modules, so
+//! changes land in the audit log with no actor, like the background jobs.
+
+use diesel::{Connection, SqliteConnection};
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use structopt::StructOpt;
+
+use server::{attachment_actions, export_actions, gift_idea_actions, group_actions, models, storage, trash, user_actions};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "rusty-gifts-admin", about = "Administer a rusty-gifts database")]
+struct Opt {
+    /// Print JSON instead of a table
+    #[structopt(long, global = true)]
+    json: bool,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Manage user accounts
+    Users(UsersCommand),
+    /// Manage groups and who is in them
+    Groups(GroupsCommand),
+    /// Inspect and manage gift ideas
+    Gifts(GiftsCommand),
+    /// Apply any pending database migrations
+    Migrate,
+    /// Write out a user's gift ideas, events and reservations, or a group's as one of its members sees it
+    Export {
+        user: UserRef,
+        /// Export this group, which the user has to be in, rather than what the user has put in
+        #[structopt(long)]
+        group: Option<GroupRef>,
+        /// json, which can be imported again, or csv
+        #[structopt(long, default_value = "json")]
+        format: ExportFormat,
+        /// Where to write the export; standard output if left out
+        #[structopt(long, parse(from_os_str))]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Bring a JSON export in as a user; CSV exports are for spreadsheets and can't be imported
+    Import {
+        user: UserRef,
+        #[structopt(parse(from_os_str))]
+        file: std::path::PathBuf,
+        /// Report what would happen without changing anything
+        #[structopt(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ExportFormat, String> {
+        match value {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("expected json or csv, got {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum UsersCommand {
+    /// List every user, disabled ones included
+    List,
+    /// Create a user, already verified unless --unverified is given
+    Create {
+        #[structopt(long)]
+        email: String,
+        /// A random one is generated and printed if left out
+        #[structopt(long)]
+        password: Option<String>,
+        #[structopt(long)]
+        first_name: Option<String>,
+        #[structopt(long)]
+        last_name: Option<String>,
+        #[structopt(long)]
+        unverified: bool,
+    },
+    /// Stop a user signing in
+    Disable { user: UserRef },
+    /// Let a disabled user sign in again
+    Enable { user: UserRef },
+    /// Set a new password
+    ResetPassword {
+        user: UserRef,
+        /// A random one is generated and printed if left out
+        #[structopt(long)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum GroupsCommand {
+    /// List every group
+    List,
+    /// Create an empty group
+    Create { name: String },
+    /// Remove a group; the people in it and their gift ideas stay
+    Delete { group: GroupRef },
+    /// List who is in a group
+    Members { group: GroupRef },
+    /// Put users in a group
+    AddMember { group: GroupRef, users: Vec<UserRef> },
+    /// Take users out of a group
+    RemoveMember { group: GroupRef, users: Vec<UserRef> },
+}
+
+#[derive(Debug, StructOpt)]
+enum GiftsCommand {
+    /// Show a gift idea, even one in the trash
+    Show { gift_id: i32 },
+    /// Give a gift idea a different owner and/or recipient
+    Reassign {
+        gift_id: i32,
+        #[structopt(long)]
+        owner: Option<UserRef>,
+        #[structopt(long)]
+        recipient: Option<UserRef>,
+    },
+    /// Move every idea one user owns, is getting or has reserved, and their group memberships, over to another, e.g. to merge duplicate accounts
+    ReassignUser { from: UserRef, to: UserRef },
+    /// Permanently remove gift ideas, with their images and comments
+    Purge { gift_ids: Vec<i32> },
+}
+
+/// A user given on the command line, by id or by email address.
+#[derive(Debug)]
+enum UserRef {
+    Id(i32),
+    Email(String),
+}
+
+impl std::str::FromStr for UserRef {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<UserRef, String> {
+        match value.parse::<i32>() {
+            Ok(id) => Ok(UserRef::Id(id)),
+            Err(_) if value.contains('@') => Ok(UserRef::Email(value.to_string())),
+            Err(_) => Err(format!("expected a user id or email address, got {}", value)),
+        }
+    }
+}
+
+impl UserRef {
+    fn find(&self, conn: &SqliteConnection) -> Result<models::User, AdminError> {
+        let user = match self {
+            UserRef::Id(id) => user_actions::find_by_id(*id, conn)?,
+            UserRef::Email(email) => user_actions::find_by_email(email.to_string(), conn)?,
+        };
+        user.ok_or_else(|| AdminError::NotFound(format!("no user {}", self)))
+    }
+}
+
+impl fmt::Display for UserRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UserRef::Id(id) => write!(f, "with id {}", id),
+            UserRef::Email(email) => write!(f, "with email {}", email),
+        }
+    }
+}
+
+/// A group given on the command line, by id or by name.
+#[derive(Debug)]
+enum GroupRef {
+    Id(i32),
+    Name(String),
+}
+
+impl std::str::FromStr for GroupRef {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<GroupRef, String> {
+        match value.parse::<i32>() {
+            Ok(id) => Ok(GroupRef::Id(id)),
+            Err(_) if !value.trim().is_empty() => Ok(GroupRef::Name(value.to_string())),
+            Err(_) => Err("expected a group id or name".to_string()),
+        }
+    }
+}
+
+impl GroupRef {
+    fn find(&self, conn: &SqliteConnection) -> Result<models::Group, AdminError> {
+        let group = match self {
+            GroupRef::Id(id) => group_actions::find_by_id(*id, conn)?,
+            GroupRef::Name(name) => group_actions::find_by_name(name, conn)?,
+        };
+        group.ok_or_else(|| AdminError::NotFound(format!("no group {}", self)))
+    }
+}
+
+impl fmt::Display for GroupRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupRef::Id(id) => write!(f, "with id {}", id),
+            GroupRef::Name(name) => write!(f, "named {}", name),
+        }
+    }
+}
+
+enum AdminError {
+    Database(diesel::result::Error),
+    Attachment(attachment_actions::AttachmentError),
+    Migration(diesel_migrations::RunMigrationsError),
+    Pool(diesel::r2d2::PoolError),
+    Io(std::io::Error),
+    NotFound(String),
+    Invalid(String),
+}
+
+impl fmt::Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdminError::Database(e) => write!(f, "database error: {}", e),
+            AdminError::Attachment(e) => write!(f, "{}", e),
+            AdminError::Migration(e) => write!(f, "migration error: {}", e),
+            AdminError::Pool(e) => write!(f, "couldn't connect to the database: {}", e),
+            AdminError::Io(e) => write!(f, "{}", e),
+            AdminError::NotFound(message) | AdminError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for AdminError {
+    fn from(e: diesel::result::Error) -> AdminError {
+        AdminError::Database(e)
+    }
+}
+
+impl From<attachment_actions::AttachmentError> for AdminError {
+    fn from(e: attachment_actions::AttachmentError) -> AdminError {
+        AdminError::Attachment(e)
+    }
+}
+
+impl From<diesel_migrations::RunMigrationsError> for AdminError {
+    fn from(e: diesel_migrations::RunMigrationsError) -> AdminError {
+        AdminError::Migration(e)
+    }
+}
+
+impl From<std::io::Error> for AdminError {
+    fn from(e: std::io::Error) -> AdminError {
+        AdminError::Io(e)
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for AdminError {
+    fn from(e: diesel::r2d2::PoolError) -> AdminError {
+        AdminError::Pool(e)
+    }
+}
+
+/// What gets printed for a user; never the password.
+#[derive(Serialize)]
+struct UserRow {
+    id: i32,
+    email: String,
+    name: String,
+    email_verified: bool,
+    date_disabled: Option<chrono::NaiveDateTime>,
+}
+
+impl From<models::User> for UserRow {
+    fn from(user: models::User) -> UserRow {
+        UserRow {
+            id: user.id,
+            name: user.display_name(),
+            email: user.email,
+            email_verified: user.email_verified,
+            date_disabled: user.date_disabled,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GroupRow {
+    id: i32,
+    name: String,
+    members: usize,
+    date_added: chrono::NaiveDateTime,
+}
+
+impl GroupRow {
+    fn new(group: models::Group, conn: &SqliteConnection) -> Result<GroupRow, AdminError> {
+        Ok(GroupRow {
+            members: group_actions::find_members(group.id, conn)?.len(),
+            id: group.id,
+            name: group.name,
+            date_added: group.date_added,
+        })
+    }
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let opt = Opt::from_args();
+    if let Err(e) = run(opt) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(opt: Opt) -> Result<(), AdminError> {
+    let pool = server::pool_from_env();
+    let conn = pool.get()?;
+    match opt.command {
+        Command::Migrate => server::run_migrations(&conn, &mut std::io::stdout())?,
+        Command::Users(command) => users(command, opt.json, &conn)?,
+        Command::Groups(command) => groups(command, opt.json, &conn)?,
+        Command::Gifts(command) => gifts(command, opt.json, &conn)?,
+        Command::Export { user, group, format, output } => {
+            let user = user.find(&conn)?;
+            let document = match group {
+                Some(group) => {
+                    let group = group.find(&conn)?;
+                    export_actions::export_group(group.id, user.id, &conn)?.ok_or_else(|| AdminError::Invalid(format!("{} isn't in {}", user.email, group.name)))?
+                }
+                None => export_actions::export(user.id, &conn)?,
+            };
+            let data = match format {
+                ExportFormat::Json => serde_json::to_vec_pretty(&document).expect("exports serialize"),
+                ExportFormat::Csv => export_actions::to_csv(&document),
+            };
+            match output {
+                Some(path) => std::fs::write(path, data)?,
+                None => std::io::stdout().write_all(&data)?,
+            }
+        }
+        Command::Import { user, file, dry_run } => {
+            let user = user.find(&conn)?;
+            let document = serde_json::from_slice::<shared::ExportDocument>(&std::fs::read(file)?).map_err(|e| AdminError::Invalid(format!("not a gifts export: {}", e)))?;
+            if !export_actions::is_supported(&document) {
+                return Err(AdminError::Invalid(format!("export version {} is newer than this tool understands", document.version)));
+            }
+            let report = export_actions::import(&document, user.id, dry_run, &conn)?;
+            print_import_report(&report, opt.json);
+        }
+    }
+    Ok(())
+}
+
+fn users(command: UsersCommand, json: bool, conn: &SqliteConnection) -> Result<(), AdminError> {
+    match command {
+        UsersCommand::List => {
+            let users = user_actions::find_all_full(conn)?.into_iter().map(UserRow::from).collect::<Vec<_>>();
+            print_users(&users, json);
+        }
+        UsersCommand::Create {
+            email,
+            password,
+            first_name,
+            last_name,
+            unverified,
+        } => {
+            if user_actions::find_by_email(email.to_string(), conn)?.is_some() {
+                return Err(AdminError::Invalid(format!("{} is already registered", email)));
+            }
+            let password = checked_password(password)?;
+            let mut new_user = models::NewUser::new(email, password.to_string());
+            new_user.first_name = first_name;
+            new_user.last_name = last_name;
+            let mut user = user_actions::add(&new_user, None, conn)?;
+            if !unverified {
+                user_actions::mark_email_verified(user.id, None, conn)?;
+                user.email_verified = true;
+            }
+            print_users(&[user.into()], json);
+            print_generated_password(&password, json);
+        }
+        UsersCommand::Disable { user } => {
+            let user = user.find(conn)?;
+            let user = user_actions::set_disabled(user.id, true, None, conn)?.expect("user went missing");
+            print_users(&[user.into()], json);
+        }
+        UsersCommand::Enable { user } => {
+            let user = user.find(conn)?;
+            let user = user_actions::set_disabled(user.id, false, None, conn)?.expect("user went missing");
+            print_users(&[user.into()], json);
+        }
+        UsersCommand::ResetPassword { user, password } => {
+            let user = user.find(conn)?;
+            let password = checked_password(password)?;
+            user_actions::set_password(user.id, password.to_string(), None, conn)?;
+            print_users(&[user.into()], json);
+            print_generated_password(&password, json);
+        }
+    }
+    Ok(())
+}
+
+fn groups(command: GroupsCommand, json: bool, conn: &SqliteConnection) -> Result<(), AdminError> {
+    match command {
+        GroupsCommand::List => {
+            let groups = group_actions::find_all(conn)?
+                .into_iter()
+                .map(|group| GroupRow::new(group, conn))
+                .collect::<Result<Vec<_>, _>>()?;
+            print_groups(&groups, json);
+        }
+        GroupsCommand::Create { name } => {
+            let name = name.trim().to_string();
+            if name.is_empty() {
+                return Err(AdminError::Invalid("a group needs a name".to_string()));
+            }
+            if group_actions::find_by_name(&name, conn)?.is_some() {
+                return Err(AdminError::Invalid(format!("there's already a group named {}", name)));
+            }
+            let group = group_actions::add(name, None, conn)?;
+            print_groups(&[GroupRow::new(group, conn)?], json);
+        }
+        GroupsCommand::Delete { group } => {
+            let group = group.find(conn)?;
+            let row = GroupRow::new(group, conn)?;
+            group_actions::delete(row.id, None, conn)?;
+            print_groups(&[row], json);
+        }
+        GroupsCommand::Members { group } => {
+            let group = group.find(conn)?;
+            let members = group_actions::find_members(group.id, conn)?.into_iter().map(UserRow::from).collect::<Vec<_>>();
+            print_users(&members, json);
+        }
+        GroupsCommand::AddMember { group, users } => {
+            let group = group.find(conn)?;
+            let users = users.iter().map(|user| user.find(conn)).collect::<Result<Vec<_>, _>>()?;
+            for user in users.iter() {
+                if !group_actions::add_member(group.id, user.id, None, conn)? {
+                    eprintln!("{} is already in {}", user.email, group.name);
+                }
+            }
+            print_groups(&[GroupRow::new(group, conn)?], json);
+        }
+        GroupsCommand::RemoveMember { group, users } => {
+            let group = group.find(conn)?;
+            let users = users.iter().map(|user| user.find(conn)).collect::<Result<Vec<_>, _>>()?;
+            for user in users.iter() {
+                if !group_actions::remove_member(group.id, user.id, None, conn)? {
+                    eprintln!("{} isn't in {}", user.email, group.name);
+                }
+            }
+            print_groups(&[GroupRow::new(group, conn)?], json);
+        }
+    }
+    Ok(())
+}
+
+fn gifts(command: GiftsCommand, json: bool, conn: &SqliteConnection) -> Result<(), AdminError> {
+    match command {
+        GiftsCommand::Show { gift_id } => {
+            let gift = gift_idea_actions::find_including_deleted(gift_id, conn)?.ok_or_else(|| AdminError::NotFound(format!("no gift idea with id {}", gift_id)))?;
+            print_gifts(&[gift], json);
+        }
+        GiftsCommand::Reassign { gift_id, owner, recipient } => {
+            if owner.is_none() && recipient.is_none() {
+                return Err(AdminError::Invalid("give --owner, --recipient or both".to_string()));
+            }
+            let owner_id = owner.map(|user| user.find(conn)).transpose()?.map(|user| user.id);
+            let recipient_user_id = recipient.map(|user| user.find(conn)).transpose()?.map(|user| user.id);
+            let gift =
+                gift_idea_actions::reassign(gift_id, owner_id, recipient_user_id, None, conn)?.ok_or_else(|| AdminError::NotFound(format!("no gift idea with id {}", gift_id)))?;
+            print_gifts(&[gift], json);
+        }
+        GiftsCommand::ReassignUser { from, to } => {
+            let from = from.find(conn)?;
+            let to = to.find(conn)?;
+            if from.id == to.id {
+                return Err(AdminError::Invalid("can't reassign a user's gift ideas to themselves".to_string()));
+            }
+            let (reassigned, groups) = conn.transaction::<_, diesel::result::Error, _>(|| {
+                Ok((
+                    gift_idea_actions::reassign_user(from.id, to.id, None, conn)?,
+                    group_actions::reassign_user(from.id, to.id, None, conn)?,
+                ))
+            })?;
+            if json {
+                println!("{}", serde_json::json!({ "reassigned": reassigned, "groups": groups }));
+            } else {
+                println!("reassigned {} gift ideas and {} group memberships", reassigned, groups);
+            }
+        }
+        GiftsCommand::Purge { gift_ids } => {
+            let storage = storage::from_env();
+            let mut purged = 0;
+            for gift_id in gift_ids {
+                if trash::purge(gift_id, None, storage.as_ref(), conn)? {
+                    purged += 1;
+                } else {
+                    eprintln!("no gift idea with id {}", gift_id);
+                }
+            }
+            print_count("purged", purged, json);
+        }
+    }
+    Ok(())
+}
+
+fn checked_password(password: Option<String>) -> Result<Password, AdminError> {
+    match password {
+        Some(password) if password.len() < user_actions::MIN_PASSWORD_LENGTH => {
+            Err(AdminError::Invalid(format!("Password must be at least {} characters", user_actions::MIN_PASSWORD_LENGTH)))
+        }
+        Some(password) => Ok(Password::Given(password)),
+        None => Ok(Password::Generated(uuid::Uuid::new_v4().to_simple().to_string())),
+    }
+}
+
+enum Password {
+    Given(String),
+    Generated(String),
+}
+
+impl fmt::Display for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Password::Given(password) | Password::Generated(password) => write!(f, "{}", password),
+        }
+    }
+}
+
+fn print_generated_password(password: &Password, json: bool) {
+    if let Password::Generated(password) = password {
+        if json {
+            println!("{}", serde_json::json!({ "generated_password": password }));
+        } else {
+            println!("\ngenerated password: {}", password);
+        }
+    }
+}
+
+fn print_count(what: &str, count: usize, json: bool) {
+    if json {
+        println!("{}", serde_json::json!({ what: count }));
+    } else {
+        println!("{} {} gift ideas", what, count);
+    }
+}
+
+fn print_import_report(report: &shared::ImportReport, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(report).expect("reports serialize"));
+        return;
+    }
+    if report.dry_run {
+        println!("dry run, nothing was changed");
+    }
+    println!("events created: {}", report.events_created);
+    println!("gift ideas created: {}", report.gift_ideas_created);
+    println!("reservations restored: {}", report.reservations_restored);
+    let lists = [
+        ("already here (events)", &report.duplicate_events),
+        ("already here (gift ideas)", &report.duplicate_gift_ideas),
+        ("not registered here", &report.unknown_users),
+        ("skipped", &report.skipped),
+    ];
+    for (heading, lines) in lists.iter().filter(|(_, lines)| !lines.is_empty()) {
+        println!("\n{}:", heading);
+        for line in lines.iter() {
+            println!("  {}", line);
+        }
+    }
+}
+
+fn print_users(users: &[UserRow], json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(users).expect("users serialize"));
+        return;
+    }
+    let rows = users
+        .iter()
+        .map(|user| {
+            vec![
+                user.id.to_string(),
+                user.email.to_string(),
+                user.name.to_string(),
+                yes_no(user.email_verified),
+                optional(user.date_disabled),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(&["id", "email", "name", "verified", "disabled"], &rows);
+}
+
+fn print_groups(groups: &[GroupRow], json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(groups).expect("groups serialize"));
+        return;
+    }
+    let rows = groups
+        .iter()
+        .map(|group| vec![group.id.to_string(), group.name.to_string(), group.members.to_string(), group.date_added.to_string()])
+        .collect::<Vec<_>>();
+    print_table(&["id", "name", "members", "added"], &rows);
+}
+
+fn print_gifts(gifts: &[models::GiftIdea], json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(gifts).expect("gift ideas serialize"));
+        return;
+    }
+    let rows = gifts
+        .iter()
+        .map(|gift| {
+            vec![
+                gift.id.to_string(),
+                gift.title.to_string(),
+                gift.owner_id.to_string(),
+                gift.recipient_user_id.to_string(),
+                optional(gift.reserved_by_user_id),
+                optional(gift.date_purchased),
+                optional(gift.event_id),
+                optional(gift.date_archived),
+                optional(gift.date_deleted),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(&["id", "title", "owner", "recipient", "reserved by", "purchased", "event", "archived", "deleted"], &rows);
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|e| e.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+/// Left aligned columns, each as wide as its widest cell.
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths = headers.iter().map(|header| header.chars().count()).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let print_row = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+    print_row(headers.to_vec());
+    print_row(widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().iter().map(String::as_str).collect());
+    for row in rows {
+        print_row(row.iter().map(String::as_str).collect());
+    }
+}
@@ -0,0 +1,112 @@
+use actix_web::web;
+use bytes::Bytes;
+use diesel::SqliteConnection;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::gift_idea_actions;
+use crate::group_actions;
+
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+struct Client {
+    user_id: i32,
+    sender: UnboundedSender<Bytes>,
+}
+
+/// Who hears about a change to one gift idea: its owner, and everyone who shares a group with its
+/// recipient.
+pub struct Audience {
+    pub owner_id: i32,
+    pub recipient_user_id: i32,
+    pub member_ids: Vec<i32>,
+}
+
+impl Audience {
+    pub fn find(owner_id: i32, recipient_user_id: i32, conn: &SqliteConnection) -> Result<Audience, diesel::result::Error> {
+        Ok(Audience {
+            owner_id,
+            recipient_user_id,
+            member_ids: group_actions::find_co_member_ids(recipient_user_id, conn)?,
+        })
+    }
+}
+
+/// Fans gift idea changes out to the open `updates/stream` connections they concern as
+/// server-sent events.
+#[derive(Default)]
+pub struct Broadcaster {
+    clients: Vec<Client>,
+}
+
+impl Broadcaster {
+    pub fn new() -> Broadcaster {
+        Broadcaster { clients: Vec::new() }
+    }
+
+    pub fn spawn_ping(broadcaster: web::Data<Mutex<Broadcaster>>) {
+        actix_rt::spawn(async move {
+            let mut interval = actix_rt::time::interval(PING_INTERVAL);
+            loop {
+                interval.tick().await;
+                broadcaster.lock().unwrap().ping();
+            }
+        });
+    }
+
+    pub fn new_client(&mut self, user_id: i32) -> UnboundedReceiver<Bytes> {
+        let (sender, receiver) = unbounded();
+        sender.unbounded_send(Bytes::from_static(b": connected\n\n")).ok();
+        self.clients.push(Client { user_id, sender });
+        receiver
+    }
+
+    /// Sends `event` to everyone in `audience`.  The recipient only hears about ideas they added
+    /// for themselves, and then nothing about reservations.
+    pub fn send(&mut self, event: &shared::GiftIdeaEvent, audience: &Audience) {
+        let message = match to_message(event) {
+            Some(message) => message,
+            None => return,
+        };
+        let recipient_message = if audience.owner_id == audience.recipient_user_id {
+            redact_for_recipient(event).as_ref().and_then(to_message)
+        } else {
+            None
+        };
+        self.clients.retain(|client| {
+            let message = if client.user_id == audience.recipient_user_id {
+                recipient_message.as_ref()
+            } else if client.user_id == audience.owner_id || audience.member_ids.contains(&client.user_id) {
+                Some(&message)
+            } else {
+                None
+            };
+            message.is_none_or(|message| client.sender.unbounded_send(message.clone()).is_ok())
+        });
+    }
+
+    fn ping(&mut self) {
+        self.clients.retain(|client| client.sender.unbounded_send(Bytes::from_static(b": ping\n\n")).is_ok());
+    }
+}
+
+fn to_message(event: &shared::GiftIdeaEvent) -> Option<Bytes> {
+    match serde_json::to_string(event) {
+        Ok(json) => Some(Bytes::from(format!("data: {}\n\n", json))),
+        Err(e) => {
+            error!("could not serialize gift idea event: {}", e);
+            None
+        }
+    }
+}
+
+// reservations never reach the recipient, not even that one was made or released
+fn redact_for_recipient(event: &shared::GiftIdeaEvent) -> Option<shared::GiftIdeaEvent> {
+    match event {
+        shared::GiftIdeaEvent::Created(gift) => Some(shared::GiftIdeaEvent::Created(gift_idea_actions::redact_for_recipient(gift.clone()))),
+        shared::GiftIdeaEvent::Updated(gift) => Some(shared::GiftIdeaEvent::Updated(gift_idea_actions::redact_for_recipient(gift.clone()))),
+        shared::GiftIdeaEvent::Reserved(_) | shared::GiftIdeaEvent::Unreserved(_) | shared::GiftIdeaEvent::Purchased(_) => None,
+        shared::GiftIdeaEvent::Deleted(gift_id) => Some(shared::GiftIdeaEvent::Deleted(*gift_id)),
+    }
+}
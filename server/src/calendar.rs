@@ -0,0 +1,114 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use url::Url;
+
+use crate::models;
+use crate::notifications;
+
+pub const CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+
+/// Where a user's feed can be fetched from, for calendar apps to subscribe to.
+pub fn feed_url(token: &str) -> String {
+    format!("{}/v1/calendar/ics/{}.ics", notifications::app_url(), token)
+}
+
+/// An iCalendar document with every event as an all day VEVENT and, for each of `reservations`,
+/// a VTODO to buy it.  A reserved idea filed under one of `events` is due on the day and carries
+/// an alarm a few days ahead, the same notice the reminder emails give.
+pub fn feed(events: &[models::Event], reservations: &[models::GiftIdea], users: &[models::User], now: NaiveDateTime) -> String {
+    let host = Url::parse(&notifications::app_url())
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "localhost".to_string());
+    let stamp = timestamp(now);
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rusty-gifts//calendar feed//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:Gifts".to_string(),
+    ];
+    for event in events.iter() {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:event-{}@{}", event.id, host));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", date(event.event_date)));
+        lines.push(format!("DTEND;VALUE=DATE:{}", date(event.event_date + Duration::days(1))));
+        lines.push(format!("SUMMARY:{}", escape(&event.name)));
+        lines.push(format!("URL:{}/events/{}", notifications::app_url(), event.id));
+        lines.push("END:VEVENT".to_string());
+    }
+    for gift in reservations.iter() {
+        let recipient = users
+            .iter()
+            .find(|user| user.id == gift.recipient_user_id)
+            .map(models::User::display_name)
+            .unwrap_or_default();
+        let summary = escape(&format!("Buy {} for {}", gift.title, recipient));
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:gift-idea-{}@{}", gift.id, host));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("SUMMARY:{}", summary));
+        let mut details = Vec::new();
+        if let Some(price) = &gift.price {
+            details.push(format!("Price: {}", price));
+        }
+        if let Some(url) = &gift.url {
+            details.push(url.to_string());
+        }
+        if !details.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&details.join("\n"))));
+        }
+        lines.push(format!("URL:{}/gift_ideas/{}", notifications::app_url(), gift.id));
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+        if let Some(event) = gift.event_id.and_then(|eid| events.iter().find(|event| event.id == eid)) {
+            lines.push(format!("DUE;VALUE=DATE:{}", date(event.event_date)));
+            lines.push("BEGIN:VALARM".to_string());
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", summary));
+            // RELATED=END makes the trigger count back from DUE
+            lines.push(format!("TRIGGER;RELATED=END:-P{}D", notifications::EVENT_REMINDER_DAYS));
+            lines.push("END:VALARM".to_string());
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("")
+}
+
+fn date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn timestamp(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// text values can't carry raw newlines, and commas and semicolons separate values
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+// lines are limited to 75 bytes; longer ones carry on in lines starting with a space, never
+// splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
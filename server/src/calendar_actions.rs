@@ -0,0 +1,46 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::models;
+use crate::schema::{calendar_feeds, users};
+use crate::token_actions;
+
+pub fn find(uid: i32, conn: &SqliteConnection) -> Result<Option<models::CalendarFeed>, diesel::result::Error> {
+    calendar_feeds::table.find(uid).first::<models::CalendarFeed>(conn).optional()
+}
+
+/// Gives `uid` a feed with a new secret, replacing any earlier one, and returns the secret.
+/// Like other tokens only its hash is kept, so this is the one chance to show it.
+pub fn issue(uid: i32, include_reservations: bool, conn: &SqliteConnection) -> Result<(models::CalendarFeed, String), diesel::result::Error> {
+    let token = token_actions::generate();
+    let feed = models::CalendarFeed {
+        user_id: uid,
+        token_hash: token_actions::hash(&token),
+        include_reservations,
+        date_added: Utc::now().naive_utc(),
+    };
+    diesel::replace_into(calendar_feeds::table).values(&feed).execute(conn)?;
+    Ok((feed, token))
+}
+
+pub fn set_include_reservations(uid: i32, include_reservations: bool, conn: &SqliteConnection) -> Result<Option<models::CalendarFeed>, diesel::result::Error> {
+    diesel::update(calendar_feeds::table.find(uid))
+        .set(calendar_feeds::dsl::include_reservations.eq(include_reservations))
+        .execute(conn)?;
+    find(uid, conn)
+}
+
+pub fn delete(uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let deleted = diesel::delete(calendar_feeds::table.find(uid)).execute(conn)?;
+    Ok(deleted > 0)
+}
+
+/// The feed and its user for a secret, unless the user has since been disabled.
+pub fn find_by_token(token: &str, conn: &SqliteConnection) -> Result<Option<(models::CalendarFeed, models::User)>, diesel::result::Error> {
+    calendar_feeds::table
+        .inner_join(users::table)
+        .filter(calendar_feeds::dsl::token_hash.eq(token_actions::hash(token)))
+        .filter(users::dsl::date_disabled.is_null())
+        .first::<(models::CalendarFeed, models::User)>(conn)
+        .optional()
+}
@@ -0,0 +1,117 @@
+use diesel::prelude::*;
+
+use crate::group_actions;
+use crate::models;
+use crate::schema::{gift_idea_comments, gift_ideas, users};
+
+pub const MAX_COMMENT_LENGTH: usize = 2000;
+
+// comments are for the people buying the gift: its owner and whoever shares a group with its
+// recipient.  The recipient never gets to see or write them.
+fn is_visible_to(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn).optional()?;
+    match gift {
+        Some(gift) if gift.recipient_user_id != uid => Ok(gift.owner_id == uid || group_actions::share_a_group(uid, gift.recipient_user_id, conn)?),
+        _ => Ok(false),
+    }
+}
+
+fn find_by_id(cid: i32, conn: &SqliteConnection) -> Result<Option<shared::CommentResponseBody>, diesel::result::Error> {
+    let comment = gift_idea_comments::table
+        .inner_join(users::table)
+        .filter(gift_idea_comments::dsl::id.eq(cid))
+        .first::<(models::GiftIdeaComment, models::User)>(conn)
+        .optional()?;
+    Ok(comment.map(|(comment, author)| comment.into_response(&author)))
+}
+
+pub fn find_by_gift_idea(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<Vec<shared::CommentResponseBody>>, diesel::result::Error> {
+    if !is_visible_to(gid, uid, conn)? {
+        return Ok(None);
+    }
+    let comments = gift_idea_comments::table
+        .inner_join(users::table)
+        .filter(gift_idea_comments::dsl::gift_idea_id.eq(gid))
+        .order(gift_idea_comments::dsl::date_added.asc())
+        .load::<(models::GiftIdeaComment, models::User)>(conn)?;
+    Ok(Some(comments.into_iter().map(|(comment, author)| comment.into_response(&author)).collect()))
+}
+
+pub fn add(gid: i32, uid: i32, body: String, conn: &SqliteConnection) -> Result<Option<shared::CommentResponseBody>, diesel::result::Error> {
+    if !is_visible_to(gid, uid, conn)? {
+        return Ok(None);
+    }
+    let new_comment = models::NewGiftIdeaComment::new(gid, uid, body);
+    diesel::insert_into(gift_idea_comments::table).values(&new_comment).execute(conn)?;
+    let cid = gift_idea_comments::table
+        .select(gift_idea_comments::dsl::id)
+        .filter(gift_idea_comments::dsl::gift_idea_id.eq(gid))
+        .filter(gift_idea_comments::dsl::author_user_id.eq(uid))
+        .order(gift_idea_comments::dsl::id.desc())
+        .first::<i32>(conn)?;
+    find_by_id(cid, conn)
+}
+
+pub fn update(cid: i32, uid: i32, body: String, conn: &SqliteConnection) -> Result<Option<shared::CommentResponseBody>, diesel::result::Error> {
+    let num_updated = diesel::update(
+        gift_idea_comments::table
+            .filter(gift_idea_comments::dsl::id.eq(cid))
+            .filter(gift_idea_comments::dsl::author_user_id.eq(uid)),
+    )
+    .set((
+        gift_idea_comments::dsl::body.eq(body),
+        gift_idea_comments::dsl::date_last_modified.eq(chrono::Utc::now().naive_utc()),
+    ))
+    .execute(conn)?;
+    if num_updated == 1 {
+        find_by_id(cid, conn)
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn delete(cid: i32, uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let num_deleted = diesel::delete(
+        gift_idea_comments::table
+            .filter(gift_idea_comments::dsl::id.eq(cid))
+            .filter(gift_idea_comments::dsl::author_user_id.eq(uid)),
+    )
+    .execute(conn)?;
+    Ok(num_deleted == 1)
+}
+
+pub fn delete_by_gift_idea(gid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::delete(gift_idea_comments::table.filter(gift_idea_comments::dsl::gift_idea_id.eq(gid))).execute(conn)
+}
+
+pub fn delete_by_author(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::delete(gift_idea_comments::table.filter(gift_idea_comments::dsl::author_user_id.eq(uid))).execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gift_idea_actions;
+    use crate::user_actions;
+
+    #[test]
+    fn only_the_owner_and_the_recipients_groups_can_comment() {
+        let conn = crate::test_connection();
+        let user = |email: &str| user_actions::add(&models::NewUser::new(email.to_string(), "password".to_string()), None, &conn).unwrap();
+        let (jane, bob, carol, stranger) = (user("jane@example.com"), user("bob@example.com"), user("carol@example.com"), user("stranger@example.com"));
+        let family = group_actions::add("Family".to_string(), None, &conn).unwrap();
+        for member in [&bob, &carol].iter() {
+            group_actions::add_member(family.id, member.id, None, &conn).unwrap();
+        }
+        // jane isn't in bob's group, but it's her idea
+        let gift = gift_idea_actions::add(&models::NewGiftIdea::new("Bread maker".to_string(), jane.id, bob.id), Some(jane.id), &conn).unwrap();
+
+        assert!(add(gift.id, jane.id, "Found it cheaper".to_string(), &conn).unwrap().is_some());
+        assert!(add(gift.id, carol.id, "I'll go halves".to_string(), &conn).unwrap().is_some());
+        assert!(add(gift.id, bob.id, "Is it for me?".to_string(), &conn).unwrap().is_none());
+        assert!(add(gift.id, stranger.id, "Hello".to_string(), &conn).unwrap().is_none());
+        assert_eq!(find_by_gift_idea(gift.id, carol.id, &conn).unwrap().map(|comments| comments.len()), Some(2));
+        assert!(find_by_gift_idea(gift.id, bob.id, &conn).unwrap().is_none());
+        assert!(find_by_gift_idea(gift.id, stranger.id, &conn).unwrap().is_none());
+    }
+}
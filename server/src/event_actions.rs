@@ -0,0 +1,216 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+use crate::gift_idea_actions;
+use crate::group_actions;
+use crate::models;
+use crate::schema::events;
+
+pub fn find_all(conn: &SqliteConnection) -> Result<Vec<models::Event>, diesel::result::Error> {
+    let results = events::table.order(events::dsl::event_date.asc()).load::<models::Event>(conn)?;
+    Ok(results)
+}
+
+pub fn find_by_id(eid: i32, conn: &SqliteConnection) -> Result<Option<models::Event>, diesel::result::Error> {
+    let event = events::table.filter(events::dsl::id.eq(eid)).first::<models::Event>(conn).optional()?;
+    Ok(event)
+}
+
+// the groups whose events `uid` sees, and the people whose events without a group they see
+fn shared_with(uid: i32, conn: &SqliteConnection) -> Result<(Vec<i32>, Vec<i32>), diesel::result::Error> {
+    let group_ids = group_actions::find_by_member(uid, conn)?.into_iter().map(|group| group.id).collect::<Vec<_>>();
+    let mut owner_ids = group_actions::find_co_member_ids(uid, conn)?;
+    owner_ids.push(uid);
+    Ok((group_ids, owner_ids))
+}
+
+/// Every event `uid` gets to see: their own, those of their groups, and those without a group made
+/// by anyone they share a group with.  Past and archived ones are included.
+pub fn find_for(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::Event>, diesel::result::Error> {
+    let (group_ids, owner_ids) = shared_with(uid, conn)?;
+    let results = events::table
+        .filter(
+            events::dsl::owner_id
+                .eq(uid)
+                .or(events::dsl::group_id.eq_any(group_ids))
+                .or(events::dsl::group_id.is_null().and(events::dsl::owner_id.eq_any(owner_ids))),
+        )
+        .order(events::dsl::event_date.asc())
+        .load::<models::Event>(conn)?;
+    Ok(results)
+}
+
+/// Events still to come that `uid` gets to see: those of their groups, and those without a group
+/// made by them or anyone they share a group with.  Archived ones are left out.
+pub fn find_upcoming_for(uid: i32, today: NaiveDate, conn: &SqliteConnection) -> Result<Vec<models::Event>, diesel::result::Error> {
+    let (group_ids, owner_ids) = shared_with(uid, conn)?;
+    let results = events::table
+        .filter(
+            events::dsl::group_id
+                .eq_any(group_ids)
+                .or(events::dsl::group_id.is_null().and(events::dsl::owner_id.eq_any(owner_ids))),
+        )
+        .filter(events::dsl::event_date.ge(today))
+        .filter(events::dsl::date_archived.is_null())
+        .order(events::dsl::event_date.asc())
+        .load::<models::Event>(conn)?;
+    Ok(results)
+}
+
+/// Who an event is shared with: its group's members, or without a group, its owner and everyone
+/// they share a group with.
+pub fn find_audience_ids(event: &models::Event, conn: &SqliteConnection) -> Result<Vec<i32>, diesel::result::Error> {
+    match event.group_id {
+        Some(gid) => Ok(group_actions::find_members(gid, conn)?.into_iter().map(|user| user.id).collect()),
+        None => {
+            let mut ids = group_actions::find_co_member_ids(event.owner_id, conn)?;
+            if !ids.contains(&event.owner_id) {
+                ids.push(event.owner_id);
+            }
+            Ok(ids)
+        }
+    }
+}
+
+pub fn add(new_event: &models::NewEvent, conn: &SqliteConnection) -> Result<models::Event, diesel::result::Error> {
+    diesel::insert_into(events::table).values(new_event).execute(conn)?;
+    let event = events::table
+        .filter(events::dsl::owner_id.eq(new_event.owner_id))
+        .order(events::dsl::id.desc())
+        .first::<models::Event>(conn)?;
+    Ok(event)
+}
+
+pub fn delete(eid: i32, uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let num_deleted = diesel::delete(events::table.filter(events::dsl::id.eq(eid)).filter(events::dsl::owner_id.eq(uid))).execute(conn)?;
+        if num_deleted == 1 {
+            gift_idea_actions::detach_from_event(eid, Some(uid), conn)?;
+        }
+        Ok(num_deleted == 1)
+    })
+}
+
+/// Archives the event if `uid` owns it, along with every idea filed under it.
+pub fn archive(eid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<models::Event>, diesel::result::Error> {
+    conn.transaction(|| {
+        let num_archived = diesel::update(
+            events::table
+                .filter(events::dsl::id.eq(eid))
+                .filter(events::dsl::owner_id.eq(uid))
+                .filter(events::dsl::date_archived.is_null()),
+        )
+        .set(events::dsl::date_archived.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)?;
+        if num_archived == 0 {
+            return Ok(None);
+        }
+        gift_idea_actions::archive_by_event(eid, Some(uid), conn)?;
+        find_by_id(eid, conn)
+    })
+}
+
+/// Creates `new_event` and copies the un-given ideas of the archived event `eid` into it, if
+/// `uid` owns that event. Returns the new event and how many ideas came with it.
+pub fn carry_over(eid: i32, uid: i32, new_event: &models::NewEvent, conn: &SqliteConnection) -> Result<Option<(models::Event, usize)>, diesel::result::Error> {
+    conn.transaction(|| {
+        let archived = find_by_id(eid, conn)?.filter(|event| event.owner_id == uid && event.date_archived.is_some());
+        if archived.is_none() {
+            return Ok(None);
+        }
+        let event = add(new_event, conn)?;
+        let carried_over = gift_idea_actions::carry_over(eid, event.id, Some(uid), conn)?;
+        Ok(Some((event, carried_over)))
+    })
+}
+
+// anything on or before `cutoff` that hasn't been reminded yet, so a server that was down on the
+// exact day still catches up
+pub fn find_needing_reminder(today: NaiveDate, cutoff: NaiveDate, conn: &SqliteConnection) -> Result<Vec<models::Event>, diesel::result::Error> {
+    let results = events::table
+        .filter(events::dsl::event_date.ge(today))
+        .filter(events::dsl::event_date.le(cutoff))
+        .filter(events::dsl::date_reminder_sent.is_null())
+        .load::<models::Event>(conn)?;
+    Ok(results)
+}
+
+pub fn mark_reminder_sent(eid: i32, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    diesel::update(events::table.find(eid))
+        .set(events::dsl::date_reminder_sent.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn delete_by_owner(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let owned = events::table.filter(events::dsl::owner_id.eq(uid)).select(events::dsl::id).load::<i32>(conn)?;
+    for eid in owned {
+        gift_idea_actions::detach_from_event(eid, Some(uid), conn)?;
+    }
+    diesel::delete(events::table.filter(events::dsl::owner_id.eq(uid))).execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_actions;
+
+    fn user(email: &str, conn: &SqliteConnection) -> models::User {
+        user_actions::add(&models::NewUser::new(email.to_string(), "password".to_string()), None, conn).unwrap()
+    }
+
+    #[test]
+    fn upcoming_events_are_those_shared_with_the_users_groups() {
+        let conn = crate::test_connection();
+        let jane = user("jane@example.com", &conn);
+        let bob = user("bob@example.com", &conn);
+        let stranger = user("stranger@example.com", &conn);
+        let group = group_actions::add("Family".to_string(), None, &conn).unwrap();
+        group_actions::add_member(group.id, jane.id, None, &conn).unwrap();
+        group_actions::add_member(group.id, bob.id, None, &conn).unwrap();
+        let today = NaiveDate::from_ymd(2020, 6, 1);
+        let event = |name: &str, date: NaiveDate, owner_id: i32| add(&models::NewEvent::new(name.to_string(), date, owner_id), &conn).unwrap();
+        let birthday = event("Bob's birthday", NaiveDate::from_ymd(2020, 7, 1), bob.id);
+        let party = event("Jane's party", today, jane.id);
+        event("Last year", NaiveDate::from_ymd(2019, 12, 25), jane.id);
+        event("Someone else's", NaiveDate::from_ymd(2020, 7, 2), stranger.id);
+        let archived = event("Called off", NaiveDate::from_ymd(2020, 8, 1), bob.id);
+        archive(archived.id, bob.id, &conn).unwrap();
+
+        let mut others = models::NewEvent::new("Book club".to_string(), NaiveDate::from_ymd(2020, 7, 3), bob.id);
+        others.group_id = Some(group_actions::add("Book club".to_string(), None, &conn).unwrap().id);
+        add(&others, &conn).unwrap();
+        let mut family = models::NewEvent::new("Reunion".to_string(), NaiveDate::from_ymd(2020, 7, 4), stranger.id);
+        family.group_id = Some(group.id);
+        let reunion = add(&family, &conn).unwrap();
+
+        let ids = |uid: i32| find_upcoming_for(uid, today, &conn).unwrap().into_iter().map(|event| event.id).collect::<Vec<_>>();
+        assert_eq!(ids(jane.id), vec![party.id, birthday.id, reunion.id]);
+        assert_eq!(ids(bob.id), vec![party.id, birthday.id, reunion.id]);
+        assert_eq!(ids(stranger.id).len(), 1);
+    }
+
+    #[test]
+    fn users_only_find_the_events_shared_with_them() {
+        let conn = crate::test_connection();
+        let jane = user("jane@example.com", &conn);
+        let bob = user("bob@example.com", &conn);
+        let stranger = user("stranger@example.com", &conn);
+        let group = group_actions::add("Family".to_string(), None, &conn).unwrap();
+        group_actions::add_member(group.id, jane.id, None, &conn).unwrap();
+        group_actions::add_member(group.id, bob.id, None, &conn).unwrap();
+        let event = |name: &str, date: NaiveDate, owner_id: i32| add(&models::NewEvent::new(name.to_string(), date, owner_id), &conn).unwrap();
+        let birthday = event("Bob's birthday", NaiveDate::from_ymd(2020, 7, 1), bob.id);
+        let last_year = event("Last year", NaiveDate::from_ymd(2019, 12, 25), jane.id);
+        archive(last_year.id, jane.id, &conn).unwrap();
+        let strangers = event("Someone else's", NaiveDate::from_ymd(2020, 7, 2), stranger.id);
+        let mut book_club = models::NewEvent::new("Book club".to_string(), NaiveDate::from_ymd(2020, 7, 3), jane.id);
+        book_club.group_id = Some(group_actions::add("Book club".to_string(), None, &conn).unwrap().id);
+        let book_club = add(&book_club, &conn).unwrap();
+
+        let ids = |uid: i32| find_for(uid, &conn).unwrap().into_iter().map(|event| event.id).collect::<Vec<_>>();
+        assert_eq!(ids(jane.id), vec![last_year.id, birthday.id, book_club.id]);
+        assert_eq!(ids(bob.id), vec![last_year.id, birthday.id]);
+        assert_eq!(ids(stranger.id), vec![strangers.id]);
+    }
+}
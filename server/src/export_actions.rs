@@ -0,0 +1,465 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::event_actions;
+use crate::gift_idea_actions;
+use crate::group_actions;
+use crate::models;
+use crate::user_actions;
+
+/// Imports are read into memory whole, so they're capped.
+pub const MAX_IMPORT_SIZE: usize = 5 * 1024 * 1024;
+
+/// Gathers up everything `uid` has put in; see `shared::ExportDocument`.  Trashed ideas are left
+/// out.
+pub fn export(uid: i32, conn: &SqliteConnection) -> Result<shared::ExportDocument, diesel::result::Error> {
+    let mut gifts = gift_idea_actions::find_by_owner_or_recipient(uid, conn)?
+        .into_iter()
+        .filter(|gift| gift.owner_id == uid)
+        .collect::<Vec<_>>();
+    gifts.extend(gift_idea_actions::find_reserved_by(uid, conn)?.into_iter().filter(|gift| gift.owner_id != uid));
+    document(uid, None, gifts, &[uid], conn)
+}
+
+/// Every member's ideas for the members of a group, and their events, as `uid` sees them: ideas
+/// someone else added for `uid` are left out, like in the app.  `None` unless `uid` is in the
+/// group.
+pub fn export_group(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<shared::ExportDocument>, diesel::result::Error> {
+    let group = match group_actions::find_by_id(gid, conn)? {
+        Some(group) => group,
+        None => return Ok(None),
+    };
+    let member_ids = group_actions::find_members(gid, conn)?.into_iter().map(|user| user.id).collect::<Vec<_>>();
+    if !member_ids.contains(&uid) {
+        return Ok(None);
+    }
+    let mut gifts = Vec::new();
+    for member_id in member_ids.iter() {
+        gifts.extend(
+            gift_idea_actions::find_by_owner_or_recipient(*member_id, conn)?
+                .into_iter()
+                .filter(|gift| gift.owner_id == *member_id && member_ids.contains(&gift.recipient_user_id))
+                .filter(|gift| gift.recipient_user_id != uid || gift.owner_id == uid),
+        );
+    }
+    document(uid, Some(group.into()), gifts, &member_ids, conn).map(Some)
+}
+
+// events are those `event_owner_ids` made and any the ideas are filed under
+fn document(
+    uid: i32,
+    group: Option<shared::GroupBody>,
+    mut gifts: Vec<models::GiftIdea>,
+    event_owner_ids: &[i32],
+    conn: &SqliteConnection,
+) -> Result<shared::ExportDocument, diesel::result::Error> {
+    gifts.retain(|gift| gift.date_deleted.is_none());
+    gifts.sort_by_key(|gift| gift.id);
+    gifts.dedup_by_key(|gift| gift.id);
+    let gift_ideas = gifts.into_iter().map(|gift| exported_gift_idea(gift, uid)).collect::<Vec<_>>();
+
+    let events = event_actions::find_all(conn)?
+        .into_iter()
+        .filter(|event| event_owner_ids.contains(&event.owner_id) || gift_ideas.iter().any(|gift| gift.event_id == Some(event.id)))
+        .map(|event| shared::ExportEvent {
+            id: event.id,
+            name: event.name,
+            event_date: event.event_date,
+            owner_id: event.owner_id,
+            date_archived: event.date_archived,
+        })
+        .collect::<Vec<_>>();
+
+    let mut user_ids = vec![uid];
+    for gift in gift_ideas.iter() {
+        user_ids.extend(vec![gift.owner_id, gift.recipient_user_id]);
+        user_ids.extend(gift.reserved_by_user_id);
+    }
+    user_ids.extend(events.iter().map(|event| event.owner_id));
+    user_ids.sort();
+    user_ids.dedup();
+    let mut users = Vec::new();
+    for user_id in user_ids {
+        if let Some(user) = user_actions::find_by_id(user_id, conn)? {
+            users.push(shared::ExportUser {
+                id: user.id,
+                email: user.email,
+                first_name: user.first_name,
+                last_name: user.last_name,
+            });
+        }
+    }
+
+    Ok(shared::ExportDocument {
+        version: shared::EXPORT_VERSION,
+        exported_at: Utc::now().naive_utc(),
+        user_id: uid,
+        group,
+        users,
+        events,
+        gift_ideas,
+    })
+}
+
+fn exported_gift_idea(gift: models::GiftIdea, uid: i32) -> shared::ExportGiftIdea {
+    // the same surprise the app keeps
+    let for_exporter = gift.recipient_user_id == uid;
+    shared::ExportGiftIdea {
+        id: gift.id,
+        title: gift.title,
+        description: gift.description,
+        price: gift.price,
+        url: gift.url,
+        owner_id: gift.owner_id,
+        recipient_user_id: gift.recipient_user_id,
+        reserved_by_user_id: gift.reserved_by_user_id.filter(|_| !for_exporter),
+        date_added: gift.date_added,
+        date_reserved: gift.date_reserved.filter(|_| !for_exporter),
+        date_purchased: gift.date_purchased.filter(|_| !for_exporter),
+        event_id: gift.event_id,
+        date_archived: gift.date_archived,
+        carried_over_from_id: gift.carried_over_from_id,
+    }
+}
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    id: i32,
+    title: &'a str,
+    description: Option<&'a str>,
+    price: Option<&'a str>,
+    url: Option<&'a str>,
+    owner_email: Option<&'a str>,
+    recipient_email: Option<&'a str>,
+    reserved_by_email: Option<&'a str>,
+    event_name: Option<&'a str>,
+    event_date: Option<NaiveDate>,
+    date_added: NaiveDateTime,
+    date_reserved: Option<NaiveDateTime>,
+    date_purchased: Option<NaiveDateTime>,
+    date_archived: Option<NaiveDateTime>,
+}
+
+/// One row per gift idea, with people and events spelled out, for opening in a spreadsheet.
+/// Only the JSON export can be imported again.
+pub fn to_csv(document: &shared::ExportDocument) -> Vec<u8> {
+    let email = |uid: i32| document.users.iter().find(|user| user.id == uid).map(|user| user.email.as_str());
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for gift in document.gift_ideas.iter() {
+        let event = gift.event_id.and_then(|eid| document.events.iter().find(|event| event.id == eid));
+        writer
+            .serialize(CsvRow {
+                id: gift.id,
+                title: &gift.title,
+                description: gift.description.as_deref(),
+                price: gift.price.as_deref(),
+                url: gift.url.as_deref(),
+                owner_email: email(gift.owner_id),
+                recipient_email: email(gift.recipient_user_id),
+                reserved_by_email: gift.reserved_by_user_id.and_then(email),
+                event_name: event.map(|event| event.name.as_str()),
+                event_date: event.map(|event| event.event_date),
+                date_added: gift.date_added,
+                date_reserved: gift.date_reserved,
+                date_purchased: gift.date_purchased,
+                date_archived: gift.date_archived,
+            })
+            .expect("csv rows serialize");
+    }
+    writer.into_inner().expect("writing to memory doesn't fail")
+}
+
+pub fn is_supported(document: &shared::ExportDocument) -> bool {
+    document.version <= shared::EXPORT_VERSION
+}
+
+/// Brings an export into this instance as `uid`: the exporting user's events and ideas become
+/// theirs, and the ideas they'd reserved are reserved again where they can be found.  Everyone
+/// else is matched up by email address.  Only the exporting user's own reservations come along,
+/// whatever else the document claims; from a group export that means the exporting member's
+/// part of it.  Anything `uid` already has is matched rather than
+/// copied, so importing the same document twice is harmless.  A dry run does all the same work
+/// and then rolls it back.
+pub fn import(document: &shared::ExportDocument, uid: i32, dry_run: bool, conn: &SqliteConnection) -> Result<shared::ImportReport, diesel::result::Error> {
+    let mut report = shared::ImportReport { dry_run, ..Default::default() };
+    let result = conn.transaction(|| {
+        apply(document, uid, &mut report, conn)?;
+        if dry_run {
+            Err(diesel::result::Error::RollbackTransaction)
+        } else {
+            Ok(())
+        }
+    });
+    match result {
+        Ok(()) => Ok(report),
+        Err(diesel::result::Error::RollbackTransaction) if dry_run => Ok(report),
+        Err(e) => Err(e),
+    }
+}
+
+fn apply(document: &shared::ExportDocument, uid: i32, report: &mut shared::ImportReport, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    let email = |id: i32| {
+        document
+            .users
+            .iter()
+            .find(|user| user.id == id)
+            .map_or_else(|| format!("user {}", id), |user| user.email.to_string())
+    };
+
+    // exported ids to this instance's
+    let mut user_ids = HashMap::new();
+    user_ids.insert(document.user_id, uid);
+    for user in document.users.iter().filter(|user| user.id != document.user_id) {
+        match user_actions::find_by_email(user.email.to_string(), conn)? {
+            Some(existing) => {
+                user_ids.insert(user.id, existing.id);
+            }
+            None => report.unknown_users.push(user.email.to_string()),
+        }
+    }
+
+    let existing_events = event_actions::find_all(conn)?;
+    let mut event_ids = HashMap::new();
+    for event in document.events.iter() {
+        let owner_id = match user_ids.get(&event.owner_id) {
+            Some(owner_id) => *owner_id,
+            None => {
+                report.skipped.push(format!("event {}: {} isn't registered here", event.name, email(event.owner_id)));
+                continue;
+            }
+        };
+        if let Some(existing) = existing_events
+            .iter()
+            .find(|e| e.owner_id == owner_id && e.name == event.name && e.event_date == event.event_date)
+        {
+            event_ids.insert(event.id, existing.id);
+            if owner_id == uid {
+                report.duplicate_events.push(event.name.to_string());
+            }
+            continue;
+        }
+        if owner_id != uid {
+            report
+                .skipped
+                .push(format!("event {}: it's {}'s and they don't have it here", event.name, email(event.owner_id)));
+            continue;
+        }
+        let mut new_event = models::NewEvent::new(event.name.to_string(), event.event_date, uid);
+        new_event.date_archived = event.date_archived;
+        let created = event_actions::add(&new_event, conn)?;
+        event_ids.insert(event.id, created.id);
+        report.events_created += 1;
+    }
+
+    // originals before their carried over copies, so the copies can point back at them
+    let mut own = document.gift_ideas.iter().filter(|gift| gift.owner_id == document.user_id).collect::<Vec<_>>();
+    own.sort_by_key(|gift| gift.id);
+    let existing_gifts = gift_idea_actions::find_by_owner_or_recipient(uid, conn)?;
+    let mut gift_ids = HashMap::new();
+    for gift in own {
+        let recipient_user_id = match user_ids.get(&gift.recipient_user_id) {
+            Some(recipient_user_id) => *recipient_user_id,
+            None => {
+                report
+                    .skipped
+                    .push(format!("gift idea {}: {} isn't registered here", gift.title, email(gift.recipient_user_id)));
+                continue;
+            }
+        };
+        if let Some(existing) = existing_gifts
+            .iter()
+            .find(|e| e.owner_id == uid && e.recipient_user_id == recipient_user_id && same_idea(e, gift))
+        {
+            gift_ids.insert(gift.id, existing.id);
+            report.duplicate_gift_ideas.push(gift.title.to_string());
+            continue;
+        }
+        let mut new_gift = models::NewGiftIdea::new(gift.title.to_string(), uid, recipient_user_id);
+        new_gift.description = gift.description.clone();
+        new_gift.price = gift.price.clone();
+        new_gift.url = gift.url.clone();
+        new_gift.date_added = gift.date_added;
+        // nobody else's reservation can be vouched for, and nobody can hold one on their own gift
+        if gift.reserved_by_user_id == Some(document.user_id) && recipient_user_id != uid {
+            new_gift.reserved_by_user_id = Some(uid);
+            new_gift.date_reserved = gift.date_reserved;
+            new_gift.date_purchased = gift.date_purchased;
+        }
+        new_gift.event_id = gift.event_id.and_then(|id| event_ids.get(&id).copied());
+        new_gift.date_archived = gift.date_archived;
+        new_gift.carried_over_from_id = gift.carried_over_from_id.and_then(|id| gift_ids.get(&id).copied());
+        let created = gift_idea_actions::add(&new_gift, Some(uid), conn)?;
+        gift_ids.insert(gift.id, created.id);
+        report.gift_ideas_created += 1;
+    }
+
+    // other people's ideas the exporting user had reserved
+    let reserved = document
+        .gift_ideas
+        .iter()
+        .filter(|gift| gift.owner_id != document.user_id && gift.reserved_by_user_id == Some(document.user_id));
+    for gift in reserved {
+        let (owner_id, recipient_user_id) = match (user_ids.get(&gift.owner_id), user_ids.get(&gift.recipient_user_id)) {
+            (_, Some(recipient_user_id)) if *recipient_user_id == uid => {
+                report.skipped.push(format!("reservation of {}: it's for you here", gift.title));
+                continue;
+            }
+            (Some(owner_id), Some(recipient_user_id)) => (*owner_id, *recipient_user_id),
+            _ => {
+                report
+                    .skipped
+                    .push(format!("reservation of {}: {}'s list isn't here", gift.title, email(gift.recipient_user_id)));
+                continue;
+            }
+        };
+        let found = gift_idea_actions::find_by_owner_or_recipient(owner_id, conn)?
+            .into_iter()
+            .find(|e| e.owner_id == owner_id && e.recipient_user_id == recipient_user_id && same_idea(e, gift));
+        match found {
+            None => report.skipped.push(format!("reservation of {}: there's no such gift idea here", gift.title)),
+            Some(existing) if existing.reserved_by_user_id == Some(uid) => (),
+            Some(existing) if existing.reserved_by_user_id.is_some() => report.skipped.push(format!("reservation of {}: someone else has reserved it here", gift.title)),
+            Some(existing) => {
+                gift_idea_actions::reserve(existing.id, uid, conn)?;
+                report.reservations_restored += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same title, ignoring case and spacing, and the same link; trashed ideas don't count.
+fn same_idea(existing: &models::GiftIdea, gift: &shared::ExportGiftIdea) -> bool {
+    existing.date_deleted.is_none() && existing.title.trim().eq_ignore_ascii_case(gift.title.trim()) && existing.url == gift.url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(email: &str, conn: &SqliteConnection) -> models::User {
+        user_actions::add(&models::NewUser::new(email.to_string(), "password".to_string()), None, conn).unwrap()
+    }
+
+    fn gift_idea(new_gift: models::NewGiftIdea, conn: &SqliteConnection) -> models::GiftIdea {
+        gift_idea_actions::add(&new_gift, Some(new_gift.owner_id), conn).unwrap()
+    }
+
+    // jane's list for bob: an idea filed under her event, and the copy it was carried over to
+    fn source() -> (SqliteConnection, shared::ExportDocument) {
+        let conn = crate::test_connection();
+        let jane = user("jane@example.com", &conn);
+        let bob = user("bob@example.com", &conn);
+        let event = event_actions::add(&models::NewEvent::new("Bob's birthday".to_string(), NaiveDate::from_ymd(2020, 7, 1), jane.id), &conn).unwrap();
+        let mut original = models::NewGiftIdea::new("Bread maker".to_string(), jane.id, bob.id);
+        original.event_id = Some(event.id);
+        let original = gift_idea(original, &conn);
+        let mut copy = models::NewGiftIdea::new("Bread maker".to_string(), jane.id, bob.id);
+        copy.url = Some("https://example.com/bread-maker".to_string());
+        copy.carried_over_from_id = Some(original.id);
+        gift_idea(copy, &conn);
+        let document = export(jane.id, &conn).unwrap();
+        (conn, document)
+    }
+
+    #[test]
+    fn ids_are_remapped_to_the_importing_instance() {
+        let (_, document) = source();
+        // the same people, signed up in another order
+        let conn = crate::test_connection();
+        user("someone@example.com", &conn);
+        let bob = user("bob@example.com", &conn);
+        let jane = user("jane@example.com", &conn);
+
+        let report = import(&document, jane.id, false, &conn).unwrap();
+        assert_eq!((report.events_created, report.gift_ideas_created), (1, 2));
+        assert!(report.unknown_users.is_empty() && report.skipped.is_empty(), "{:?}", report);
+
+        let events = event_actions::find_all(&conn).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].owner_id, jane.id);
+        let gifts = gift_idea_actions::find_by_owner_or_recipient(jane.id, &conn).unwrap();
+        assert_eq!(gifts.len(), 2);
+        assert!(gifts.iter().all(|gift| gift.owner_id == jane.id && gift.recipient_user_id == bob.id));
+        let original = gifts.iter().find(|gift| gift.carried_over_from_id.is_none()).unwrap();
+        let copy = gifts.iter().find(|gift| gift.carried_over_from_id.is_some()).unwrap();
+        assert_eq!(original.event_id, Some(events[0].id));
+        assert_eq!(copy.carried_over_from_id, Some(original.id));
+    }
+
+    #[test]
+    fn importing_again_matches_what_is_already_there() {
+        let (_, document) = source();
+        let conn = crate::test_connection();
+        user("bob@example.com", &conn);
+        let jane = user("jane@example.com", &conn);
+
+        import(&document, jane.id, false, &conn).unwrap();
+        let report = import(&document, jane.id, false, &conn).unwrap();
+        assert_eq!((report.events_created, report.gift_ideas_created), (0, 0));
+        assert_eq!(report.duplicate_events, vec!["Bob's birthday"]);
+        assert_eq!(report.duplicate_gift_ideas, vec!["Bread maker", "Bread maker"]);
+        assert_eq!(event_actions::find_all(&conn).unwrap().len(), 1);
+        assert_eq!(gift_idea_actions::find_by_owner_or_recipient(jane.id, &conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn a_dry_run_reports_without_changing_anything() {
+        let (_, document) = source();
+        let conn = crate::test_connection();
+        user("bob@example.com", &conn);
+        let jane = user("jane@example.com", &conn);
+
+        let report = import(&document, jane.id, true, &conn).unwrap();
+        assert!(report.dry_run);
+        assert_eq!((report.events_created, report.gift_ideas_created), (1, 2));
+        assert!(event_actions::find_all(&conn).unwrap().is_empty());
+        assert!(gift_idea_actions::find_by_owner_or_recipient(jane.id, &conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn people_missing_here_are_reported_and_their_ideas_skipped() {
+        let (_, document) = source();
+        let conn = crate::test_connection();
+        let jane = user("jane@example.com", &conn);
+
+        let report = import(&document, jane.id, false, &conn).unwrap();
+        assert_eq!(report.unknown_users, vec!["bob@example.com"]);
+        assert_eq!(report.gift_ideas_created, 0);
+        assert_eq!(report.skipped.len(), 2);
+    }
+
+    #[test]
+    fn only_the_exporters_own_reservations_come_along() {
+        let (from, _) = source();
+        let jane = user_actions::find_by_email("jane@example.com".to_string(), &from).unwrap().unwrap();
+        let carol = user("carol@example.com", &from);
+        let gifts = gift_idea_actions::find_by_owner_or_recipient(jane.id, &from).unwrap();
+        gift_idea_actions::reserve(gifts[0].id, jane.id, &from).unwrap();
+        gift_idea_actions::reserve(gifts[1].id, carol.id, &from).unwrap();
+        let mut document = export(jane.id, &from).unwrap();
+        // and one of carol's that the document claims for someone else
+        let mut forged = document.gift_ideas[0].clone();
+        forged.id = 1000;
+        forged.title = "Stand mixer".to_string();
+        forged.reserved_by_user_id = Some(carol.id);
+        document.gift_ideas.push(forged);
+
+        let conn = crate::test_connection();
+        user("bob@example.com", &conn);
+        user("carol@example.com", &conn);
+        let jane = user("jane@example.com", &conn);
+        let report = import(&document, jane.id, false, &conn).unwrap();
+        assert_eq!(report.gift_ideas_created, 3);
+        let gifts = gift_idea_actions::find_by_owner_or_recipient(jane.id, &conn).unwrap();
+        let reserved = gifts.iter().filter(|gift| gift.reserved_by_user_id.is_some()).collect::<Vec<_>>();
+        assert_eq!(reserved.len(), 1);
+        assert_eq!(reserved[0].reserved_by_user_id, Some(jane.id));
+        assert!(gifts
+            .iter()
+            .filter(|gift| gift.reserved_by_user_id.is_none())
+            .all(|gift| gift.date_reserved.is_none() && gift.date_purchased.is_none()));
+    }
+}
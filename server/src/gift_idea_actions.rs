@@ -1,13 +1,22 @@
-use crate::diesel::sqlite::Sqlite;
-use diesel::debug_query;
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
 
+use crate::audit_actions;
+use crate::group_actions;
 use crate::models;
 use crate::schema::gift_ideas;
+use crate::telemetry;
 use actix_web::*;
 
+no_arg_sql_function!(last_insert_rowid, diesel::sql_types::Integer, "The id of the last row this connection inserted.");
+
+/// Archived ideas are left out; see `find_by_event`.
 pub fn find_all(conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
-    let results = gift_ideas::table.load::<models::GiftIdea>(conn).expect("failed to find all");
+    let results = gift_ideas::table
+        .filter(gift_ideas::dsl::date_deleted.is_null())
+        .filter(gift_ideas::dsl::date_archived.is_null())
+        .load::<models::GiftIdea>(conn)
+        .expect("failed to find all");
     Ok(results)
 }
 
@@ -19,49 +28,525 @@ pub fn find_all(conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel
 //     Ok(results)
 // }
 
+/// Ideas in the trash aren't found; see `find_deleted_by_owner`.
 pub fn find_by_id(gid: i32, conn: &SqliteConnection) -> Result<Option<models::GiftIdea>, diesel::result::Error> {
-    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid));
-    println!("{}", debug_query::<Sqlite, _>(&gift).to_string());
+    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).filter(gift_ideas::dsl::date_deleted.is_null());
+    telemetry::sql(&gift);
     let results = gift.first::<models::GiftIdea>(conn).optional()?;
     Ok(results)
 }
 
-pub fn add(new_gift: &models::NewGiftIdea, conn: &SqliteConnection) -> Result<models::GiftIdea, diesel::result::Error> {
-    // RETURNING is not supported by sqlite...the following will work w/ postgresql
-    //let user = diesel::insert_into(users).values(&new_user).get_result(conn).expect("Error savig");
-    let insert = diesel::insert_into(gift_ideas::table).values(new_gift);
-    println!("{}", debug_query::<Sqlite, _>(&insert).to_string());
-    insert.execute(conn)?;
-    let gift = gift_ideas::table
-        .filter(gift_ideas::dsl::title.eq(new_gift.title.to_string()))
-        .first::<models::GiftIdea>(conn)?;
+pub fn find_including_deleted(gid: i32, conn: &SqliteConnection) -> Result<Option<models::GiftIdea>, diesel::result::Error> {
+    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn).optional()?;
     Ok(gift)
 }
 
-pub fn reserve(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<shared::GiftIdeaResponseBody, diesel::result::Error> {
-    let mut gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn).optional()?.unwrap();
-    gift.reserved_by_user_id = Some(uid);
-    let updated_row = diesel::update(gift_ideas::table.find(gid)).set(gift).execute(conn)?;
-    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn)?;
-    Ok(gift.into())
+/// The owner's ideas deleted on or after `since`, most recent first.
+pub fn find_deleted_by_owner(uid: i32, since: NaiveDateTime, conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
+    let results = gift_ideas::table
+        .filter(gift_ideas::dsl::owner_id.eq(uid))
+        .filter(gift_ideas::dsl::date_deleted.ge(since))
+        .order(gift_ideas::dsl::date_deleted.desc())
+        .load::<models::GiftIdea>(conn)?;
+    Ok(results)
+}
+
+/// Everything filed under the event, archived or not.
+pub fn find_by_event(eid: i32, conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
+    let results = gift_ideas::table
+        .filter(gift_ideas::dsl::event_id.eq(eid))
+        .filter(gift_ideas::dsl::date_deleted.is_null())
+        .order(gift_ideas::dsl::id.asc())
+        .load::<models::GiftIdea>(conn)?;
+    Ok(results)
+}
+
+pub fn find_deleted_before(cutoff: NaiveDateTime, conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
+    let results = gift_ideas::table.filter(gift_ideas::dsl::date_deleted.lt(cutoff)).load::<models::GiftIdea>(conn)?;
+    Ok(results)
+}
+
+// every change to a gift idea goes through here so its history stays complete
+fn audit(
+    actor_user_id: Option<i32>,
+    action: &str,
+    gid: i32,
+    before: Option<&models::GiftIdea>,
+    after: Option<&models::GiftIdea>,
+    conn: &SqliteConnection,
+) -> Result<(), diesel::result::Error> {
+    audit_actions::record(actor_user_id, audit_actions::GIFT_IDEA, gid, action, &audit_actions::diff(before, after), conn)
+}
+
+pub fn add(new_gift: &models::NewGiftIdea, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<models::GiftIdea, diesel::result::Error> {
+    conn.transaction(|| {
+        // RETURNING is not supported by sqlite...the following will work w/ postgresql
+        //let user = diesel::insert_into(users).values(&new_user).get_result(conn).expect("Error savig");
+        let insert = diesel::insert_into(gift_ideas::table).values(new_gift);
+        telemetry::sql(&insert);
+        insert.execute(conn)?;
+        let gid = diesel::select(last_insert_rowid).get_result::<i32>(conn)?;
+        let gift = gift_ideas::table.find(gid).first::<models::GiftIdea>(conn)?;
+        audit(actor_user_id, audit_actions::CREATED, gift.id, None, Some(&gift), conn)?;
+        Ok(gift)
+    })
 }
 
-pub fn unreserve(gid: i32, conn: &SqliteConnection) -> Result<shared::GiftIdeaResponseBody, diesel::result::Error> {
-    let mut gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn).optional()?.unwrap();
+/// A gift idea as its recipient may see it: nothing about who reserved or bought it.
+pub fn redact_for_recipient(mut gift: shared::GiftIdeaResponseBody) -> shared::GiftIdeaResponseBody {
     gift.reserved_by_user_id = None;
-    let updated_row = diesel::update(gift_ideas::table.find(gid)).set(gift).execute(conn)?;
-    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn)?;
-    Ok(gift.into())
+    gift.date_reserved = None;
+    gift.date_purchased = None;
+    gift
 }
 
-pub fn delete(gid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
-    let num_deleted = diesel::delete(gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid))).execute(conn)?;
-    println!("num_deleted: {}", num_deleted);
-    Ok(num_deleted == 1)
+/// What `uid` gets to see of `gifts`, the same as the live updates: ideas they added and ideas for
+/// anyone they share a group with, leaving out those someone else added for them and anything
+/// about reservations on their own ones for themselves.
+pub fn visible_to(gifts: Vec<models::GiftIdea>, uid: i32, conn: &SqliteConnection) -> Result<Vec<shared::GiftIdeaResponseBody>, diesel::result::Error> {
+    let co_member_ids = group_actions::find_co_member_ids(uid, conn)?;
+    Ok(gifts
+        .into_iter()
+        .filter(|gift| gift.owner_id == uid || (gift.recipient_user_id != uid && co_member_ids.contains(&gift.recipient_user_id)))
+        .map(|gift| {
+            if gift.recipient_user_id == uid {
+                redact_for_recipient(gift.into())
+            } else {
+                gift.into()
+            }
+        })
+        .collect())
 }
 
-pub fn update(gift: &models::GiftIdea, conn: &SqliteConnection) -> Result<models::GiftIdea, diesel::result::Error> {
-    diesel::update(gift_ideas::table.filter(gift_ideas::dsl::id.eq(gift.id))).set(gift).execute(conn).unwrap();
-    let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gift.id)).first::<models::GiftIdea>(conn)?;
-    Ok(gift)
+/// Reserves the idea for `uid`.  `None` if there's no such idea or it's in the trash, `Some(None)`
+/// if it's already reserved or is for `uid`.
+pub fn reserve(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<Option<shared::GiftIdeaResponseBody>>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = match find_by_id(gid, conn)? {
+            Some(before) => before,
+            None => return Ok(None),
+        };
+        if before.reserved_by_user_id.is_some() || before.recipient_user_id == uid {
+            return Ok(Some(None));
+        }
+        let mut gift = before.clone();
+        gift.reserved_by_user_id = Some(uid);
+        gift.date_reserved = Some(Utc::now().naive_utc());
+        diesel::update(gift_ideas::table.find(gid)).set(gift).execute(conn)?;
+        let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn)?;
+        audit(Some(uid), audit_actions::RESERVED, gid, Some(&before), Some(&gift), conn)?;
+        Ok(Some(Some(gift.into())))
+    })
+}
+
+/// Releases the idea's reservation.  `None` if there's no such idea or it's in the trash.
+pub fn unreserve(gid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<Option<shared::GiftIdeaResponseBody>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = match find_by_id(gid, conn)? {
+            Some(before) => before,
+            None => return Ok(None),
+        };
+        let mut gift = before.clone();
+        gift.reserved_by_user_id = None;
+        gift.date_reserved = None;
+        gift.date_purchased = None;
+        diesel::update(gift_ideas::table.find(gid)).set(gift).execute(conn)?;
+        let gift = gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid)).first::<models::GiftIdea>(conn)?;
+        audit(actor_user_id, audit_actions::UNRESERVED, gid, Some(&before), Some(&gift), conn)?;
+        Ok(Some(gift.into()))
+    })
+}
+
+/// Drops `uid`'s reservations on ideas in the trash, for when their account goes; the rest are
+/// released with `unreserve`.
+pub fn clear_reserver(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::update(
+        gift_ideas::table
+            .filter(gift_ideas::dsl::reserved_by_user_id.eq(uid))
+            .filter(gift_ideas::dsl::date_deleted.is_not_null()),
+    )
+    .set((
+        gift_ideas::dsl::reserved_by_user_id.eq(None::<i32>),
+        gift_ideas::dsl::date_reserved.eq(None::<NaiveDateTime>),
+        gift_ideas::dsl::date_purchased.eq(None::<NaiveDateTime>),
+    ))
+    .execute(conn)
+}
+
+/// Only whoever reserved the gift idea can mark it purchased.  `None` means it isn't reserved by `uid`.
+pub fn mark_purchased(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<Option<models::GiftIdea>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_by_id(gid, conn)?;
+        let num_updated = diesel::update(
+            gift_ideas::table
+                .filter(gift_ideas::dsl::id.eq(gid))
+                .filter(gift_ideas::dsl::reserved_by_user_id.eq(uid))
+                .filter(gift_ideas::dsl::date_deleted.is_null()),
+        )
+        .set(gift_ideas::dsl::date_purchased.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+        if num_updated == 1 {
+            let gift = find_by_id(gid, conn)?;
+            audit(Some(uid), audit_actions::PURCHASED, gid, before.as_ref(), gift.as_ref(), conn)?;
+            Ok(gift)
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+/// Moves the idea to the trash if `uid` owns it, stamped with who deleted it and when.  It keeps
+/// its reservation in case it's restored.
+pub fn delete(gid: i32, uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_by_id(gid, conn)?;
+        let num_deleted = diesel::update(
+            gift_ideas::table
+                .filter(gift_ideas::dsl::id.eq(gid))
+                .filter(gift_ideas::dsl::owner_id.eq(uid))
+                .filter(gift_ideas::dsl::date_deleted.is_null()),
+        )
+        .set((gift_ideas::dsl::date_deleted.eq(Utc::now().naive_utc()), gift_ideas::dsl::deleted_by_user_id.eq(uid)))
+        .execute(conn)?;
+        if num_deleted == 1 {
+            let after = find_including_deleted(gid, conn)?;
+            audit(Some(uid), audit_actions::DELETED, gid, before.as_ref(), after.as_ref(), conn)?;
+        }
+        Ok(num_deleted == 1)
+    })
+}
+
+/// Takes the idea back out of the trash if `uid` owns it and it was deleted on or after `since`.
+pub fn restore(gid: i32, uid: i32, since: NaiveDateTime, conn: &SqliteConnection) -> Result<Option<models::GiftIdea>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_including_deleted(gid, conn)?;
+        let num_restored = diesel::update(
+            gift_ideas::table
+                .filter(gift_ideas::dsl::id.eq(gid))
+                .filter(gift_ideas::dsl::owner_id.eq(uid))
+                .filter(gift_ideas::dsl::date_deleted.ge(since)),
+        )
+        .set((gift_ideas::dsl::date_deleted.eq(None::<NaiveDateTime>), gift_ideas::dsl::deleted_by_user_id.eq(None::<i32>)))
+        .execute(conn)?;
+        if num_restored == 1 {
+            let gift = find_by_id(gid, conn)?;
+            audit(Some(uid), audit_actions::RESTORED, gid, before.as_ref(), gift.as_ref(), conn)?;
+            Ok(gift)
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+/// Removes the row for good, whether or not it was in the trash.  Attachments and comments have
+/// to be deleted first.
+pub fn purge(gid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_including_deleted(gid, conn)?;
+        let num_deleted = diesel::delete(gift_ideas::table.filter(gift_ideas::dsl::id.eq(gid))).execute(conn)?;
+        if num_deleted == 1 {
+            audit(actor_user_id, audit_actions::PURGED, gid, before.as_ref(), None, conn)?;
+        }
+        Ok(num_deleted == 1)
+    })
+}
+
+/// Hands the idea to a different owner and/or recipient, trashed or not.  `None` if there's no
+/// such idea.
+pub fn reassign(
+    gid: i32,
+    owner_id: Option<i32>,
+    recipient_user_id: Option<i32>,
+    actor_user_id: Option<i32>,
+    conn: &SqliteConnection,
+) -> Result<Option<models::GiftIdea>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = match find_including_deleted(gid, conn)? {
+            Some(gift) => gift,
+            None => return Ok(None),
+        };
+        let owner_id = owner_id.unwrap_or(before.owner_id);
+        let recipient_user_id = recipient_user_id.unwrap_or(before.recipient_user_id);
+        let (reserved_by_user_id, date_reserved, date_purchased) = reservation_kept(&before, recipient_user_id, before.reserved_by_user_id);
+        diesel::update(gift_ideas::table.find(gid))
+            .set((
+                gift_ideas::dsl::owner_id.eq(owner_id),
+                gift_ideas::dsl::recipient_user_id.eq(recipient_user_id),
+                gift_ideas::dsl::reserved_by_user_id.eq(reserved_by_user_id),
+                gift_ideas::dsl::date_reserved.eq(date_reserved),
+                gift_ideas::dsl::date_purchased.eq(date_purchased),
+                gift_ideas::dsl::date_last_modified.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+        let after = find_including_deleted(gid, conn)?;
+        audit(actor_user_id, audit_actions::UPDATED, gid, Some(&before), after.as_ref(), conn)?;
+        Ok(after)
+    })
+}
+
+/// The reservation an idea keeps once it goes to `recipient_user_id`: nobody can hold a
+/// reservation on their own gift, so it's dropped, purchase and all, when they'd be the reserver.
+fn reservation_kept(gift: &models::GiftIdea, recipient_user_id: i32, reserved_by_user_id: Option<i32>) -> (Option<i32>, Option<NaiveDateTime>, Option<NaiveDateTime>) {
+    if reserved_by_user_id == Some(recipient_user_id) {
+        (None, None, None)
+    } else {
+        (reserved_by_user_id, gift.date_reserved, gift.date_purchased)
+    }
+}
+
+/// Moves everything one user owns, is getting or has reserved over to another, for merging
+/// duplicate accounts.  Returns how many ideas changed.
+pub fn reassign_user(from_uid: i32, to_uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    conn.transaction(|| {
+        let affected = gift_ideas::table
+            .filter(
+                gift_ideas::dsl::owner_id
+                    .eq(from_uid)
+                    .or(gift_ideas::dsl::recipient_user_id.eq(from_uid))
+                    .or(gift_ideas::dsl::reserved_by_user_id.eq(from_uid)),
+            )
+            .load::<models::GiftIdea>(conn)?;
+        let swap = |uid: i32| if uid == from_uid { to_uid } else { uid };
+        for before in affected.iter() {
+            let recipient_user_id = swap(before.recipient_user_id);
+            let (reserved_by_user_id, date_reserved, date_purchased) = reservation_kept(before, recipient_user_id, before.reserved_by_user_id.map(swap));
+            diesel::update(gift_ideas::table.find(before.id))
+                .set((
+                    gift_ideas::dsl::owner_id.eq(swap(before.owner_id)),
+                    gift_ideas::dsl::recipient_user_id.eq(recipient_user_id),
+                    gift_ideas::dsl::reserved_by_user_id.eq(reserved_by_user_id),
+                    gift_ideas::dsl::date_reserved.eq(date_reserved),
+                    gift_ideas::dsl::date_purchased.eq(date_purchased),
+                    gift_ideas::dsl::date_last_modified.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+            let after = find_including_deleted(before.id, conn)?;
+            audit(actor_user_id, audit_actions::UPDATED, before.id, Some(before), after.as_ref(), conn)?;
+        }
+        Ok(affected.len())
+    })
+}
+
+/// Takes every idea filed under the event out of the active listings.
+pub fn archive_by_event(eid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    conn.transaction(|| {
+        let active = find_by_event(eid, conn)?.into_iter().filter(|gift| gift.date_archived.is_none()).collect::<Vec<_>>();
+        let now = Utc::now().naive_utc();
+        for before in active.iter() {
+            diesel::update(gift_ideas::table.find(before.id))
+                .set(gift_ideas::dsl::date_archived.eq(now))
+                .execute(conn)?;
+            let after = find_including_deleted(before.id, conn)?;
+            audit(actor_user_id, audit_actions::ARCHIVED, before.id, Some(before), after.as_ref(), conn)?;
+        }
+        Ok(active.len())
+    })
+}
+
+/// Copies the ideas from one event that were never purchased into another, each pointing back at
+/// the idea it came from. Reservations, comments and images stay with the originals.  An idea
+/// already carried over into `to_eid` isn't copied again, so this can safely be repeated.
+pub fn carry_over(from_eid: i32, to_eid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    conn.transaction(|| {
+        let already_copied = find_by_event(to_eid, conn)?.into_iter().filter_map(|gift| gift.carried_over_from_id).collect::<Vec<_>>();
+        let ungiven = find_by_event(from_eid, conn)?
+            .into_iter()
+            .filter(|gift| gift.date_purchased.is_none() && !already_copied.contains(&gift.id))
+            .collect::<Vec<_>>();
+        for original in ungiven.iter() {
+            let mut copy = models::NewGiftIdea::new(original.title.clone(), original.owner_id, original.recipient_user_id);
+            copy.description = original.description.clone();
+            copy.price = original.price.clone();
+            copy.url = original.url.clone();
+            copy.event_id = Some(to_eid);
+            copy.carried_over_from_id = Some(original.id);
+            add(&copy, actor_user_id, conn)?;
+        }
+        Ok(ungiven.len())
+    })
+}
+
+/// Files the event's ideas under no event, ahead of the event being deleted.  Ideas archived with
+/// it come back into the active listings, as there's no longer an archived event to find them
+/// under.
+pub fn detach_from_event(eid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    conn.transaction(|| {
+        let attached = gift_ideas::table.filter(gift_ideas::dsl::event_id.eq(eid)).load::<models::GiftIdea>(conn)?;
+        for before in attached.iter() {
+            diesel::update(gift_ideas::table.find(before.id))
+                .set((gift_ideas::dsl::event_id.eq(None::<i32>), gift_ideas::dsl::date_archived.eq(None::<NaiveDateTime>)))
+                .execute(conn)?;
+            let after = find_including_deleted(before.id, conn)?;
+            audit(actor_user_id, audit_actions::UPDATED, before.id, Some(before), after.as_ref(), conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Ideas in the trash aren't found; see `find_by_owner_or_recipient_including_deleted`.
+pub fn find_by_owner_or_recipient(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
+    let results = gift_ideas::table
+        .filter(gift_ideas::dsl::owner_id.eq(uid).or(gift_ideas::dsl::recipient_user_id.eq(uid)))
+        .filter(gift_ideas::dsl::date_deleted.is_null())
+        .load::<models::GiftIdea>(conn)?;
+    Ok(results)
+}
+
+pub fn find_by_owner_or_recipient_including_deleted(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
+    let results = gift_ideas::table
+        .filter(gift_ideas::dsl::owner_id.eq(uid).or(gift_ideas::dsl::recipient_user_id.eq(uid)))
+        .load::<models::GiftIdea>(conn)?;
+    Ok(results)
+}
+
+/// Everyone other than the recipient who has added or reserved an idea for them.
+pub fn find_giver_ids(recipient_user_id: i32, conn: &SqliteConnection) -> Result<Vec<i32>, diesel::result::Error> {
+    let gifts = gift_ideas::table
+        .filter(gift_ideas::dsl::recipient_user_id.eq(recipient_user_id))
+        .filter(gift_ideas::dsl::date_deleted.is_null())
+        .select((gift_ideas::dsl::owner_id, gift_ideas::dsl::reserved_by_user_id))
+        .load::<(i32, Option<i32>)>(conn)?;
+    let mut giver_ids = gifts
+        .into_iter()
+        .flat_map(|(owner_id, reserved_by_user_id)| std::iter::once(owner_id).chain(reserved_by_user_id))
+        .filter(|uid| *uid != recipient_user_id)
+        .collect::<Vec<_>>();
+    giver_ids.sort();
+    giver_ids.dedup();
+    Ok(giver_ids)
+}
+
+/// Ideas in the trash aren't found.
+pub fn find_reserved_by(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::GiftIdea>, diesel::result::Error> {
+    let results = gift_ideas::table
+        .filter(gift_ideas::dsl::reserved_by_user_id.eq(uid))
+        .filter(gift_ideas::dsl::date_deleted.is_null())
+        .load::<models::GiftIdea>(conn)?;
+    Ok(results)
+}
+
+/// Applies `changes` if `uid` owns the gift idea.  `None` means there was nothing to update:
+/// either no such idea or someone else's.
+pub fn update_owned(gid: i32, uid: i32, changes: &models::GiftIdeaChangeset, conn: &SqliteConnection) -> Result<Option<models::GiftIdea>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_by_id(gid, conn)?;
+        let num_updated = diesel::update(
+            gift_ideas::table
+                .filter(gift_ideas::dsl::id.eq(gid))
+                .filter(gift_ideas::dsl::owner_id.eq(uid))
+                .filter(gift_ideas::dsl::date_deleted.is_null()),
+        )
+        .set(changes)
+        .execute(conn)?;
+        if num_updated == 1 {
+            let gift = find_by_id(gid, conn)?;
+            audit(Some(uid), audit_actions::UPDATED, gid, before.as_ref(), gift.as_ref(), conn)?;
+            Ok(gift)
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_actions;
+    use crate::user_actions;
+
+    fn setup(conn: &SqliteConnection) -> (models::User, models::User, models::Event) {
+        let user = |email: &str| user_actions::add(&models::NewUser::new(email.to_string(), "password".to_string()), None, conn).unwrap();
+        let (jane, bob) = (user("jane@example.com"), user("bob@example.com"));
+        let event = event_actions::add(&models::NewEvent::new("Christmas".to_string(), chrono::NaiveDate::from_ymd(2019, 12, 25), jane.id), conn).unwrap();
+        (jane, bob, event)
+    }
+
+    fn filed_under(title: &str, event: &models::Event, owner_id: i32, recipient_user_id: i32, conn: &SqliteConnection) -> models::GiftIdea {
+        let mut new_gift = models::NewGiftIdea::new(title.to_string(), owner_id, recipient_user_id);
+        new_gift.event_id = Some(event.id);
+        add(&new_gift, Some(owner_id), conn).unwrap()
+    }
+
+    #[test]
+    fn adding_returns_the_new_row_when_titles_repeat() {
+        let conn = crate::test_connection();
+        let (jane, bob, _) = setup(&conn);
+        let first = add(&models::NewGiftIdea::new("Socks".to_string(), jane.id, bob.id), Some(jane.id), &conn).unwrap();
+        let second = add(&models::NewGiftIdea::new("Socks".to_string(), bob.id, jane.id), Some(bob.id), &conn).unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!((second.owner_id, second.recipient_user_id), (bob.id, jane.id));
+        // another table's insert in between doesn't throw it off
+        event_actions::add(&models::NewEvent::new("Birthday".to_string(), chrono::NaiveDate::from_ymd(2020, 1, 1), jane.id), &conn).unwrap();
+        let third = add(&models::NewGiftIdea::new("Socks".to_string(), jane.id, bob.id), Some(jane.id), &conn).unwrap();
+        assert_eq!(find_by_id(third.id, &conn).unwrap(), Some(third));
+    }
+
+    #[test]
+    fn carrying_over_twice_copies_each_idea_once() {
+        let conn = crate::test_connection();
+        let (jane, bob, christmas) = setup(&conn);
+        let original = filed_under("Bread maker", &christmas, jane.id, bob.id, &conn);
+        let bought = filed_under("Stand mixer", &christmas, jane.id, bob.id, &conn);
+        reserve(bought.id, jane.id, &conn).unwrap();
+        mark_purchased(bought.id, jane.id, &conn).unwrap();
+        event_actions::archive(christmas.id, jane.id, &conn).unwrap();
+        let next = event_actions::add(&models::NewEvent::new("Christmas".to_string(), chrono::NaiveDate::from_ymd(2020, 12, 25), jane.id), &conn).unwrap();
+
+        assert_eq!(carry_over(christmas.id, next.id, Some(jane.id), &conn).unwrap(), 1);
+        assert_eq!(carry_over(christmas.id, next.id, Some(jane.id), &conn).unwrap(), 0);
+        let copies = find_by_event(next.id, &conn).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].carried_over_from_id, Some(original.id));
+    }
+
+    #[test]
+    fn deleting_an_archived_event_brings_its_ideas_back() {
+        let conn = crate::test_connection();
+        let (jane, bob, christmas) = setup(&conn);
+        let gift = filed_under("Bread maker", &christmas, jane.id, bob.id, &conn);
+        event_actions::archive(christmas.id, jane.id, &conn).unwrap();
+        assert!(find_all(&conn).unwrap().is_empty());
+
+        assert!(event_actions::delete(christmas.id, jane.id, &conn).unwrap());
+        let restored = find_by_id(gift.id, &conn).unwrap().unwrap();
+        assert_eq!((restored.event_id, restored.date_archived), (None, None));
+        assert_eq!(find_all(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn recipients_see_only_their_own_ideas_for_themselves_and_no_reservations() {
+        let conn = crate::test_connection();
+        let (jane, bob, christmas) = setup(&conn);
+        let stranger = user_actions::add(&models::NewUser::new("stranger@example.com".to_string(), "password".to_string()), None, &conn).unwrap();
+        let family = group_actions::add("Family".to_string(), None, &conn).unwrap();
+        group_actions::add_member(family.id, jane.id, None, &conn).unwrap();
+        group_actions::add_member(family.id, bob.id, None, &conn).unwrap();
+        let janes = filed_under("Bread maker", &christmas, jane.id, bob.id, &conn);
+        let bobs = filed_under("Socks", &christmas, bob.id, bob.id, &conn);
+        reserve(janes.id, jane.id, &conn).unwrap();
+        reserve(bobs.id, jane.id, &conn).unwrap();
+
+        let seen = |uid: i32| visible_to(find_by_event(christmas.id, &conn).unwrap(), uid, &conn).unwrap();
+        let for_jane = seen(jane.id);
+        assert_eq!(for_jane.len(), 2);
+        assert!(for_jane.iter().all(|gift| gift.reserved_by_user_id == Some(jane.id)));
+        let for_bob = seen(bob.id);
+        assert_eq!(for_bob.len(), 1);
+        assert_eq!((for_bob[0].id, for_bob[0].reserved_by_user_id, for_bob[0].date_reserved), (bobs.id, None, None));
+        assert!(seen(stranger.id).is_empty());
+    }
+
+    #[test]
+    fn an_idea_can_only_be_reserved_once_and_not_by_its_recipient() {
+        let conn = crate::test_connection();
+        let (jane, bob, christmas) = setup(&conn);
+        let carol = user_actions::add(&models::NewUser::new("carol@example.com".to_string(), "password".to_string()), None, &conn).unwrap();
+        let gift = filed_under("Bread maker", &christmas, jane.id, bob.id, &conn);
+
+        assert!(matches!(reserve(gift.id, bob.id, &conn).unwrap(), Some(None)));
+        assert!(reserve(gift.id, jane.id, &conn).unwrap().unwrap().is_some());
+        assert!(matches!(reserve(gift.id, carol.id, &conn).unwrap(), Some(None)));
+        assert_eq!(find_by_id(gift.id, &conn).unwrap().unwrap().reserved_by_user_id, Some(jane.id));
+        assert!(reserve(gift.id + 100, jane.id, &conn).unwrap().is_none());
+    }
 }
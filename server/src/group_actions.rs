@@ -0,0 +1,152 @@
+use diesel::prelude::*;
+
+use crate::audit_actions;
+use crate::models;
+use crate::schema::{events, group_members, groups, users};
+
+pub fn find_all(conn: &SqliteConnection) -> Result<Vec<models::Group>, diesel::result::Error> {
+    groups::table.order(groups::dsl::name).load::<models::Group>(conn)
+}
+
+pub fn find_by_id(gid: i32, conn: &SqliteConnection) -> Result<Option<models::Group>, diesel::result::Error> {
+    groups::table.find(gid).first::<models::Group>(conn).optional()
+}
+
+pub fn find_by_name(group_name: &str, conn: &SqliteConnection) -> Result<Option<models::Group>, diesel::result::Error> {
+    groups::table.filter(groups::dsl::name.eq(group_name)).first::<models::Group>(conn).optional()
+}
+
+pub fn add(name: String, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<models::Group, diesel::result::Error> {
+    conn.transaction(|| {
+        diesel::insert_into(groups::table).values(&models::NewGroup::new(name.to_string())).execute(conn)?;
+        let group = groups::table.filter(groups::dsl::name.eq(name)).first::<models::Group>(conn)?;
+        audit_actions::record(
+            actor_user_id,
+            audit_actions::GROUP,
+            group.id,
+            audit_actions::CREATED,
+            &audit_actions::diff(None, Some(&group)),
+            conn,
+        )?;
+        Ok(group)
+    })
+}
+
+/// Removes a group and its memberships.  The people in it, and their ideas, stay; its events go
+/// back to being shared with their owners' groups.
+pub fn delete(gid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = match find_by_id(gid, conn)? {
+            Some(group) => group,
+            None => return Ok(false),
+        };
+        diesel::update(events::table.filter(events::dsl::group_id.eq(gid)))
+            .set(events::dsl::group_id.eq(None::<i32>))
+            .execute(conn)?;
+        diesel::delete(group_members::table.filter(group_members::dsl::group_id.eq(gid))).execute(conn)?;
+        diesel::delete(groups::table.find(gid)).execute(conn)?;
+        audit_actions::record(
+            actor_user_id,
+            audit_actions::GROUP,
+            gid,
+            audit_actions::DELETED,
+            &audit_actions::diff(Some(&before), None),
+            conn,
+        )?;
+        Ok(true)
+    })
+}
+
+pub fn find_members(gid: i32, conn: &SqliteConnection) -> Result<Vec<models::User>, diesel::result::Error> {
+    group_members::table
+        .inner_join(users::table)
+        .filter(group_members::dsl::group_id.eq(gid))
+        .order(users::dsl::id)
+        .select(users::all_columns)
+        .load::<models::User>(conn)
+}
+
+pub fn find_by_member(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::Group>, diesel::result::Error> {
+    group_members::table
+        .inner_join(groups::table)
+        .filter(group_members::dsl::user_id.eq(uid))
+        .order(groups::dsl::name)
+        .select(groups::all_columns)
+        .load::<models::Group>(conn)
+}
+
+/// Everyone who shares at least one group with `uid`, `uid` included when they're in any group.
+pub fn find_co_member_ids(uid: i32, conn: &SqliteConnection) -> Result<Vec<i32>, diesel::result::Error> {
+    let group_ids = group_members::table
+        .filter(group_members::dsl::user_id.eq(uid))
+        .select(group_members::dsl::group_id)
+        .load::<i32>(conn)?;
+    group_members::table
+        .filter(group_members::dsl::group_id.eq_any(group_ids))
+        .select(group_members::dsl::user_id)
+        .distinct()
+        .order(group_members::dsl::user_id)
+        .load::<i32>(conn)
+}
+
+/// Whether `uid` and `other_uid` share a group.
+pub fn share_a_group(uid: i32, other_uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    Ok(find_co_member_ids(uid, conn)?.contains(&other_uid))
+}
+
+/// Returns false if they were already a member.
+pub fn add_member(gid: i32, uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let added = diesel::insert_or_ignore_into(group_members::table)
+            .values(&models::GroupMember::new(gid, uid))
+            .execute(conn)?;
+        if added == 0 {
+            return Ok(false);
+        }
+        audit_member(actor_user_id, audit_actions::MEMBER_ADDED, gid, None, Some(uid), conn)?;
+        Ok(true)
+    })
+}
+
+/// Returns false if they weren't a member.
+pub fn remove_member(gid: i32, uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let removed = diesel::delete(group_members::table.find((gid, uid))).execute(conn)?;
+        if removed == 0 {
+            return Ok(false);
+        }
+        audit_member(actor_user_id, audit_actions::MEMBER_REMOVED, gid, Some(uid), None, conn)?;
+        Ok(true)
+    })
+}
+
+/// Takes `uid` out of every group, for when their account goes.
+pub fn remove_from_all(uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let groups = find_by_member(uid, conn)?;
+    for group in groups.iter() {
+        remove_member(group.id, uid, actor_user_id, conn)?;
+    }
+    Ok(groups.len())
+}
+
+/// Puts `to_uid` in every group `from_uid` is in, and takes `from_uid` out, for merging duplicate
+/// accounts.
+pub fn reassign_user(from_uid: i32, to_uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    conn.transaction(|| {
+        let groups = find_by_member(from_uid, conn)?;
+        for group in groups.iter() {
+            add_member(group.id, to_uid, actor_user_id, conn)?;
+            remove_member(group.id, from_uid, actor_user_id, conn)?;
+        }
+        Ok(groups.len())
+    })
+}
+
+fn audit_member(actor_user_id: Option<i32>, action: &str, gid: i32, before: Option<i32>, after: Option<i32>, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    let change = shared::FieldChange {
+        field: "user_id".to_string(),
+        before: before.map(|uid| uid.to_string()),
+        after: after.map(|uid| uid.to_string()),
+    };
+    audit_actions::record(actor_user_id, audit_actions::GROUP, gid, action, &[change], conn)
+}
@@ -0,0 +1,98 @@
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::models;
+use crate::schema::jobs;
+
+pub fn find_all(conn: &SqliteConnection) -> Result<Vec<models::Job>, diesel::result::Error> {
+    let results = jobs::table.order(jobs::dsl::name.asc()).load::<models::Job>(conn)?;
+    Ok(results)
+}
+
+pub fn find(name: &str, conn: &SqliteConnection) -> Result<Option<models::Job>, diesel::result::Error> {
+    jobs::table.find(name).first::<models::Job>(conn).optional()
+}
+
+/// Adds a row for a job the first time it's seen.  A job whose schedule has changed since is
+/// moved to `date_next_run`; otherwise its next run is left as it was, restarts included.
+pub fn register(name: &str, schedule: &str, date_next_run: NaiveDateTime, conn: &SqliteConnection) -> Result<models::Job, diesel::result::Error> {
+    conn.transaction(|| {
+        match find(name, conn)? {
+            None => {
+                diesel::insert_into(jobs::table).values(&models::Job::new(name, schedule, date_next_run)).execute(conn)?;
+            }
+            Some(job) if job.schedule != schedule => {
+                diesel::update(jobs::table.find(name))
+                    .set((jobs::dsl::schedule.eq(schedule), jobs::dsl::date_next_run.eq(date_next_run)))
+                    .execute(conn)?;
+            }
+            Some(_) => (),
+        }
+        jobs::table.find(name).first::<models::Job>(conn)
+    })
+}
+
+/// Drops the rows of jobs the server no longer has.
+pub fn delete_unregistered(names: &[&str], conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::delete(jobs::table.filter(jobs::dsl::name.ne_all(names))).execute(conn)
+}
+
+/// Takes the lease on a job that's due and that no one else holds; only the runner that gets
+/// `true` back may run it.  An expired lease means its holder died mid-run, so it's fair game.
+pub fn claim(name: &str, owner: &str, now: NaiveDateTime, lease: Duration, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let claimed = diesel::update(
+        jobs::table
+            .filter(jobs::dsl::name.eq(name))
+            .filter(jobs::dsl::date_next_run.le(now))
+            .filter(jobs::dsl::date_lease_expires.is_null().or(jobs::dsl::date_lease_expires.lt(now))),
+    )
+    .set((
+        jobs::dsl::lease_owner.eq(owner),
+        jobs::dsl::date_lease_expires.eq(now + lease),
+        jobs::dsl::date_last_started.eq(now),
+    ))
+    .execute(conn)?;
+    Ok(claimed == 1)
+}
+
+pub fn mark_succeeded(name: &str, owner: &str, now: NaiveDateTime, date_next_run: NaiveDateTime, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    diesel::update(jobs::table.filter(jobs::dsl::name.eq(name)).filter(jobs::dsl::lease_owner.eq(owner)))
+        .set((
+            jobs::dsl::attempts.eq(0),
+            jobs::dsl::last_error.eq(None::<String>),
+            jobs::dsl::date_next_run.eq(date_next_run),
+            jobs::dsl::date_last_succeeded.eq(now),
+            jobs::dsl::lease_owner.eq(None::<String>),
+            jobs::dsl::date_lease_expires.eq(None::<NaiveDateTime>),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn mark_failed(
+    name: &str,
+    owner: &str,
+    error: String,
+    attempts: i32,
+    now: NaiveDateTime,
+    date_next_run: NaiveDateTime,
+    conn: &SqliteConnection,
+) -> Result<(), diesel::result::Error> {
+    diesel::update(jobs::table.filter(jobs::dsl::name.eq(name)).filter(jobs::dsl::lease_owner.eq(owner)))
+        .set((
+            jobs::dsl::attempts.eq(attempts),
+            jobs::dsl::last_error.eq(error),
+            jobs::dsl::date_next_run.eq(date_next_run),
+            jobs::dsl::date_last_failed.eq(now),
+            jobs::dsl::lease_owner.eq(None::<String>),
+            jobs::dsl::date_lease_expires.eq(None::<NaiveDateTime>),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Makes a job due now; `None` if there's no such job.
+pub fn trigger(name: &str, now: NaiveDateTime, conn: &SqliteConnection) -> Result<Option<models::Job>, diesel::result::Error> {
+    diesel::update(jobs::table.find(name)).set(jobs::dsl::date_next_run.eq(now)).execute(conn)?;
+    find(name, conn)
+}
@@ -0,0 +1,374 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use cron::Schedule;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+use std::thread;
+
+use crate::attachment_actions::AttachmentError;
+use crate::job_actions;
+use crate::mailer::Mailer;
+use crate::metrics;
+use crate::notifications;
+use crate::occasions;
+use crate::storage::AttachmentStorage;
+use crate::trash;
+use crate::DbPool;
+
+// a failing job is retried sooner than its schedule this many times before it just waits for it
+const MAX_ATTEMPTS: i32 = 5;
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long a runner holds a job.  A server that dies mid-run gives the job up once this is
+/// over, so no job should take anywhere near this long.
+fn lease() -> Duration {
+    Duration::minutes(10)
+}
+
+pub enum JobError {
+    Database(diesel::result::Error),
+    Attachment(AttachmentError),
+    Pool(diesel::r2d2::PoolError),
+    Panicked(String),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobError::Database(e) => write!(f, "database error: {}", e),
+            JobError::Attachment(e) => write!(f, "{}", e),
+            JobError::Pool(e) => write!(f, "couldn't connect to the database: {}", e),
+            JobError::Panicked(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for JobError {
+    fn from(e: diesel::result::Error) -> JobError {
+        JobError::Database(e)
+    }
+}
+
+impl From<AttachmentError> for JobError {
+    fn from(e: AttachmentError) -> JobError {
+        JobError::Attachment(e)
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for JobError {
+    fn from(e: diesel::r2d2::PoolError) -> JobError {
+        JobError::Pool(e)
+    }
+}
+
+/// What jobs have to work with.
+pub struct JobContext {
+    pub pool: DbPool,
+    pub mailer: Box<dyn Mailer>,
+    pub storage: AttachmentStorage,
+}
+
+/// A piece of periodic work.  `schedule` is a cron expression with a leading seconds field, e.g.
+/// `0 30 * * * *` for half past every hour, in UTC.  `run` returns how much it did, for the logs.
+pub struct Job {
+    pub name: &'static str,
+    pub schedule: &'static str,
+    pub run: fn(&mut JobContext) -> Result<usize, JobError>,
+}
+
+pub fn registered() -> Vec<Job> {
+    vec![
+        Job {
+            name: "deliver_notifications",
+            schedule: "*/30 * * * * *",
+            run: deliver_notifications,
+        },
+        Job {
+            name: "queue_event_reminders",
+            schedule: "0 0 * * * *",
+            run: queue_event_reminders,
+        },
+        Job {
+            name: "purge_trash",
+            schedule: "0 10 * * * *",
+            run: purge_trash,
+        },
+        Job {
+            name: "create_date_events",
+            schedule: "0 20 * * * *",
+            run: create_date_events,
+        },
+    ]
+}
+
+fn deliver_notifications(context: &mut JobContext) -> Result<usize, JobError> {
+    Ok(notifications::deliver_pending(&context.pool, context.mailer.as_mut())?)
+}
+
+fn queue_event_reminders(context: &mut JobContext) -> Result<usize, JobError> {
+    let conn = context.pool.get()?;
+    Ok(notifications::queue_event_reminders(chrono::Local::today().naive_local(), &conn)?)
+}
+
+fn purge_trash(context: &mut JobContext) -> Result<usize, JobError> {
+    let conn = context.pool.get()?;
+    Ok(trash::purge_expired(context.storage.as_ref(), &conn)?)
+}
+
+fn create_date_events(context: &mut JobContext) -> Result<usize, JobError> {
+    let conn = context.pool.get()?;
+    Ok(occasions::create_due_events(Utc::now().naive_utc().date(), &conn)?)
+}
+
+pub fn next_run(schedule: &str, after: NaiveDateTime) -> NaiveDateTime {
+    let schedule = Schedule::from_str(schedule).expect("job schedules are valid cron expressions");
+    schedule
+        .after(&DateTime::<Utc>::from_utc(after, Utc))
+        .next()
+        .map_or_else(|| after + Duration::days(1), |next| next.naive_utc())
+}
+
+// 30 seconds, doubling with each failure, up to an hour
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::seconds((30i64 << (attempts - 1).clamp(0, 7)).min(60 * 60))
+}
+
+/// Starts the one thread that runs every job in `registered()`.  Their schedules and results are
+/// kept in the `jobs` table, so a restart carries on where the last run left off, and each run
+/// takes a lease on its row first, so a job never runs twice at once even with several servers
+/// on the same database.
+pub fn spawn_runner(mut context: JobContext) {
+    let owner = uuid::Uuid::new_v4().to_simple().to_string();
+    let jobs = registered();
+    thread::spawn(move || {
+        if let Err(e) = register_all(&jobs, &context.pool) {
+            error!("couldn't register jobs: {}", e);
+        }
+        loop {
+            for job in jobs.iter() {
+                let span = info_span!("job", name = job.name);
+                let _entered = span.enter();
+                if let Err(e) = run_if_due(job, &owner, &mut context) {
+                    error!("{}", e);
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+fn register_all(jobs: &[Job], pool: &DbPool) -> Result<(), JobError> {
+    let conn = pool.get()?;
+    let now = Utc::now().naive_utc();
+    for job in jobs.iter() {
+        job_actions::register(job.name, job.schedule, next_run(job.schedule, now), &conn)?;
+    }
+    job_actions::delete_unregistered(&jobs.iter().map(|job| job.name).collect::<Vec<_>>(), &conn)?;
+    Ok(())
+}
+
+fn run_if_due(job: &Job, owner: &str, context: &mut JobContext) -> Result<(), JobError> {
+    // the connection goes back to the pool while the job runs, it may want its own
+    let claimed = {
+        let conn = context.pool.get()?;
+        job_actions::claim(job.name, owner, Utc::now().naive_utc(), lease(), &conn)?
+    };
+    if !claimed {
+        return Ok(());
+    }
+    // a panic would take the runner thread, and every job with it, down
+    let result = panic::catch_unwind(AssertUnwindSafe(|| (job.run)(context))).unwrap_or_else(|panic| Err(JobError::Panicked(panic_message(panic))));
+    let now = Utc::now().naive_utc();
+    let scheduled = next_run(job.schedule, now);
+    let conn = context.pool.get()?;
+    metrics::observe_job(job.name, result.is_ok());
+    match result {
+        Ok(done) => {
+            if done > 0 {
+                info!(done, "job finished");
+            }
+            job_actions::mark_succeeded(job.name, owner, now, scheduled, &conn)?;
+        }
+        Err(e) => {
+            let attempts = job_actions::find(job.name, &conn)?.map_or(0, |job| job.attempts) + 1;
+            let retry_at = if attempts < MAX_ATTEMPTS {
+                scheduled.min(now + retry_delay(attempts))
+            } else {
+                scheduled
+            };
+            warn!(attempts, "job failed: {}", e);
+            job_actions::mark_failed(job.name, owner, e.to_string(), attempts, now, retry_at, &conn)?;
+        }
+    }
+    Ok(())
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => panic.downcast_ref::<&str>().map_or_else(|| "unknown panic".to_string(), |message| message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailer::MailError;
+    use crate::storage::MemoryStorage;
+    use std::sync::Arc;
+
+    const NAME: &str = "test_job";
+    const SCHEDULE: &str = "0 0 * * * *";
+
+    struct NoMailer;
+
+    impl Mailer for NoMailer {
+        fn send(&mut self, _to: &str, _subject: &str, _body: &str) -> Result<(), MailError> {
+            Ok(())
+        }
+    }
+
+    fn context() -> JobContext {
+        JobContext {
+            pool: crate::test_pool(),
+            mailer: Box::new(NoMailer),
+            storage: Arc::new(MemoryStorage::default()),
+        }
+    }
+
+    fn succeeds(_context: &mut JobContext) -> Result<usize, JobError> {
+        Ok(1)
+    }
+
+    fn fails(_context: &mut JobContext) -> Result<usize, JobError> {
+        Err(JobError::Database(diesel::result::Error::NotFound))
+    }
+
+    fn panics(_context: &mut JobContext) -> Result<usize, JobError> {
+        panic!("out of cheese")
+    }
+
+    fn job(run: fn(&mut JobContext) -> Result<usize, JobError>) -> Job {
+        Job {
+            name: NAME,
+            schedule: SCHEDULE,
+            run,
+        }
+    }
+
+    fn run(run: fn(&mut JobContext) -> Result<usize, JobError>, context: &mut JobContext) {
+        if let Err(e) = run_if_due(&job(run), "a", context) {
+            panic!("{}", e);
+        }
+    }
+
+    fn due(context: &JobContext) -> NaiveDateTime {
+        let now = Utc::now().naive_utc();
+        let conn = context.pool.get().unwrap();
+        job_actions::register(NAME, SCHEDULE, now, &conn).unwrap();
+        job_actions::trigger(NAME, now - Duration::minutes(1), &conn).unwrap();
+        now
+    }
+
+    fn row(context: &JobContext) -> crate::models::Job {
+        job_actions::find(NAME, &context.pool.get().unwrap()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn a_due_job_is_claimed_by_one_runner_at_a_time() {
+        let context = context();
+        let now = due(&context);
+        let conn = context.pool.get().unwrap();
+        assert!(job_actions::claim(NAME, "a", now, lease(), &conn).unwrap());
+        assert!(!job_actions::claim(NAME, "a", now, lease(), &conn).unwrap());
+        assert!(!job_actions::claim(NAME, "b", now, lease(), &conn).unwrap());
+        drop(conn);
+        assert_eq!(row(&context).lease_owner.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn a_job_that_is_not_due_is_not_claimed() {
+        let context = context();
+        let now = Utc::now().naive_utc();
+        let conn = context.pool.get().unwrap();
+        job_actions::register(NAME, SCHEDULE, now + Duration::minutes(1), &conn).unwrap();
+        assert!(!job_actions::claim(NAME, "a", now, lease(), &conn).unwrap());
+    }
+
+    #[test]
+    fn an_expired_lease_can_be_taken_over() {
+        let context = context();
+        let now = due(&context);
+        let conn = context.pool.get().unwrap();
+        assert!(job_actions::claim(NAME, "a", now, lease(), &conn).unwrap());
+        assert!(!job_actions::claim(NAME, "b", now + lease(), lease(), &conn).unwrap());
+        assert!(job_actions::claim(NAME, "b", now + lease() + Duration::seconds(1), lease(), &conn).unwrap());
+        drop(conn);
+        assert_eq!(row(&context).lease_owner.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn the_retry_delay_doubles_up_to_an_hour() {
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(3), Duration::seconds(120));
+        assert_eq!(retry_delay(7), Duration::seconds(1920));
+        assert_eq!(retry_delay(8), Duration::hours(1));
+        assert_eq!(retry_delay(100), Duration::hours(1));
+    }
+
+    #[test]
+    fn a_failed_run_is_retried_sooner_than_its_schedule() {
+        let mut context = context();
+        let before = due(&context);
+        run(fails, &mut context);
+        let failed = row(&context);
+        assert_eq!(failed.attempts, 1);
+        assert!(failed.last_error.is_some());
+        assert!(failed.lease_owner.is_none() && failed.date_lease_expires.is_none());
+        assert!(failed.date_next_run >= before + retry_delay(1));
+        assert!(failed.date_next_run <= Utc::now().naive_utc() + retry_delay(1));
+    }
+
+    #[test]
+    fn a_job_that_keeps_failing_waits_for_its_schedule() {
+        let mut context = context();
+        for attempts in 1..=MAX_ATTEMPTS {
+            due(&context);
+            run(fails, &mut context);
+            assert_eq!(row(&context).attempts, attempts);
+        }
+        let failed = row(&context);
+        assert_eq!(failed.date_next_run, next_run(SCHEDULE, failed.date_last_failed.unwrap()));
+    }
+
+    #[test]
+    fn a_panicking_job_is_a_failed_run_and_the_next_success_clears_it() {
+        let mut context = context();
+        due(&context);
+        run(panics, &mut context);
+        let failed = row(&context);
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("panicked: out of cheese"));
+        assert!(failed.lease_owner.is_none());
+
+        due(&context);
+        run(succeeds, &mut context);
+        let succeeded = row(&context);
+        assert_eq!(succeeded.attempts, 0);
+        assert!(succeeded.last_error.is_none());
+        assert!(succeeded.date_last_succeeded.is_some());
+        assert!(succeeded.date_next_run > succeeded.date_last_succeeded.unwrap());
+    }
+
+    #[test]
+    fn a_job_held_by_another_runner_is_left_alone() {
+        let mut context = context();
+        let now = due(&context);
+        assert!(job_actions::claim(NAME, "b", now, lease(), &context.pool.get().unwrap()).unwrap());
+        run(panics, &mut context);
+        let held = row(&context);
+        assert_eq!(held.attempts, 0);
+        assert_eq!(held.lease_owner.as_deref(), Some("b"));
+    }
+}
@@ -0,0 +1,149 @@
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate tracing;
+
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+
+pub mod account_actions;
+pub mod attachment_actions;
+pub mod audit_actions;
+pub mod auth;
+pub mod broadcaster;
+pub mod calendar;
+pub mod calendar_actions;
+pub mod comment_actions;
+pub mod event_actions;
+pub mod export_actions;
+pub mod gift_idea_actions;
+pub mod group_actions;
+pub mod job_actions;
+pub mod jobs;
+pub mod link_metadata;
+pub mod mailer;
+pub mod metrics;
+pub mod models;
+pub mod notification_actions;
+pub mod notifications;
+pub mod occasions;
+pub mod openapi;
+pub mod schema;
+pub mod storage;
+pub mod telemetry;
+pub mod token_actions;
+pub mod trash;
+pub mod user_actions;
+pub mod user_date_actions;
+pub mod wishlist;
+
+pub type DbPool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+
+embed_migrations!("../migrations");
+
+/// Builds the connection pool for `DATABASE_URL`.
+pub fn pool_from_env() -> DbPool {
+    let connspec = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+    let manager = ConnectionManager::<SqliteConnection>::new(connspec);
+
+    // let conn = SqliteConnection::establish(db_url).unwrap();
+    // conn.batch_execute(
+    //     "PRAGMA synchronous = NORMAL; PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;",
+    // )
+    // .unwrap();
+
+    r2d2::Pool::builder().max_size(1).build(manager).expect("Failed to create pool.")
+}
+
+/// Applies any migrations the database hasn't had yet, writing each one's name to `out`.
+pub fn run_migrations(conn: &SqliteConnection, out: &mut dyn std::io::Write) -> Result<(), diesel_migrations::RunMigrationsError> {
+    embedded_migrations::run_with_output(conn, out)
+}
+
+// the versions of the migrations above, written out by build.rs
+include!(concat!(env!("OUT_DIR"), "/migration_versions.rs"));
+
+/// Whether this build has migrations the database hasn't had yet.  It goes by the migrations built
+/// into the server, so it doesn't matter which directory the server was started from.
+pub fn has_pending_migrations(conn: &SqliteConnection) -> Result<bool, diesel_migrations::RunMigrationsError> {
+    use diesel_migrations::MigrationConnection;
+
+    let already_run = conn.previously_run_migration_versions()?;
+    Ok(MIGRATION_VERSIONS.iter().any(|version| !already_run.contains(*version)))
+}
+
+// The test helpers below are compiled in always: the server binary's handler tests use them too,
+// and they can't see anything behind the library's cfg(test).
+
+/// A new in-memory database with the migrations applied, for tests.
+#[doc(hidden)]
+pub fn test_connection() -> SqliteConnection {
+    let conn = SqliteConnection::establish(":memory:").expect("in-memory database");
+    apply_test_migrations(&conn);
+    conn
+}
+
+/// A pool over a single in-memory database with the migrations applied, for tests of code that
+/// takes the pool.  It only ever has the one connection, so the database lives as long as it does.
+#[doc(hidden)]
+pub fn test_pool() -> DbPool {
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+        .expect("in-memory pool");
+    apply_test_migrations(&pool.get().expect("pooled connection"));
+    pool
+}
+
+fn apply_test_migrations(conn: &SqliteConnection) {
+    use diesel::connection::SimpleConnection;
+
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../migrations");
+    let mut migrations = std::fs::read_dir(&dir)
+        .expect("migrations directory")
+        .map(|entry| entry.expect("migration").path())
+        .collect::<Vec<_>>();
+    migrations.sort();
+    for migration in migrations {
+        // its SQL doesn't parse, and nothing uses the table it was meant to make
+        if migration.ends_with("2020-05-16-165145_create_oauth_account") {
+            continue;
+        }
+        let sql = std::fs::read_to_string(migration.join("up.sql")).expect("up.sql");
+        conn.batch_execute(&sql).unwrap_or_else(|e| panic!("{}: {}", migration.display(), e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_migration_directory_is_built_in() {
+        let count = std::fs::read_dir(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../migrations")).unwrap().count();
+        assert_eq!(MIGRATION_VERSIONS.len(), count);
+        assert_eq!(MIGRATION_VERSIONS[0], "20200427152324");
+    }
+
+    #[test]
+    fn migrations_are_pending_until_the_database_has_every_built_in_one() {
+        use diesel_migrations::MigrationConnection;
+
+        let conn = test_connection();
+        diesel_migrations::setup_database(&conn).unwrap();
+        let (last, rest) = MIGRATION_VERSIONS.split_last().unwrap();
+        for version in rest {
+            conn.insert_new_migration(version).unwrap();
+        }
+        assert!(has_pending_migrations(&conn).unwrap());
+
+        conn.insert_new_migration(last).unwrap();
+        assert!(!has_pending_migrations(&conn).unwrap());
+    }
+}
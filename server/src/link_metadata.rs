@@ -0,0 +1,398 @@
+use actix_connect::{Connect as TcpConnect, TcpConnector};
+use actix_service::{apply_fn, Service};
+use actix_web::client::{Client, Connector};
+use actix_web::http::Uri;
+use actix_web::web;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use url::Url;
+
+pub const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug)]
+pub enum LinkMetadataError {
+    InvalidUrl(String),
+    Forbidden(String),
+    Fetch(String),
+}
+
+impl fmt::Display for LinkMetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LinkMetadataError::InvalidUrl(msg) => write!(f, "invalid url: {}", msg),
+            LinkMetadataError::Forbidden(msg) => write!(f, "forbidden url: {}", msg),
+            LinkMetadataError::Fetch(msg) => write!(f, "could not fetch url: {}", msg),
+        }
+    }
+}
+
+pub struct LinkMetadataFetcher {
+    pub timeout: Duration,
+    pub max_body_size: usize,
+    pub allow_private_addresses: bool,
+}
+
+impl Default for LinkMetadataFetcher {
+    fn default() -> Self {
+        LinkMetadataFetcher {
+            timeout: TIMEOUT,
+            max_body_size: MAX_BODY_SIZE,
+            allow_private_addresses: false,
+        }
+    }
+}
+
+impl LinkMetadataFetcher {
+    pub async fn fetch(&self, url: &str) -> Result<shared::LinkMetadataResponseBody, LinkMetadataError> {
+        let mut url = Url::parse(url).map_err(|e| LinkMetadataError::InvalidUrl(e.to_string()))?;
+
+        // redirects are followed by hand so that every hop goes through the same address checks
+        for _ in 0..=MAX_REDIRECTS {
+            let addresses = self.check_url(&url).await?;
+            let mut response = self
+                .client(addresses)
+                .get(url.as_str())
+                .header("Accept", "text/html,application/xhtml+xml")
+                .header("User-Agent", "rusty-gifts link preview")
+                .send()
+                .await
+                .map_err(|e| LinkMetadataError::Fetch(e.to_string()))?;
+
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get("location")
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| LinkMetadataError::Fetch(format!("{} redirected without a location", url)))?;
+                url = url.join(location).map_err(|e| LinkMetadataError::InvalidUrl(e.to_string()))?;
+                continue;
+            }
+
+            if !response.status().is_success() {
+                return Err(LinkMetadataError::Fetch(format!("{} responded with {}", url, response.status())));
+            }
+
+            let content_type = response.headers().get("content-type").and_then(|value| value.to_str().ok()).unwrap_or("").to_string();
+            if !content_type.contains("html") {
+                return Err(LinkMetadataError::Fetch(format!("unsupported content type: {}", content_type)));
+            }
+
+            let body = actix_rt::time::timeout(self.timeout, response.body().limit(self.max_body_size))
+                .await
+                .map_err(|_| LinkMetadataError::Fetch(format!("timed out reading {}", url)))?
+                .map_err(|e| LinkMetadataError::Fetch(e.to_string()))?;
+            return Ok(extract(&String::from_utf8_lossy(&body), &url));
+        }
+        Err(LinkMetadataError::Fetch("too many redirects".to_string()))
+    }
+
+    /// A client that only ever connects to `addresses`, the ones `check_url` vetted, rather than
+    /// looking the host up again: a dns server could answer differently the second time.
+    fn client(&self, addresses: Vec<SocketAddr>) -> Client {
+        let tcp = apply_fn(TcpConnector::new(), move |connect: TcpConnect<Uri>, tcp: &mut TcpConnector<Uri>| {
+            tcp.call(connect.set_addrs(addresses.clone()))
+        });
+        Client::build()
+            .timeout(self.timeout)
+            .connector(Connector::new().connector(tcp).timeout(self.timeout).finish())
+            .finish()
+    }
+
+    /// Resolves `url`'s host, refusing it unless every address it has is public.
+    async fn check_url(&self, url: &Url) -> Result<Vec<SocketAddr>, LinkMetadataError> {
+        match url.scheme() {
+            "http" | "https" => (),
+            scheme => return Err(LinkMetadataError::Forbidden(format!("unsupported scheme: {}", scheme))),
+        }
+        let host = url.host_str().ok_or_else(|| LinkMetadataError::InvalidUrl(format!("{} has no host", url)))?.to_string();
+        let lookup = url.clone();
+        let addresses = web::block(move || lookup.socket_addrs(|| None))
+            .await
+            .map_err(|e| LinkMetadataError::Fetch(format!("could not resolve {}: {}", host, e)))?;
+        if addresses.is_empty() {
+            return Err(LinkMetadataError::Fetch(format!("could not resolve {}", host)));
+        }
+        if !self.allow_private_addresses && addresses.iter().any(|address| !is_public_address(&address.ip())) {
+            return Err(LinkMetadataError::Forbidden(format!("{} resolves to a non-public address", host)));
+        }
+        Ok(addresses)
+    }
+}
+
+pub fn is_public_address(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || octets[0] == 0
+                || octets[0] >= 240
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // ipv4-mapped (::ffff:a.b.c.d) and nat64 (64:ff9b::a.b.c.d) addresses reach the ipv4 one
+            let mapped = segments[..5].iter().all(|s| *s == 0) && segments[5] == 0xffff;
+            let nat64 = segments[0] == 0x64 && segments[1] == 0xff9b && segments[2..6].iter().all(|s| *s == 0);
+            if mapped || nat64 {
+                let embedded = Ipv4Addr::new((segments[6] >> 8) as u8, segments[6] as u8, (segments[7] >> 8) as u8, segments[7] as u8);
+                return is_public_address(&IpAddr::V4(embedded));
+            }
+            !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || (segments[0] & 0xfe00) == 0xfc00 || (segments[0] & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+pub fn extract(html: &str, base: &Url) -> shared::LinkMetadataResponseBody {
+    let document = Html::parse_document(html);
+    let mut metadata = shared::LinkMetadataResponseBody {
+        url: base.to_string(),
+        ..shared::LinkMetadataResponseBody::default()
+    };
+
+    // JSON-LD carries the most reliable product data, so it wins over the OpenGraph tags
+    let ld_selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    for script in document.select(&ld_selector) {
+        let text: String = script.text().collect();
+        if let Some(product) = serde_json::from_str::<Value>(&text).ok().as_ref().and_then(find_product) {
+            apply_product(product, &mut metadata);
+            break;
+        }
+    }
+
+    let meta_selector = Selector::parse("meta[content]").unwrap();
+    for meta in document.select(&meta_selector) {
+        let element = meta.value();
+        let key = match element.attr("property").or_else(|| element.attr("name")) {
+            Some(key) => key.to_lowercase(),
+            None => continue,
+        };
+        let content = element.attr("content").map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+        let field = match key.as_str() {
+            "og:title" | "twitter:title" => &mut metadata.title,
+            "og:description" | "twitter:description" | "description" => &mut metadata.description,
+            "og:image" | "og:image:secure_url" | "twitter:image" => &mut metadata.image,
+            "product:price:amount" | "og:price:amount" => &mut metadata.price,
+            "product:price:currency" | "og:price:currency" => &mut metadata.currency,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = content;
+        }
+    }
+
+    if metadata.title.is_none() {
+        let title_selector = Selector::parse("title").unwrap();
+        metadata.title = document
+            .select(&title_selector)
+            .next()
+            .map(|title| title.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty());
+    }
+
+    metadata.image = metadata.image.and_then(|image| base.join(&image).ok()).map(|image| image.to_string());
+    metadata
+}
+
+fn is_product(value: &Value) -> bool {
+    match value.get("@type") {
+        Some(Value::String(t)) => t == "Product",
+        Some(Value::Array(types)) => types.iter().any(|t| t == "Product"),
+        _ => false,
+    }
+}
+
+fn find_product(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(values) => values.iter().find_map(find_product),
+        Value::Object(_) if is_product(value) => Some(value),
+        Value::Object(object) => object.get("@graph").and_then(find_product),
+        _ => None,
+    }
+}
+
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(values) => values.iter().find_map(value_to_string),
+        Value::Object(object) => object.get("url").and_then(value_to_string),
+        _ => None,
+    }
+}
+
+fn apply_product(product: &Value, metadata: &mut shared::LinkMetadataResponseBody) {
+    metadata.title = product.get("name").and_then(value_to_string);
+    metadata.description = product.get("description").and_then(value_to_string);
+    metadata.image = product.get("image").and_then(value_to_string);
+
+    let offer = match product.get("offers") {
+        Some(Value::Array(offers)) => offers.first(),
+        offer => offer,
+    };
+    if let Some(offer) = offer {
+        metadata.price = offer.get("price").or_else(|| offer.get("lowPrice")).and_then(value_to_string);
+        metadata.currency = offer.get("priceCurrency").and_then(value_to_string);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpResponse, HttpServer};
+
+    const PRODUCT_PAGE: &str = r#"<html><head>
+        <title>Fallback title</title>
+        <meta property="og:title" content="Blue Scarf" />
+        <meta property="og:image" content="/images/scarf.png" />
+        <meta property="product:price:amount" content="19.99" />
+        <meta property="product:price:currency" content="USD" />
+        </head><body></body></html>"#;
+
+    const JSON_LD_PAGE: &str = r#"<html><head>
+        <meta property="og:title" content="Ignored in favour of JSON-LD" />
+        <script type="application/ld+json">
+        {"@context": "https://schema.org", "@graph": [
+            {"@type": "WebSite", "name": "Shop"},
+            {"@type": "Product", "name": "Red Mittens", "image": ["https://cdn.example.com/mittens.jpg"],
+             "offers": [{"@type": "Offer", "price": 12.5, "priceCurrency": "EUR"}]}
+        ]}
+        </script>
+        </head><body></body></html>"#;
+
+    fn base() -> Url {
+        Url::parse("https://shop.example.com/items/1").unwrap()
+    }
+
+    #[test]
+    fn extracts_open_graph_metadata() {
+        let metadata = extract(PRODUCT_PAGE, &base());
+        assert_eq!(metadata.title.as_deref(), Some("Blue Scarf"));
+        assert_eq!(metadata.image.as_deref(), Some("https://shop.example.com/images/scarf.png"));
+        assert_eq!(metadata.price.as_deref(), Some("19.99"));
+        assert_eq!(metadata.currency.as_deref(), Some("USD"));
+    }
+
+    #[test]
+    fn prefers_json_ld_product_metadata() {
+        let metadata = extract(JSON_LD_PAGE, &base());
+        assert_eq!(metadata.title.as_deref(), Some("Red Mittens"));
+        assert_eq!(metadata.image.as_deref(), Some("https://cdn.example.com/mittens.jpg"));
+        assert_eq!(metadata.price.as_deref(), Some("12.5"));
+        assert_eq!(metadata.currency.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn falls_back_to_document_title() {
+        let metadata = extract("<html><head><title> Plain page </title></head></html>", &base());
+        assert_eq!(metadata.title.as_deref(), Some("Plain page"));
+        assert_eq!(metadata.price, None);
+    }
+
+    #[test]
+    fn rejects_non_public_addresses() {
+        for address in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "192.168.0.10",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_address(&address.parse().unwrap()), "{} should not be public", address);
+        }
+        for address in &["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946", "64:ff9b::5db8:d822"] {
+            assert!(is_public_address(&address.parse().unwrap()), "{} should be public", address);
+        }
+    }
+
+    fn start_fixture_server() -> (actix_web::dev::Server, String) {
+        let server = HttpServer::new(|| {
+            App::new()
+                .route("/product", web::get().to(|| HttpResponse::Ok().content_type("text/html; charset=utf-8").body(PRODUCT_PAGE)))
+                .route("/json_ld", web::get().to(|| HttpResponse::Ok().content_type("text/html").body(JSON_LD_PAGE)))
+                .route("/redirect", web::get().to(|| HttpResponse::Found().header("location", "/product").finish()))
+                .route("/large", web::get().to(|| HttpResponse::Ok().content_type("text/html").body("x".repeat(64 * 1024))))
+                .route("/image", web::get().to(|| HttpResponse::Ok().content_type("image/png").body(vec![0u8; 16])))
+                .route(
+                    "/slow",
+                    web::get().to(|| async {
+                        actix_rt::time::delay_for(Duration::from_secs(3)).await;
+                        Ok::<_, actix_web::Error>(HttpResponse::Ok().content_type("text/html").body(PRODUCT_PAGE))
+                    }),
+                )
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let address = server.addrs()[0];
+        (server.run(), format!("http://{}", address))
+    }
+
+    fn fixture_fetcher() -> LinkMetadataFetcher {
+        LinkMetadataFetcher {
+            timeout: Duration::from_secs(1),
+            max_body_size: 16 * 1024,
+            allow_private_addresses: true,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn fetches_metadata_from_fixture_server() {
+        let (server, base_url) = start_fixture_server();
+        let fetcher = fixture_fetcher();
+
+        let metadata = fetcher.fetch(&format!("{}/product", base_url)).await.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Blue Scarf"));
+        assert_eq!(metadata.image, Some(format!("{}/images/scarf.png", base_url)));
+
+        let metadata = fetcher.fetch(&format!("{}/json_ld", base_url)).await.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Red Mittens"));
+
+        let metadata = fetcher.fetch(&format!("{}/redirect", base_url)).await.unwrap();
+        assert_eq!(metadata.url, format!("{}/product", base_url));
+
+        server.stop(true).await;
+    }
+
+    #[actix_rt::test]
+    async fn enforces_limits_against_fixture_server() {
+        let (server, base_url) = start_fixture_server();
+        let fetcher = fixture_fetcher();
+
+        assert!(matches!(fetcher.fetch(&format!("{}/large", base_url)).await, Err(LinkMetadataError::Fetch(_))));
+        assert!(matches!(fetcher.fetch(&format!("{}/slow", base_url)).await, Err(LinkMetadataError::Fetch(_))));
+        assert!(matches!(fetcher.fetch(&format!("{}/image", base_url)).await, Err(LinkMetadataError::Fetch(_))));
+        assert!(matches!(fetcher.fetch(&format!("{}/missing", base_url)).await, Err(LinkMetadataError::Fetch(_))));
+
+        let default_fetcher = LinkMetadataFetcher::default();
+        assert!(matches!(
+            default_fetcher.fetch(&format!("{}/product", base_url)).await,
+            Err(LinkMetadataError::Forbidden(_))
+        ));
+        assert!(matches!(default_fetcher.fetch("file:///etc/passwd").await, Err(LinkMetadataError::Forbidden(_))));
+        assert!(matches!(default_fetcher.fetch("not a url").await, Err(LinkMetadataError::InvalidUrl(_))));
+
+        server.stop(true).await;
+    }
+}
@@ -0,0 +1,123 @@
+use lettre::smtp::authentication::Credentials;
+use lettre::{SmtpClient, SmtpTransport, Transport};
+use lettre_email::EmailBuilder;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::telemetry;
+
+#[derive(Debug)]
+pub enum MailError {
+    Email(lettre_email::error::Error),
+    Smtp(lettre::smtp::error::Error),
+    Io(io::Error),
+    Config(String),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MailError::Email(e) => write!(f, "email error: {}", e),
+            MailError::Smtp(e) => write!(f, "smtp error: {}", e),
+            MailError::Io(e) => write!(f, "io error: {}", e),
+            MailError::Config(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<lettre_email::error::Error> for MailError {
+    fn from(e: lettre_email::error::Error) -> MailError {
+        MailError::Email(e)
+    }
+}
+
+impl From<lettre::smtp::error::Error> for MailError {
+    fn from(e: lettre::smtp::error::Error) -> MailError {
+        MailError::Smtp(e)
+    }
+}
+
+impl From<io::Error> for MailError {
+    fn from(e: io::Error) -> MailError {
+        MailError::Io(e)
+    }
+}
+
+/// Outgoing mail transport.  Picked at startup from `MAIL_TRANSPORT`: `smtp` for real delivery,
+/// `file` to drop each message into `MAIL_DIR`, or `log` (the default) to just log it.
+pub trait Mailer: Send {
+    fn send(&mut self, to: &str, subject: &str, body: &str) -> Result<(), MailError>;
+}
+
+/// Fails when the chosen transport can't be set up, e.g. `SMTP_HOST` is missing or doesn't
+/// resolve, so a bad configuration stops the server at startup with a message saying why.
+pub fn from_env() -> Result<Box<dyn Mailer>, MailError> {
+    let from = std::env::var("MAIL_FROM").unwrap_or_else(|_| "rusty-gifts@localhost".to_string());
+    match std::env::var("MAIL_TRANSPORT").as_ref().map(String::as_str) {
+        Ok("smtp") => {
+            let host = std::env::var("SMTP_HOST").map_err(|_| MailError::Config("MAIL_TRANSPORT is smtp but SMTP_HOST isn't set".to_string()))?;
+            let mut client = SmtpClient::new_simple(&host)?;
+            if let (Ok(username), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+                client = client.credentials(Credentials::new(username, password));
+            }
+            Ok(Box::new(SmtpMailer {
+                from,
+                transport: client.transport(),
+            }))
+        }
+        Ok("file") => {
+            let dir = std::env::var("MAIL_DIR").unwrap_or_else(|_| "./mail".to_string());
+            Ok(Box::new(FileMailer::new(from, dir.into())?))
+        }
+        _ => Ok(Box::new(LogMailer { from })),
+    }
+}
+
+pub struct SmtpMailer {
+    from: String,
+    transport: SmtpTransport,
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&mut self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        let email = EmailBuilder::new().to(to).from(self.from.as_str()).subject(subject).text(body).build()?;
+        self.transport.send(email.into())?;
+        Ok(())
+    }
+}
+
+pub struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: String, dir: PathBuf) -> io::Result<FileMailer> {
+        fs::create_dir_all(&dir)?;
+        Ok(FileMailer { from, dir })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&mut self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        let file_name = format!("{}_{}.eml", chrono::Utc::now().format("%Y%m%d%H%M%S"), uuid::Uuid::new_v4().to_simple());
+        let message = format!("From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}\r\n", self.from, to, subject, body);
+        fs::write(self.dir.join(file_name), message)?;
+        Ok(())
+    }
+}
+
+pub struct LogMailer {
+    from: String,
+}
+
+impl Mailer for LogMailer {
+    fn send(&mut self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        info!(from = %self.from, to = %telemetry::redact_email(to), subject, "mail");
+        // the body can hold a verification or reset link, whose token would let anyone reading the log in
+        debug!(to = %telemetry::redact_email(to), "mail body:\n{}", telemetry::redact_tokens(body));
+        Ok(())
+    }
+}
@@ -1,57 +1,256 @@
 #[macro_use]
-extern crate diesel;
-#[macro_use]
-extern crate log;
+extern crate tracing;
 
 use actix_files::{Files, NamedFile};
+use actix_multipart::Multipart;
+use actix_session::{CookieSession, Session};
+use actix_web::dev::Service;
 use actix_web::*;
 use diesel::prelude::*;
-use diesel::r2d2::{self, ConnectionManager};
-
-mod gift_idea_actions;
-mod models;
-mod schema;
-mod user_actions;
+use futures::{FutureExt, StreamExt};
+use std::sync::Mutex;
 
-pub type DbPool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+use server::{
+    account_actions, attachment_actions, audit_actions, auth, broadcaster, calendar, calendar_actions, comment_actions, event_actions, export_actions, gift_idea_actions,
+    group_actions, job_actions, jobs, link_metadata, mailer, metrics, models, notification_actions, notifications, occasions, openapi, storage, telemetry, token_actions, trash,
+    user_actions, user_date_actions, wishlist, DbPool,
+};
 
 #[post("users/add")]
-async fn add_user(pool: web::Data<DbPool>, form: web::Json<shared::RegisterRequestBody>) -> Result<HttpResponse, Error> {
+async fn add_user(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::RegisterRequestBody>) -> Result<HttpResponse, Error> {
     let new_user = form.into_inner().into();
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let user = web::block(move || user_actions::add(&new_user, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let user = telemetry::block(move || -> Result<models::User, diesel::result::Error> {
+        let user = user_actions::add(&new_user, None, &conn)?;
+        notifications::queue_email_verification(&user, &conn)?;
+        Ok(user)
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
-    Ok(HttpResponse::Ok().json(user))
+    metrics::USERS_REGISTERED.inc();
+    auth::login(&session, user.id)?;
+    Ok(HttpResponse::Ok().json(shared::LoggedUser::from(user)))
 }
 
 #[post("users/login")]
-async fn login(pool: web::Data<DbPool>, form: web::Json<shared::LoginRequestBody>) -> Result<HttpResponse, Error> {
+async fn login(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::LoginRequestBody>) -> Result<HttpResponse, Error> {
     let new_user = form.into_inner().into();
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let user = web::block(move || user_actions::login(&new_user, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let user = telemetry::block(move || user_actions::login(&new_user, &conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
-    Ok(HttpResponse::Ok().json(user))
+    if let Some(user) = &user {
+        auth::login(&session, user.id)?;
+    }
+    Ok(HttpResponse::Ok().json(user.map(shared::LoggedUser::from)))
 }
 
-#[patch("users/update")]
-async fn update_user(pool: web::Data<DbPool>, form: web::Json<models::NewUser>) -> Result<HttpResponse, Error> {
+#[post("users/logout")]
+async fn logout(session: Session) -> Result<HttpResponse, Error> {
+    auth::logout(&session);
+    Ok(HttpResponse::Ok().json(true))
+}
+
+#[post("users/verify_email/request")]
+async fn request_email_verification(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let user = web::block(move || user_actions::update(&form.into_inner(), &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    // None when there's no such user, Some(None) when they've asked too often
+    let queued = telemetry::block(move || -> Result<Option<Option<bool>>, diesel::result::Error> {
+        match user_actions::find_by_id(user_id, &conn)? {
+            Some(user) if user.email_verified => Ok(Some(Some(false))),
+            Some(user) if token_actions::is_rate_limited(user.id, token_actions::VERIFY_EMAIL, &conn)? => Ok(Some(None)),
+            Some(user) => {
+                notifications::queue_email_verification(&user, &conn)?;
+                Ok(Some(Some(true)))
+            }
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
-    Ok(HttpResponse::Ok().json(user))
+
+    match queued {
+        Some(Some(queued)) => Ok(HttpResponse::Ok().json(queued)),
+        Some(None) => Ok(HttpResponse::TooManyRequests().body("Too many verification emails requested, try again later")),
+        None => Ok(HttpResponse::NotFound().body(format!("No user found with id: {}", user_id))),
+    }
+}
+
+#[post("users/verify_email/redeem")]
+async fn verify_email(pool: web::Data<DbPool>, form: web::Json<shared::VerifyEmailRequestBody>) -> Result<HttpResponse, Error> {
+    let token = form.into_inner().token;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let verified = telemetry::block(move || -> Result<bool, diesel::result::Error> {
+        match token_actions::redeem(&token, token_actions::VERIFY_EMAIL, &conn)? {
+            Some(user) => {
+                user_actions::mark_email_verified(user.id, Some(user.id), &conn)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if verified {
+        Ok(HttpResponse::Ok().json(verified))
+    } else {
+        Ok(HttpResponse::BadRequest().body("Invalid or expired token"))
+    }
+}
+
+// always answers the same way so it can't be used to find out who has an account
+#[post("users/reset_password/request")]
+async fn request_password_reset(pool: web::Data<DbPool>, form: web::Json<shared::ForgotPasswordRequestBody>) -> Result<HttpResponse, Error> {
+    let email = form.into_inner().email.trim().to_string();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    telemetry::block(move || -> Result<(), diesel::result::Error> {
+        if let Some(user) = user_actions::find_by_email(email, &conn)? {
+            if !token_actions::is_rate_limited(user.id, token_actions::RESET_PASSWORD, &conn)? {
+                notifications::queue_password_reset(&user, &conn)?;
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(true))
+}
+
+#[post("users/reset_password/redeem")]
+async fn reset_password(pool: web::Data<DbPool>, form: web::Json<shared::ResetPasswordRequestBody>) -> Result<HttpResponse, Error> {
+    let form = form.into_inner();
+    if form.password.len() < user_actions::MIN_PASSWORD_LENGTH {
+        return Ok(HttpResponse::BadRequest().body(format!("Password must be at least {} characters", user_actions::MIN_PASSWORD_LENGTH)));
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let reset = telemetry::block(move || -> Result<bool, diesel::result::Error> {
+        match token_actions::redeem(&form.token, token_actions::RESET_PASSWORD, &conn)? {
+            Some(user) => {
+                user_actions::set_password(user.id, form.password, Some(user.id), &conn)?;
+                // following the emailed link proves the address works too
+                user_actions::mark_email_verified(user.id, Some(user.id), &conn)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if reset {
+        Ok(HttpResponse::Ok().json(reset))
+    } else {
+        Ok(HttpResponse::BadRequest().body("Invalid or expired token"))
+    }
+}
+
+#[post("users/change_password")]
+async fn change_password(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::ChangePasswordRequestBody>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let form = form.into_inner();
+    if form.new_password.len() < user_actions::MIN_PASSWORD_LENGTH {
+        return Ok(HttpResponse::BadRequest().body(format!("Password must be at least {} characters", user_actions::MIN_PASSWORD_LENGTH)));
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let changed = telemetry::block(move || account_actions::change_password(user_id, &form.current_password, form.new_password, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+
+    if changed {
+        Ok(HttpResponse::Ok().json(changed))
+    } else {
+        Ok(HttpResponse::Forbidden().body("Current password is incorrect"))
+    }
+}
+
+#[post("users/delete")]
+async fn delete_account(
+    pool: web::Data<DbPool>,
+    session: Session,
+    storage: web::Data<storage::AttachmentStorage>,
+    broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>,
+    form: web::Json<shared::DeleteAccountRequestBody>,
+) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let password = form.into_inner().password;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let storage = storage.get_ref().clone();
+    let deleted = telemetry::block(move || -> Result<Option<account_actions::DeletedAccount>, attachment_actions::AttachmentError> {
+        match account_actions::check_password(user_id, &password, &conn)? {
+            Some(user) => Ok(Some(account_actions::delete(user.id, storage.as_ref(), &conn)?)),
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    match deleted {
+        Some(deleted) => {
+            for gift in deleted.deleted_gift_ideas {
+                broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Deleted(gift.id), gift.owner_id, gift.recipient_user_id).await;
+            }
+            for gift in deleted.released_gift_ideas {
+                broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Unreserved(gift.clone()), gift.owner_id, gift.recipient_user_id).await;
+            }
+            auth::logout(&session);
+            Ok(HttpResponse::Ok().json(true))
+        }
+        None => Ok(HttpResponse::Forbidden().body("Password is incorrect")),
+    }
+}
+
+#[patch("users/update")]
+async fn update_user(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::UpdateProfileRequest>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let changes: models::UserProfileChangeset = form.into_inner().into();
+    if let Some(email) = &changes.email {
+        if email.is_empty() || !email.contains('@') {
+            return Ok(HttpResponse::BadRequest().body(format!("Invalid email address: {}", email)));
+        }
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let user = telemetry::block(move || user_actions::update_profile(user_id, changes, Some(user_id), &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(shared::LoggedUser::from(user))),
+        None => Ok(HttpResponse::Conflict().body("That email address is already in use")),
+    }
 }
 
 #[get("users/find_all")]
 async fn find_all_users(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let users = web::block(move || user_actions::find_all(&conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let users = telemetry::block(move || user_actions::find_all(&conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
 
@@ -67,13 +266,13 @@ async fn find_all_users(pool: web::Data<DbPool>) -> Result<HttpResponse, Error>
 async fn find_user_by_id(pool: web::Data<DbPool>, user_id: web::Path<i32>) -> Result<HttpResponse, Error> {
     let conn = pool.get().expect("couldn't get db connection from pool");
     let user_uid = user_id.into_inner();
-    let user = web::block(move || user_actions::find_by_id(user_uid, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let user = telemetry::block(move || user_actions::find_by_id(user_uid, &conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
 
     if let Some(user) = user {
-        Ok(HttpResponse::Ok().json(user))
+        Ok(HttpResponse::Ok().json(shared::MinimalUserInfo::from(user)))
     } else {
         let res = HttpResponse::NotFound().body(format!("No user found with uid: {}", user_uid));
         Ok(res)
@@ -84,87 +283,362 @@ async fn find_user_by_id(pool: web::Data<DbPool>, user_id: web::Path<i32>) -> Re
 async fn find_user_by_email(pool: web::Data<DbPool>, user_email: web::Path<String>) -> Result<HttpResponse, Error> {
     let conn = pool.get().expect("couldn't get db connection from pool");
     let email = user_email.to_owned();
-    let user = web::block(move || user_actions::find_by_email(email, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let user = telemetry::block(move || user_actions::find_by_email(email, &conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
 
     if let Some(user) = user {
-        Ok(HttpResponse::Ok().json(user))
+        Ok(HttpResponse::Ok().json(shared::MinimalUserInfo::from(user)))
     } else {
         let res = HttpResponse::NotFound().body(format!("No user found with email: {}", user_email.to_owned()));
         Ok(res)
     }
 }
 
+#[get("users/dates/find_by_user/{user_id}")]
+async fn find_user_dates(pool: web::Data<DbPool>, session: Session, user_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let viewer_id = auth::current_user_id(&session, &pool).await?;
+    let u_id = user_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let dates = telemetry::block(move || user_date_actions::find_by_user(u_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    let today = chrono::Utc::now().naive_utc().date();
+    Ok(HttpResponse::Ok().json(dates.into_iter().map(|date| date.into_body(viewer_id, today)).collect::<Vec<_>>()))
+}
+
+#[post("users/dates/add")]
+async fn add_user_date(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::UserDateRequest>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let today = chrono::Utc::now().naive_utc().date();
+    if let Some(problem) = occasions::validate(&form, today) {
+        return Ok(HttpResponse::BadRequest().body(problem));
+    }
+    let new_date = models::NewUserDate::new(user_id, &form);
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let date = telemetry::block(move || user_date_actions::add(&new_date, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(date.into_body(user_id, today)))
+}
+
+#[put("users/dates/update/{date_id}")]
+async fn update_user_date(pool: web::Data<DbPool>, session: Session, date_id: web::Path<i32>, form: web::Json<shared::UserDateRequest>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let d_id = date_id.into_inner();
+    let today = chrono::Utc::now().naive_utc().date();
+    if let Some(problem) = occasions::validate(&form, today) {
+        return Ok(HttpResponse::BadRequest().body(problem));
+    }
+    let changes = models::NewUserDate::new(user_id, &form);
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let date = telemetry::block(move || user_date_actions::update(d_id, user_id, &changes, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    match date {
+        Some(date) => Ok(HttpResponse::Ok().json(date.into_body(user_id, today))),
+        None => Ok(HttpResponse::NotFound().body(format!("No date of yours found with id: {}", d_id))),
+    }
+}
+
+#[delete("users/dates/delete/{date_id}")]
+async fn delete_user_date(pool: web::Data<DbPool>, session: Session, date_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let d_id = date_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let deleted = telemetry::block(move || user_date_actions::delete(d_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    if deleted {
+        Ok(HttpResponse::Ok().json(deleted))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No date of yours found with id: {}", d_id)))
+    }
+}
+
 #[post("gifts/add")]
-async fn add_gift(pool: web::Data<DbPool>, form: web::Json<shared::GiftIdeaRequestBody>) -> Result<HttpResponse, Error> {
+async fn add_gift(
+    pool: web::Data<DbPool>,
+    session: Session,
+    broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>,
+    form: web::Json<shared::GiftIdeaRequestBody>,
+) -> Result<HttpResponse, Error> {
     let new_gift = form.into_inner().into();
+    let added_by_user_id = auth::current_user_id(&session, &pool).await.ok();
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let gift = web::block(move || gift_idea_actions::add(&new_gift, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let gift = telemetry::block(move || -> Result<models::GiftIdea, diesel::result::Error> {
+        let gift = gift_idea_actions::add(&new_gift, added_by_user_id, &conn)?;
+        notifications::queue_new_gift_idea(&gift, &conn)?;
+        Ok(gift)
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
+    metrics::GIFT_IDEAS_ADDED.inc();
+    broadcast(
+        &pool,
+        &broadcaster,
+        shared::GiftIdeaEvent::Created(gift.clone().into()),
+        gift.owner_id,
+        gift.recipient_user_id,
+    )
+    .await;
     Ok(HttpResponse::Ok().json(gift))
 }
 
-#[patch("/gifts/update")]
-async fn update_gift(pool: web::Data<DbPool>, form: web::Json<models::GiftIdea>) -> Result<HttpResponse, Error> {
+#[post("gifts/import_wishlist/preview")]
+async fn preview_wishlist(pool: web::Data<DbPool>, session: Session, payload: web::Payload) -> Result<HttpResponse, Error> {
+    auth::current_user_id(&session, &pool).await?;
+    let request = read_json::<shared::WishlistPreviewRequest>(payload, wishlist::MAX_TEXT_SIZE).await?;
+    let mut rows = wishlist::parse(request.format, &request.text);
+    if rows.len() > wishlist::MAX_ROWS {
+        return Ok(HttpResponse::BadRequest().body(format!("Wishlists are limited to {} gift ideas at a time", wishlist::MAX_ROWS)));
+    }
+    let recipient_user_id = request.recipient_user_id;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let existing = telemetry::block(move || gift_idea_actions::find_by_owner_or_recipient(recipient_user_id, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+    wishlist::validate(&mut rows, recipient_user_id, &existing);
+    Ok(HttpResponse::Ok().json(rows))
+}
+
+#[post("gifts/import_wishlist")]
+async fn import_wishlist(pool: web::Data<DbPool>, session: Session, broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>, payload: web::Payload) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let request = read_json::<shared::WishlistImportRequest>(payload, wishlist::MAX_TEXT_SIZE).await?;
+    if request.rows.len() > wishlist::MAX_ROWS {
+        return Ok(HttpResponse::BadRequest().body(format!("Wishlists are limited to {} gift ideas at a time", wishlist::MAX_ROWS)));
+    }
+    let recipient_user_id = request.recipient_user_id;
+    let mut rows = request.rows;
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let gift = web::block(move || gift_idea_actions::update(&form.into_inner(), &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    // the ideas created and the rows turned away
+    type Imported = (Vec<models::GiftIdea>, Vec<shared::WishlistRow>);
+    // checked again in case the list changed since the preview; bulk imports don't send emails
+    let imported = telemetry::block(move || -> Result<Option<Imported>, diesel::result::Error> {
+        if user_actions::find_by_id(recipient_user_id, &conn)?.is_none() {
+            return Ok(None);
+        }
+        let existing = gift_idea_actions::find_by_owner_or_recipient(recipient_user_id, &conn)?;
+        wishlist::validate(&mut rows, recipient_user_id, &existing);
+        let (valid, rejected): (Vec<_>, Vec<_>) = rows.into_iter().partition(|row| row.problems.is_empty());
+        let created = conn.transaction(|| {
+            valid
+                .iter()
+                .map(|row| gift_idea_actions::add(&wishlist::new_gift_idea(row, user_id, recipient_user_id), Some(user_id), &conn))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        Ok(Some((created, rejected)))
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
-    Ok(HttpResponse::Ok().json(gift))
+    let (created, rejected) = match imported {
+        Some(imported) => imported,
+        None => return Ok(HttpResponse::NotFound().body(format!("No user found with id: {}", recipient_user_id))),
+    };
+    metrics::GIFT_IDEAS_ADDED.inc_by(created.len() as i64);
+    for gift in created.iter() {
+        broadcast(
+            &pool,
+            &broadcaster,
+            shared::GiftIdeaEvent::Created(gift.clone().into()),
+            gift.owner_id,
+            gift.recipient_user_id,
+        )
+        .await;
+    }
+    Ok(HttpResponse::Ok().json(shared::WishlistImportResponseBody {
+        created: created.into_iter().map(Into::into).collect(),
+        rejected,
+    }))
+}
+
+#[patch("gifts/update/{gift_id}")]
+async fn update_gift_idea(
+    pool: web::Data<DbPool>,
+    session: Session,
+    broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>,
+    gift_id: web::Path<i32>,
+    form: web::Json<shared::UpdateGiftIdeaRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let g_id = gift_id.into_inner();
+    let changes: models::GiftIdeaChangeset = form.into_inner().into();
+    if changes.title.as_ref().is_some_and(|title| title.is_empty()) {
+        return Ok(HttpResponse::BadRequest().body("Title is required"));
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let gift = telemetry::block(move || gift_idea_actions::update_owned(g_id, user_id, &changes, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+
+    if let Some(gift) = gift {
+        broadcast(
+            &pool,
+            &broadcaster,
+            shared::GiftIdeaEvent::Updated(gift.clone().into()),
+            gift.owner_id,
+            gift.recipient_user_id,
+        )
+        .await;
+        Ok(HttpResponse::Ok().json(shared::GiftIdeaResponseBody::from(gift)))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No gift idea of yours found with id: {}", g_id)))
+    }
+}
+
+// signed in only, or anyone could use the server to fetch pages for them
+#[post("gifts/link_metadata")]
+async fn find_link_metadata(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::LinkMetadataRequestBody>) -> Result<HttpResponse, Error> {
+    auth::current_user_id(&session, &pool).await?;
+    let fetcher = link_metadata::LinkMetadataFetcher::default();
+    match fetcher.fetch(&form.url).await {
+        Ok(metadata) => Ok(HttpResponse::Ok().json(metadata)),
+        Err(e @ link_metadata::LinkMetadataError::Fetch(_)) => Ok(HttpResponse::BadGateway().body(format!("{}", e))),
+        Err(e) => Ok(HttpResponse::BadRequest().body(format!("{}", e))),
+    }
+}
+
+#[get("gifts/find_by_event/{event_id}")]
+async fn find_gifts_by_event(pool: web::Data<DbPool>, session: Session, event_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let e_id = event_id.into_inner();
+    let gifts = telemetry::block(move || gift_idea_actions::visible_to(gift_idea_actions::find_by_event(e_id, &conn)?, user_id, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+    Ok(HttpResponse::Ok().json(gifts))
 }
 
 #[get("gifts/find_by_id/{gift_id}")]
-async fn find_gift_by_id(pool: web::Data<DbPool>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+async fn find_gift_by_id(pool: web::Data<DbPool>, session: Session, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
     let conn = pool.get().expect("couldn't get db connection from pool");
     let g_id = gift_id.into_inner();
-    let gift = web::block(move || gift_idea_actions::find_by_id(g_id, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    // one the user doesn't get to see is as good as missing
+    let gift = telemetry::block(move || -> Result<Option<shared::GiftIdeaResponseBody>, diesel::result::Error> {
+        let gifts = gift_idea_actions::find_by_id(g_id, &conn)?.into_iter().collect();
+        Ok(gift_idea_actions::visible_to(gifts, user_id, &conn)?.pop())
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
 
     if let Some(gift) = gift {
         Ok(HttpResponse::Ok().json(gift))
     } else {
-        let res = HttpResponse::NotFound().body(format!("No user found with uid: {}", g_id));
+        let res = HttpResponse::NotFound().body(format!("No gift found with id: {}", g_id));
         Ok(res)
     }
 }
 
-#[patch("gifts/reserve/{gift_id}/{user_id}")]
-async fn reserve(pool: web::Data<DbPool>, idz: web::Path<(i32, i32)>) -> Result<HttpResponse, Error> {
-    let idz = idz.into_inner();
+#[patch("gifts/reserve/{gift_id}")]
+async fn reserve(pool: web::Data<DbPool>, session: Session, broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let g_id = gift_id.into_inner();
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let gift = web::block(move || gift_idea_actions::reserve(idz.0, idz.1, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let gift = telemetry::block(move || gift_idea_actions::reserve(g_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
+    let gift = match gift {
+        Some(Some(gift)) => gift,
+        Some(None) => return Ok(HttpResponse::Conflict().body("That gift idea is already reserved, or it's for you")),
+        None => return Ok(HttpResponse::NotFound().body(format!("No gift found with id: {}", g_id))),
+    };
+    metrics::GIFT_IDEAS_RESERVED.inc();
+    broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Reserved(gift.clone()), gift.owner_id, gift.recipient_user_id).await;
     Ok(HttpResponse::Ok().json(gift))
 }
 
 #[patch("gifts/unreserve/{gift_id}")]
-async fn unreserve(pool: web::Data<DbPool>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+async fn unreserve(pool: web::Data<DbPool>, session: Session, broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
     let gift_id = gift_id.into_inner();
+    let user_id = auth::current_user_id(&session, &pool).await?;
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let gift = web::block(move || gift_idea_actions::unreserve(gift_id, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    // None when there's no such idea, Some(None) when someone else holds the reservation
+    let gift = telemetry::block(move || -> Result<Option<Option<shared::GiftIdeaResponseBody>>, diesel::result::Error> {
+        match gift_idea_actions::find_by_id(gift_id, &conn)? {
+            Some(gift) if gift.reserved_by_user_id == Some(user_id) => {
+                notifications::queue_reservation_released(&gift, Some(user_id), &conn)?;
+                Ok(gift_idea_actions::unreserve(gift_id, Some(user_id), &conn)?.map(Some))
+            }
+            Some(_) => Ok(Some(None)),
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
+    let gift = match gift {
+        Some(Some(gift)) => gift,
+        Some(None) => return Ok(HttpResponse::Forbidden().body("Only whoever reserved a gift idea can release it")),
+        None => return Ok(HttpResponse::NotFound().body(format!("No gift found with id: {}", gift_id))),
+    };
+    broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Unreserved(gift.clone()), gift.owner_id, gift.recipient_user_id).await;
     Ok(HttpResponse::Ok().json(gift))
 }
 
-#[get("gifts/find_all")]
-async fn find_all_gifts(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
+#[patch("gifts/purchase/{gift_id}")]
+async fn mark_purchased(
+    pool: web::Data<DbPool>,
+    session: Session,
+    broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>,
+    gift_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let g_id = gift_id.into_inner();
     let conn = pool.get().expect("couldn't get db connection from pool");
-    let gifts = web::block(move || gift_idea_actions::find_all(&conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    let gift = telemetry::block(move || gift_idea_actions::mark_purchased(g_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
 
+    if let Some(gift) = gift {
+        metrics::GIFT_IDEAS_PURCHASED.inc();
+        let gift = shared::GiftIdeaResponseBody::from(gift);
+        broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Purchased(gift.clone()), gift.owner_id, gift.recipient_user_id).await;
+        Ok(HttpResponse::Ok().json(gift))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No gift idea reserved by you found with id: {}", g_id)))
+    }
+}
+
+#[get("gifts/find_all")]
+async fn find_all_gifts(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let gifts = telemetry::block(move || gift_idea_actions::visible_to(gift_idea_actions::find_all(&conn)?, user_id, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+
     if !gifts.is_empty() {
         Ok(HttpResponse::Ok().json(gifts))
     } else {
@@ -177,8 +651,8 @@ async fn find_all_gifts(pool: web::Data<DbPool>) -> Result<HttpResponse, Error>
 // async fn find_my_gifts(pool: web::Data<DbPool>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
 //     let conn = pool.get().expect("couldn't get db connection from pool");
 //     let g_id = gift_id.into_inner();
-//     let gifts = web::block(move || gift_idea_actions::find_my_gifts(g_id, &conn)).await.map_err(|e| {
-//         eprintln!("{}", e);
+//     let gifts = telemetry::block(move || gift_idea_actions::find_my_gifts(g_id, &conn)).await.map_err(|e| {
+//         error!("{}", e);
 //         HttpResponse::InternalServerError().finish()
 //     })?;
 //
@@ -190,68 +664,1082 @@ async fn find_all_gifts(pool: web::Data<DbPool>) -> Result<HttpResponse, Error>
 //     }
 // }
 
+#[get("gifts/history/{gift_id}")]
+async fn find_gift_history(pool: web::Data<DbPool>, session: Session, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let g_id = gift_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    // None when there's no such idea or it's not for the user to see, otherwise whether they're its recipient
+    let history = telemetry::block(move || -> Result<Option<(Vec<shared::AuditEntryResponseBody>, bool)>, diesel::result::Error> {
+        let entries = audit_actions::find_by_entity(audit_actions::GIFT_IDEA, g_id, &conn)?;
+        // a purged idea's owner and recipient are only known from its history
+        let (owner_id, recipient_user_id) = match gift_idea_actions::find_including_deleted(g_id, &conn)? {
+            Some(gift) => (gift.owner_id, gift.recipient_user_id),
+            None => match (audit_actions::last_owner(&entries), audit_actions::last_recipient(&entries)) {
+                (Some(owner_id), Some(recipient_user_id)) => (owner_id, recipient_user_id),
+                _ => return Ok(None),
+            },
+        };
+        // the same people who see the idea itself
+        let visible = owner_id == user_id || (recipient_user_id != user_id && group_actions::share_a_group(user_id, recipient_user_id, &conn)?);
+        Ok(if visible { Some((entries, recipient_user_id == user_id)) } else { None })
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    match history {
+        None => Ok(HttpResponse::NotFound().body(format!("No history found for gift idea with id: {}", g_id))),
+        Some((entries, true)) => Ok(HttpResponse::Ok().json(audit_actions::redact_for_recipient(entries))),
+        Some((entries, false)) => Ok(HttpResponse::Ok().json(entries)),
+    }
+}
+
+/// Moves the idea to the trash; the `purge_trash` job deletes it for good once it's past the
+/// retention window.
 #[delete("gifts/delete/{gift_id}")]
-async fn delete_gift(pool: web::Data<DbPool>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+async fn delete_gift(pool: web::Data<DbPool>, session: Session, broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
     let conn = pool.get().expect("couldn't get db connection from pool");
     let g_id = gift_id.into_inner();
-    let result = web::block(move || gift_idea_actions::delete(g_id, &conn)).await.map_err(|e| {
-        eprintln!("{}", e);
+    // None when it's someone else's idea
+    let deleted = telemetry::block(move || -> Result<Option<(bool, Option<models::GiftIdea>)>, diesel::result::Error> {
+        conn.transaction(|| {
+            let gift = gift_idea_actions::find_by_id(g_id, &conn)?;
+            if gift.as_ref().is_some_and(|gift| gift.owner_id != user_id) {
+                return Ok(None);
+            }
+            let result = gift_idea_actions::delete(g_id, user_id, &conn)?;
+            if let (true, Some(gift)) = (result, &gift) {
+                notifications::queue_gift_idea_removed(gift, Some(user_id), &conn)?;
+            }
+            Ok(Some((result, gift)))
+        })
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    match deleted {
+        Some((result, gift)) => {
+            if let (true, Some(gift)) = (result, gift) {
+                broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Deleted(g_id), gift.owner_id, gift.recipient_user_id).await;
+            }
+            Ok(HttpResponse::Ok().json(result))
+        }
+        None => Ok(HttpResponse::Forbidden().body("Only the gift idea's owner can delete it")),
+    }
+}
+
+#[get("gifts/trash")]
+async fn find_trash(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let gifts = telemetry::block(move || gift_idea_actions::find_deleted_by_owner(user_id, trash::restorable_since(), &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+    Ok(HttpResponse::Ok().json(gifts.into_iter().map(shared::GiftIdeaResponseBody::from).collect::<Vec<_>>()))
+}
+
+#[patch("gifts/restore/{gift_id}")]
+async fn restore_gift(pool: web::Data<DbPool>, session: Session, broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let g_id = gift_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let gift = telemetry::block(move || gift_idea_actions::restore(g_id, user_id, trash::restorable_since(), &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+
+    if let Some(gift) = gift {
+        let gift = shared::GiftIdeaResponseBody::from(gift);
+        broadcast(&pool, &broadcaster, shared::GiftIdeaEvent::Created(gift.clone()), gift.owner_id, gift.recipient_user_id).await;
+        Ok(HttpResponse::Ok().json(gift))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No restorable gift idea of yours found with id: {}", g_id)))
+    }
+}
+
+fn validate_comment(form: &shared::CommentRequestBody) -> Option<HttpResponse> {
+    let body = form.body.trim();
+    if body.is_empty() {
+        Some(HttpResponse::BadRequest().body("Comment must not be empty"))
+    } else if body.chars().count() > comment_actions::MAX_COMMENT_LENGTH {
+        Some(HttpResponse::BadRequest().body(format!("Comments are limited to {} characters", comment_actions::MAX_COMMENT_LENGTH)))
+    } else {
+        None
+    }
+}
+
+#[get("comments/find_by_gift_idea/{gift_id}")]
+async fn find_comments_by_gift_idea(pool: web::Data<DbPool>, session: Session, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let g_id = gift_id.into_inner();
+    let comments = telemetry::block(move || comment_actions::find_by_gift_idea(g_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some(comments) = comments {
+        Ok(HttpResponse::Ok().json(comments))
+    } else {
+        let res = HttpResponse::NotFound().body(format!("No gift found with id: {}", g_id));
+        Ok(res)
+    }
+}
+
+#[post("comments/add/{gift_id}")]
+async fn add_comment(pool: web::Data<DbPool>, session: Session, gift_id: web::Path<i32>, form: web::Json<shared::CommentRequestBody>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    if let Some(res) = validate_comment(&form) {
+        return Ok(res);
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let g_id = gift_id.into_inner();
+    let body = form.into_inner().body.trim().to_string();
+    let comment = telemetry::block(move || comment_actions::add(g_id, user_id, body, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some(comment) = comment {
+        Ok(HttpResponse::Ok().json(comment))
+    } else {
+        let res = HttpResponse::NotFound().body(format!("No gift found with id: {}", g_id));
+        Ok(res)
+    }
+}
+
+#[patch("comments/update/{comment_id}")]
+async fn update_comment(pool: web::Data<DbPool>, session: Session, comment_id: web::Path<i32>, form: web::Json<shared::CommentRequestBody>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    if let Some(res) = validate_comment(&form) {
+        return Ok(res);
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let c_id = comment_id.into_inner();
+    let body = form.into_inner().body.trim().to_string();
+    let comment = telemetry::block(move || comment_actions::update(c_id, user_id, body, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some(comment) = comment {
+        Ok(HttpResponse::Ok().json(comment))
+    } else {
+        let res = HttpResponse::NotFound().body(format!("No comment of yours found with id: {}", c_id));
+        Ok(res)
+    }
+}
+
+#[delete("comments/delete/{comment_id}")]
+async fn delete_comment(pool: web::Data<DbPool>, session: Session, comment_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let c_id = comment_id.into_inner();
+    let result = telemetry::block(move || comment_actions::delete(c_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
         HttpResponse::InternalServerError().finish()
     })?;
     Ok(HttpResponse::Ok().json(result))
 }
 
+#[get("events/find_all")]
+async fn find_all_events(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let events = telemetry::block(move || event_actions::find_for(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(events.into_iter().map(shared::EventResponseBody::from).collect::<Vec<_>>()))
+}
+
+#[get("events/find_by_id/{event_id}")]
+async fn find_event_by_id(pool: web::Data<DbPool>, session: Session, event_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let e_id = event_id.into_inner();
+    // an event the user isn't shared on is as good as missing
+    let event = telemetry::block(move || -> Result<Option<models::Event>, diesel::result::Error> {
+        match event_actions::find_by_id(e_id, &conn)? {
+            Some(event) if event.owner_id == user_id || event_actions::find_audience_ids(&event, &conn)?.contains(&user_id) => Ok(Some(event)),
+            _ => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some(event) = event {
+        Ok(HttpResponse::Ok().json(shared::EventResponseBody::from(event)))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No event found with id: {}", e_id)))
+    }
+}
+
+#[post("events/add")]
+async fn add_event(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::EventRequestBody>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let form = form.into_inner();
+    if form.name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Event name is required"));
+    }
+    let new_event = models::NewEvent::new(form.name.trim().to_string(), form.event_date, user_id);
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let event = telemetry::block(move || event_actions::add(&new_event, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(shared::EventResponseBody::from(event)))
+}
+
+#[delete("events/delete/{event_id}")]
+async fn delete_event(pool: web::Data<DbPool>, session: Session, event_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let e_id = event_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let deleted = telemetry::block(move || event_actions::delete(e_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if deleted {
+        Ok(HttpResponse::Ok().json(deleted))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No event found with id: {}", e_id)))
+    }
+}
+
+#[patch("events/archive/{event_id}")]
+async fn archive_event(pool: web::Data<DbPool>, session: Session, event_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let e_id = event_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let event = telemetry::block(move || event_actions::archive(e_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some(event) = event {
+        Ok(HttpResponse::Ok().json(shared::EventResponseBody::from(event)))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No unarchived event of yours found with id: {}", e_id)))
+    }
+}
+
+#[post("events/carry_over/{event_id}")]
+async fn carry_over_event(pool: web::Data<DbPool>, session: Session, event_id: web::Path<i32>, form: web::Json<shared::CarryOverRequestBody>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let e_id = event_id.into_inner();
+    let form = form.into_inner();
+    if form.name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Event name is required"));
+    }
+    let new_event = models::NewEvent::new(form.name.trim().to_string(), form.event_date, user_id);
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let carried_over = telemetry::block(move || event_actions::carry_over(e_id, user_id, &new_event, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some((event, carried_over)) = carried_over {
+        Ok(HttpResponse::Ok().json(shared::CarryOverResponseBody {
+            event: event.into(),
+            carried_over,
+        }))
+    } else {
+        Ok(HttpResponse::NotFound().body(format!("No archived event of yours found with id: {}", e_id)))
+    }
+}
+
+#[get("notifications/preferences")]
+async fn find_notification_preferences(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let preferences = telemetry::block(move || notification_actions::find_preferences(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(shared::NotificationPreferencesBody::from(preferences)))
+}
+
+#[put("notifications/preferences")]
+async fn update_notification_preferences(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::NotificationPreferencesBody>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let form = form.into_inner();
+    let preferences = models::NotificationPreferences {
+        user_id,
+        new_gift_idea: form.new_gift_idea,
+        reservation_released: form.reservation_released,
+        event_reminder: form.event_reminder,
+    };
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let preferences = telemetry::block(move || notification_actions::save_preferences(&preferences, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(shared::NotificationPreferencesBody::from(preferences)))
+}
+
+#[get("calendar/feed")]
+async fn find_calendar_feed(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let feed = telemetry::block(move || calendar_actions::find(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(feed.map(shared::CalendarFeedBody::from).unwrap_or_default()))
+}
+
+// creating the feed again gives it a new address, which is how a leaked one gets shut off
+#[post("calendar/feed")]
+async fn create_calendar_feed(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::CalendarFeedRequest>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let include_reservations = form.include_reservations;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let (feed, token) = telemetry::block(move || calendar_actions::issue(user_id, include_reservations, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(shared::CalendarFeedBody {
+        url: Some(calendar::feed_url(&token)),
+        ..feed.into()
+    }))
+}
+
+#[put("calendar/feed")]
+async fn update_calendar_feed(pool: web::Data<DbPool>, session: Session, form: web::Json<shared::CalendarFeedRequest>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let include_reservations = form.include_reservations;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let feed = telemetry::block(move || calendar_actions::set_include_reservations(user_id, include_reservations, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+    match feed {
+        Some(feed) => Ok(HttpResponse::Ok().json(shared::CalendarFeedBody::from(feed))),
+        None => Ok(HttpResponse::NotFound().body("You don't have a calendar feed")),
+    }
+}
+
+#[delete("calendar/feed")]
+async fn delete_calendar_feed(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let deleted = telemetry::block(move || calendar_actions::delete(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(deleted))
+}
+
+// calendar apps fetch this without a session, so the secret in the address is all there is
+#[get("calendar/ics/{token}")]
+async fn calendar_ics(pool: web::Data<DbPool>, token: web::Path<String>) -> Result<HttpResponse, Error> {
+    let token = token.into_inner();
+    let token = token.trim_end_matches(".ics").to_string();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let feed = telemetry::block(move || -> Result<Option<String>, diesel::result::Error> {
+        let (feed, user) = match calendar_actions::find_by_token(&token, &conn)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let now = chrono::Utc::now().naive_utc();
+        let events = event_actions::find_upcoming_for(user.id, now.date(), &conn)?;
+        let reservations = if feed.include_reservations {
+            gift_idea_actions::find_reserved_by(user.id, &conn)?
+                .into_iter()
+                .filter(|gift| gift.date_purchased.is_none() && gift.date_deleted.is_none() && gift.date_archived.is_none())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let users = user_actions::find_all_full(&conn)?;
+        Ok(Some(calendar::feed(&events, &reservations, &users, now)))
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    match feed {
+        Some(feed) => Ok(HttpResponse::Ok().content_type(calendar::CONTENT_TYPE).body(feed)),
+        None => Ok(HttpResponse::NotFound().body("No such calendar feed")),
+    }
+}
+
+#[get("updates/stream")]
+async fn stream_updates(pool: web::Data<DbPool>, session: Session, broadcaster: web::Data<Mutex<broadcaster::Broadcaster>>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let receiver = broadcaster.lock().unwrap().new_client(user_id);
+    Ok(HttpResponse::Ok()
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .streaming(receiver.map(Ok::<_, Error>)))
+}
+
+#[post("attachments/add/{gift_id}")]
+async fn add_attachment(
+    pool: web::Data<DbPool>,
+    session: Session,
+    storage: web::Data<storage::AttachmentStorage>,
+    gift_id: web::Path<i32>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let g_id = gift_id.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let gift = telemetry::block(move || gift_idea_actions::find_by_id(g_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    if gift.is_none() {
+        return Ok(HttpResponse::NotFound().body(format!("No gift found with id: {}", g_id)));
+    }
+
+    let mut attachments: Vec<shared::AttachmentResponseBody> = Vec::new();
+    while let Some(field) = payload.next().await {
+        let mut field = field?;
+        let content_type = format!("{}/{}", field.content_type().type_(), field.content_type().subtype());
+        if !attachment_actions::is_allowed_content_type(&content_type) {
+            return Ok(HttpResponse::UnsupportedMediaType().body(format!("Unsupported content type: {}", content_type)));
+        }
+        let file_name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename().map(|name| name.to_string()))
+            .unwrap_or_else(|| "attachment".to_string());
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk?;
+            if data.len() + chunk.len() > attachment_actions::MAX_ATTACHMENT_SIZE {
+                return Ok(HttpResponse::PayloadTooLarge().body(format!("Attachments are limited to {} bytes", attachment_actions::MAX_ATTACHMENT_SIZE)));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        let conn = pool.get().expect("couldn't get db connection from pool");
+        let storage = storage.get_ref().clone();
+        let attachment = telemetry::block(move || attachment_actions::add(g_id, user_id, file_name, content_type, data, storage.as_ref(), &conn))
+            .await
+            .map_err(|e| match e {
+                error::BlockingError::Error(attachment_actions::AttachmentError::TooLarge(_)) => HttpResponse::PayloadTooLarge().body(format!("{}", e)),
+                error::BlockingError::Error(attachment_actions::AttachmentError::Image(_))
+                | error::BlockingError::Error(attachment_actions::AttachmentError::ContentTypeMismatch(_)) => HttpResponse::BadRequest().body(format!("{}", e)),
+                _ => {
+                    error!("{}", e);
+                    HttpResponse::InternalServerError().finish()
+                }
+            })?;
+        attachments.push(attachment.into());
+    }
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+#[get("attachments/find_all")]
+async fn find_all_attachments(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let attachments = telemetry::block(move || attachment_actions::find_all(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    let results: Vec<shared::AttachmentResponseBody> = attachments.into_iter().map(|e| e.into()).collect();
+    Ok(HttpResponse::Ok().json(results))
+}
+
+#[get("attachments/find_by_gift_idea/{gift_id}")]
+async fn find_attachments_by_gift_idea(pool: web::Data<DbPool>, session: Session, gift_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let g_id = gift_id.into_inner();
+    let attachments = telemetry::block(move || attachment_actions::find_visible_by_gift_idea(g_id, user_id, &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+    let attachments = match attachments {
+        Some(attachments) => attachments,
+        None => return Ok(HttpResponse::NotFound().body(format!("No gift found with id: {}", g_id))),
+    };
+    let results: Vec<shared::AttachmentResponseBody> = attachments.into_iter().map(|e| e.into()).collect();
+    Ok(HttpResponse::Ok().json(results))
+}
+
+async fn serve_attachment(pool: web::Data<DbPool>, session: Session, storage: web::Data<storage::AttachmentStorage>, a_id: i32, thumbnail: bool) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let storage = storage.get_ref().clone();
+    let result = telemetry::block(move || -> Result<Option<(String, Vec<u8>)>, attachment_actions::AttachmentError> {
+        match attachment_actions::find_visible(a_id, user_id, &conn)? {
+            Some(attachment) => {
+                let data = attachment_actions::read(&attachment, thumbnail, storage.as_ref())?;
+                let content_type = if thumbnail { "image/png".to_string() } else { attachment.content_type };
+                Ok(Some((content_type, data)))
+            }
+            None => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+
+    if let Some((content_type, data)) = result {
+        Ok(HttpResponse::Ok().content_type(content_type).body(data))
+    } else {
+        let res = HttpResponse::NotFound().body(format!("No attachment found with id: {}", a_id));
+        Ok(res)
+    }
+}
+
+#[get("attachments/download/{attachment_id}")]
+async fn download_attachment(
+    pool: web::Data<DbPool>,
+    session: Session,
+    storage: web::Data<storage::AttachmentStorage>,
+    attachment_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    serve_attachment(pool, session, storage, attachment_id.into_inner(), false).await
+}
+
+#[get("attachments/thumbnail/{attachment_id}")]
+async fn download_attachment_thumbnail(
+    pool: web::Data<DbPool>,
+    session: Session,
+    storage: web::Data<storage::AttachmentStorage>,
+    attachment_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    serve_attachment(pool, session, storage, attachment_id.into_inner(), true).await
+}
+
+// only whoever uploaded it or the owner of the gift idea; false when there's no such attachment
+#[delete("attachments/delete/{attachment_id}")]
+async fn delete_attachment(
+    pool: web::Data<DbPool>,
+    session: Session,
+    storage: web::Data<storage::AttachmentStorage>,
+    attachment_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let storage = storage.get_ref().clone();
+    let a_id = attachment_id.into_inner();
+    let result = telemetry::block(move || -> Result<Option<bool>, attachment_actions::AttachmentError> {
+        let attachment = match attachment_actions::find_by_id(a_id, &conn)? {
+            Some(attachment) => attachment,
+            None => return Ok(Some(false)),
+        };
+        match gift_idea_actions::find_including_deleted(attachment.gift_idea_id, &conn)? {
+            Some(gift) if attachment.can_delete(&gift, user_id) => Ok(Some(attachment_actions::delete(a_id, storage.as_ref(), &conn)?)),
+            _ => Ok(None),
+        }
+    })
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    match result {
+        Some(deleted) => Ok(HttpResponse::Ok().json(deleted)),
+        None => Ok(HttpResponse::Forbidden().body("Only whoever uploaded an image or the gift idea's owner can delete it")),
+    }
+}
+
+#[get("export/json")]
+async fn export_json(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let document = telemetry::block(move || export_actions::export(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().header("content-disposition", "attachment; filename=\"gifts.json\"").json(document))
+}
+
+#[get("export/csv")]
+async fn export_csv(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let document = telemetry::block(move || export_actions::export(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header("content-disposition", "attachment; filename=\"gifts.csv\"")
+        .body(export_actions::to_csv(&document)))
+}
+
+#[get("groups/mine")]
+async fn find_my_groups(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let groups = telemetry::block(move || group_actions::find_by_member(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(groups.into_iter().map(shared::GroupBody::from).collect::<Vec<_>>()))
+}
+
+async fn find_group_export(pool: &DbPool, session: &Session, group_id: i32) -> Result<shared::ExportDocument, Error> {
+    let user_id = auth::current_user_id(session, pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let document = telemetry::block(move || export_actions::export_group(group_id, user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    document.ok_or_else(|| HttpResponse::NotFound().body(format!("No group of yours found with id: {}", group_id)).into())
+}
+
+#[get("export/group/{group_id}/json")]
+async fn export_group_json(pool: web::Data<DbPool>, session: Session, group_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let document = find_group_export(&pool, &session, group_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().header("content-disposition", "attachment; filename=\"group-gifts.json\"").json(document))
+}
+
+#[get("export/group/{group_id}/csv")]
+async fn export_group_csv(pool: web::Data<DbPool>, session: Session, group_id: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let document = find_group_export(&pool, &session, group_id.into_inner()).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .header("content-disposition", "attachment; filename=\"group-gifts.csv\"")
+        .body(export_actions::to_csv(&document)))
+}
+
+#[post("import")]
+async fn import(pool: web::Data<DbPool>, session: Session, payload: web::Payload) -> Result<HttpResponse, Error> {
+    run_import(pool, session, payload, false).await
+}
+
+#[post("import/dry_run")]
+async fn import_dry_run(pool: web::Data<DbPool>, session: Session, payload: web::Payload) -> Result<HttpResponse, Error> {
+    run_import(pool, session, payload, true).await
+}
+
+async fn run_import(pool: web::Data<DbPool>, session: Session, payload: web::Payload, dry_run: bool) -> Result<HttpResponse, Error> {
+    let user_id = auth::current_user_id(&session, &pool).await?;
+    let document = read_json::<shared::ExportDocument>(payload, export_actions::MAX_IMPORT_SIZE).await?;
+    if !export_actions::is_supported(&document) {
+        return Ok(HttpResponse::BadRequest().body(format!("Export version {} is newer than this server understands", document.version)));
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let report = telemetry::block(move || export_actions::import(&document, user_id, dry_run, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Reads a JSON body by hand, for requests that can be bigger than web::Json's 32k.
+async fn read_json<T: serde::de::DeserializeOwned>(mut payload: web::Payload, max_size: usize) -> Result<T, Error> {
+    let mut body = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > max_size {
+            return Err(HttpResponse::PayloadTooLarge().body(format!("Requests are limited to {} bytes", max_size)).into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&body).map_err(|e| HttpResponse::BadRequest().body(format!("Invalid request body: {}", e)).into())
+}
+
+// only for verified addresses in ADMIN_EMAILS
+async fn current_admin_id(pool: &DbPool, session: &Session) -> Result<i32, Error> {
+    let user_id = auth::current_user_id(session, pool).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let user = telemetry::block(move || user_actions::find_by_id(user_id, &conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    match user {
+        Some(user) if auth::is_admin(&user) => Ok(user_id),
+        _ => Err(HttpResponse::Forbidden().body("Only admins can do that").into()),
+    }
+}
+
+/// Tells the people a gift idea concerns about a change to it.  Failing to is only logged: the
+/// change itself has already been made.
+async fn broadcast(pool: &DbPool, broadcaster: &Mutex<broadcaster::Broadcaster>, event: shared::GiftIdeaEvent, owner_id: i32, recipient_user_id: i32) {
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    match telemetry::block(move || broadcaster::Audience::find(owner_id, recipient_user_id, &conn)).await {
+        Ok(audience) => broadcaster.lock().unwrap().send(&event, &audience),
+        Err(e) => error!("{}", e),
+    }
+}
+
+#[get("admin/jobs")]
+async fn find_jobs(pool: web::Data<DbPool>, session: Session) -> Result<HttpResponse, Error> {
+    current_admin_id(&pool, &session).await?;
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let jobs = telemetry::block(move || job_actions::find_all(&conn)).await.map_err(|e| {
+        error!("{}", e);
+        HttpResponse::InternalServerError().finish()
+    })?;
+    Ok(HttpResponse::Ok().json(jobs.into_iter().map(shared::JobBody::from).collect::<Vec<_>>()))
+}
+
+/// Makes the job due now; the runner picks it up within a few seconds unless it's already running.
+#[post("admin/jobs/run/{job_name}")]
+async fn run_job(pool: web::Data<DbPool>, session: Session, job_name: web::Path<String>) -> Result<HttpResponse, Error> {
+    current_admin_id(&pool, &session).await?;
+    let name = job_name.into_inner();
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let job_name = name.clone();
+    let job = telemetry::block(move || job_actions::trigger(&job_name, chrono::Utc::now().naive_utc(), &conn))
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        })?;
+    match job {
+        Some(job) => Ok(HttpResponse::Ok().json(shared::JobBody::from(job))),
+        None => Ok(HttpResponse::NotFound().body(format!("No job named {}", name))),
+    }
+}
+
+#[get("openapi.json")]
+async fn openapi_document() -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().json(openapi::document()))
+}
+
+#[get("docs")]
+async fn openapi_docs() -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(openapi::DOCS_PAGE))
+}
+
 async fn index() -> Result<NamedFile> {
     Ok(NamedFile::open("./client/index.html")?)
 }
 
+/// Up as long as the process is; for restarting one that has hung.
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+/// Whether the server can take traffic: the database answers and has every migration.
+async fn readyz(pool: web::Data<DbPool>) -> HttpResponse {
+    let pool = pool.get_ref().clone();
+    let pending = telemetry::block(move || -> Result<bool, String> {
+        // don't queue behind a slow request for the pool's whole timeout, the probe would give up first
+        let conn = pool.get_timeout(std::time::Duration::from_secs(2)).map_err(|e| e.to_string())?;
+        server::has_pending_migrations(&conn).map_err(|e| e.to_string())
+    })
+    .await;
+    match pending {
+        Ok(false) => HttpResponse::Ok().body("ok"),
+        Ok(true) => HttpResponse::ServiceUnavailable().body("migrations pending"),
+        Err(e) => {
+            warn!("not ready: {}", e);
+            HttpResponse::ServiceUnavailable().body("database unavailable")
+        }
+    }
+}
+
+async fn metrics_text(pool: web::Data<DbPool>) -> HttpResponse {
+    HttpResponse::Ok().content_type(metrics::CONTENT_TYPE).body(metrics::render(&pool))
+}
+
+/// Registers every `/v1` route.
+fn v1(cfg: &mut web::ServiceConfig) {
+    cfg.service(add_user)
+        .service(login)
+        .service(logout)
+        .service(request_email_verification)
+        .service(verify_email)
+        .service(request_password_reset)
+        .service(reset_password)
+        .service(change_password)
+        .service(delete_account)
+        .service(update_user)
+        .service(find_user_by_id)
+        .service(find_user_by_email)
+        .service(find_user_dates)
+        .service(add_user_date)
+        .service(update_user_date)
+        .service(delete_user_date)
+        .service(find_all_users)
+        .service(add_gift)
+        .service(delete_gift)
+        .service(update_gift_idea)
+        .service(find_gifts_by_event)
+        .service(find_gift_by_id)
+        .service(find_all_gifts)
+        .service(reserve)
+        .service(unreserve)
+        .service(mark_purchased)
+        .service(find_gift_history)
+        .service(find_trash)
+        .service(restore_gift)
+        .service(find_link_metadata)
+        .service(find_comments_by_gift_idea)
+        .service(add_comment)
+        .service(update_comment)
+        .service(delete_comment)
+        .service(stream_updates)
+        .service(find_all_events)
+        .service(find_event_by_id)
+        .service(add_event)
+        .service(delete_event)
+        .service(archive_event)
+        .service(carry_over_event)
+        .service(find_notification_preferences)
+        .service(update_notification_preferences)
+        .service(find_calendar_feed)
+        .service(create_calendar_feed)
+        .service(update_calendar_feed)
+        .service(delete_calendar_feed)
+        .service(calendar_ics)
+        .service(add_attachment)
+        .service(find_all_attachments)
+        .service(find_attachments_by_gift_idea)
+        .service(download_attachment)
+        .service(download_attachment_thumbnail)
+        .service(delete_attachment)
+        .service(preview_wishlist)
+        .service(import_wishlist)
+        .service(export_json)
+        .service(export_csv)
+        .service(find_my_groups)
+        .service(export_group_json)
+        .service(export_group_csv)
+        .service(import)
+        .service(import_dry_run)
+        .service(find_jobs)
+        .service(run_job)
+        .service(openapi_document)
+        .service(openapi_docs);
+}
+
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
-    std::env::set_var("RUST_LOG", "actix_web=debug,diesel=debug");
-    env_logger::init();
     dotenv::dotenv().ok();
+    telemetry::init();
 
     // set up database connection pool
-    let connspec = std::env::var("DATABASE_URL").expect("DATABASE_URL");
-    let manager = ConnectionManager::<SqliteConnection>::new(connspec);
+    let pool = server::pool_from_env();
 
-    // let conn = SqliteConnection::establish(db_url).unwrap();
-    // conn.batch_execute(
-    //     "PRAGMA synchronous = NORMAL; PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;",
-    // )
-    // .unwrap();
+    let attachment_storage = storage::from_env();
 
-    let pool = r2d2::Pool::builder().max_size(1).build(manager).expect("Failed to create pool.");
+    let session_key = auth::session_key();
+    let secure_cookies = auth::secure_cookies();
+
+    let broadcaster = web::Data::new(Mutex::new(broadcaster::Broadcaster::new()));
+    broadcaster::Broadcaster::spawn_ping(broadcaster.clone());
+
+    let mailer = mailer::from_env().map_err(|e| std::io::Error::other(format!("couldn't set up mail: {}", e)))?;
+    jobs::spawn_runner(jobs::JobContext {
+        pool: pool.clone(),
+        mailer,
+        storage: attachment_storage.clone(),
+    });
 
     let bind = "127.0.0.1:8080";
 
-    println!("Starting server at: {}", &bind);
+    info!("Starting server at: {}", &bind);
 
     HttpServer::new(move || {
         App::new()
             .data(pool.clone())
-            .wrap(middleware::Logger::default())
-            .service(
-                web::scope("/v1/")
-                    .service(add_user)
-                    .service(login)
-                    .service(update_user)
-                    .service(find_user_by_id)
-                    .service(find_user_by_email)
-                    .service(find_all_users)
-                    .service(add_gift)
-                    .service(delete_gift)
-                    .service(update_gift)
-                    .service(find_gift_by_id)
-                    .service(find_all_gifts)
-                    .service(reserve)
-                    .service(unreserve)
-                    .default_service(web::route().to(web::HttpResponse::NotFound)),
-            )
+            .data(attachment_storage.clone())
+            .app_data(broadcaster.clone())
+            .wrap(CookieSession::signed(&session_key).name("gifts_session").secure(secure_cookies))
+            .wrap_fn(|req, srv| {
+                let started = std::time::Instant::now();
+                let method = req.method().to_string();
+                let route = metrics::route_label(&method, req.path());
+                srv.call(req).map(move |res| {
+                    if let Ok(res) = &res {
+                        metrics::observe_request(&method, &route, res.status().as_u16(), started.elapsed());
+                    }
+                    res
+                })
+            })
+            .wrap_fn(telemetry::trace_request)
+            .service(web::resource("/healthz").route(web::get().to(healthz)))
+            .service(web::resource("/readyz").route(web::get().to(readyz)))
+            .service(web::resource("/metrics").route(web::get().to(metrics_text)))
+            .service(web::scope("/v1/").configure(v1).default_service(web::route().to(web::HttpResponse::NotFound)))
             .service(Files::new("/pkg", "./client/pkg"))
             .default_service(web::get().to(index))
     })
-    .bind(&bind)?
+    .bind(bind)?
     .run()
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use std::sync::Arc;
+
+    const PASSWORD: &str = "password";
+
+    // the `/v1` routes as the server has them, over `pool` and, if given, `storage`
+    macro_rules! app {
+        ($pool:expr) => {
+            app!($pool, Arc::new(storage::MemoryStorage::default()) as storage::AttachmentStorage)
+        };
+        ($pool:expr, $storage:expr) => {
+            test::init_service(
+                App::new()
+                    .data($pool.clone())
+                    .data($storage.clone())
+                    .app_data(web::Data::new(Mutex::new(broadcaster::Broadcaster::new())))
+                    .wrap(CookieSession::signed(&[0; 32]).name("gifts_session").secure(false))
+                    .service(web::scope("/v1/").configure(v1)),
+            )
+            .await
+        };
+    }
+
+    // signs `user` in through `users/login` and hands back their session cookie
+    macro_rules! sign_in {
+        ($app:expr, $user:expr) => {{
+            let body = shared::LoginRequestBody {
+                email: $user.email.clone(),
+                password: PASSWORD.to_string(),
+            };
+            let response = test::call_service(&mut $app, TestRequest::post().uri("/v1/users/login").set_json(&body).to_request()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            response
+                .response()
+                .cookies()
+                .find(|cookie| cookie.name() == "gifts_session")
+                .expect("session cookie")
+                .into_owned()
+        }};
+    }
+
+    fn user(email: &str, pool: &DbPool) -> models::User {
+        user_actions::add(&models::NewUser::new(email.to_string(), PASSWORD.to_string()), None, &pool.get().unwrap()).unwrap()
+    }
+
+    fn gift_idea(title: &str, owner: &models::User, recipient: &models::User, pool: &DbPool) -> models::GiftIdea {
+        let new_gift = models::NewGiftIdea::new(title.to_string(), owner.id, recipient.id);
+        gift_idea_actions::add(&new_gift, Some(owner.id), &pool.get().unwrap()).unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn only_the_owner_can_move_an_idea_to_the_trash() {
+        let pool = server::test_pool();
+        let (jane, bob) = (user("jane@example.com", &pool), user("bob@example.com", &pool));
+        let gift = gift_idea("Bread maker", &jane, &bob, &pool);
+        let mut app = app!(pool);
+        let uri = format!("/v1/gifts/delete/{}", gift.id);
+
+        let response = test::call_service(&mut app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let bobs = sign_in!(app, bob);
+        let response = test::call_service(&mut app, TestRequest::delete().uri(&uri).cookie(bobs).to_request()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(gift_idea_actions::find_by_id(gift.id, &pool.get().unwrap()).unwrap().is_some());
+        assert!(gift_idea_actions::find_deleted_by_owner(jane.id, trash::restorable_since(), &pool.get().unwrap())
+            .unwrap()
+            .is_empty());
+
+        let janes = sign_in!(app, jane);
+        let response = test::call_service(&mut app, TestRequest::delete().uri(&uri).cookie(janes).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(gift_idea_actions::find_by_id(gift.id, &pool.get().unwrap()).unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn an_idea_is_reserved_once_and_released_by_its_reserver() {
+        let pool = server::test_pool();
+        let (jane, bob, carol) = (user("jane@example.com", &pool), user("bob@example.com", &pool), user("carol@example.com", &pool));
+        let gift = gift_idea("Bread maker", &jane, &bob, &pool);
+        let mut app = app!(pool);
+        let (reserve_uri, unreserve_uri) = (format!("/v1/gifts/reserve/{}", gift.id), format!("/v1/gifts/unreserve/{}", gift.id));
+        let (janes, bobs, carols) = (sign_in!(app, jane), sign_in!(app, bob), sign_in!(app, carol));
+
+        let response = test::call_service(&mut app, TestRequest::patch().uri(&reserve_uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = test::call_service(&mut app, TestRequest::patch().uri(&reserve_uri).cookie(bobs).to_request()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = test::call_service(&mut app, TestRequest::patch().uri(&reserve_uri).cookie(carols.clone()).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = test::call_service(&mut app, TestRequest::patch().uri(&reserve_uri).cookie(janes.clone()).to_request()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let reserved_by = || gift_idea_actions::find_by_id(gift.id, &pool.get().unwrap()).unwrap().unwrap().reserved_by_user_id;
+        assert_eq!(reserved_by(), Some(carol.id));
+
+        let response = test::call_service(&mut app, TestRequest::patch().uri(&unreserve_uri).cookie(janes).to_request()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(reserved_by(), Some(carol.id));
+        let response = test::call_service(&mut app, TestRequest::patch().uri(&unreserve_uri).cookie(carols).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(reserved_by(), None);
+    }
+
+    #[actix_rt::test]
+    async fn only_people_who_can_see_an_idea_get_its_attachments() {
+        let pool = server::test_pool();
+        let (jane, bob, dave, carol) = (
+            user("jane@example.com", &pool),
+            user("bob@example.com", &pool),
+            user("dave@example.com", &pool),
+            user("carol@example.com", &pool),
+        );
+        let family = group_actions::add("Family".to_string(), None, &pool.get().unwrap()).unwrap();
+        group_actions::add_member(family.id, bob.id, None, &pool.get().unwrap()).unwrap();
+        group_actions::add_member(family.id, dave.id, None, &pool.get().unwrap()).unwrap();
+        let gift = gift_idea("Bread maker", &jane, &bob, &pool);
+        let storage = Arc::new(storage::MemoryStorage::default()) as storage::AttachmentStorage;
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(10, 10))
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let attachment = attachment_actions::add(
+            gift.id,
+            jane.id,
+            "bread.png".to_string(),
+            "image/png".to_string(),
+            png,
+            storage.as_ref(),
+            &pool.get().unwrap(),
+        )
+        .unwrap();
+        let mut app = app!(pool, storage);
+        let uris = [
+            "/v1/attachments/find_all".to_string(),
+            format!("/v1/attachments/find_by_gift_idea/{}", gift.id),
+            format!("/v1/attachments/download/{}", attachment.id),
+            format!("/v1/attachments/thumbnail/{}", attachment.id),
+        ];
+
+        for uri in &uris {
+            let response = test::call_service(&mut app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", uri);
+        }
+        for (viewer, sees) in &[(&jane, true), (&dave, true), (&bob, false), (&carol, false)] {
+            let cookie = sign_in!(app, viewer);
+            let found: Vec<shared::AttachmentResponseBody> = test::read_response_json(&mut app, TestRequest::get().uri(&uris[0]).cookie(cookie.clone()).to_request()).await;
+            assert_eq!(found.len(), *sees as usize, "{}", viewer.email);
+            for uri in &uris[1..] {
+                let response = test::call_service(&mut app, TestRequest::get().uri(uri).cookie(cookie.clone()).to_request()).await;
+                let expected = if *sees { StatusCode::OK } else { StatusCode::NOT_FOUND };
+                assert_eq!(response.status(), expected, "{} for {}", uri, viewer.email);
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::time::Duration;
+
+use crate::openapi;
+use crate::DbPool;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// for anything that isn't an API route, so scanners and static files can't mint new series
+const UNMATCHED: &str = "unmatched";
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+    static ref HTTP_REQUESTS: IntCounterVec = counter_vec("http_requests_total", "Requests served, by route and status", &["method", "route", "status"]);
+    static ref HTTP_REQUEST_DURATION: HistogramVec = {
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "How long requests took to serve, by route"),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        REGISTRY.register(Box::new(histogram.clone())).expect("metric registered once");
+        histogram
+    };
+    static ref DB_POOL_CONNECTIONS: IntGauge = gauge("db_pool_connections", "Connections the pool has open");
+    static ref DB_POOL_IDLE_CONNECTIONS: IntGauge = gauge("db_pool_idle_connections", "Open connections nobody is using");
+    pub static ref USERS_REGISTERED: IntCounter = counter("users_registered_total", "Users who signed up");
+    pub static ref GIFT_IDEAS_ADDED: IntCounter = counter("gift_ideas_added_total", "Gift ideas added, one at a time or imported");
+    pub static ref GIFT_IDEAS_RESERVED: IntCounter = counter("gift_ideas_reserved_total", "Gift ideas someone reserved");
+    pub static ref GIFT_IDEAS_PURCHASED: IntCounter = counter("gift_ideas_purchased_total", "Gift ideas marked purchased");
+    pub static ref NOTIFICATIONS_SENT: IntCounter = counter("notifications_sent_total", "Emails the mail server accepted");
+    pub static ref NOTIFICATIONS_FAILED: IntCounter = counter("notifications_failed_total", "Emails the mail server refused, each attempt counted");
+    pub static ref JOB_RUNS: IntCounterVec = counter_vec("job_runs_total", "Scheduled job runs, by job and outcome", &["job", "outcome"]);
+    static ref ROUTES: Vec<(&'static str, Vec<&'static str>)> = openapi::routes().into_iter().map(|route| (route.method, route.path.split('/').collect())).collect();
+}
+
+fn counter(name: &str, help: &str) -> IntCounter {
+    let counter = IntCounter::new(name, help).expect("valid metric");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric registered once");
+    counter
+}
+
+fn counter_vec(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+    REGISTRY.register(Box::new(counter.clone())).expect("metric registered once");
+    counter
+}
+
+fn gauge(name: &str, help: &str) -> IntGauge {
+    let gauge = IntGauge::new(name, help).expect("valid metric");
+    REGISTRY.register(Box::new(gauge.clone())).expect("metric registered once");
+    gauge
+}
+
+/// The route a request went to as written in its route attribute, e.g.
+/// `gifts/find_by_id/{gift_id}`, so every gift shares one series.  When a literal segment and a
+/// parameter could both match, the route with more literal segments wins.
+pub fn route_label(method: &str, path: &str) -> String {
+    match path {
+        "/healthz" | "/readyz" | "/metrics" => return path.to_string(),
+        _ => (),
+    }
+    if !path.starts_with("/v1/") {
+        return UNMATCHED.to_string();
+    }
+    let path = path["/v1/".len()..].split('/').collect::<Vec<_>>();
+    ROUTES
+        .iter()
+        .filter(|(route_method, segments)| route_method.eq_ignore_ascii_case(method) && segments.len() == path.len())
+        .filter(|(_, segments)| segments.iter().zip(path.iter()).all(|(segment, part)| segment.starts_with('{') || segment == part))
+        .max_by_key(|(_, segments)| segments.iter().filter(|segment| !segment.starts_with('{')).count())
+        .map_or_else(|| UNMATCHED.to_string(), |(_, segments)| segments.join("/"))
+}
+
+pub fn observe_request(method: &str, route: &str, status: u16, took: Duration) {
+    HTTP_REQUESTS.with_label_values(&[method, route, &status.to_string()]).inc();
+    HTTP_REQUEST_DURATION.with_label_values(&[method, route]).observe(took.as_secs_f64());
+}
+
+pub fn observe_job(name: &str, succeeded: bool) {
+    JOB_RUNS.with_label_values(&[name, if succeeded { "succeeded" } else { "failed" }]).inc();
+}
+
+/// Everything in the Prometheus text format, with the pool gauges read as of now.
+pub fn render(pool: &DbPool) -> String {
+    // metrics register on first use, so without this a quiet server's scrape would leave them out
+    lazy_static::initialize(&HTTP_REQUESTS);
+    lazy_static::initialize(&HTTP_REQUEST_DURATION);
+    lazy_static::initialize(&USERS_REGISTERED);
+    lazy_static::initialize(&GIFT_IDEAS_ADDED);
+    lazy_static::initialize(&GIFT_IDEAS_RESERVED);
+    lazy_static::initialize(&GIFT_IDEAS_PURCHASED);
+    lazy_static::initialize(&NOTIFICATIONS_SENT);
+    lazy_static::initialize(&NOTIFICATIONS_FAILED);
+    lazy_static::initialize(&JOB_RUNS);
+    let state = pool.state();
+    DB_POOL_CONNECTIONS.set(state.connections as i64);
+    DB_POOL_IDLE_CONNECTIONS.set(state.idle_connections as i64);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer).expect("metrics encode as text");
+    String::from_utf8(buffer).expect("the text format is utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_to_one_route_share_a_label() {
+        for path in &["/v1/gifts/find_by_id/1", "/v1/gifts/find_by_id/42", "/v1/gifts/find_by_id/anything"] {
+            assert_eq!(route_label("GET", path), "gifts/find_by_id/{gift_id}");
+        }
+        assert_eq!(route_label("get", "/v1/export/group/3/csv"), "export/group/{group_id}/csv");
+        assert_eq!(route_label("GET", "/v1/export/group/3/json"), "export/group/{group_id}/json");
+        assert_eq!(route_label("POST", "/v1/gifts/import_wishlist/preview"), "gifts/import_wishlist/preview");
+    }
+
+    #[test]
+    fn anything_else_is_unmatched() {
+        assert_eq!(route_label("GET", "/healthz"), "/healthz");
+        assert_eq!(route_label("GET", "/metrics"), "/metrics");
+        for (method, path) in &[
+            ("POST", "/v1/gifts/find_by_id/1"),
+            ("GET", "/v1/gifts/find_by_id"),
+            ("GET", "/v1/gifts/find_by_id/1/2"),
+            ("GET", "/v1/wp-login.php"),
+            ("GET", "/static/app.js"),
+            ("GET", "/"),
+        ] {
+            assert_eq!(route_label(method, path), UNMATCHED, "{} {}", method, path);
+        }
+    }
+
+    #[test]
+    fn requests_are_counted_and_timed_by_route() {
+        let pool = crate::test_pool();
+        // a route of its own, so other tests' requests don't show up in the numbers
+        observe_request("GET", "metrics/test/{id}", 200, Duration::from_millis(30));
+        observe_request("GET", "metrics/test/{id}", 200, Duration::from_millis(300));
+        observe_request("GET", "metrics/test/{id}", 404, Duration::from_millis(2));
+        let text = render(&pool);
+
+        let lines = text.lines().collect::<Vec<_>>();
+        let line = |line: &str| assert!(lines.contains(&line), "no `{}` in\n{}", line, text);
+        line(r#"http_requests_total{method="GET",route="metrics/test/{id}",status="200"} 2"#);
+        line(r#"http_requests_total{method="GET",route="metrics/test/{id}",status="404"} 1"#);
+        line(r#"http_request_duration_seconds_bucket{method="GET",route="metrics/test/{id}",le="0.005"} 1"#);
+        line(r#"http_request_duration_seconds_bucket{method="GET",route="metrics/test/{id}",le="0.05"} 2"#);
+        line(r#"http_request_duration_seconds_bucket{method="GET",route="metrics/test/{id}",le="+Inf"} 3"#);
+        line(r#"http_request_duration_seconds_count{method="GET",route="metrics/test/{id}"} 3"#);
+        line("db_pool_connections 1");
+        line("db_pool_idle_connections 1");
+        for name in &["users_registered_total", "gift_ideas_added_total", "notifications_failed_total"] {
+            assert!(lines.iter().any(|line| line.starts_with(name)), "no {} in\n{}", name, text);
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use chrono::prelude::*;
 use diesel::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::schema::*;
 
-#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations, JsonSchema)]
 #[changeset_options(treat_none_as_null = "true")]
 #[table_name = "users"]
 pub struct User {
@@ -14,6 +15,19 @@ pub struct User {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub phone: Option<String>,
+    pub email_verified: bool,
+    pub date_disabled: Option<NaiveDateTime>,
+}
+
+impl User {
+    pub fn display_name(&self) -> String {
+        let name = format!("{} {}", self.first_name.as_deref().unwrap_or(""), self.last_name.as_deref().unwrap_or(""));
+        if name.trim().is_empty() {
+            self.email.to_string()
+        } else {
+            name.trim().to_string()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Insertable, AsChangeset, Associations)]
@@ -51,21 +65,56 @@ impl From<shared::RegisterRequestBody> for NewUser {
 
 impl From<shared::LoginRequestBody> for NewUser {
     fn from(body: shared::LoginRequestBody) -> NewUser {
-        let mut user = NewUser::new(body.email, body.password);
-        user
+        NewUser::new(body.email, body.password)
     }
 }
 
 impl From<User> for shared::MinimalUserInfo {
     fn from(body: User) -> shared::MinimalUserInfo {
-        let user = shared::MinimalUserInfo {
+        shared::MinimalUserInfo {
             id: body.id,
             email: body.email,
             first_name: body.first_name,
             last_name: body.last_name,
             phone: body.phone,
-        };
-        user
+        }
+    }
+}
+
+impl From<User> for shared::LoggedUser {
+    fn from(body: User) -> shared::LoggedUser {
+        shared::LoggedUser {
+            id: body.id,
+            email: body.email,
+            first_name: body.first_name.unwrap_or_default(),
+            last_name: body.last_name.unwrap_or_default(),
+            phone: body.phone,
+            email_verified: body.email_verified,
+        }
+    }
+}
+
+// `None` leaves a column alone, `Some(None)` sets it to NULL
+#[derive(Debug, Clone, Default, AsChangeset)]
+#[table_name = "users"]
+pub struct UserProfileChangeset {
+    pub email: Option<String>,
+    pub first_name: Option<Option<String>>,
+    pub last_name: Option<Option<String>>,
+    pub phone: Option<Option<String>>,
+    pub email_verified: Option<bool>,
+}
+
+impl From<shared::UpdateProfileRequest> for UserProfileChangeset {
+    fn from(body: shared::UpdateProfileRequest) -> UserProfileChangeset {
+        let clearable = |value: Option<String>| value.map(|e| Some(e.trim().to_string()).filter(|e| !e.is_empty()));
+        UserProfileChangeset {
+            email: body.email.map(|e| e.trim().to_string()),
+            first_name: clearable(body.first_name),
+            last_name: clearable(body.last_name),
+            phone: clearable(body.phone),
+            email_verified: None,
+        }
     }
 }
 
@@ -104,7 +153,7 @@ impl NewDestination {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations, JsonSchema)]
 #[changeset_options(treat_none_as_null = "true")]
 #[table_name = "gift_ideas"]
 pub struct GiftIdea {
@@ -119,6 +168,12 @@ pub struct GiftIdea {
     pub owner_id: i32,
     pub recipient_user_id: i32,
     pub reserved_by_user_id: Option<i32>,
+    pub date_purchased: Option<NaiveDateTime>,
+    pub date_deleted: Option<NaiveDateTime>,
+    pub deleted_by_user_id: Option<i32>,
+    pub event_id: Option<i32>,
+    pub date_archived: Option<NaiveDateTime>,
+    pub carried_over_from_id: Option<i32>,
 }
 // pub owner_id: i32,
 // pub recipient_user_id: i32,
@@ -126,7 +181,7 @@ pub struct GiftIdea {
 
 impl From<GiftIdea> for shared::GiftIdeaResponseBody {
     fn from(body: GiftIdea) -> shared::GiftIdeaResponseBody {
-        let gift_idea = shared::GiftIdeaResponseBody {
+        shared::GiftIdeaResponseBody {
             id: body.id,
             title: body.title,
             description: body.description,
@@ -135,8 +190,42 @@ impl From<GiftIdea> for shared::GiftIdeaResponseBody {
             owner_id: body.owner_id,
             recipient_user_id: body.recipient_user_id,
             reserved_by_user_id: body.reserved_by_user_id,
-        };
-        gift_idea
+            date_added: Some(body.date_added),
+            date_reserved: body.date_reserved,
+            date_purchased: body.date_purchased,
+            date_deleted: body.date_deleted,
+            event_id: body.event_id,
+            date_archived: body.date_archived,
+            carried_over_from_id: body.carried_over_from_id,
+        }
+    }
+}
+
+// `None` leaves a column alone, `Some(None)` sets it to NULL
+#[derive(Debug, Clone, AsChangeset)]
+#[table_name = "gift_ideas"]
+pub struct GiftIdeaChangeset {
+    pub title: Option<String>,
+    pub description: Option<Option<String>>,
+    pub price: Option<Option<String>>,
+    pub url: Option<Option<String>>,
+    pub recipient_user_id: Option<i32>,
+    pub event_id: Option<Option<i32>>,
+    pub date_last_modified: NaiveDateTime,
+}
+
+impl From<shared::UpdateGiftIdeaRequest> for GiftIdeaChangeset {
+    fn from(body: shared::UpdateGiftIdeaRequest) -> GiftIdeaChangeset {
+        let clearable = |value: Option<String>| value.map(|e| Some(e.trim().to_string()).filter(|e| !e.is_empty()));
+        GiftIdeaChangeset {
+            title: body.title.map(|e| e.trim().to_string()),
+            description: clearable(body.description),
+            price: clearable(body.price),
+            url: clearable(body.url),
+            recipient_user_id: body.recipient_user_id,
+            event_id: body.event_id.map(|e| Some(e).filter(|e| *e > 0)),
+            date_last_modified: Utc::now().naive_utc(),
+        }
     }
 }
 
@@ -154,6 +243,12 @@ pub struct NewGiftIdea {
     pub owner_id: i32,
     pub recipient_user_id: i32,
     pub reserved_by_user_id: Option<i32>,
+    pub date_purchased: Option<NaiveDateTime>,
+    pub date_deleted: Option<NaiveDateTime>,
+    pub deleted_by_user_id: Option<i32>,
+    pub event_id: Option<i32>,
+    pub date_archived: Option<NaiveDateTime>,
+    pub carried_over_from_id: Option<i32>,
 }
 
 impl NewGiftIdea {
@@ -169,6 +264,12 @@ impl NewGiftIdea {
             owner_id,
             recipient_user_id,
             reserved_by_user_id: None,
+            date_purchased: None,
+            date_deleted: None,
+            deleted_by_user_id: None,
+            event_id: None,
+            date_archived: None,
+            carried_over_from_id: None,
         }
     }
 }
@@ -179,10 +280,124 @@ impl From<shared::GiftIdeaRequestBody> for NewGiftIdea {
         gift_idea.description = body.description;
         gift_idea.price = body.price;
         gift_idea.url = body.url;
+        gift_idea.event_id = body.event_id.filter(|e| *e > 0);
         gift_idea
     }
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[belongs_to(GiftIdea)]
+#[table_name = "gift_idea_attachments"]
+pub struct GiftIdeaAttachment {
+    pub id: i32,
+    pub gift_idea_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i32,
+    pub storage_key: String,
+    pub thumbnail_storage_key: String,
+    pub date_added: NaiveDateTime,
+    pub uploaded_by_user_id: Option<i32>,
+}
+
+impl GiftIdeaAttachment {
+    /// Whoever uploaded it, or the owner of the idea it's on.
+    pub fn can_delete(&self, gift: &GiftIdea, uid: i32) -> bool {
+        self.uploaded_by_user_id == Some(uid) || gift.owner_id == uid
+    }
+}
+
+impl From<GiftIdeaAttachment> for shared::AttachmentResponseBody {
+    fn from(body: GiftIdeaAttachment) -> shared::AttachmentResponseBody {
+        shared::AttachmentResponseBody {
+            id: body.id,
+            gift_idea_id: body.gift_idea_id,
+            file_name: body.file_name,
+            content_type: body.content_type,
+            size: body.size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Insertable, AsChangeset, Associations)]
+#[table_name = "gift_idea_attachments"]
+pub struct NewGiftIdeaAttachment {
+    pub gift_idea_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i32,
+    pub storage_key: String,
+    pub thumbnail_storage_key: String,
+    pub date_added: NaiveDateTime,
+    pub uploaded_by_user_id: Option<i32>,
+}
+
+impl NewGiftIdeaAttachment {
+    pub fn new(gift_idea_id: i32, file_name: String, content_type: String, size: i32, uploaded_by_user_id: i32) -> NewGiftIdeaAttachment {
+        let key = uuid::Uuid::new_v4().to_simple().to_string();
+        NewGiftIdeaAttachment {
+            gift_idea_id,
+            file_name,
+            content_type,
+            size,
+            thumbnail_storage_key: format!("{}_thumbnail", key),
+            storage_key: key,
+            date_added: Utc::now().naive_utc(),
+            uploaded_by_user_id: Some(uploaded_by_user_id),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[belongs_to(GiftIdea)]
+#[belongs_to(User, foreign_key = "author_user_id")]
+#[table_name = "gift_idea_comments"]
+pub struct GiftIdeaComment {
+    pub id: i32,
+    pub gift_idea_id: i32,
+    pub author_user_id: i32,
+    pub body: String,
+    pub date_added: NaiveDateTime,
+    pub date_last_modified: NaiveDateTime,
+}
+
+impl GiftIdeaComment {
+    pub fn into_response(self, author: &User) -> shared::CommentResponseBody {
+        shared::CommentResponseBody {
+            id: self.id,
+            gift_idea_id: self.gift_idea_id,
+            author_user_id: self.author_user_id,
+            author_name: author.display_name(),
+            body: self.body,
+            date_added: self.date_added,
+            date_last_modified: self.date_last_modified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Insertable, AsChangeset, Associations)]
+#[table_name = "gift_idea_comments"]
+pub struct NewGiftIdeaComment {
+    pub gift_idea_id: i32,
+    pub author_user_id: i32,
+    pub body: String,
+    pub date_added: NaiveDateTime,
+    pub date_last_modified: NaiveDateTime,
+}
+
+impl NewGiftIdeaComment {
+    pub fn new(gift_idea_id: i32, author_user_id: i32, body: String) -> NewGiftIdeaComment {
+        let now = Utc::now().naive_utc();
+        NewGiftIdeaComment {
+            gift_idea_id,
+            author_user_id,
+            body,
+            date_added: now,
+            date_last_modified: now,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
 #[belongs_to(User)]
 #[belongs_to(Destination)]
@@ -205,3 +420,411 @@ impl NewUserDestination {
         NewUserDestination { user_id, destination_id }
     }
 }
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[belongs_to(User, foreign_key = "owner_id")]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "events"]
+pub struct Event {
+    pub id: i32,
+    pub name: String,
+    pub event_date: NaiveDate,
+    pub owner_id: i32,
+    pub date_added: NaiveDateTime,
+    pub date_reminder_sent: Option<NaiveDateTime>,
+    pub date_archived: Option<NaiveDateTime>,
+    // the group it's shared with; without one it's shared with every group its owner is in
+    pub group_id: Option<i32>,
+}
+
+impl From<Event> for shared::EventResponseBody {
+    fn from(body: Event) -> shared::EventResponseBody {
+        shared::EventResponseBody {
+            id: body.id,
+            name: body.name,
+            event_date: body.event_date,
+            owner_id: body.owner_id,
+            date_archived: body.date_archived,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Insertable, AsChangeset, Associations)]
+#[table_name = "events"]
+pub struct NewEvent {
+    pub name: String,
+    pub event_date: NaiveDate,
+    pub owner_id: i32,
+    pub date_added: NaiveDateTime,
+    pub date_reminder_sent: Option<NaiveDateTime>,
+    pub date_archived: Option<NaiveDateTime>,
+    pub group_id: Option<i32>,
+}
+
+impl NewEvent {
+    pub fn new(name: String, event_date: NaiveDate, owner_id: i32) -> NewEvent {
+        NewEvent {
+            name,
+            event_date,
+            owner_id,
+            date_added: Utc::now().naive_utc(),
+            date_reminder_sent: None,
+            date_archived: None,
+            group_id: None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, AsChangeset)]
+#[table_name = "groups"]
+pub struct Group {
+    pub id: i32,
+    pub name: String,
+    pub date_added: NaiveDateTime,
+}
+
+impl From<Group> for shared::GroupBody {
+    fn from(group: Group) -> shared::GroupBody {
+        shared::GroupBody { id: group.id, name: group.name }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Insertable)]
+#[table_name = "groups"]
+pub struct NewGroup {
+    pub name: String,
+    pub date_added: NaiveDateTime,
+}
+
+impl NewGroup {
+    pub fn new(name: String) -> NewGroup {
+        NewGroup {
+            name,
+            date_added: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, Associations)]
+#[primary_key(group_id, user_id)]
+#[belongs_to(Group)]
+#[belongs_to(User)]
+#[table_name = "group_members"]
+pub struct GroupMember {
+    pub group_id: i32,
+    pub user_id: i32,
+    pub date_added: NaiveDateTime,
+}
+
+impl GroupMember {
+    pub fn new(group_id: i32, user_id: i32) -> GroupMember {
+        GroupMember {
+            group_id,
+            user_id,
+            date_added: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, AsChangeset, Associations)]
+#[belongs_to(User)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "user_dates"]
+pub struct UserDate {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: String,
+    pub label: Option<String>,
+    pub month: i32,
+    pub day: i32,
+    pub year: Option<i32>,
+    pub year_visible: bool,
+    pub date_added: NaiveDateTime,
+    pub date_last_event: Option<NaiveDate>,
+}
+
+impl UserDate {
+    /// What `viewer_id` gets to see of it: the year stays with its owner unless they've shared it.
+    pub fn into_body(self, viewer_id: i32, today: NaiveDate) -> shared::UserDateBody {
+        let show_year = self.year_visible || self.user_id == viewer_id;
+        shared::UserDateBody {
+            id: self.id,
+            user_id: self.user_id,
+            kind: shared::UserDateKind::parse(&self.kind).unwrap_or(shared::UserDateKind::Other),
+            next_occurrence: crate::occasions::next_occurrence(self.month as u32, self.day as u32, today),
+            label: self.label,
+            month: self.month as u32,
+            day: self.day as u32,
+            year: self.year.filter(|_| show_year),
+            year_visible: self.year_visible,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Insertable)]
+#[table_name = "user_dates"]
+pub struct NewUserDate {
+    pub user_id: i32,
+    pub kind: String,
+    pub label: Option<String>,
+    pub month: i32,
+    pub day: i32,
+    pub year: Option<i32>,
+    pub year_visible: bool,
+    pub date_added: NaiveDateTime,
+}
+
+impl NewUserDate {
+    pub fn new(user_id: i32, request: &shared::UserDateRequest) -> NewUserDate {
+        NewUserDate {
+            user_id,
+            kind: request.kind.as_str().to_string(),
+            label: request.label.clone(),
+            month: request.month as i32,
+            day: request.day as i32,
+            year: request.year,
+            year_visible: request.year_visible,
+            date_added: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[primary_key(user_id)]
+#[belongs_to(User)]
+#[table_name = "calendar_feeds"]
+pub struct CalendarFeed {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub include_reservations: bool,
+    pub date_added: NaiveDateTime,
+}
+
+impl From<CalendarFeed> for shared::CalendarFeedBody {
+    fn from(feed: CalendarFeed) -> shared::CalendarFeedBody {
+        shared::CalendarFeedBody {
+            enabled: true,
+            include_reservations: feed.include_reservations,
+            url: None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[primary_key(user_id)]
+#[belongs_to(User)]
+#[table_name = "notification_preferences"]
+pub struct NotificationPreferences {
+    pub user_id: i32,
+    pub new_gift_idea: bool,
+    pub reservation_released: bool,
+    pub event_reminder: bool,
+}
+
+impl NotificationPreferences {
+    pub fn new(user_id: i32) -> NotificationPreferences {
+        NotificationPreferences {
+            user_id,
+            new_gift_idea: true,
+            reservation_released: true,
+            event_reminder: true,
+        }
+    }
+}
+
+impl From<NotificationPreferences> for shared::NotificationPreferencesBody {
+    fn from(body: NotificationPreferences) -> shared::NotificationPreferencesBody {
+        shared::NotificationPreferencesBody {
+            new_gift_idea: body.new_gift_idea,
+            reservation_released: body.reservation_released,
+            event_reminder: body.event_reminder,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[belongs_to(User)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "notifications"]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: String,
+    pub subject: String,
+    pub body: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub date_added: NaiveDateTime,
+    pub date_next_attempt: NaiveDateTime,
+    pub date_sent: Option<NaiveDateTime>,
+    pub date_failed: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Insertable, AsChangeset, Associations)]
+#[table_name = "notifications"]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub kind: String,
+    pub subject: String,
+    pub body: String,
+    pub attempts: i32,
+    pub date_added: NaiveDateTime,
+    pub date_next_attempt: NaiveDateTime,
+}
+
+impl NewNotification {
+    pub fn new(user_id: i32, kind: &str, subject: String, body: String) -> NewNotification {
+        NewNotification {
+            user_id,
+            kind: kind.to_string(),
+            subject,
+            body,
+            attempts: 0,
+            date_added: Utc::now().naive_utc(),
+            date_next_attempt: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset, Associations)]
+#[belongs_to(User)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "user_tokens"]
+pub struct UserToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub purpose: String,
+    pub token_hash: String,
+    pub email: String,
+    pub date_added: NaiveDateTime,
+    pub date_expires: NaiveDateTime,
+    pub date_used: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Insertable, AsChangeset, Associations)]
+#[table_name = "user_tokens"]
+pub struct NewUserToken {
+    pub user_id: i32,
+    pub purpose: String,
+    pub token_hash: String,
+    pub email: String,
+    pub date_added: NaiveDateTime,
+    pub date_expires: NaiveDateTime,
+}
+
+impl NewUserToken {
+    pub fn new(user_id: i32, purpose: &str, token_hash: String, email: String, valid_for: chrono::Duration) -> NewUserToken {
+        NewUserToken {
+            user_id,
+            purpose: purpose.to_string(),
+            token_hash,
+            email,
+            date_added: Utc::now().naive_utc(),
+            date_expires: Utc::now().naive_utc() + valid_for,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Queryable, Identifiable, Associations)]
+#[table_name = "audit_log"]
+pub struct AuditEntry {
+    pub id: i32,
+    pub actor_user_id: Option<i32>,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub action: String,
+    pub changes: String,
+    pub date_added: NaiveDateTime,
+}
+
+impl AuditEntry {
+    pub fn into_response(self, actor: Option<&User>) -> shared::AuditEntryResponseBody {
+        shared::AuditEntryResponseBody {
+            id: self.id,
+            actor_user_id: self.actor_user_id,
+            actor_name: actor.map(|e| e.display_name()),
+            action: self.action,
+            changes: serde_json::from_str(&self.changes).unwrap_or_default(),
+            date_added: self.date_added,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Insertable)]
+#[table_name = "audit_log"]
+pub struct NewAuditEntry {
+    pub actor_user_id: Option<i32>,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub action: String,
+    pub changes: String,
+    pub date_added: NaiveDateTime,
+}
+
+impl NewAuditEntry {
+    pub fn new(actor_user_id: Option<i32>, entity_type: &str, entity_id: i32, action: &str, changes: &[shared::FieldChange]) -> NewAuditEntry {
+        NewAuditEntry {
+            actor_user_id,
+            entity_type: entity_type.to_string(),
+            entity_id,
+            action: action.to_string(),
+            changes: serde_json::to_string(changes).expect("field changes always serialize"),
+            date_added: Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable, AsChangeset)]
+#[primary_key(name)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "jobs"]
+pub struct Job {
+    pub name: String,
+    pub schedule: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub date_next_run: NaiveDateTime,
+    pub date_last_started: Option<NaiveDateTime>,
+    pub date_last_succeeded: Option<NaiveDateTime>,
+    pub date_last_failed: Option<NaiveDateTime>,
+    pub lease_owner: Option<String>,
+    pub date_lease_expires: Option<NaiveDateTime>,
+}
+
+impl Job {
+    pub fn new(name: &str, schedule: &str, date_next_run: NaiveDateTime) -> Job {
+        Job {
+            name: name.to_string(),
+            schedule: schedule.to_string(),
+            attempts: 0,
+            last_error: None,
+            date_next_run,
+            date_last_started: None,
+            date_last_succeeded: None,
+            date_last_failed: None,
+            lease_owner: None,
+            date_lease_expires: None,
+        }
+    }
+
+    pub fn is_running(&self, now: NaiveDateTime) -> bool {
+        self.date_lease_expires.is_some_and(|expires| expires > now)
+    }
+}
+
+impl From<Job> for shared::JobBody {
+    fn from(job: Job) -> shared::JobBody {
+        let running = job.is_running(Utc::now().naive_utc());
+        shared::JobBody {
+            name: job.name,
+            schedule: job.schedule,
+            running,
+            attempts: job.attempts,
+            last_error: job.last_error,
+            date_next_run: job.date_next_run,
+            date_last_started: job.date_last_started,
+            date_last_succeeded: job.date_last_succeeded,
+            date_last_failed: job.date_last_failed,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::models;
+use crate::schema::{notification_preferences, notifications, users};
+
+pub fn find_preferences(uid: i32, conn: &SqliteConnection) -> Result<models::NotificationPreferences, diesel::result::Error> {
+    let preferences = notification_preferences::table
+        .filter(notification_preferences::dsl::user_id.eq(uid))
+        .first::<models::NotificationPreferences>(conn)
+        .optional()?;
+    Ok(preferences.unwrap_or_else(|| models::NotificationPreferences::new(uid)))
+}
+
+pub fn save_preferences(preferences: &models::NotificationPreferences, conn: &SqliteConnection) -> Result<models::NotificationPreferences, diesel::result::Error> {
+    diesel::replace_into(notification_preferences::table).values(preferences).execute(conn)?;
+    find_preferences(preferences.user_id, conn)
+}
+
+/// Those of `user_ids` who haven't opted out of `kind` and aren't disabled.  Users without a
+/// preferences row get the defaults, which is everything turned on.
+pub fn find_subscribers(kind: &str, user_ids: &[i32], conn: &SqliteConnection) -> Result<Vec<models::User>, diesel::result::Error> {
+    let results = users::table
+        .left_join(notification_preferences::table)
+        .filter(users::dsl::id.eq_any(user_ids))
+        .filter(users::dsl::date_disabled.is_null())
+        .load::<(models::User, Option<models::NotificationPreferences>)>(conn)?;
+    Ok(results
+        .into_iter()
+        .filter(|(user, preferences)| {
+            let preferences = preferences.clone().unwrap_or_else(|| models::NotificationPreferences::new(user.id));
+            match kind {
+                crate::notifications::NEW_GIFT_IDEA => preferences.new_gift_idea,
+                crate::notifications::RESERVATION_RELEASED => preferences.reservation_released,
+                crate::notifications::EVENT_REMINDER => preferences.event_reminder,
+                _ => false,
+            }
+        })
+        .map(|(user, _)| user)
+        .collect())
+}
+
+pub fn queue(new_notification: &models::NewNotification, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(notifications::table).values(new_notification).execute(conn)?;
+    Ok(())
+}
+
+pub fn find_due(now: NaiveDateTime, limit: i64, conn: &SqliteConnection) -> Result<Vec<(models::Notification, models::User)>, diesel::result::Error> {
+    let results = notifications::table
+        .inner_join(users::table)
+        .filter(notifications::dsl::date_sent.is_null())
+        .filter(notifications::dsl::date_failed.is_null())
+        .filter(notifications::dsl::date_next_attempt.le(now))
+        .order(notifications::dsl::date_next_attempt.asc())
+        .limit(limit)
+        .load::<(models::Notification, models::User)>(conn)?;
+    Ok(results)
+}
+
+// the body isn't needed once it's delivered, so it isn't kept
+pub fn mark_sent(nid: i32, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    diesel::update(notifications::table.find(nid))
+        .set((
+            notifications::dsl::attempts.eq(notifications::dsl::attempts + 1),
+            notifications::dsl::body.eq(""),
+            notifications::dsl::date_sent.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// How long to wait before trying again after `attempts` failed tries: 1, 2, 4, 8... minutes,
+/// never more than 1024.
+pub fn retry_delay(attempts: i32) -> Duration {
+    Duration::minutes(1_i64 << (attempts.clamp(1, 11) - 1))
+}
+
+// gives up for good after `max_attempts`
+pub fn mark_failed(notification: &models::Notification, error: String, max_attempts: i32, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let attempts = notification.attempts + 1;
+    let date_failed = if attempts >= max_attempts { Some(now) } else { None };
+    diesel::update(notifications::table.find(notification.id))
+        .set((
+            notifications::dsl::attempts.eq(attempts),
+            notifications::dsl::last_error.eq(Some(error)),
+            notifications::dsl::date_next_attempt.eq(now + retry_delay(attempts)),
+            notifications::dsl::date_failed.eq(date_failed),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn delete_by_user(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::delete(notification_preferences::table.filter(notification_preferences::dsl::user_id.eq(uid))).execute(conn)?;
+    diesel::delete(notifications::table.filter(notifications::dsl::user_id.eq(uid))).execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_a_cap() {
+        let minutes = (1..=13).map(|attempts| retry_delay(attempts).num_minutes()).collect::<Vec<_>>();
+        assert_eq!(minutes, vec![1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 1024, 1024]);
+    }
+
+    #[test]
+    fn a_retry_is_never_immediate() {
+        assert_eq!(retry_delay(0), Duration::minutes(1));
+        assert_eq!(retry_delay(-1), Duration::minutes(1));
+    }
+}
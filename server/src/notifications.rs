@@ -0,0 +1,330 @@
+use chrono::{Duration, NaiveDate};
+use diesel::prelude::*;
+
+use crate::event_actions;
+use crate::gift_idea_actions;
+use crate::group_actions;
+use crate::mailer::Mailer;
+use crate::metrics;
+use crate::models;
+use crate::notification_actions;
+use crate::token_actions;
+use crate::trash;
+use crate::user_actions;
+use crate::DbPool;
+
+pub const NEW_GIFT_IDEA: &str = "new_gift_idea";
+pub const RESERVATION_RELEASED: &str = "reservation_released";
+pub const EVENT_REMINDER: &str = "event_reminder";
+
+pub const EVENT_REMINDER_DAYS: i64 = 7;
+
+const MAX_ATTEMPTS: i32 = 5;
+const BATCH_SIZE: i64 = 20;
+
+pub fn app_url() -> String {
+    std::env::var("APP_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".to_string())
+}
+
+fn describe(gift: &models::GiftIdea) -> String {
+    let mut lines = vec![gift.title.to_string()];
+    if let Some(description) = &gift.description {
+        lines.push(description.to_string());
+    }
+    if let Some(price) = &gift.price {
+        lines.push(format!("Price: {}", price));
+    }
+    if let Some(url) = &gift.url {
+        lines.push(url.to_string());
+    }
+    lines.join("\n")
+}
+
+fn new_gift_idea_template(gift: &models::GiftIdea, recipient: &models::User) -> (String, String) {
+    let subject = format!("New gift idea for {}", recipient.display_name());
+    let body = format!(
+        "A new gift idea was added for {}:\n\n{}\n\nLog in to reserve it before someone else does.",
+        recipient.display_name(),
+        describe(gift)
+    );
+    (subject, body)
+}
+
+fn reservation_released_template(gift: &models::GiftIdea, recipient: &models::User) -> (String, String) {
+    let subject = format!("Your reservation for {} was released", gift.title);
+    let body = format!(
+        "The gift idea you reserved for {} is no longer reserved by you, either because someone else released it or because it was removed:\n\n{}",
+        recipient.display_name(),
+        describe(gift)
+    );
+    (subject, body)
+}
+
+fn gift_idea_removed_template(gift: &models::GiftIdea, recipient: &models::User) -> (String, String) {
+    let subject = format!("{} was removed", gift.title);
+    let body = format!(
+        "The gift idea you reserved for {} was removed from the list.  Its owner has {} days to restore it, and your reservation comes back with it if they do:\n\n{}",
+        recipient.display_name(),
+        trash::retention().num_days(),
+        describe(gift)
+    );
+    (subject, body)
+}
+
+fn event_reminder_template(event: &models::Event) -> (String, String) {
+    let subject = format!("{} is coming up on {}", event.name, event.event_date.format("%A, %B %-d"));
+    let body = format!(
+        "Just a reminder that {} is on {}.  Now is a good time to check the gift ideas list and reserve something.",
+        event.name,
+        event.event_date.format("%Y-%m-%d")
+    );
+    (subject, body)
+}
+
+const VERIFY_EMAIL_SUBJECT: &str = "Please verify your email address";
+const RESET_PASSWORD_SUBJECT: &str = "Reset your password";
+
+fn verify_email_template(user: &models::User, token: &str) -> (String, String) {
+    let subject = VERIFY_EMAIL_SUBJECT.to_string();
+    let body = format!(
+        "Hi {},\n\nConfirm that this is your email address by opening the link below:\n\n{}/verify_email?token={}\n\nThe link expires in {} hours.",
+        user.display_name(),
+        app_url(),
+        token,
+        token_actions::valid_for(token_actions::VERIFY_EMAIL).num_hours()
+    );
+    (subject, body)
+}
+
+fn reset_password_template(user: &models::User, token: &str) -> (String, String) {
+    let subject = RESET_PASSWORD_SUBJECT.to_string();
+    let body = format!(
+        "Hi {},\n\nSomeone asked to reset the password for this account.  If it was you, choose a new password here:\n\n{}/reset_password?token={}\n\nThe link expires in {} minutes.  If it wasn't you, you can ignore this email.",
+        user.display_name(),
+        app_url(),
+        token,
+        token_actions::valid_for(token_actions::RESET_PASSWORD).num_minutes()
+    );
+    (subject, body)
+}
+
+// account emails go out regardless of notification preferences, and are queued without a body:
+// the link in it is made when it's sent, see `render`
+pub fn queue_email_verification(user: &models::User, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    let notification = models::NewNotification::new(user.id, token_actions::VERIFY_EMAIL, VERIFY_EMAIL_SUBJECT.to_string(), String::new());
+    notification_actions::queue(&notification, conn)
+}
+
+pub fn queue_password_reset(user: &models::User, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    let notification = models::NewNotification::new(user.id, token_actions::RESET_PASSWORD, RESET_PASSWORD_SUBJECT.to_string(), String::new());
+    notification_actions::queue(&notification, conn)
+}
+
+/// The subject and body to send for `notification`.  Account emails get a new token here, so the
+/// raw token only ever exists in the email itself; a retry issues another, which replaces it.
+fn render(notification: &models::Notification, user: &models::User, conn: &SqliteConnection) -> Result<(String, String), diesel::result::Error> {
+    match notification.kind.as_str() {
+        token_actions::VERIFY_EMAIL => Ok(verify_email_template(user, &token_actions::issue(user, token_actions::VERIFY_EMAIL, conn)?)),
+        token_actions::RESET_PASSWORD => Ok(reset_password_template(user, &token_actions::issue(user, token_actions::RESET_PASSWORD, conn)?)),
+        _ => Ok((notification.subject.to_string(), notification.body.to_string())),
+    }
+}
+
+pub fn queue_new_gift_idea(gift: &models::GiftIdea, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let recipient = match user_actions::find_by_id(gift.recipient_user_id, conn)? {
+        Some(recipient) => recipient,
+        None => return Ok(0),
+    };
+    let (subject, body) = new_gift_idea_template(gift, &recipient);
+    let mut candidate_ids = group_actions::find_co_member_ids(gift.recipient_user_id, conn)?;
+    candidate_ids.extend(gift_idea_actions::find_giver_ids(gift.recipient_user_id, conn)?);
+    candidate_ids.retain(|uid| *uid != gift.recipient_user_id && *uid != gift.owner_id);
+    let subscribers = notification_actions::find_subscribers(NEW_GIFT_IDEA, &candidate_ids, conn)?;
+    for user in subscribers.iter() {
+        notification_actions::queue(&models::NewNotification::new(user.id, NEW_GIFT_IDEA, subject.to_string(), body.to_string()), conn)?;
+    }
+    Ok(subscribers.len())
+}
+
+/// Lets the previous reserver know they no longer hold `gift`.  Nothing is sent when they
+/// released it themselves.
+pub fn queue_reservation_released(gift: &models::GiftIdea, released_by_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let reserved_by_user_id = match gift.reserved_by_user_id {
+        Some(uid) if Some(uid) != released_by_user_id => uid,
+        _ => return Ok(0),
+    };
+    let recipient = match user_actions::find_by_id(gift.recipient_user_id, conn)? {
+        Some(recipient) => recipient,
+        None => return Ok(0),
+    };
+    match notification_actions::find_subscribers(RESERVATION_RELEASED, &[reserved_by_user_id], conn)?.first() {
+        Some(user) => {
+            let (subject, body) = reservation_released_template(gift, &recipient);
+            notification_actions::queue(&models::NewNotification::new(user.id, RESERVATION_RELEASED, subject, body), conn)?;
+            Ok(1)
+        }
+        None => Ok(0),
+    }
+}
+
+/// Lets the reserver know `gift` went in the trash.  Nothing is sent when they deleted it
+/// themselves.
+pub fn queue_gift_idea_removed(gift: &models::GiftIdea, removed_by_user_id: Option<i32>, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let reserved_by_user_id = match gift.reserved_by_user_id {
+        Some(uid) if Some(uid) != removed_by_user_id => uid,
+        _ => return Ok(0),
+    };
+    let recipient = match user_actions::find_by_id(gift.recipient_user_id, conn)? {
+        Some(recipient) => recipient,
+        None => return Ok(0),
+    };
+    match notification_actions::find_subscribers(RESERVATION_RELEASED, &[reserved_by_user_id], conn)?.first() {
+        Some(user) => {
+            let (subject, body) = gift_idea_removed_template(gift, &recipient);
+            notification_actions::queue(&models::NewNotification::new(user.id, RESERVATION_RELEASED, subject, body), conn)?;
+            Ok(1)
+        }
+        None => Ok(0),
+    }
+}
+
+pub fn queue_event_reminders(today: NaiveDate, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let mut queued = 0;
+    for event in event_actions::find_needing_reminder(today, today + Duration::days(EVENT_REMINDER_DAYS), conn)? {
+        let (subject, body) = event_reminder_template(&event);
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let candidate_ids = event_actions::find_audience_ids(&event, conn)?;
+            for user in notification_actions::find_subscribers(EVENT_REMINDER, &candidate_ids, conn)? {
+                notification_actions::queue(&models::NewNotification::new(user.id, EVENT_REMINDER, subject.to_string(), body.to_string()), conn)?;
+                queued += 1;
+            }
+            event_actions::mark_reminder_sent(event.id, conn)
+        })?;
+    }
+    Ok(queued)
+}
+
+/// Sends a batch from the `notifications` outbox.  Rows are only ever written by request
+/// handlers, so a crash or restart just means the next run picks up where this one left off.
+pub fn deliver_pending(pool: &DbPool, mailer: &mut dyn Mailer) -> Result<usize, diesel::result::Error> {
+    // don't sit on the (only) connection while talking to the mail server
+    let due = {
+        let conn = pool.get().expect("couldn't get db connection from pool");
+        let mut due = Vec::new();
+        for (notification, user) in notification_actions::find_due(chrono::Utc::now().naive_utc(), BATCH_SIZE, &conn)? {
+            let (subject, body) = render(&notification, &user, &conn)?;
+            due.push((notification, user, subject, body));
+        }
+        due
+    };
+    let mut results = Vec::new();
+    for (notification, user, subject, body) in due.iter() {
+        results.push((notification, mailer.send(&user.email, subject, body)));
+    }
+    let conn = pool.get().expect("couldn't get db connection from pool");
+    let mut sent = 0;
+    for (notification, result) in results {
+        match result {
+            Ok(()) => {
+                notification_actions::mark_sent(notification.id, &conn)?;
+                metrics::NOTIFICATIONS_SENT.inc();
+                sent += 1;
+            }
+            Err(e) => {
+                warn!("failed to send notification {}: {}", notification.id, e);
+                metrics::NOTIFICATIONS_FAILED.inc();
+                notification_actions::mark_failed(notification, e.to_string(), MAX_ATTEMPTS, &conn)?;
+            }
+        }
+    }
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(first_name: Option<&str>) -> models::User {
+        models::User {
+            id: 1,
+            email: "jane@example.com".to_string(),
+            password: "secret".to_string(),
+            first_name: first_name.map(str::to_string),
+            last_name: None,
+            phone: None,
+            email_verified: true,
+            date_disabled: None,
+        }
+    }
+
+    fn gift_idea() -> models::GiftIdea {
+        let now = NaiveDate::from_ymd(2020, 6, 1).and_hms(12, 0, 0);
+        models::GiftIdea {
+            id: 7,
+            title: "Bread maker".to_string(),
+            description: Some("The small one".to_string()),
+            price: Some("$80".to_string()),
+            url: None,
+            date_added: now,
+            date_last_modified: now,
+            date_reserved: None,
+            owner_id: 2,
+            recipient_user_id: 1,
+            reserved_by_user_id: None,
+            date_purchased: None,
+            date_deleted: None,
+            deleted_by_user_id: None,
+            event_id: None,
+            date_archived: None,
+            carried_over_from_id: None,
+        }
+    }
+
+    #[test]
+    fn new_gift_idea_emails_name_the_recipient_and_describe_the_idea() {
+        let (subject, body) = new_gift_idea_template(&gift_idea(), &user(Some("Jane")));
+        assert_eq!(subject, "New gift idea for Jane");
+        assert!(body.contains("Bread maker\nThe small one\nPrice: $80"), "{}", body);
+        assert!(!body.contains("None"), "{}", body);
+    }
+
+    #[test]
+    fn recipients_without_a_name_go_by_their_email() {
+        let (subject, _) = new_gift_idea_template(&gift_idea(), &user(None));
+        assert_eq!(subject, "New gift idea for jane@example.com");
+    }
+
+    #[test]
+    fn removed_ideas_mention_how_long_they_can_be_restored() {
+        let (subject, body) = gift_idea_removed_template(&gift_idea(), &user(Some("Jane")));
+        assert_eq!(subject, "Bread maker was removed");
+        assert!(body.contains(&format!("{} days", trash::retention().num_days())), "{}", body);
+    }
+
+    #[test]
+    fn event_reminders_give_the_date() {
+        let event = models::Event {
+            id: 3,
+            name: "Jane's birthday".to_string(),
+            event_date: NaiveDate::from_ymd(2020, 6, 12),
+            owner_id: 1,
+            date_added: NaiveDate::from_ymd(2020, 6, 1).and_hms(12, 0, 0),
+            date_reminder_sent: None,
+            date_archived: None,
+            group_id: None,
+        };
+        let (subject, body) = event_reminder_template(&event);
+        assert_eq!(subject, "Jane's birthday is coming up on Friday, June 12");
+        assert!(body.contains("2020-06-12"), "{}", body);
+    }
+
+    #[test]
+    fn account_emails_link_to_the_app_with_the_token() {
+        let (subject, body) = verify_email_template(&user(Some("Jane")), "abc123");
+        assert_eq!(subject, VERIFY_EMAIL_SUBJECT);
+        assert!(body.contains(&format!("{}/verify_email?token=abc123", app_url())), "{}", body);
+        let (subject, body) = reset_password_template(&user(Some("Jane")), "abc123");
+        assert_eq!(subject, RESET_PASSWORD_SUBJECT);
+        assert!(body.contains(&format!("{}/reset_password?token=abc123", app_url())), "{}", body);
+    }
+}
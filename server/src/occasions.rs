@@ -0,0 +1,206 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use diesel::prelude::*;
+
+use crate::event_actions;
+use crate::group_actions;
+use crate::models;
+use crate::user_date_actions;
+
+const DEFAULT_DAYS_AHEAD: i64 = 30;
+const MAX_LABEL_LENGTH: usize = 100;
+
+/// How far ahead of a birthday or anniversary its event is created, from `DATE_EVENTS_DAYS_AHEAD`.
+pub fn days_ahead() -> Duration {
+    let days = std::env::var("DATE_EVENTS_DAYS_AHEAD")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_DAYS_AHEAD);
+    Duration::days(days)
+}
+
+/// The first time `month`/`day` comes round on or after `from`.  The 29th of February falls on
+/// the 28th in other years.
+pub fn next_occurrence(month: u32, day: u32, from: NaiveDate) -> NaiveDate {
+    let in_year = |year: i32| NaiveDate::from_ymd_opt(year, month, day).or_else(|| NaiveDate::from_ymd_opt(year, month, day - 1));
+    match in_year(from.year()) {
+        Some(date) if date >= from => date,
+        _ => in_year(from.year() + 1).unwrap_or(from),
+    }
+}
+
+/// What's wrong with a date from a profile form, if anything.
+pub fn validate(request: &shared::UserDateRequest, today: NaiveDate) -> Option<String> {
+    // 2000 was a leap year, so this allows the 29th of February
+    if NaiveDate::from_ymd_opt(2000, request.month, request.day).is_none() {
+        return Some("That's not a day of the year".to_string());
+    }
+    if let Some(year) = request.year {
+        match NaiveDate::from_ymd_opt(year, request.month, request.day) {
+            Some(date) if date <= today && year >= 1900 => (),
+            _ => return Some("That's not a past date".to_string()),
+        }
+    }
+    let label = request.label.as_deref().map(str::trim).unwrap_or_default();
+    if request.kind == shared::UserDateKind::Other && label.is_empty() {
+        return Some("Give the date a name".to_string());
+    }
+    if label.chars().count() > MAX_LABEL_LENGTH {
+        return Some(format!("Names are limited to {} characters", MAX_LABEL_LENGTH));
+    }
+    None
+}
+
+pub fn event_name(date: &models::UserDate, user: &models::User) -> String {
+    let label = date.label.as_deref().map(str::trim).filter(|label| !label.is_empty());
+    match (shared::UserDateKind::parse(&date.kind), label) {
+        (Some(shared::UserDateKind::Birthday), _) => format!("{}'s birthday", user.display_name()),
+        (Some(shared::UserDateKind::Anniversary), None) => format!("{}'s anniversary", user.display_name()),
+        (_, Some(label)) => format!("{}: {}", user.display_name(), label),
+        (_, None) => user.display_name(),
+    }
+}
+
+/// Creates an event, owned by the date's user, in each of their groups for every profile date
+/// coming up within `days_ahead` that doesn't have one for this occurrence yet.  A date whose user
+/// isn't in any group waits until they are.  The events' reminder emails then go out as for any
+/// other.
+pub fn create_due_events(today: NaiveDate, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    let cutoff = today + days_ahead();
+    let mut created = 0;
+    for (date, user) in user_date_actions::find_all_active(conn)? {
+        let occurrence = next_occurrence(date.month as u32, date.day as u32, today);
+        if occurrence > cutoff || date.date_last_event == Some(occurrence) {
+            continue;
+        }
+        let groups = group_actions::find_by_member(user.id, conn)?;
+        if groups.is_empty() {
+            continue;
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            for group in groups.iter() {
+                let mut new_event = models::NewEvent::new(event_name(&date, &user), occurrence, user.id);
+                new_event.group_id = Some(group.id);
+                event_actions::add(&new_event, conn)?;
+            }
+            user_date_actions::mark_event_created(date.id, occurrence, conn)
+        })?;
+        created += groups.len();
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user_actions;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    fn request(kind: shared::UserDateKind, label: Option<&str>, month: u32, day: u32, year: Option<i32>) -> shared::UserDateRequest {
+        shared::UserDateRequest {
+            kind,
+            label: label.map(str::to_string),
+            month,
+            day,
+            year,
+            year_visible: false,
+        }
+    }
+
+    #[test]
+    fn the_next_occurrence_is_on_or_after_the_day() {
+        assert_eq!(next_occurrence(7, 1, day(2020, 6, 1)), day(2020, 7, 1));
+        assert_eq!(next_occurrence(6, 1, day(2020, 6, 1)), day(2020, 6, 1));
+        assert_eq!(next_occurrence(5, 31, day(2020, 6, 1)), day(2021, 5, 31));
+        assert_eq!(next_occurrence(1, 1, day(2020, 12, 31)), day(2021, 1, 1));
+    }
+
+    #[test]
+    fn the_29th_of_february_falls_on_the_28th_outside_leap_years() {
+        assert_eq!(next_occurrence(2, 29, day(2020, 1, 1)), day(2020, 2, 29));
+        assert_eq!(next_occurrence(2, 29, day(2021, 1, 1)), day(2021, 2, 28));
+        assert_eq!(next_occurrence(2, 29, day(2021, 3, 1)), day(2022, 2, 28));
+    }
+
+    #[test]
+    fn dates_must_be_real_past_days() {
+        let today = day(2020, 6, 1);
+        let birthday = |month, day, year| validate(&request(shared::UserDateKind::Birthday, None, month, day, year), today);
+        assert_eq!(birthday(6, 1, Some(1980)), None);
+        assert_eq!(birthday(2, 29, None), None);
+        assert_eq!(birthday(2, 29, Some(1980)), None);
+        assert!(birthday(2, 30, None).is_some());
+        assert!(birthday(13, 1, None).is_some());
+        assert!(birthday(2, 29, Some(1981)).is_some());
+        assert!(birthday(6, 2, Some(2020)).is_some());
+        assert!(birthday(1, 1, Some(1899)).is_some());
+    }
+
+    #[test]
+    fn other_dates_need_a_short_name() {
+        let today = day(2020, 6, 1);
+        let other = |label| validate(&request(shared::UserDateKind::Other, label, 3, 14, None), today);
+        assert_eq!(other(Some("Name day")), None);
+        assert!(other(None).is_some());
+        assert!(other(Some("  ")).is_some());
+        assert!(other(Some("x".repeat(MAX_LABEL_LENGTH + 1).as_str())).is_some());
+        assert_eq!(validate(&request(shared::UserDateKind::Anniversary, None, 3, 14, None), today), None);
+    }
+
+    #[test]
+    fn events_are_created_once_in_each_of_the_users_groups() {
+        let conn = crate::test_connection();
+        let jane = user_actions::add(&models::NewUser::new("jane@example.com".to_string(), "password".to_string()), None, &conn).unwrap();
+        let loner = user_actions::add(&models::NewUser::new("loner@example.com".to_string(), "password".to_string()), None, &conn).unwrap();
+        let family = group_actions::add("Family".to_string(), None, &conn).unwrap();
+        let work = group_actions::add("Work".to_string(), None, &conn).unwrap();
+        group_actions::add_member(family.id, jane.id, None, &conn).unwrap();
+        group_actions::add_member(work.id, jane.id, None, &conn).unwrap();
+        let birthday = request(shared::UserDateKind::Birthday, None, 6, 20, None);
+        user_date_actions::add(&models::NewUserDate::new(jane.id, &birthday), &conn).unwrap();
+        user_date_actions::add(&models::NewUserDate::new(loner.id, &birthday), &conn).unwrap();
+
+        let today = day(2020, 6, 1);
+        assert_eq!(create_due_events(today, &conn).unwrap(), 2);
+        assert_eq!(create_due_events(today, &conn).unwrap(), 0);
+        let mut group_ids = event_actions::find_all(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|event| {
+                assert_eq!((event.owner_id, event.event_date), (jane.id, day(2020, 6, 20)));
+                event.group_id
+            })
+            .collect::<Vec<_>>();
+        group_ids.sort();
+        assert_eq!(group_ids, vec![Some(family.id), Some(work.id)]);
+    }
+
+    #[test]
+    fn a_moved_date_gets_a_new_event() {
+        let conn = crate::test_connection();
+        let jane = user_actions::add(&models::NewUser::new("jane@example.com".to_string(), "password".to_string()), None, &conn).unwrap();
+        let family = group_actions::add("Family".to_string(), None, &conn).unwrap();
+        group_actions::add_member(family.id, jane.id, None, &conn).unwrap();
+        let mut birthday = request(shared::UserDateKind::Birthday, None, 6, 20, None);
+        let date = user_date_actions::add(&models::NewUserDate::new(jane.id, &birthday), &conn).unwrap();
+        let today = day(2020, 6, 1);
+        create_due_events(today, &conn).unwrap();
+
+        birthday.year = Some(1980);
+        let updated = user_date_actions::update(date.id, jane.id, &models::NewUserDate::new(jane.id, &birthday), &conn)
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.date_last_event, Some(day(2020, 6, 20)));
+        assert_eq!(create_due_events(today, &conn).unwrap(), 0);
+
+        birthday.day = 22;
+        let updated = user_date_actions::update(date.id, jane.id, &models::NewUserDate::new(jane.id, &birthday), &conn)
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.date_last_event, None);
+        assert_eq!(create_due_events(today, &conn).unwrap(), 1);
+        assert_eq!(event_actions::find_all(&conn).unwrap().last().unwrap().event_date, day(2020, 6, 22));
+    }
+}
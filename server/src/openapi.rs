@@ -0,0 +1,841 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::models;
+
+const SESSION_COOKIE: &str = "gifts_session";
+
+/// What goes over the wire in a request or response.
+pub enum Body {
+    Empty,
+    Json(fn(&mut SchemaGenerator) -> Schema),
+    Multipart,
+    Binary(&'static str),
+    EventStream,
+    Html,
+}
+
+fn json<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// One handler in `main.rs`; `path` is as written in its route attribute, relative to `/v1/`.
+pub struct Route {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub signed_in: bool,
+    pub request: Body,
+    pub response: Body,
+}
+
+fn route(method: &'static str, path: &'static str, summary: &'static str, signed_in: bool, request: Body, response: Body) -> Route {
+    Route {
+        method,
+        path,
+        summary,
+        signed_in,
+        request,
+        response,
+    }
+}
+
+// Written out by hand: actix doesn't let us list the routes an App has, so this can't be built
+// from `v1` in main.rs.  The tests below read main.rs instead and fail when a handler `v1`
+// registers isn't here, or isn't registered through a route attribute they can read.  The
+// schemas themselves come from the types' JsonSchema derives.
+pub fn routes() -> Vec<Route> {
+    vec![
+        route(
+            "post",
+            "users/add",
+            "Register a user and sign them in",
+            false,
+            Body::Json(json::<shared::RegisterRequestBody>),
+            Body::Json(json::<shared::LoggedUser>),
+        ),
+        route(
+            "post",
+            "users/login",
+            "Sign in; null when the email or password is wrong",
+            false,
+            Body::Json(json::<shared::LoginRequestBody>),
+            Body::Json(json::<Option<shared::LoggedUser>>),
+        ),
+        route("post", "users/logout", "Sign out", false, Body::Empty, Body::Json(json::<bool>)),
+        route(
+            "post",
+            "users/verify_email/request",
+            "Email a new verification link; false when already verified",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "post",
+            "users/verify_email/redeem",
+            "Verify an email address with an emailed token",
+            false,
+            Body::Json(json::<shared::VerifyEmailRequestBody>),
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "post",
+            "users/reset_password/request",
+            "Email a password reset link, if the address is known",
+            false,
+            Body::Json(json::<shared::ForgotPasswordRequestBody>),
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "post",
+            "users/reset_password/redeem",
+            "Set a new password with an emailed token",
+            false,
+            Body::Json(json::<shared::ResetPasswordRequestBody>),
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "post",
+            "users/change_password",
+            "Change the signed in user's password",
+            true,
+            Body::Json(json::<shared::ChangePasswordRequestBody>),
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "post",
+            "users/delete",
+            "Delete the signed in user's account",
+            true,
+            Body::Json(json::<shared::DeleteAccountRequestBody>),
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "patch",
+            "users/update",
+            "Update the signed in user's profile",
+            true,
+            Body::Json(json::<shared::UpdateProfileRequest>),
+            Body::Json(json::<shared::LoggedUser>),
+        ),
+        route("get", "users/find_all", "List users", false, Body::Empty, Body::Json(json::<Vec<shared::MinimalUserInfo>>)),
+        route(
+            "get",
+            "users/find_by_id/{user_id}",
+            "Find a user by id",
+            false,
+            Body::Empty,
+            Body::Json(json::<shared::MinimalUserInfo>),
+        ),
+        route(
+            "get",
+            "users/find_by_email/{user_email}",
+            "Find a user by email address",
+            false,
+            Body::Empty,
+            Body::Json(json::<shared::MinimalUserInfo>),
+        ),
+        route(
+            "get",
+            "users/dates/find_by_user/{user_id}",
+            "A user's birthdays and other yearly dates; years they keep private are left out",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::UserDateBody>>),
+        ),
+        route(
+            "post",
+            "users/dates/add",
+            "Add a yearly date to the signed in user's profile",
+            true,
+            Body::Json(json::<shared::UserDateRequest>),
+            Body::Json(json::<shared::UserDateBody>),
+        ),
+        route(
+            "put",
+            "users/dates/update/{date_id}",
+            "Change one of the signed in user's dates",
+            true,
+            Body::Json(json::<shared::UserDateRequest>),
+            Body::Json(json::<shared::UserDateBody>),
+        ),
+        route(
+            "delete",
+            "users/dates/delete/{date_id}",
+            "Remove one of the signed in user's dates",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "post",
+            "gifts/add",
+            "Add a gift idea",
+            false,
+            Body::Json(json::<shared::GiftIdeaRequestBody>),
+            Body::Json(json::<models::GiftIdea>),
+        ),
+        route(
+            "post",
+            "gifts/import_wishlist/preview",
+            "Read a pasted wishlist and check each row, without adding anything",
+            true,
+            Body::Json(json::<shared::WishlistPreviewRequest>),
+            Body::Json(json::<Vec<shared::WishlistRow>>),
+        ),
+        route(
+            "post",
+            "gifts/import_wishlist",
+            "Add the rows of a wishlist as gift ideas for someone",
+            true,
+            Body::Json(json::<shared::WishlistImportRequest>),
+            Body::Json(json::<shared::WishlistImportResponseBody>),
+        ),
+        route(
+            "patch",
+            "gifts/update/{gift_id}",
+            "Change some fields of one of your gift ideas",
+            true,
+            Body::Json(json::<shared::UpdateGiftIdeaRequest>),
+            Body::Json(json::<shared::GiftIdeaResponseBody>),
+        ),
+        route(
+            "post",
+            "gifts/link_metadata",
+            "Fetch the title, description, price and image of a product page",
+            true,
+            Body::Json(json::<shared::LinkMetadataRequestBody>),
+            Body::Json(json::<shared::LinkMetadataResponseBody>),
+        ),
+        route(
+            "get",
+            "gifts/find_by_event/{event_id}",
+            "List the gift ideas filed under an event, archived or not, that the signed in user gets to see",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::GiftIdeaResponseBody>>),
+        ),
+        route(
+            "get",
+            "gifts/find_by_id/{gift_id}",
+            "Find a gift idea by id, as the signed in user gets to see it",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::GiftIdeaResponseBody>),
+        ),
+        route(
+            "patch",
+            "gifts/reserve/{gift_id}",
+            "Reserve a gift idea; not one for you or one that's already reserved",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::GiftIdeaResponseBody>),
+        ),
+        route(
+            "patch",
+            "gifts/unreserve/{gift_id}",
+            "Release a reservation you hold",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::GiftIdeaResponseBody>),
+        ),
+        route(
+            "patch",
+            "gifts/purchase/{gift_id}",
+            "Mark a gift idea you reserved as purchased",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::GiftIdeaResponseBody>),
+        ),
+        route(
+            "get",
+            "gifts/find_all",
+            "List the active gift ideas the signed in user gets to see",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::GiftIdeaResponseBody>>),
+        ),
+        route(
+            "get",
+            "gifts/history/{gift_id}",
+            "A gift idea's change history",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::AuditEntryResponseBody>>),
+        ),
+        route(
+            "delete",
+            "gifts/delete/{gift_id}",
+            "Move one of your gift ideas to the trash",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "get",
+            "gifts/trash",
+            "List your gift ideas that can still be restored",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::GiftIdeaResponseBody>>),
+        ),
+        route(
+            "patch",
+            "gifts/restore/{gift_id}",
+            "Restore one of your gift ideas from the trash",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::GiftIdeaResponseBody>),
+        ),
+        route(
+            "get",
+            "comments/find_by_gift_idea/{gift_id}",
+            "List the comments on a gift idea",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::CommentResponseBody>>),
+        ),
+        route(
+            "post",
+            "comments/add/{gift_id}",
+            "Comment on a gift idea",
+            true,
+            Body::Json(json::<shared::CommentRequestBody>),
+            Body::Json(json::<shared::CommentResponseBody>),
+        ),
+        route(
+            "patch",
+            "comments/update/{comment_id}",
+            "Edit one of your comments",
+            true,
+            Body::Json(json::<shared::CommentRequestBody>),
+            Body::Json(json::<shared::CommentResponseBody>),
+        ),
+        route(
+            "delete",
+            "comments/delete/{comment_id}",
+            "Delete one of your comments",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "get",
+            "events/find_all",
+            "List the events shared with you, past and archived ones included",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::EventResponseBody>>),
+        ),
+        route(
+            "get",
+            "events/find_by_id/{event_id}",
+            "Find an event shared with the signed in user by id",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::EventResponseBody>),
+        ),
+        route(
+            "post",
+            "events/add",
+            "Add an event",
+            true,
+            Body::Json(json::<shared::EventRequestBody>),
+            Body::Json(json::<shared::EventResponseBody>),
+        ),
+        route(
+            "delete",
+            "events/delete/{event_id}",
+            "Delete one of your events",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "patch",
+            "events/archive/{event_id}",
+            "Archive one of your events along with its gift ideas",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::EventResponseBody>),
+        ),
+        route(
+            "post",
+            "events/carry_over/{event_id}",
+            "Copy an archived event's un-purchased gift ideas into a new event",
+            true,
+            Body::Json(json::<shared::CarryOverRequestBody>),
+            Body::Json(json::<shared::CarryOverResponseBody>),
+        ),
+        route(
+            "get",
+            "notifications/preferences",
+            "The signed in user's email preferences",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::NotificationPreferencesBody>),
+        ),
+        route(
+            "put",
+            "notifications/preferences",
+            "Change the signed in user's email preferences",
+            true,
+            Body::Json(json::<shared::NotificationPreferencesBody>),
+            Body::Json(json::<shared::NotificationPreferencesBody>),
+        ),
+        route(
+            "get",
+            "calendar/feed",
+            "Whether the signed in user has a calendar feed",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::CalendarFeedBody>),
+        ),
+        route(
+            "post",
+            "calendar/feed",
+            "Create the signed in user's calendar feed, or give it a new address; the address is only returned here",
+            true,
+            Body::Json(json::<shared::CalendarFeedRequest>),
+            Body::Json(json::<shared::CalendarFeedBody>),
+        ),
+        route(
+            "put",
+            "calendar/feed",
+            "Change what the signed in user's calendar feed includes",
+            true,
+            Body::Json(json::<shared::CalendarFeedRequest>),
+            Body::Json(json::<shared::CalendarFeedBody>),
+        ),
+        route(
+            "delete",
+            "calendar/feed",
+            "Turn off the signed in user's calendar feed",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "get",
+            "calendar/ics/{token}",
+            "A calendar feed, by the secret in its address",
+            false,
+            Body::Empty,
+            Body::Binary("text/calendar"),
+        ),
+        route("get", "updates/stream", "Server-sent gift idea events", true, Body::Empty, Body::EventStream),
+        route(
+            "post",
+            "attachments/add/{gift_id}",
+            "Upload images for a gift idea",
+            true,
+            Body::Multipart,
+            Body::Json(json::<Vec<shared::AttachmentResponseBody>>),
+        ),
+        route(
+            "get",
+            "attachments/find_all",
+            "List the attachments on gift ideas you can see",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::AttachmentResponseBody>>),
+        ),
+        route(
+            "get",
+            "attachments/find_by_gift_idea/{gift_id}",
+            "List the attachments on a gift idea you can see",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::AttachmentResponseBody>>),
+        ),
+        route(
+            "get",
+            "attachments/download/{attachment_id}",
+            "Download an attachment on a gift idea you can see",
+            true,
+            Body::Empty,
+            Body::Binary("application/octet-stream"),
+        ),
+        route(
+            "get",
+            "attachments/thumbnail/{attachment_id}",
+            "Download the thumbnail of an attachment on a gift idea you can see",
+            true,
+            Body::Empty,
+            Body::Binary("image/png"),
+        ),
+        route(
+            "delete",
+            "attachments/delete/{attachment_id}",
+            "Delete an attachment you uploaded or that's on your gift idea",
+            true,
+            Body::Empty,
+            Body::Json(json::<bool>),
+        ),
+        route(
+            "get",
+            "export/json",
+            "Export your gift ideas, events and reservations",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::ExportDocument>),
+        ),
+        route("get", "export/csv", "Export your gift ideas as a spreadsheet", true, Body::Empty, Body::Binary("text/csv")),
+        route("get", "groups/mine", "The groups you're in", true, Body::Empty, Body::Json(json::<Vec<shared::GroupBody>>)),
+        route(
+            "get",
+            "export/group/{group_id}/json",
+            "Export every member's gift ideas and events for a group you're in",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::ExportDocument>),
+        ),
+        route(
+            "get",
+            "export/group/{group_id}/csv",
+            "Export a group you're in as a spreadsheet",
+            true,
+            Body::Empty,
+            Body::Binary("text/csv"),
+        ),
+        route(
+            "post",
+            "import",
+            "Import an export",
+            true,
+            Body::Json(json::<shared::ExportDocument>),
+            Body::Json(json::<shared::ImportReport>),
+        ),
+        route(
+            "post",
+            "import/dry_run",
+            "Report what importing an export would do, without doing it",
+            true,
+            Body::Json(json::<shared::ExportDocument>),
+            Body::Json(json::<shared::ImportReport>),
+        ),
+        route(
+            "get",
+            "admin/jobs",
+            "The server's background jobs and how they last went; admins only",
+            true,
+            Body::Empty,
+            Body::Json(json::<Vec<shared::JobBody>>),
+        ),
+        route(
+            "post",
+            "admin/jobs/run/{job_name}",
+            "Run a background job now; admins only",
+            true,
+            Body::Empty,
+            Body::Json(json::<shared::JobBody>),
+        ),
+        route("get", "openapi.json", "This document", false, Body::Empty, Body::Json(json::<Value>)),
+        route("get", "docs", "Browsable documentation for this API", false, Body::Empty, Body::Html),
+    ]
+}
+
+// `{gift_id}` and friends are numeric ids; anything else, like `{user_email}`, is a string
+fn path_parameters(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter(|segment| segment.starts_with('{') && segment.ends_with('}'))
+        .map(|segment| {
+            let name = &segment[1..segment.len() - 1];
+            let schema = if name.ends_with("_id") {
+                json!({ "type": "integer", "format": "int32" })
+            } else {
+                json!({ "type": "string" })
+            };
+            json!({ "name": name, "in": "path", "required": true, "schema": schema })
+        })
+        .collect()
+}
+
+fn content(body: &Body, gen: &mut SchemaGenerator) -> Option<Value> {
+    match body {
+        Body::Empty => None,
+        Body::Json(schema) => Some(json!({ "application/json": { "schema": schema(gen) } })),
+        Body::Multipart => Some(json!({
+            "multipart/form-data": {
+                "schema": { "type": "object", "additionalProperties": { "type": "string", "format": "binary" } }
+            }
+        })),
+        Body::Binary(content_type) => Some(json!({ *content_type: { "schema": { "type": "string", "format": "binary" } } })),
+        Body::EventStream => Some(json!({ "text/event-stream": { "schema": json::<shared::GiftIdeaEvent>(gen) } })),
+        Body::Html => Some(json!({ "text/html": { "schema": { "type": "string" } } })),
+    }
+}
+
+fn operation(route: &Route, gen: &mut SchemaGenerator) -> Value {
+    let mut ok = json!({ "description": "OK" });
+    if let Some(content) = content(&route.response, gen) {
+        ok["content"] = content;
+    }
+    let mut responses = Map::new();
+    responses.insert("200".to_string(), ok);
+    if route.signed_in {
+        responses.insert("401".to_string(), json!({ "description": "Not signed in" }));
+    }
+
+    let mut operation = json!({
+        "summary": route.summary,
+        "tags": [route.path.split('/').next().unwrap_or_default()],
+        "parameters": path_parameters(route.path),
+        "responses": responses,
+    });
+    if let Some(content) = content(&route.request, gen) {
+        operation["requestBody"] = json!({ "required": true, "content": content });
+    }
+    if route.signed_in {
+        operation["security"] = json!([{ "session": [] }]);
+    }
+    operation
+}
+
+/// The OpenAPI 3 document for everything under `/v1/`.
+pub fn document() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for route in routes().iter() {
+        let operation = operation(route, &mut gen);
+        let item = paths.entry(format!("/{}", route.path)).or_insert_with(|| json!({}));
+        item[route.method] = operation;
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "rusty-gifts",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/v1" }],
+        "paths": paths,
+        "components": {
+            "schemas": gen.definitions(),
+            "securitySchemes": {
+                "session": { "type": "apiKey", "in": "cookie", "name": SESSION_COOKIE },
+            },
+        },
+    })
+}
+
+pub const DOCS_PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>rusty-gifts API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@3/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@3/swagger-ui-bundle.js"></script>
+  <script>
+    SwaggerUIBundle({ url: "/v1/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashMap};
+
+    const METHODS: &[&str] = &["get", "post", "put", "patch", "delete"];
+
+    /// `(method, path, handler)` for every route attribute in main.rs, skipping commented out ones.
+    fn declared_routes() -> Vec<(String, String, String)> {
+        let lines = include_str!("main.rs").lines().map(str::trim).collect::<Vec<_>>();
+        let mut routes = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            for method in METHODS {
+                let prefix = format!("#[{}(\"", method);
+                if line.starts_with(&prefix) && line.ends_with("\")]") {
+                    let path = &line[prefix.len()..line.len() - "\")]".len()];
+                    let handler = lines[i + 1..]
+                        .iter()
+                        .find_map(|line| line.split("async fn ").nth(1))
+                        .and_then(|rest| rest.split('(').next())
+                        .expect("route attribute without a handler");
+                    routes.push((method.to_string(), path.trim_start_matches('/').to_string(), handler.to_string()));
+                }
+            }
+        }
+        routes
+    }
+
+    /// The handlers `v1` in main.rs registers.
+    fn registered_handlers() -> Vec<String> {
+        let v1 = &functions()["v1"];
+        for other in &[".route(", "web::resource(", "web::scope(", ".configure("] {
+            assert!(!v1.contains(other), "v1 registers something with {}, which the tests can't check against routes()", other);
+        }
+        v1.split(".service(").skip(1).map(|rest| rest.split(')').next().unwrap().trim().to_string()).collect()
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let registered = registered_handlers();
+        assert!(registered.len() > 50, "found only {} handlers in v1", registered.len());
+        let declared_routes = declared_routes();
+        for handler in &registered {
+            assert!(
+                declared_routes.iter().any(|(_, _, declared)| declared == handler),
+                "{} is registered without a route attribute, so it can't be checked against routes()",
+                handler
+            );
+        }
+        let declared = declared_routes
+            .into_iter()
+            .filter(|(_, _, handler)| registered.contains(handler))
+            .map(|(method, path, _)| (method, path))
+            .collect::<BTreeSet<_>>();
+        let documented = routes().iter().map(|route| (route.method.to_string(), route.path.to_string())).collect::<BTreeSet<_>>();
+
+        let undocumented = declared.difference(&documented).collect::<Vec<_>>();
+        assert!(undocumented.is_empty(), "routes missing from openapi::routes(): {:?}", undocumented);
+        let stale = documented.difference(&declared).collect::<Vec<_>>();
+        assert!(stale.is_empty(), "openapi::routes() has routes main.rs doesn't serve: {:?}", stale);
+    }
+
+    /// Every function in main.rs by name, with its source down to the closing brace.
+    fn functions() -> HashMap<String, String> {
+        let mut functions = HashMap::new();
+        let mut current: Option<(String, Vec<&str>)> = None;
+        for line in include_str!("main.rs").lines() {
+            if let Some((name, mut source)) = current.take() {
+                source.push(line);
+                if line == "}" {
+                    functions.insert(name, source.join("\n"));
+                } else {
+                    current = Some((name, source));
+                }
+            } else if line.starts_with("async fn ") || line.starts_with("fn ") {
+                let name = line.split_once("fn ").unwrap().1.split(['(', '<']).next().unwrap().to_string();
+                current = Some((name, vec![line]));
+            }
+        }
+        functions
+    }
+
+    // the other functions of main.rs that `source` calls, leaving out method calls and paths
+    fn callees<'a>(source: &str, functions: &'a HashMap<String, String>) -> Vec<&'a String> {
+        functions
+            .iter()
+            .filter(|(_, body)| body.as_str() != source)
+            .filter(|(name, _)| {
+                source.match_indices(&format!("{}(", name)).any(|(i, _)| {
+                    let before = source[..i].chars().last().unwrap_or(' ');
+                    !before.is_alphanumeric() && !"_.:".contains(before)
+                })
+            })
+            .map(|(_, body)| body)
+            .collect()
+    }
+
+    // a handler needs a session if it, or a helper it calls, turns away anyone not signed in
+    fn requires_session(source: &str, functions: &HashMap<String, String>) -> bool {
+        source
+            .lines()
+            .any(|line| (line.contains("current_user_id(") || line.contains("current_admin_id(")) && line.contains(".await?"))
+            || callees(source, functions).into_iter().any(|callee| requires_session(callee, functions))
+    }
+
+    // "json:<type name>", "multipart" or "empty", from the handler's extractors or what its helpers read
+    fn request_kind(source: &str, functions: &HashMap<String, String>) -> String {
+        let signature = source.split('{').next().unwrap();
+        let between = |text: &str, start: &str, end: &str| text.split(start).nth(1).and_then(|rest| rest.split(end).next()).map(str::to_string);
+        let type_name = between(signature, "web::Json<", ">").or_else(|| between(source, "read_json::<", ">("));
+        if let Some(type_name) = type_name {
+            return format!("json:{}", type_name.rsplit("::").next().unwrap());
+        }
+        if signature.contains("Multipart") {
+            return "multipart".to_string();
+        }
+        callees(source, functions)
+            .into_iter()
+            .map(|callee| request_kind(callee, functions))
+            .find(|kind| kind != "empty")
+            .unwrap_or_else(|| "empty".to_string())
+    }
+
+    fn documented_request_kind(body: &Body) -> String {
+        match body {
+            Body::Json(schema) => {
+                let schema = serde_json::to_value(schema(&mut SchemaSettings::openapi3().into_generator())).unwrap();
+                let reference = schema["$ref"].as_str().expect("request bodies are named types");
+                format!("json:{}", reference.rsplit('/').next().unwrap())
+            }
+            Body::Multipart => "multipart".to_string(),
+            Body::Empty => "empty".to_string(),
+            _ => "other".to_string(),
+        }
+    }
+
+    #[test]
+    fn sessions_and_request_bodies_match_the_handlers() {
+        let functions = functions();
+        let documented = routes();
+        for (method, path, handler) in declared_routes() {
+            let route = match documented.iter().find(|route| route.method == method && route.path == path) {
+                Some(route) => route,
+                None => continue,
+            };
+            let source = &functions[&handler];
+            assert_eq!(route.signed_in, requires_session(source, &functions), "signed_in for {} {}", method, path);
+            assert_eq!(
+                documented_request_kind(&route.request),
+                request_kind(source, &functions),
+                "request body for {} {}",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn routes_are_not_documented_twice() {
+        let routes = routes();
+        let mut seen = BTreeSet::new();
+        for route in routes.iter() {
+            assert!(seen.insert((route.method, route.path)), "{} {} is documented twice", route.method, route.path);
+        }
+    }
+
+    fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::String(reference)) = fields.get("$ref") {
+                    refs.push(reference.clone());
+                }
+                fields.values().for_each(|value| collect_refs(value, refs));
+            }
+            Value::Array(values) => values.iter().for_each(|value| collect_refs(value, refs)),
+            _ => (),
+        }
+    }
+
+    #[test]
+    fn every_schema_reference_resolves() {
+        let document = document();
+        let mut refs = Vec::new();
+        collect_refs(&document, &mut refs);
+        assert!(!refs.is_empty());
+        for reference in refs {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            assert!(document["components"]["schemas"].get(name).is_some(), "dangling reference: {}", reference);
+        }
+    }
+
+    #[test]
+    fn path_parameters_match_the_path() {
+        let document = document();
+        for route in routes() {
+            let operation = &document["paths"][format!("/{}", route.path)][route.method];
+            let parameters = operation["parameters"].as_array().unwrap();
+            assert_eq!(parameters.len(), route.path.matches('{').count(), "{} {}", route.method, route.path);
+        }
+    }
+}
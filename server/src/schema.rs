@@ -1,3 +1,24 @@
+table! {
+    audit_log (id) {
+        id -> Integer,
+        actor_user_id -> Nullable<Integer>,
+        entity_type -> Text,
+        entity_id -> Integer,
+        action -> Text,
+        changes -> Text,
+        date_added -> Timestamp,
+    }
+}
+
+table! {
+    calendar_feeds (user_id) {
+        user_id -> Integer,
+        token_hash -> Text,
+        include_reservations -> Bool,
+        date_added -> Timestamp,
+    }
+}
+
 table! {
     destinations (id) {
         id -> Integer,
@@ -9,6 +30,44 @@ table! {
     }
 }
 
+table! {
+    events (id) {
+        id -> Integer,
+        name -> Text,
+        event_date -> Date,
+        owner_id -> Integer,
+        date_added -> Timestamp,
+        date_reminder_sent -> Nullable<Timestamp>,
+        date_archived -> Nullable<Timestamp>,
+        group_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    gift_idea_attachments (id) {
+        id -> Integer,
+        gift_idea_id -> Integer,
+        file_name -> Text,
+        content_type -> Text,
+        size -> Integer,
+        storage_key -> Text,
+        thumbnail_storage_key -> Text,
+        date_added -> Timestamp,
+        uploaded_by_user_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    gift_idea_comments (id) {
+        id -> Integer,
+        gift_idea_id -> Integer,
+        author_user_id -> Integer,
+        body -> Text,
+        date_added -> Timestamp,
+        date_last_modified -> Timestamp,
+    }
+}
+
 table! {
     gift_ideas (id) {
         id -> Integer,
@@ -22,6 +81,83 @@ table! {
         owner_id -> Integer,
         recipient_user_id -> Integer,
         reserved_by_user_id -> Nullable<Integer>,
+        date_purchased -> Nullable<Timestamp>,
+        date_deleted -> Nullable<Timestamp>,
+        deleted_by_user_id -> Nullable<Integer>,
+        event_id -> Nullable<Integer>,
+        date_archived -> Nullable<Timestamp>,
+        carried_over_from_id -> Nullable<Integer>,
+    }
+}
+
+table! {
+    group_members (group_id, user_id) {
+        group_id -> Integer,
+        user_id -> Integer,
+        date_added -> Timestamp,
+    }
+}
+
+table! {
+    groups (id) {
+        id -> Integer,
+        name -> Text,
+        date_added -> Timestamp,
+    }
+}
+
+table! {
+    jobs (name) {
+        name -> Text,
+        schedule -> Text,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
+        date_next_run -> Timestamp,
+        date_last_started -> Nullable<Timestamp>,
+        date_last_succeeded -> Nullable<Timestamp>,
+        date_last_failed -> Nullable<Timestamp>,
+        lease_owner -> Nullable<Text>,
+        date_lease_expires -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    notification_preferences (user_id) {
+        user_id -> Integer,
+        new_gift_idea -> Bool,
+        reservation_released -> Bool,
+        event_reminder -> Bool,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Integer,
+        user_id -> Integer,
+        kind -> Text,
+        subject -> Text,
+        body -> Text,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
+        date_added -> Timestamp,
+        date_next_attempt -> Timestamp,
+        date_sent -> Nullable<Timestamp>,
+        date_failed -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    user_dates (id) {
+        id -> Integer,
+        user_id -> Integer,
+        kind -> Text,
+        label -> Nullable<Text>,
+        month -> Integer,
+        day -> Integer,
+        year -> Nullable<Integer>,
+        year_visible -> Bool,
+        date_added -> Timestamp,
+        date_last_event -> Nullable<Date>,
     }
 }
 
@@ -33,6 +169,19 @@ table! {
     }
 }
 
+table! {
+    user_tokens (id) {
+        id -> Integer,
+        user_id -> Integer,
+        purpose -> Text,
+        token_hash -> Text,
+        email -> Text,
+        date_added -> Timestamp,
+        date_expires -> Timestamp,
+        date_used -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     users (id) {
         id -> Integer,
@@ -41,15 +190,43 @@ table! {
         first_name -> Nullable<Text>,
         last_name -> Nullable<Text>,
         phone -> Nullable<Text>,
+        email_verified -> Bool,
+        date_disabled -> Nullable<Timestamp>,
     }
 }
 
+joinable!(audit_log -> users (actor_user_id));
+joinable!(calendar_feeds -> users (user_id));
+joinable!(events -> groups (group_id));
+joinable!(events -> users (owner_id));
+joinable!(gift_ideas -> events (event_id));
+joinable!(gift_idea_attachments -> gift_ideas (gift_idea_id));
+joinable!(gift_idea_comments -> gift_ideas (gift_idea_id));
+joinable!(gift_idea_comments -> users (author_user_id));
+joinable!(group_members -> groups (group_id));
+joinable!(group_members -> users (user_id));
+joinable!(notification_preferences -> users (user_id));
+joinable!(notifications -> users (user_id));
+joinable!(user_dates -> users (user_id));
 joinable!(user_destinations -> destinations (destination_id));
 joinable!(user_destinations -> users (user_id));
+joinable!(user_tokens -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    audit_log,
+    calendar_feeds,
     destinations,
+    events,
+    gift_idea_attachments,
+    gift_idea_comments,
     gift_ideas,
+    group_members,
+    groups,
+    jobs,
+    notification_preferences,
+    notifications,
+    user_dates,
     user_destinations,
+    user_tokens,
     users,
 );
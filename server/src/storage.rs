@@ -0,0 +1,79 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::{collections::HashMap, sync::Mutex};
+
+/// Blob storage for uploaded files, keyed by an opaque string.  The local filesystem is the only
+/// backend today, but anything S3-compatible only has to implement these three calls.
+pub trait Storage: Send + Sync {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+    fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+pub type AttachmentStorage = Arc<dyn Storage>;
+
+/// Filesystem storage under `ATTACHMENT_DIR`, `./attachments` by default.
+pub fn from_env() -> AttachmentStorage {
+    let attachment_dir = std::env::var("ATTACHMENT_DIR").unwrap_or_else(|_| "./attachments".to_string());
+    Arc::new(FileSystemStorage::new(attachment_dir.into()).expect("Failed to create attachment storage."))
+}
+
+pub struct FileSystemStorage {
+    root: PathBuf,
+}
+
+impl FileSystemStorage {
+    pub fn new(root: PathBuf) -> io::Result<FileSystemStorage> {
+        fs::create_dir_all(&root)?;
+        Ok(FileSystemStorage { root })
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid storage key: {}", key)));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+impl Storage for FileSystemStorage {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        fs::write(self.path(key)?, data)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(key)?)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Keeps everything in memory, for tests, the server binary's included.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct MemoryStorage {
+    pub files: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        self.files.lock().unwrap().insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.files.lock().unwrap().get(key).cloned().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        self.files.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
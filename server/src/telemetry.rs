@@ -0,0 +1,239 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::error::BlockingError;
+use actix_web::http::{HeaderName, HeaderValue};
+use actix_web::web;
+use diesel::debug_query;
+use diesel::query_builder::QueryFragment;
+use diesel::sqlite::Sqlite;
+use futures::{Future, FutureExt};
+use std::fmt;
+use tracing::Span;
+use tracing_futures::Instrument;
+use tracing_subscriber::EnvFilter;
+
+use crate::metrics;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// what `RUST_LOG` defaults to; `sql=debug` adds every logged query
+const DEFAULT_FILTER: &str = "info";
+
+/// Sends `tracing` events, and anything still using `log` such as actix and diesel, to stdout:
+/// one JSON object per line when `LOG_FORMAT=json`, otherwise plain text.  `RUST_LOG` filters
+/// them, e.g. `RUST_LOG=info,sql=debug`.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match std::env::var("LOG_FORMAT") {
+        Ok(format) if format.eq_ignore_ascii_case("json") => builder.json().init(),
+        _ => builder.init(),
+    }
+}
+
+/// The caller's request id if it sent a sensible one, so its logs and ours line up, else a new one.
+pub fn request_id(header: Option<&HeaderValue>) -> String {
+    header
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        .map_or_else(|| uuid::Uuid::new_v4().to_simple().to_string(), str::to_string)
+}
+
+/// For `wrap_fn`: runs the request in a span carrying its request id, method and route, logs how
+/// it went and hands the id back in the `x-request-id` header.
+pub fn trace_request<S, B>(req: ServiceRequest, srv: &mut S) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    // the route rather than the path, which can hold an email or a token
+    let started = std::time::Instant::now();
+    let request_id = request_id(req.headers().get(REQUEST_ID_HEADER));
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        route = %metrics::route_label(req.method().as_str(), req.path())
+    );
+    srv.call(req).instrument(span.clone()).map(move |mut res| {
+        let _entered = span.enter();
+        match &mut res {
+            Ok(res) => {
+                info!(status = res.status().as_u16(), took_ms = started.elapsed().as_millis() as u64, "request finished");
+                if let Ok(value) = HeaderValue::from_str(&request_id) {
+                    res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                }
+            }
+            Err(e) => error!("request failed: {}", e),
+        }
+        res
+    })
+}
+
+/// `web::block` that keeps the caller's span, so what the closure logs carries the request id.
+pub fn block<F, I, E>(f: F) -> impl Future<Output = Result<I, BlockingError<E>>>
+where
+    F: FnOnce() -> Result<I, E> + Send + 'static,
+    I: Send + 'static,
+    E: Send + fmt::Debug + 'static,
+{
+    let span = Span::current();
+    web::block(move || span.in_scope(f))
+}
+
+/// Logs a query and its bind values at debug level under the `sql` target, emails masked.
+pub fn sql<T: QueryFragment<Sqlite>>(query: &T) {
+    debug!(target: "sql", "{}", redact_emails(&debug_query::<Sqlite, _>(query).to_string()));
+}
+
+/// `jane@example.com` becomes `j***@example.com`: enough to tell people apart in the logs.
+pub fn redact_email(email: &str) -> String {
+    match email.find('@') {
+        Some(at) if at > 0 => {
+            let first = email.chars().next().map_or(0, char::len_utf8);
+            format!("{}***{}", &email[..first], &email[at..])
+        }
+        Some(at) => format!("***{}", &email[at..]),
+        None => "***".to_string(),
+    }
+}
+
+fn is_email_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._%+-".contains(c)
+}
+
+/// Masks everything in `text` that looks like an email address.
+pub fn redact_emails(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        let start = rest[..at].char_indices().rev().find(|&(_, c)| !is_email_char(c)).map_or(0, |(i, c)| i + c.len_utf8());
+        let end = rest[at + 1..].find(|c: char| !is_email_char(c)).map_or(rest.len(), |i| at + 1 + i);
+        let domain = &rest[at + 1..end];
+        redacted.push_str(&rest[..start]);
+        if start < at && domain.contains('.') {
+            redacted.push_str(&redact_email(&rest[start..end]));
+        } else {
+            redacted.push_str(&rest[start..end]);
+        }
+        rest = &rest[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// Masks the value of every `token=` query parameter in `text`, so sign-in and reset links can
+/// be logged without handing out the token.
+pub fn redact_tokens(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("token=") {
+        let value = start + "token=".len();
+        let end = rest[value..].find(|c: char| c.is_whitespace() || c == '&' || c == '#').map_or(rest.len(), |i| value + i);
+        redacted.push_str(&rest[..value]);
+        if end > value {
+            redacted.push_str("***");
+        }
+        rest = &rest[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, HttpResponse};
+    use serde_json::Value;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    // log output kept in memory, one JSON object per line
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Logs {
+        fn lines(&self) -> Vec<Value> {
+            let logs = self.0.lock().unwrap();
+            String::from_utf8_lossy(&logs)
+                .lines()
+                .map(|line| serde_json::from_str(line).expect("a JSON log line"))
+                .collect()
+        }
+
+        fn find(&self, message: &str) -> Vec<Value> {
+            self.lines().into_iter().filter(|line| line["fields"]["message"] == message).collect()
+        }
+    }
+
+    #[test]
+    fn emails_keep_their_first_letter_and_domain() {
+        assert_eq!(redact_email("jane@example.com"), "j***@example.com");
+        assert_eq!(redact_email("\u{e9}lise@example.com"), "\u{e9}***@example.com");
+        assert_eq!(redact_email("@example.com"), "***@example.com");
+        assert_eq!(redact_email("jane"), "***");
+    }
+
+    #[test]
+    fn every_email_in_a_query_is_masked() {
+        assert_eq!(
+            redact_emails("SELECT * FROM users WHERE email = 'jane.doe+gifts@example.com' OR email = \"bob@mail.example.org\" -- binds: [\"carol@example.com\"]"),
+            "SELECT * FROM users WHERE email = 'j***@example.com' OR email = \"b***@mail.example.org\" -- binds: [\"c***@example.com\"]"
+        );
+        assert_eq!(redact_emails("@handle and user@localhost stay"), "@handle and user@localhost stay");
+        assert_eq!(redact_emails("no addresses"), "no addresses");
+    }
+
+    #[actix_rt::test]
+    async fn the_request_id_is_logged_with_everything_and_sent_back() {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        // as `init` sets it up with LOG_FORMAT=json
+        let subscriber = tracing_subscriber::fmt().json().with_writer(move || writer.clone()).finish();
+        let _default = tracing::subscriber::set_default(subscriber);
+        let handler = || async {
+            info!("handled");
+            Ok::<_, actix_web::Error>(HttpResponse::Ok().finish())
+        };
+        let mut app = test::init_service(App::new().wrap_fn(trace_request).route("/v1/users/login", web::post().to(handler))).await;
+
+        let request = TestRequest::post().uri("/v1/users/login").header(REQUEST_ID_HEADER, "abc-123").to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+        let handled = logs.find("handled");
+        assert_eq!(handled.len(), 1);
+        assert_eq!(handled[0]["span"]["request_id"], "abc-123");
+        assert_eq!(handled[0]["span"]["route"], "users/login");
+        let finished = logs.find("request finished");
+        assert_eq!(finished[0]["span"]["request_id"], "abc-123");
+        assert_eq!(finished[0]["fields"]["status"], 200);
+
+        // one that won't do gets replaced
+        let request = TestRequest::post().uri("/v1/users/login").header(REQUEST_ID_HEADER, "not one of ours!").to_request();
+        let response = test::call_service(&mut app, request).await;
+        let request_id = response.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        assert_eq!(request_id.len(), 32);
+        assert!(request_id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(logs.find("handled")[1]["span"]["request_id"], request_id.as_str());
+    }
+
+    #[test]
+    fn token_values_are_masked() {
+        let body = "Open this:\n\nhttps://gifts.example.com/reset_password?token=abc123\n\nor https://gifts.example.com/verify_email?token=def456&next=home#top";
+        assert_eq!(
+            redact_tokens(body),
+            "Open this:\n\nhttps://gifts.example.com/reset_password?token=***\n\nor https://gifts.example.com/verify_email?token=***&next=home#top"
+        );
+        assert_eq!(redact_tokens("token=&token="), "token=&token=");
+        assert_eq!(redact_tokens("no links here"), "no links here");
+    }
+}
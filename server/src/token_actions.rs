@@ -0,0 +1,93 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::models;
+use crate::schema::{notifications, user_tokens, users};
+
+pub const VERIFY_EMAIL: &str = "verify_email";
+pub const RESET_PASSWORD: &str = "reset_password";
+
+// how many emails carrying a token of one kind a user can ask for in an hour
+pub const MAX_TOKENS_PER_HOUR: i64 = 3;
+
+pub fn valid_for(purpose: &str) -> Duration {
+    match purpose {
+        RESET_PASSWORD => Duration::hours(1),
+        _ => Duration::days(2),
+    }
+}
+
+// only the hash is stored; the raw token is issued as its email is sent (see
+// `notifications::deliver_pending`) and never written anywhere, so a leaked database can't be
+// used to take over accounts
+pub fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub fn generate() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().to_simple(), uuid::Uuid::new_v4().to_simple())
+}
+
+/// Counts the emails queued for `purpose` rather than the tokens issued, since a token is only
+/// issued when its email goes out and requests made before then would slip through.
+pub fn is_rate_limited(uid: i32, purpose: &str, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let since = Utc::now().naive_utc() - Duration::hours(1);
+    let count = notifications::table
+        .filter(notifications::dsl::user_id.eq(uid))
+        .filter(notifications::dsl::kind.eq(purpose))
+        .filter(notifications::dsl::date_added.gt(since))
+        .count()
+        .get_result::<i64>(conn)?;
+    Ok(count >= MAX_TOKENS_PER_HOUR)
+}
+
+/// Issues a new token for `user`, invalidating any earlier unused one for the same purpose, and
+/// returns the raw value to put in the email.  The token is tied to the user's current email so
+/// changing it afterwards makes the token useless.
+pub fn issue(user: &models::User, purpose: &str, conn: &SqliteConnection) -> Result<String, diesel::result::Error> {
+    let token = generate();
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::update(
+            user_tokens::table
+                .filter(user_tokens::dsl::user_id.eq(user.id))
+                .filter(user_tokens::dsl::purpose.eq(purpose))
+                .filter(user_tokens::dsl::date_used.is_null()),
+        )
+        .set(user_tokens::dsl::date_used.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+        let new_token = models::NewUserToken::new(user.id, purpose, hash(&token), user.email.to_string(), valid_for(purpose));
+        diesel::insert_into(user_tokens::table).values(&new_token).execute(conn)?;
+        Ok(())
+    })?;
+    Ok(token)
+}
+
+/// Marks the token used and returns its user, or `None` if it is unknown, expired, already used,
+/// or was issued for an email address the user no longer has.
+pub fn redeem(token: &str, purpose: &str, conn: &SqliteConnection) -> Result<Option<models::User>, diesel::result::Error> {
+    let now = Utc::now().naive_utc();
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        let found = user_tokens::table
+            .inner_join(users::table)
+            .filter(user_tokens::dsl::token_hash.eq(hash(token)))
+            .filter(user_tokens::dsl::purpose.eq(purpose))
+            .filter(user_tokens::dsl::date_used.is_null())
+            .filter(user_tokens::dsl::date_expires.gt(now))
+            .first::<(models::UserToken, models::User)>(conn)
+            .optional()?;
+        match found {
+            Some((user_token, user)) => {
+                diesel::update(user_tokens::table.find(user_token.id))
+                    .set(user_tokens::dsl::date_used.eq(now))
+                    .execute(conn)?;
+                Ok(if user_token.email == user.email { Some(user) } else { None })
+            }
+            None => Ok(None),
+        }
+    })
+}
+
+pub fn delete_by_user(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::delete(user_tokens::table.filter(user_tokens::dsl::user_id.eq(uid))).execute(conn)
+}
@@ -0,0 +1,104 @@
+use chrono::{Duration, Utc};
+
+use crate::attachment_actions;
+use crate::comment_actions;
+use crate::gift_idea_actions;
+use crate::storage::Storage;
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// How long a deleted gift idea can still be restored, from `TRASH_RETENTION_DAYS`.
+pub fn retention() -> Duration {
+    let days = std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    Duration::days(days)
+}
+
+/// Ideas deleted before this can no longer be restored.
+pub fn restorable_since() -> chrono::NaiveDateTime {
+    Utc::now().naive_utc() - retention()
+}
+
+/// Permanently removes ideas that have been in the trash longer than the retention window,
+/// along with their images and comments.
+pub fn purge_expired(storage: &dyn Storage, conn: &diesel::SqliteConnection) -> Result<usize, attachment_actions::AttachmentError> {
+    let expired = gift_idea_actions::find_deleted_before(restorable_since(), conn)?;
+    for gift in expired.iter() {
+        purge(gift.id, None, storage, conn)?;
+    }
+    Ok(expired.len())
+}
+
+/// Permanently removes one idea, in the trash or not, with its images and comments.
+pub fn purge(gid: i32, actor_user_id: Option<i32>, storage: &dyn Storage, conn: &diesel::SqliteConnection) -> Result<bool, attachment_actions::AttachmentError> {
+    attachment_actions::delete_by_gift_idea(gid, storage, conn)?;
+    comment_actions::delete_by_gift_idea(gid, conn)?;
+    Ok(gift_idea_actions::purge(gid, actor_user_id, conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models;
+    use crate::schema::{gift_idea_comments, gift_ideas};
+    use crate::storage::MemoryStorage;
+    use crate::user_actions;
+    use diesel::prelude::*;
+
+    fn trashed(title: &str, deleted_ago: Duration, conn: &SqliteConnection) -> (models::User, models::GiftIdea) {
+        let user = |email: &str| {
+            user_actions::find_by_email(email.to_string(), conn)
+                .unwrap()
+                .unwrap_or_else(|| user_actions::add(&models::NewUser::new(email.to_string(), "password".to_string()), None, conn).unwrap())
+        };
+        let (jane, bob) = (user("jane@example.com"), user("bob@example.com"));
+        let gift = gift_idea_actions::add(&models::NewGiftIdea::new(title.to_string(), jane.id, bob.id), Some(jane.id), conn).unwrap();
+        gift_idea_actions::delete(gift.id, jane.id, conn).unwrap();
+        diesel::update(gift_ideas::table.find(gift.id))
+            .set(gift_ideas::dsl::date_deleted.eq(Utc::now().naive_utc() - deleted_ago))
+            .execute(conn)
+            .unwrap();
+        (jane, gift)
+    }
+
+    #[test]
+    fn ideas_can_only_be_restored_within_the_retention_window() {
+        let conn = crate::test_connection();
+        let (jane, recent) = trashed("Bread maker", Duration::days(1), &conn);
+        let (_, expired) = trashed("Stand mixer", retention() + Duration::days(1), &conn);
+
+        let listed = gift_idea_actions::find_deleted_by_owner(jane.id, restorable_since(), &conn).unwrap();
+        assert_eq!(listed.into_iter().map(|gift| gift.id).collect::<Vec<_>>(), vec![recent.id]);
+        assert!(gift_idea_actions::restore(expired.id, jane.id, restorable_since(), &conn).unwrap().is_none());
+        let restored = gift_idea_actions::restore(recent.id, jane.id, restorable_since(), &conn).unwrap().unwrap();
+        assert_eq!((restored.date_deleted, restored.deleted_by_user_id), (None, None));
+        assert!(gift_idea_actions::find_by_id(expired.id, &conn).unwrap().is_none());
+    }
+
+    #[test]
+    fn expired_ideas_are_purged_with_their_images_and_comments() {
+        let conn = crate::test_connection();
+        let storage = MemoryStorage::default();
+        let (jane, expired) = trashed("Stand mixer", retention() + Duration::days(1), &conn);
+        let (_, recent) = trashed("Bread maker", Duration::days(1), &conn);
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(10, 10))
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        for gift in [&expired, &recent].iter() {
+            attachment_actions::add(gift.id, jane.id, "photo.png".to_string(), "image/png".to_string(), png.clone(), &storage, &conn).unwrap();
+            comment_actions::add(gift.id, jane.id, "Found it cheaper".to_string(), &conn).unwrap().unwrap();
+        }
+
+        assert_eq!(purge_expired(&storage, &conn).unwrap(), 1);
+        assert!(gift_idea_actions::find_including_deleted(expired.id, &conn).unwrap().is_none());
+        assert!(gift_idea_actions::find_including_deleted(recent.id, &conn).unwrap().is_some());
+        // the recent one's image and thumbnail are all that's left
+        assert_eq!(storage.files.lock().unwrap().len(), 2);
+        let comments = gift_idea_comments::table.select(gift_idea_comments::dsl::gift_idea_id).load::<i32>(&conn).unwrap();
+        assert_eq!(comments, vec![recent.id]);
+        assert_eq!(purge_expired(&storage, &conn).unwrap(), 0);
+    }
+}
@@ -1,13 +1,19 @@
+use chrono::Utc;
 use diesel::prelude::*;
 
+use crate::audit_actions;
 use crate::models;
 use crate::schema::users;
 use actix_web::*;
 
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Disabled users can't sign in.
 pub fn login(user: &models::NewUser, conn: &SqliteConnection) -> Result<Option<models::User>, diesel::result::Error> {
     let user = users::table
         .filter(users::dsl::email.eq(user.email.to_string()))
         .filter(users::dsl::password.eq(user.password.to_string()))
+        .filter(users::dsl::date_disabled.is_null())
         .first::<models::User>(conn)
         .optional()?;
     Ok(user)
@@ -19,6 +25,12 @@ pub fn find_all(conn: &SqliteConnection) -> Result<Vec<shared::MinimalUserInfo>,
     Ok(results)
 }
 
+/// Every user, disabled ones included.
+pub fn find_all_full(conn: &SqliteConnection) -> Result<Vec<models::User>, diesel::result::Error> {
+    let results = users::table.order(users::dsl::id).load::<models::User>(conn)?;
+    Ok(results)
+}
+
 pub fn find_by_id(uid: i32, conn: &SqliteConnection) -> Result<Option<models::User>, diesel::result::Error> {
     let user = users::table.filter(users::dsl::id.eq(uid)).first::<models::User>(conn).optional()?;
     Ok(user)
@@ -29,19 +41,123 @@ pub fn find_by_email(mail: String, conn: &SqliteConnection) -> Result<Option<mod
     Ok(user)
 }
 
-pub fn update(user: &models::NewUser, conn: &SqliteConnection) -> Result<models::User, diesel::result::Error> {
-    diesel::update(users::table.filter(users::dsl::email.eq(user.email.to_string())))
-        .set(user)
-        .execute(conn)
-        .unwrap();
-    let user = users::table.filter(users::dsl::email.eq(user.email.to_string())).first::<models::User>(conn)?;
-    Ok(user)
+/// Applies `changes` to the user's profile, returning `None` if the new email address already
+/// belongs to someone else.  A changed address needs verifying again.
+pub fn update_profile(
+    uid: i32,
+    mut changes: models::UserProfileChangeset,
+    actor_user_id: Option<i32>,
+    conn: &SqliteConnection,
+) -> Result<Option<models::User>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = users::table.find(uid).first::<models::User>(conn)?;
+        let email_changed = match &changes.email {
+            Some(email) if *email != before.email => {
+                if find_by_email(email.to_string(), conn)?.is_some() {
+                    return Ok(None);
+                }
+                changes.email_verified = Some(false);
+                true
+            }
+            _ => false,
+        };
+        if changes.email.is_some() || changes.first_name.is_some() || changes.last_name.is_some() || changes.phone.is_some() {
+            diesel::update(users::table.find(uid)).set(&changes).execute(conn)?;
+        }
+        let user = users::table.find(uid).first::<models::User>(conn)?;
+        let diff = audit_actions::diff(Some(&before), Some(&user));
+        if !diff.is_empty() {
+            audit_actions::record(actor_user_id, audit_actions::USER, uid, audit_actions::UPDATED, &diff, conn)?;
+        }
+        if email_changed {
+            crate::notifications::queue_email_verification(&user, conn)?;
+        }
+        Ok(Some(user))
+    })
 }
 
-pub fn add(new_user: &models::NewUser, conn: &SqliteConnection) -> Result<models::User, diesel::result::Error> {
-    // RETURNING is not supported by sqlite...the following will work w/ postgresql
-    //let user = diesel::insert_into(users).values(&new_user).get_result(conn).expect("Error savig");
-    diesel::insert_into(users::table).values(new_user).execute(conn)?;
-    let user = users::table.filter(users::dsl::email.eq(new_user.email.to_string())).first::<models::User>(conn)?;
-    Ok(user)
+/// Anyone signing themselves up is recorded as the actor of their own registration.
+pub fn add(new_user: &models::NewUser, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<models::User, diesel::result::Error> {
+    conn.transaction(|| {
+        // RETURNING is not supported by sqlite...the following will work w/ postgresql
+        //let user = diesel::insert_into(users).values(&new_user).get_result(conn).expect("Error savig");
+        diesel::insert_into(users::table).values(new_user).execute(conn)?;
+        let user = users::table.filter(users::dsl::email.eq(new_user.email.to_string())).first::<models::User>(conn)?;
+        let actor_user_id = actor_user_id.or(Some(user.id));
+        audit_actions::record(
+            actor_user_id,
+            audit_actions::USER,
+            user.id,
+            audit_actions::CREATED,
+            &audit_actions::diff(None, Some(&user)),
+            conn,
+        )?;
+        Ok(user)
+    })
+}
+
+pub fn set_password(uid: i32, password: String, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_by_id(uid, conn)?;
+        diesel::update(users::table.find(uid)).set(users::dsl::password.eq(password)).execute(conn)?;
+        let after = find_by_id(uid, conn)?;
+        let diff = audit_actions::diff(before.as_ref(), after.as_ref());
+        audit_actions::record(actor_user_id, audit_actions::USER, uid, audit_actions::PASSWORD_CHANGED, &diff, conn)
+    })
+}
+
+pub fn mark_email_verified(uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_by_id(uid, conn)?;
+        diesel::update(users::table.find(uid)).set(users::dsl::email_verified.eq(true)).execute(conn)?;
+        let after = find_by_id(uid, conn)?;
+        let diff = audit_actions::diff(before.as_ref(), after.as_ref());
+        if diff.is_empty() {
+            return Ok(());
+        }
+        audit_actions::record(actor_user_id, audit_actions::USER, uid, audit_actions::EMAIL_VERIFIED, &diff, conn)
+    })
+}
+
+/// Whether the user still exists and isn't disabled.
+pub fn is_active(uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let count = users::table.find(uid).filter(users::dsl::date_disabled.is_null()).count().get_result::<i64>(conn)?;
+    Ok(count == 1)
+}
+
+/// Stops (or, with `disabled` false, lets again) the user signing in.  `None` if there's no such user.
+pub fn set_disabled(uid: i32, disabled: bool, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<Option<models::User>, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = match find_by_id(uid, conn)? {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+        if before.date_disabled.is_some() == disabled {
+            return Ok(Some(before));
+        }
+        let date_disabled = if disabled { Some(Utc::now().naive_utc()) } else { None };
+        diesel::update(users::table.find(uid)).set(users::dsl::date_disabled.eq(date_disabled)).execute(conn)?;
+        let after = find_by_id(uid, conn)?;
+        let action = if disabled { audit_actions::DISABLED } else { audit_actions::ENABLED };
+        audit_actions::record(actor_user_id, audit_actions::USER, uid, action, &audit_actions::diff(Some(&before), after.as_ref()), conn)?;
+        Ok(after)
+    })
+}
+
+pub fn delete(uid: i32, actor_user_id: Option<i32>, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    conn.transaction(|| {
+        let before = find_by_id(uid, conn)?;
+        let num_deleted = diesel::delete(users::table.find(uid)).execute(conn)?;
+        if num_deleted == 1 {
+            audit_actions::record(
+                actor_user_id,
+                audit_actions::USER,
+                uid,
+                audit_actions::DELETED,
+                &audit_actions::diff(before.as_ref(), None),
+                conn,
+            )?;
+        }
+        Ok(num_deleted == 1)
+    })
 }
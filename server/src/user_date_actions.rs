@@ -0,0 +1,73 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+use crate::models;
+use crate::schema::{user_dates, users};
+
+pub fn find_by_user(uid: i32, conn: &SqliteConnection) -> Result<Vec<models::UserDate>, diesel::result::Error> {
+    let results = user_dates::table
+        .filter(user_dates::dsl::user_id.eq(uid))
+        .order((user_dates::dsl::month.asc(), user_dates::dsl::day.asc()))
+        .load::<models::UserDate>(conn)?;
+    Ok(results)
+}
+
+pub fn add(new_date: &models::NewUserDate, conn: &SqliteConnection) -> Result<models::UserDate, diesel::result::Error> {
+    diesel::insert_into(user_dates::table).values(new_date).execute(conn)?;
+    let date = user_dates::table
+        .filter(user_dates::dsl::user_id.eq(new_date.user_id))
+        .order(user_dates::dsl::id.desc())
+        .first::<models::UserDate>(conn)?;
+    Ok(date)
+}
+
+/// Changes one of `uid`'s dates; `None` if there's no such date of theirs.  A changed day can come
+/// round again this year, so it's free to get a new event.
+pub fn update(did: i32, uid: i32, changes: &models::NewUserDate, conn: &SqliteConnection) -> Result<Option<models::UserDate>, diesel::result::Error> {
+    let found = user_dates::table
+        .filter(user_dates::dsl::id.eq(did))
+        .filter(user_dates::dsl::user_id.eq(uid))
+        .first::<models::UserDate>(conn)
+        .optional()?;
+    match found {
+        Some(mut date) => {
+            if date.month != changes.month || date.day != changes.day {
+                date.date_last_event = None;
+            }
+            date.kind = changes.kind.to_string();
+            date.label = changes.label.clone();
+            date.month = changes.month;
+            date.day = changes.day;
+            date.year = changes.year;
+            date.year_visible = changes.year_visible;
+            diesel::update(user_dates::table.find(did)).set(&date).execute(conn)?;
+            Ok(Some(date))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn delete(did: i32, uid: i32, conn: &SqliteConnection) -> Result<bool, diesel::result::Error> {
+    let num_deleted = diesel::delete(user_dates::table.filter(user_dates::dsl::id.eq(did)).filter(user_dates::dsl::user_id.eq(uid))).execute(conn)?;
+    Ok(num_deleted == 1)
+}
+
+pub fn delete_by_user(uid: i32, conn: &SqliteConnection) -> Result<usize, diesel::result::Error> {
+    diesel::delete(user_dates::table.filter(user_dates::dsl::user_id.eq(uid))).execute(conn)
+}
+
+/// Every date along with its owner, leaving out disabled users.
+pub fn find_all_active(conn: &SqliteConnection) -> Result<Vec<(models::UserDate, models::User)>, diesel::result::Error> {
+    let results = user_dates::table
+        .inner_join(users::table)
+        .filter(users::dsl::date_disabled.is_null())
+        .load::<(models::UserDate, models::User)>(conn)?;
+    Ok(results)
+}
+
+pub fn mark_event_created(did: i32, occurrence: NaiveDate, conn: &SqliteConnection) -> Result<(), diesel::result::Error> {
+    diesel::update(user_dates::table.find(did))
+        .set(user_dates::dsl::date_last_event.eq(occurrence))
+        .execute(conn)?;
+    Ok(())
+}
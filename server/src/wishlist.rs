@@ -0,0 +1,175 @@
+use scraper::{Html, Selector};
+use url::Url;
+
+use crate::models;
+
+/// Pasted lists and bookmark files are read into memory whole, so they're capped.
+pub const MAX_TEXT_SIZE: usize = 2 * 1024 * 1024;
+
+pub const MAX_ROWS: usize = 500;
+
+pub const MAX_TITLE_LENGTH: usize = 200;
+
+/// Reads the gift ideas out of a pasted wishlist.  Nothing is rejected here; see `validate`.
+pub fn parse(format: shared::WishlistFormat, text: &str) -> Vec<shared::WishlistRow> {
+    match format {
+        shared::WishlistFormat::Urls => parse_urls(text),
+        shared::WishlistFormat::Csv => parse_csv(text),
+        shared::WishlistFormat::Bookmarks => parse_bookmarks(text),
+    }
+}
+
+// blank lines and `#` comments are skipped; titles are guessed from the address
+fn parse_urls(text: &str) -> Vec<shared::WishlistRow> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(row, line)| shared::WishlistRow {
+            row,
+            title: title_from_url(line),
+            price: None,
+            url: Some(line.to_string()),
+            problems: Vec::new(),
+        })
+        .collect()
+}
+
+// a header row naming the columns is optional; without one they're taken as title, price, url
+fn parse_csv(text: &str) -> Vec<shared::WishlistRow> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let mut columns = (Some(0), Some(1), Some(2));
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                rows.push(shared::WishlistRow {
+                    row: i + 1,
+                    problems: vec![format!("Couldn't read this line: {}", e)],
+                    ..Default::default()
+                });
+                continue;
+            }
+        };
+        let row = record.position().map_or(i + 1, |position| position.line() as usize);
+        if i == 0 {
+            let names = record.iter().map(str::to_lowercase).collect::<Vec<_>>();
+            let find = |candidates: &[&str]| names.iter().position(|name| candidates.contains(&name.as_str()));
+            if let Some(title) = find(&["title", "name", "item"]) {
+                columns = (Some(title), find(&["price", "cost"]), find(&["url", "link"]));
+                continue;
+            }
+        }
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let cell = |column: Option<usize>| column.and_then(|column| record.get(column)).map(str::to_string).filter(|e| !e.is_empty());
+        rows.push(shared::WishlistRow {
+            row,
+            title: cell(columns.0).unwrap_or_default(),
+            price: cell(columns.1),
+            url: cell(columns.2),
+            problems: Vec::new(),
+        });
+    }
+    rows
+}
+
+// every link in the file, whatever folder it's in
+fn parse_bookmarks(text: &str) -> Vec<shared::WishlistRow> {
+    let document = Html::parse_document(text);
+    let links = Selector::parse("a[href]").expect("valid selector");
+    document
+        .select(&links)
+        .filter_map(|link| link.value().attr("href").map(|href| (href.trim().to_string(), link.text().collect::<String>())))
+        // firefox exports its smart folders as place: links
+        .filter(|(href, _)| !href.starts_with("place:"))
+        .enumerate()
+        .map(|(i, (href, text))| shared::WishlistRow {
+            row: i + 1,
+            title: Some(text.trim().to_string()).filter(|e| !e.is_empty()).unwrap_or_else(|| title_from_url(&href)),
+            price: None,
+            url: Some(href),
+            problems: Vec::new(),
+        })
+        .collect()
+}
+
+/// Something readable from an address: its wordiest path segment, e.g. `Lego Millennium Falcon`
+/// from `https://example.com/toys/lego-millennium-falcon/75192`, or else the site's name.
+pub fn title_from_url(url: &str) -> String {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return url.to_string(),
+    };
+    let segment = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .map(|segment| segment.rsplitn(2, '.').last().unwrap_or(segment))
+        .filter(|segment| segment.chars().any(char::is_alphabetic))
+        .max_by_key(|segment| segment.matches(['-', '_']).count());
+    match segment {
+        Some(segment) => {
+            let words = segment.split(['-', '_', '+']).filter(|word| !word.is_empty());
+            words
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        None => url.host_str().map(|host| host.trim_start_matches("www.").to_string()).unwrap_or_default(),
+    }
+}
+
+/// Tidies each row and fills in its problems: a missing or overlong title, an address that
+/// isn't http(s), or an idea that's already on the list, either earlier in this one or among
+/// `existing` (which can be any gift ideas; only the recipient's count).
+pub fn validate(rows: &mut [shared::WishlistRow], recipient_user_id: i32, existing: &[models::GiftIdea]) {
+    for i in 0..rows.len() {
+        let (earlier, rest) = rows.split_at_mut(i);
+        let row = &mut rest[0];
+        row.problems.clear();
+        row.title = row.title.trim().to_string();
+        row.price = row.price.take().map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+        row.url = row.url.take().map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+
+        if row.title.is_empty() {
+            row.problems.push("Needs a title".to_string());
+        } else if row.title.chars().count() > MAX_TITLE_LENGTH {
+            row.problems.push(format!("Titles are limited to {} characters", MAX_TITLE_LENGTH));
+        }
+        if let Some(url) = &row.url {
+            match Url::parse(url) {
+                Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => (),
+                _ => row.problems.push(format!("Not a web address: {}", url)),
+            }
+        }
+        if let Some(same) = earlier.iter().find(|e| same_idea(&e.title, &e.url, row)) {
+            row.problems.push(format!("Same as row {}", same.row));
+        } else if existing
+            .iter()
+            .any(|gift| gift.recipient_user_id == recipient_user_id && gift.date_deleted.is_none() && same_idea(&gift.title, &gift.url, row))
+        {
+            row.problems.push("Already on their list".to_string());
+        }
+    }
+}
+
+fn same_idea(title: &str, url: &Option<String>, row: &shared::WishlistRow) -> bool {
+    title.trim().eq_ignore_ascii_case(&row.title) && *url == row.url
+}
+
+pub fn new_gift_idea(row: &shared::WishlistRow, owner_id: i32, recipient_user_id: i32) -> models::NewGiftIdea {
+    let mut new_gift = models::NewGiftIdea::new(row.title.to_string(), owner_id, recipient_user_id);
+    new_gift.price = row.price.clone();
+    new_gift.url = row.url.clone();
+    new_gift
+}
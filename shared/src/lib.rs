@@ -1,15 +1,20 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[allow(dead_code)]
 pub struct LoggedUser {
     pub id: i32,
     pub email: String,
     pub first_name: String,
     pub last_name: String,
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct RegisterRequestBody {
     pub email: String,
     pub password: String,
@@ -18,7 +23,7 @@ pub struct RegisterRequestBody {
     pub phone: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct RegisterResponseBody {
     pub id: i32,
     pub email: String,
@@ -28,7 +33,7 @@ pub struct RegisterResponseBody {
     pub phone: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct MinimalUserInfo {
     pub id: i32,
     pub email: String,
@@ -37,13 +42,13 @@ pub struct MinimalUserInfo {
     pub phone: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct LoginRequestBody {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct LoginResponseBody {
     pub id: i32,
     pub email: String,
@@ -53,7 +58,7 @@ pub struct LoginResponseBody {
     pub phone: Option<String>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct GiftIdeaRequestBody {
     pub title: String,
     pub description: Option<String>,
@@ -61,9 +66,11 @@ pub struct GiftIdeaRequestBody {
     pub url: Option<String>,
     pub owner_id: i32,
     pub recipient_user_id: i32,
+    #[serde(default)]
+    pub event_id: Option<i32>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct GiftIdeaResponseBody {
     pub id: i32,
     pub title: String,
@@ -73,4 +80,394 @@ pub struct GiftIdeaResponseBody {
     pub owner_id: i32,
     pub recipient_user_id: i32,
     pub reserved_by_user_id: Option<i32>,
+    #[serde(default)]
+    pub date_added: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub date_reserved: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub date_purchased: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub date_deleted: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub event_id: Option<i32>,
+    #[serde(default)]
+    pub date_archived: Option<NaiveDateTime>,
+    // the idea this one was copied from when its event was carried over
+    #[serde(default)]
+    pub carried_over_from_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AttachmentResponseBody {
+    pub id: i32,
+    pub gift_idea_id: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LinkMetadataRequestBody {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LinkMetadataResponseBody {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub price: Option<String>,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CommentRequestBody {
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommentResponseBody {
+    pub id: i32,
+    pub gift_idea_id: i32,
+    pub author_user_id: i32,
+    pub author_name: String,
+    pub body: String,
+    pub date_added: NaiveDateTime,
+    pub date_last_modified: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", content = "gift_idea")]
+pub enum GiftIdeaEvent {
+    Created(GiftIdeaResponseBody),
+    Updated(GiftIdeaResponseBody),
+    Reserved(GiftIdeaResponseBody),
+    Unreserved(GiftIdeaResponseBody),
+    Purchased(GiftIdeaResponseBody),
+    Deleted(i32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EventRequestBody {
+    pub name: String,
+    pub event_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EventResponseBody {
+    pub id: i32,
+    pub name: String,
+    pub event_date: NaiveDate,
+    pub owner_id: i32,
+    #[serde(default)]
+    pub date_archived: Option<NaiveDateTime>,
+}
+
+/// The event to copy an archived event's un-given ideas into.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CarryOverRequestBody {
+    pub name: String,
+    pub event_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CarryOverResponseBody {
+    pub event: EventResponseBody,
+    pub carried_over: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationPreferencesBody {
+    pub new_gift_idea: bool,
+    pub reservation_released: bool,
+    pub event_reminder: bool,
+}
+
+impl Default for NotificationPreferencesBody {
+    fn default() -> Self {
+        NotificationPreferencesBody {
+            new_gift_idea: true,
+            reservation_released: true,
+            event_reminder: true,
+        }
+    }
+}
+
+/// A user's calendar feed.  `url` holds the secret address only in the response that creates
+/// it; afterwards all that's known is that there is one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarFeedBody {
+    pub enabled: bool,
+    pub include_reservations: bool,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarFeedRequest {
+    pub include_reservations: bool,
+}
+
+/// One of the server's background jobs.  `attempts` counts failures since it last succeeded.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JobBody {
+    pub name: String,
+    pub schedule: String,
+    pub running: bool,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub date_next_run: NaiveDateTime,
+    pub date_last_started: Option<NaiveDateTime>,
+    pub date_last_succeeded: Option<NaiveDateTime>,
+    pub date_last_failed: Option<NaiveDateTime>,
+}
+
+/// The kinds of yearly date a user can put on their profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserDateKind {
+    #[default]
+    Birthday,
+    Anniversary,
+    // anything else that comes round every year; it needs a label
+    Other,
+}
+
+impl UserDateKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserDateKind::Birthday => "birthday",
+            UserDateKind::Anniversary => "anniversary",
+            UserDateKind::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<UserDateKind> {
+        match value {
+            "birthday" => Some(UserDateKind::Birthday),
+            "anniversary" => Some(UserDateKind::Anniversary),
+            "other" => Some(UserDateKind::Other),
+            _ => None,
+        }
+    }
+}
+
+/// A yearly date.  The year is optional, and only shown to other people when `year_visible`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UserDateRequest {
+    pub kind: UserDateKind,
+    pub label: Option<String>,
+    pub month: u32,
+    pub day: u32,
+    pub year: Option<i32>,
+    pub year_visible: bool,
+}
+
+/// A yearly date on someone's profile; `year` is left out for anyone but its owner unless
+/// `year_visible`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UserDateBody {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: UserDateKind,
+    pub label: Option<String>,
+    pub month: u32,
+    pub day: u32,
+    pub year: Option<i32>,
+    pub year_visible: bool,
+    pub next_occurrence: NaiveDate,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ForgotPasswordRequestBody {
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ResetPasswordRequestBody {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyEmailRequestBody {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ChangePasswordRequestBody {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteAccountRequestBody {
+    pub password: String,
+}
+
+/// Only the fields that are present get changed; an empty string clears a name or phone number.
+/// Changing the email address marks it unverified until the new address is confirmed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateProfileRequest {
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub phone: Option<String>,
+}
+
+/// Partial update of a gift idea: fields left out are unchanged, and an empty description,
+/// price or url clears it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateGiftIdeaRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub price: Option<String>,
+    pub url: Option<String>,
+    pub recipient_user_id: Option<i32>,
+    // 0 takes the idea off its event
+    #[serde(default)]
+    pub event_id: Option<i32>,
+}
+
+/// One field's value before and after a change; `None` where it was unset, or didn't exist yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntryResponseBody {
+    pub id: i32,
+    pub actor_user_id: Option<i32>,
+    pub actor_name: Option<String>,
+    pub action: String,
+    pub changes: Vec<FieldChange>,
+    pub date_added: NaiveDateTime,
+}
+
+/// Bumped whenever `ExportDocument` changes shape; imports refuse anything newer.
+pub const EXPORT_VERSION: u32 = 2;
+
+/// Everything one user has put in: the ideas they added, the events they made or filed those
+/// ideas under, the ideas they've reserved, and enough about the people involved to find them
+/// again on another instance.  A group export has every member's ideas for each other instead,
+/// as the exporting member sees them.  Ids are the exporting instance's and get remapped on
+/// import.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportDocument {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    // who the export is for; their ideas and events become the importer's
+    pub user_id: i32,
+    // set for a group export
+    #[serde(default)]
+    pub group: Option<GroupBody>,
+    pub users: Vec<ExportUser>,
+    pub events: Vec<ExportEvent>,
+    pub gift_ideas: Vec<ExportGiftIdea>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GroupBody {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportUser {
+    pub id: i32,
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportEvent {
+    pub id: i32,
+    pub name: String,
+    pub event_date: NaiveDate,
+    pub owner_id: i32,
+    pub date_archived: Option<NaiveDateTime>,
+}
+
+/// Reservations and purchases are left out of ideas meant for the exporting user.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportGiftIdea {
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub price: Option<String>,
+    pub url: Option<String>,
+    pub owner_id: i32,
+    pub recipient_user_id: i32,
+    pub reserved_by_user_id: Option<i32>,
+    pub date_added: NaiveDateTime,
+    pub date_reserved: Option<NaiveDateTime>,
+    pub date_purchased: Option<NaiveDateTime>,
+    pub event_id: Option<i32>,
+    pub date_archived: Option<NaiveDateTime>,
+    pub carried_over_from_id: Option<i32>,
+}
+
+/// What an import did, or with a dry run would have done.  Duplicates are things the importer
+/// already had, which are matched up rather than copied again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub events_created: usize,
+    pub duplicate_events: Vec<String>,
+    pub gift_ideas_created: usize,
+    pub duplicate_gift_ideas: Vec<String>,
+    pub reservations_restored: usize,
+    // emails in the document nobody here is registered with
+    pub unknown_users: Vec<String>,
+    // one line for each thing that couldn't be imported, and why
+    pub skipped: Vec<String>,
+}
+
+/// The kinds of list a wishlist can be pasted in as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WishlistFormat {
+    // one address per line
+    #[default]
+    Urls,
+    // title, price and url columns, with or without a header row
+    Csv,
+    // a browser's bookmarks export
+    Bookmarks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WishlistPreviewRequest {
+    pub recipient_user_id: i32,
+    pub format: WishlistFormat,
+    pub text: String,
+}
+
+/// One gift idea read from a wishlist: `row` is its line in pasted text, or its place among a
+/// bookmark file's links.  Rows with problems aren't imported.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WishlistRow {
+    pub row: usize,
+    pub title: String,
+    pub price: Option<String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WishlistImportRequest {
+    pub recipient_user_id: i32,
+    pub rows: Vec<WishlistRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WishlistImportResponseBody {
+    pub created: Vec<GiftIdeaResponseBody>,
+    // rows that failed validation this time round, with their problems filled in
+    pub rejected: Vec<WishlistRow>,
 }
@@ -0,0 +1,391 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::future::Future;
+use tui::widgets::ListState;
+
+pub const GIFT_FIELDS: [&str; 4] = ["Title", "Description", "Price", "Url"];
+
+pub enum Screen {
+    Login(LoginForm),
+    Browse,
+    Edit(GiftForm),
+}
+
+#[derive(Default)]
+pub struct LoginForm {
+    pub email: String,
+    pub password: String,
+    // 0 is the email, 1 the password
+    pub field: usize,
+}
+
+impl LoginForm {
+    fn value(&mut self) -> &mut String {
+        if self.field == 0 {
+            &mut self.email
+        } else {
+            &mut self.password
+        }
+    }
+}
+
+pub struct GiftForm {
+    // `None` while adding a new idea
+    pub gift_id: Option<i32>,
+    pub recipient_user_id: i32,
+    // in the order of `GIFT_FIELDS`
+    pub values: [String; 4],
+    pub field: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Pane {
+    Recipients,
+    Ideas,
+}
+
+pub struct App {
+    client: api::Client,
+    runtime: tokio::runtime::Runtime,
+    pub screen: Screen,
+    pub viewer: Option<shared::LoggedUser>,
+    pub users: Vec<shared::MinimalUserInfo>,
+    pub gift_ideas: Vec<shared::GiftIdeaResponseBody>,
+    pub recipients: ListState,
+    pub ideas: ListState,
+    pub pane: Pane,
+    pub status: Option<String>,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(client: api::Client, runtime: tokio::runtime::Runtime, email: Option<String>) -> App {
+        let mut login = LoginForm::default();
+        if let Some(email) = email {
+            login.email = email;
+            login.field = 1;
+        }
+        App {
+            client,
+            runtime,
+            screen: Screen::Login(login),
+            viewer: None,
+            users: Vec::new(),
+            gift_ideas: Vec::new(),
+            recipients: ListState::default(),
+            ideas: ListState::default(),
+            pane: Pane::Recipients,
+            status: None,
+            quit: false,
+        }
+    }
+
+    /// Runs one request to completion; the UI waits on it.
+    fn wait<T>(&mut self, request: impl Future<Output = api::Result<T>>) -> api::Result<T> {
+        self.runtime.block_on(request)
+    }
+
+    /// Reports a failed request, sending the user back to log in if their session has gone.
+    fn failed(&mut self, error: api::ApiError) {
+        if let api::ApiError::Unauthorized = error {
+            self.viewer = None;
+            self.screen = Screen::Login(LoginForm::default());
+        }
+        self.status = Some(error.to_string());
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+        match self.screen {
+            Screen::Login(_) => self.on_login_key(key.code),
+            Screen::Browse => self.on_browse_key(key.code),
+            Screen::Edit(_) => self.on_edit_key(key.code),
+        }
+    }
+
+    fn on_login_key(&mut self, code: KeyCode) {
+        let form = match &mut self.screen {
+            Screen::Login(form) => form,
+            _ => return,
+        };
+        match code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => form.field = 1 - form.field,
+            KeyCode::Enter if form.field == 0 => form.field = 1,
+            KeyCode::Enter => {
+                let body = shared::LoginRequestBody {
+                    email: form.email.trim().to_string(),
+                    password: form.password.to_string(),
+                };
+                self.login(body);
+            }
+            KeyCode::Backspace => {
+                form.value().pop();
+            }
+            KeyCode::Char(c) => form.value().push(c),
+            _ => (),
+        }
+    }
+
+    fn login(&mut self, body: shared::LoginRequestBody) {
+        let client = self.client.clone();
+        match self.wait(client.login(&body)) {
+            Ok(Some(viewer)) => {
+                self.viewer = Some(viewer);
+                self.screen = Screen::Browse;
+                self.status = None;
+                self.reload();
+            }
+            Ok(None) => self.status = Some("Incorrect email or password".to_string()),
+            Err(error) => self.failed(error),
+        }
+    }
+
+    /// Fetches everyone and every idea again, keeping the selection where it can.
+    pub fn reload(&mut self) {
+        let client = self.client.clone();
+        let users = self.wait(client.find_all_users());
+        let gift_ideas = self.wait(client.find_all_gift_ideas());
+        match (users, gift_ideas) {
+            (Ok(users), Ok(gift_ideas)) => {
+                self.users = users;
+                self.gift_ideas = gift_ideas;
+                if self.recipients.selected().is_none_or(|i| i >= self.users.len()) {
+                    self.recipients.select(if self.users.is_empty() { None } else { Some(0) });
+                }
+                self.clamp_idea_selection();
+            }
+            (Err(error), _) | (_, Err(error)) => self.failed(error),
+        }
+    }
+
+    fn viewer_id(&self) -> i32 {
+        self.viewer.as_ref().map_or(0, |viewer| viewer.id)
+    }
+
+    pub fn selected_recipient(&self) -> Option<&shared::MinimalUserInfo> {
+        self.recipients.selected().and_then(|i| self.users.get(i))
+    }
+
+    /// Whether the selected recipient is the person signed in, whose reservations stay hidden.
+    pub fn viewing_own_list(&self) -> bool {
+        self.selected_recipient().is_some_and(|recipient| recipient.id == self.viewer_id())
+    }
+
+    /// The selected recipient's ideas; on your own list, only the ones you added yourself.
+    pub fn visible_ideas(&self) -> Vec<&shared::GiftIdeaResponseBody> {
+        let viewer_id = self.viewer_id();
+        match self.selected_recipient() {
+            Some(recipient) => self
+                .gift_ideas
+                .iter()
+                .filter(|gift| gift.recipient_user_id == recipient.id)
+                .filter(|gift| recipient.id != viewer_id || gift.owner_id == viewer_id)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn selected_idea(&self) -> Option<&shared::GiftIdeaResponseBody> {
+        self.ideas.selected().and_then(|i| self.visible_ideas().get(i).copied())
+    }
+
+    fn clamp_idea_selection(&mut self) {
+        let count = self.visible_ideas().len();
+        match self.ideas.selected() {
+            _ if count == 0 => self.ideas.select(None),
+            Some(i) if i < count => (),
+            _ => self.ideas.select(Some(0)),
+        }
+    }
+
+    fn on_browse_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Left | KeyCode::Right => {
+                self.pane = if self.pane == Pane::Recipients { Pane::Ideas } else { Pane::Recipients };
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Char('g') => {
+                self.reload();
+                if self.viewer.is_some() {
+                    self.status = Some("Reloaded".to_string());
+                }
+            }
+            KeyCode::Char('r') => self.toggle_reservation(),
+            KeyCode::Char('a') => {
+                if let Some(recipient_user_id) = self.selected_recipient().map(|recipient| recipient.id) {
+                    self.screen = Screen::Edit(GiftForm {
+                        gift_id: None,
+                        recipient_user_id,
+                        values: Default::default(),
+                        field: 0,
+                    });
+                }
+            }
+            KeyCode::Char('e') => self.edit_selected(),
+            _ => (),
+        }
+    }
+
+    fn move_selection(&mut self, by: isize) {
+        let (state, count) = match self.pane {
+            Pane::Recipients => (&mut self.recipients, self.users.len()),
+            Pane::Ideas => {
+                let count = self.visible_ideas().len();
+                (&mut self.ideas, count)
+            }
+        };
+        if count == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + by).max(0).min(count as isize - 1) as usize));
+        if self.pane == Pane::Recipients {
+            self.ideas.select(None);
+            self.clamp_idea_selection();
+        }
+    }
+
+    fn toggle_reservation(&mut self) {
+        if self.viewing_own_list() {
+            self.status = Some("You can't reserve your own gift ideas".to_string());
+            return;
+        }
+        let viewer_id = self.viewer_id();
+        let (gift_id, reserved_by) = match self.selected_idea() {
+            Some(gift) => (gift.id, gift.reserved_by_user_id),
+            None => return,
+        };
+        let client = self.client.clone();
+        let result = match reserved_by {
+            None => self.wait(client.reserve_gift_idea(gift_id)),
+            Some(user_id) if user_id == viewer_id => self.wait(client.unreserve_gift_idea(gift_id)),
+            Some(_) => {
+                self.status = Some("Someone else has already reserved that".to_string());
+                return;
+            }
+        };
+        match result {
+            Ok(gift) => {
+                self.status = Some(if gift.reserved_by_user_id.is_some() { "Reserved" } else { "Unreserved" }.to_string());
+                self.apply(gift);
+            }
+            Err(error) => self.failed(error),
+        }
+    }
+
+    fn edit_selected(&mut self) {
+        let gift = match self.selected_idea() {
+            Some(gift) => gift.clone(),
+            None => return,
+        };
+        if gift.owner_id != self.viewer_id() {
+            self.status = Some("Only whoever added an idea can edit it".to_string());
+            return;
+        }
+        self.screen = Screen::Edit(GiftForm {
+            gift_id: Some(gift.id),
+            recipient_user_id: gift.recipient_user_id,
+            values: [
+                gift.title,
+                gift.description.unwrap_or_default(),
+                gift.price.unwrap_or_default(),
+                gift.url.unwrap_or_default(),
+            ],
+            field: 0,
+        });
+    }
+
+    fn on_edit_key(&mut self, code: KeyCode) {
+        let form = match &mut self.screen {
+            Screen::Edit(form) => form,
+            _ => return,
+        };
+        match code {
+            KeyCode::Esc => {
+                self.screen = Screen::Browse;
+                self.status = None;
+            }
+            KeyCode::Tab | KeyCode::Down => form.field = (form.field + 1) % GIFT_FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => form.field = (form.field + GIFT_FIELDS.len() - 1) % GIFT_FIELDS.len(),
+            KeyCode::Backspace => {
+                form.values[form.field].pop();
+            }
+            KeyCode::Char(c) => form.values[form.field].push(c),
+            KeyCode::Enter => self.save(),
+            _ => (),
+        }
+    }
+
+    fn save(&mut self) {
+        let form = match &self.screen {
+            Screen::Edit(form) => form,
+            _ => return,
+        };
+        let (gift_id, recipient_user_id) = (form.gift_id, form.recipient_user_id);
+        let [title, description, price, url] = form.values.clone();
+        let title = title.trim().to_string();
+        if title.is_empty() {
+            self.status = Some("A gift idea needs a title".to_string());
+            return;
+        }
+        let optional = |value: String| Some(value.trim().to_string()).filter(|e| !e.is_empty());
+        let client = self.client.clone();
+        let result = match gift_id {
+            Some(gift_id) => {
+                // empty fields are cleared on the server
+                let body = shared::UpdateGiftIdeaRequest {
+                    title: Some(title),
+                    description: Some(description),
+                    price: Some(price),
+                    url: Some(url),
+                    recipient_user_id: None,
+                    event_id: None,
+                };
+                self.wait(client.update_gift_idea(gift_id, &body))
+            }
+            None => {
+                let body = shared::GiftIdeaRequestBody {
+                    title,
+                    description: optional(description),
+                    price: optional(price),
+                    url: optional(url),
+                    owner_id: self.viewer_id(),
+                    recipient_user_id,
+                    event_id: None,
+                };
+                self.wait(client.add_gift_idea(&body))
+            }
+        };
+        match result {
+            Ok(gift) => {
+                self.status = Some(format!("Saved {}", gift.title));
+                self.screen = Screen::Browse;
+                self.apply(gift);
+            }
+            Err(error) => self.failed(error),
+        }
+    }
+
+    fn apply(&mut self, gift: shared::GiftIdeaResponseBody) {
+        match self.gift_ideas.iter_mut().find(|e| e.id == gift.id) {
+            Some(existing) => *existing = gift,
+            None => self.gift_ideas.push(gift),
+        }
+        self.clamp_idea_selection();
+    }
+}
+
+/// First and last name, or the email address when neither is set.
+pub fn display_name(user: &shared::MinimalUserInfo) -> String {
+    let name = format!("{} {}", user.first_name.as_deref().unwrap_or(""), user.last_name.as_deref().unwrap_or(""));
+    if name.trim().is_empty() {
+        user.email.to_string()
+    } else {
+        name.trim().to_string()
+    }
+}
@@ -0,0 +1,65 @@
+//! A terminal client for browsing and reserving gift ideas, talking to the same `/v1` API as the
+//! web client.  The server defaults to `http://127.0.0.1:8080`; pass another address as the
+//! first argument or set `GIFTS_URL`.  Set `GIFTS_EMAIL` to have the login form start at the
+//! password.
+
+use crossterm::event::{self, Event};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use std::io::{self, Write};
+use tui::backend::CrosstermBackend;
+use tui::Terminal;
+
+mod app;
+mod ui;
+
+fn main() {
+    let base_url = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("GIFTS_URL").ok())
+        .unwrap_or_else(|| "http://127.0.0.1:8080".to_string());
+    let client = match api::Client::new(&base_url) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime.");
+    let mut app = app::App::new(client, runtime, std::env::var("GIFTS_EMAIL").ok());
+
+    if let Err(e) = run(&mut app) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(app: &mut app::App) -> io::Result<()> {
+    terminal::enable_raw_mode().map_err(to_io)?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen).map_err(to_io)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    terminal.hide_cursor()?;
+
+    let result = event_loop(&mut terminal, app);
+
+    // put the terminal back however the loop ended
+    terminal::disable_raw_mode().map_err(to_io)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(to_io)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn event_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut app::App) -> io::Result<()> {
+    while !app.quit {
+        terminal.draw(|mut f| ui::draw(&mut f, app))?;
+        if let Event::Key(key) = event::read().map_err(to_io)? {
+            app.on_key(key);
+        }
+    }
+    Ok(())
+}
+
+fn to_io(e: crossterm::ErrorKind) -> io::Error {
+    io::Error::other(e.to_string())
+}
@@ -0,0 +1,151 @@
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, List, Paragraph, Text};
+use tui::Frame;
+
+use crate::app::{self, App, Pane, Screen};
+
+const BROWSE_HELP: &str = "tab switch pane  up/down move  r reserve/unreserve  a add  e edit  g reload  q quit";
+const FORM_HELP: &str = "tab/up/down move between fields  enter save  esc cancel";
+const LOGIN_HELP: &str = "tab switch field  enter log in  esc quit";
+
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)].as_ref())
+        .split(f.size());
+    let help = match app.screen {
+        Screen::Login(_) => {
+            draw_login(f, app, rows[0]);
+            LOGIN_HELP
+        }
+        Screen::Browse => {
+            draw_browse(f, app, rows[0]);
+            BROWSE_HELP
+        }
+        Screen::Edit(_) => {
+            draw_form(f, app, rows[0]);
+            FORM_HELP
+        }
+    };
+    let status = app.status.clone().unwrap_or_default();
+    f.render_widget(Paragraph::new([Text::styled(status, Style::default().fg(Color::Yellow))].iter()), rows[1]);
+    f.render_widget(Paragraph::new([Text::styled(help, Style::default().fg(Color::DarkGray))].iter()), rows[2]);
+}
+
+fn draw_login<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let form = match &app.screen {
+        Screen::Login(form) => form,
+        _ => return,
+    };
+    let masked = "*".repeat(form.password.chars().count());
+    let fields = [("Email", form.email.as_str()), ("Password", masked.as_str())];
+    draw_fields(f, "Log in", &fields, form.field, area);
+}
+
+fn draw_form<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let form = match &app.screen {
+        Screen::Edit(form) => form,
+        _ => return,
+    };
+    let recipient = app.users.iter().find(|user| user.id == form.recipient_user_id).map(app::display_name).unwrap_or_default();
+    let title = match form.gift_id {
+        Some(_) => format!("Edit gift idea for {}", recipient),
+        None => format!("New gift idea for {}", recipient),
+    };
+    let fields = app::GIFT_FIELDS
+        .iter()
+        .zip(form.values.iter())
+        .map(|(label, value)| (*label, value.as_str()))
+        .collect::<Vec<_>>();
+    draw_fields(f, &title, &fields, form.field, area);
+}
+
+/// One labelled line per field, with the focused one highlighted and showing a cursor.
+fn draw_fields<B: Backend>(f: &mut Frame<B>, title: &str, fields: &[(&str, &str)], focused: usize, area: Rect) {
+    let mut lines = Vec::new();
+    for (i, (label, value)) in fields.iter().enumerate() {
+        let style = if i == focused { Style::default().modifier(Modifier::BOLD) } else { Style::default() };
+        let cursor = if i == focused { "_" } else { "" };
+        lines.push(Text::styled(format!("{:>12}: {}{}\n", label, value, cursor), style));
+    }
+    let block = Block::default().borders(Borders::ALL).title(title);
+    f.render_widget(Paragraph::new(lines.iter()).block(block).wrap(true), area);
+}
+
+fn draw_browse<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(25), Constraint::Percentage(40), Constraint::Percentage(35)].as_ref())
+        .split(area);
+
+    let viewer_id = app.viewer.as_ref().map_or(0, |viewer| viewer.id);
+    let recipients = app
+        .users
+        .iter()
+        .map(|user| {
+            let name = app::display_name(user);
+            Text::raw(if user.id == viewer_id { format!("{} (you)", name) } else { name })
+        })
+        .collect::<Vec<_>>();
+    let recipients = List::new(recipients.into_iter())
+        .block(pane_block("People", app.pane == Pane::Recipients))
+        .highlight_style(Style::default().modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(recipients, columns[0], &mut app.recipients);
+
+    // reservations on your own list stay a surprise
+    let own_list = app.viewing_own_list();
+    let ideas = app
+        .visible_ideas()
+        .into_iter()
+        .map(|gift| {
+            let marker = match gift.reserved_by_user_id {
+                _ if own_list => "   ",
+                Some(user_id) if user_id == viewer_id => "[*]",
+                Some(_) => "[x]",
+                None => "[ ]",
+            };
+            Text::raw(format!("{} {}", marker, gift.title))
+        })
+        .collect::<Vec<_>>();
+    let ideas = List::new(ideas.into_iter())
+        .block(pane_block("Gift ideas", app.pane == Pane::Ideas))
+        .highlight_style(Style::default().modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(ideas, columns[1], &mut app.ideas);
+
+    let mut details = Vec::new();
+    if let Some(gift) = app.selected_idea() {
+        details.push(Text::styled(format!("{}\n\n", gift.title), Style::default().modifier(Modifier::BOLD)));
+        if let Some(description) = &gift.description {
+            details.push(Text::raw(format!("{}\n\n", description)));
+        }
+        if let Some(price) = &gift.price {
+            details.push(Text::raw(format!("Price: {}\n", price)));
+        }
+        if let Some(url) = &gift.url {
+            details.push(Text::raw(format!("Url: {}\n", url)));
+        }
+        let name_of = |user_id: i32| app.users.iter().find(|user| user.id == user_id).map(app::display_name).unwrap_or_default();
+        details.push(Text::raw(format!("Added by: {}\n", name_of(gift.owner_id))));
+        if !own_list {
+            let reserved = match gift.reserved_by_user_id {
+                Some(user_id) if user_id == viewer_id => "you".to_string(),
+                Some(user_id) => name_of(user_id),
+                None => "nobody".to_string(),
+            };
+            details.push(Text::raw(format!("Reserved by: {}\n", reserved)));
+            if gift.date_purchased.is_some() {
+                details.push(Text::raw("Purchased\n"));
+            }
+        }
+    }
+    f.render_widget(Paragraph::new(details.iter()).block(pane_block("Details", false)).wrap(true), columns[2]);
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'_> {
+    let style = if focused { Style::default().fg(Color::Cyan) } else { Style::default() };
+    Block::default().borders(Borders::ALL).border_style(style).title(title)
+}